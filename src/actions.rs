@@ -0,0 +1,1288 @@
+use crate::app::{App, ConfirmAction, Mode, SPLIT_STEP};
+use crate::modes;
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Everything a key can do outside of text prompts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    ViewDiff,
+    ViewFiles,
+    BrowseTree,
+    WorkingTree,
+    Apply,
+    ApplyTo,
+    Pop,
+    Drop,
+    ToggleMark,
+    UnmarkAll,
+    TogglePin,
+    Export,
+    Email,
+    Publish,
+    RemoteStashes,
+    Archive,
+    ArchiveBrowser,
+    Recover,
+    ClearAll,
+    Report,
+    OpenInBrowser,
+    Cleanup,
+    Triage,
+    PruneMerged,
+    NewStash,
+    QuickStash,
+    Rename,
+    Remind,
+    Annotate,
+    Label,
+    Grep,
+    BranchOnly,
+    Compare,
+    ToBranch,
+    Squash,
+    StashHunks,
+    Import,
+    Search,
+    ClearSearch,
+    CycleSort,
+    GroupByBranch,
+    TogglePreview,
+    NarrowList,
+    WidenList,
+    ToggleDates,
+    Refresh,
+    SwitchRepo,
+    Worktrees,
+    Submodules,
+    History,
+    SelectFirst,
+    SelectLast,
+    ToggleFold,
+    FoldAll,
+    UnfoldAll,
+    YankName,
+    YankDiff,
+    YankFiles,
+    Help,
+    Palette,
+    Repeat,
+    Quit,
+
+    ScrollUp,
+    ScrollDown,
+    HalfPageUp,
+    HalfPageDown,
+    PageUp,
+    PageDown,
+    ScrollTop,
+    ScrollBottom,
+    ToggleFile,
+    NextFile,
+    PrevFile,
+    NextHunk,
+    PrevHunk,
+    ScrollLeft,
+    ScrollRight,
+    ToggleWrap,
+    SearchDiff,
+    NextMatch,
+    PrevMatch,
+    CycleAlgorithm,
+    ToggleFunctionContext,
+    DiffWorktree,
+    EditFile,
+    PageDiff,
+    RenderDiff,
+    Back,
+}
+
+impl Action {
+    /// Changes to the selected stash that `.` can make again without a prompt
+    /// of its own; Label, Remind and Annotate are remembered with their text
+    /// when their prompt is submitted, and Export with the popup's format
+    fn repeatable(self) -> bool {
+        matches!(
+            self,
+            Action::Apply | Action::Pop | Action::Drop | Action::TogglePin
+        )
+    }
+}
+
+/// The last change made, which `.` makes again on the selected stash
+#[derive(Debug, Clone)]
+pub struct LastChange {
+    pub action: Action,
+    pub input: Option<String>, // what was typed at the action's prompt
+}
+
+impl LastChange {
+    /// A change made through a prompt, with the text it was given
+    pub fn typed(action: Action, input: &str) -> Self {
+        Self {
+            action,
+            input: Some(input.to_string()),
+        }
+    }
+}
+
+/// Where a binding is active
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// The stash list
+    List,
+    /// The diff and files views
+    View,
+    /// The diff view only
+    Diff,
+}
+
+impl Scope {
+    /// True if bindings of this scope apply to the app right now. Prompts
+    /// that take typed text (search) switch them off.
+    pub fn active(self, app: &App) -> bool {
+        match self {
+            Scope::List => app.mode == Mode::Normal && !app.searching,
+            Scope::View => matches!(app.mode, Mode::Diff | Mode::Files) && !app.diff_searching,
+            Scope::Diff => app.mode == Mode::Diff && !app.diff_searching,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Key {
+    Code(KeyCode),
+    /// A letter pressed with Ctrl held
+    Ctrl(char),
+    /// Any other key pressed with Ctrl held, e.g. Ctrl-Left
+    CtrlCode(KeyCode),
+    /// Two keys pressed one after the other, e.g. `g g`
+    Chord(char, char),
+}
+
+/// One entry of the keymap. The key handlers, the footer hints, the help
+/// overlay and the chord hints are all read from `BINDINGS`.
+pub struct Binding {
+    pub action: Action,
+    /// Stable kebab-case identifier, also matched by the command palette
+    pub name: &'static str,
+    pub description: &'static str,
+    pub keys: &'static [Key],
+    /// Keys as shown in the help overlay
+    pub label: &'static str,
+    /// (keys, text) for the footer; several bindings can share one hint
+    pub footer: Option<(&'static str, &'static str)>,
+    pub scope: Scope,
+}
+
+const fn ch(c: char) -> Key {
+    Key::Code(KeyCode::Char(c))
+}
+
+pub const BINDINGS: &[Binding] = &[
+    Binding {
+        action: Action::MoveUp,
+        name: "move-up",
+        description: "previous stash",
+        keys: &[Key::Code(KeyCode::Up), ch('k')],
+        label: "↑ / k",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::MoveDown,
+        name: "move-down",
+        description: "next stash",
+        keys: &[Key::Code(KeyCode::Down), ch('j')],
+        label: "↓ / j",
+        footer: Some(("↑↓/jk", "navigate")),
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::ViewDiff,
+        name: "view-diff",
+        description: "view diff (on a branch heading: fold it)",
+        keys: &[Key::Code(KeyCode::Enter), ch('d')],
+        label: "Enter / d",
+        footer: Some(("Enter/d", "diff")),
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::ViewFiles,
+        name: "view-files",
+        description: "view changed files",
+        keys: &[ch('f')],
+        label: "f",
+        footer: Some(("f", "files")),
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::BrowseTree,
+        name: "browse-tree",
+        description: "browse the stash's full file tree",
+        keys: &[ch('t')],
+        label: "t",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::WorkingTree,
+        name: "working-tree",
+        description: "show uncommitted changes (what n would stash)",
+        keys: &[ch('w')],
+        label: "w",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Apply,
+        name: "apply",
+        description: "apply stash (keep in list)",
+        keys: &[ch('a')],
+        label: "a",
+        footer: Some(("a", "apply")),
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::ApplyTo,
+        name: "apply-to",
+        description: "apply onto another branch/worktree",
+        keys: &[ch('A')],
+        label: "A",
+        footer: Some(("A", "apply to…")),
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Pop,
+        name: "pop",
+        description: "pop stash (apply + remove)",
+        keys: &[ch('p')],
+        label: "p",
+        footer: Some(("p", "pop")),
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Drop,
+        name: "drop",
+        description: "drop stash (or all marked stashes)",
+        keys: &[ch('x'), Key::Code(KeyCode::Delete)],
+        label: "x / Del",
+        footer: Some(("x", "drop")),
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::ToggleMark,
+        name: "toggle-mark",
+        description: "mark / unmark for bulk drop & export",
+        keys: &[ch(' ')],
+        label: "Space",
+        footer: Some(("Space", "mark")),
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::UnmarkAll,
+        name: "unmark-all",
+        description: "unmark all",
+        keys: &[ch('u')],
+        label: "u",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Compare,
+        name: "compare",
+        description: "diff the marked stash against the selected one",
+        keys: &[ch('=')],
+        label: "=",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::ToBranch,
+        name: "to-branch",
+        description: "commit the stash onto a new branch",
+        keys: &[ch('K')],
+        label: "K",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Squash,
+        name: "squash",
+        description: "squash the marked stashes into one",
+        keys: &[ch('S')],
+        label: "S",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::TogglePin,
+        name: "toggle-pin",
+        description: "pin / unpin (pinned stashes skip bulk drops and cleanup)",
+        keys: &[ch('P')],
+        label: "P",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Export,
+        name: "export",
+        description: "export selected or marked stashes as .patch, .html or .diff",
+        keys: &[ch('e')],
+        label: "e",
+        footer: Some(("e", "export")),
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Email,
+        name: "email",
+        description: "write the stash as a format-patch mail and send it with git send-email",
+        keys: &[Key::Ctrl('e')],
+        label: "Ctrl-e",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Publish,
+        name: "publish",
+        description: "publish the stash to refs/stashes/<user>/ on the remote",
+        keys: &[ch('U')],
+        label: "U",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::RemoteStashes,
+        name: "remote-stashes",
+        description: "browse stashes published on the remote and import them",
+        keys: &[Key::Ctrl('f')],
+        label: "Ctrl-f",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Archive,
+        name: "archive",
+        description: "copy selected or marked stashes into the archive directory",
+        keys: &[ch('Z')],
+        label: "Z",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::ArchiveBrowser,
+        name: "archive-browser",
+        description: "browse archived stashes and restore one",
+        keys: &[Key::Ctrl('a')],
+        label: "Ctrl-a",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Recover,
+        name: "recover",
+        description: "find lost stash commits with git fsck and restore them",
+        keys: &[ch('V')],
+        label: "V",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::ClearAll,
+        name: "clear-all",
+        description: "drop every unpinned stash, archiving them first (asks to type CLEAR)",
+        keys: &[Key::Ctrl('x')],
+        label: "Ctrl-x",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Report,
+        name: "report",
+        description: "write a Markdown report of marked/all stashes",
+        keys: &[ch('E')],
+        label: "E",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::OpenInBrowser,
+        name: "open-in-browser",
+        description: "open the stash's base commit in the browser",
+        keys: &[ch('W')],
+        label: "W",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Cleanup,
+        name: "cleanup",
+        description: "clean up stale stashes one by one",
+        keys: &[ch('C')],
+        label: "C",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Triage,
+        name: "triage",
+        description: "triage all stashes in $EDITOR (keep/drop/export/rename)",
+        keys: &[ch('T')],
+        label: "T",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::PruneMerged,
+        name: "prune-merged",
+        description: "prune stashes already merged into HEAD",
+        keys: &[ch('M')],
+        label: "M",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::NewStash,
+        name: "new-stash",
+        description: "create new stash",
+        keys: &[ch('n')],
+        label: "n",
+        footer: Some(("n", "new")),
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::QuickStash,
+        name: "quick-stash",
+        description: "stash everything now, untracked files too, without asking",
+        keys: &[ch('N')],
+        label: "N",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Rename,
+        name: "rename",
+        description: "rename (edit the message of) the stash",
+        keys: &[ch('r')],
+        label: "r",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Remind,
+        name: "remind",
+        description: "set or clear a date to revisit the stash",
+        keys: &[ch('m')],
+        label: "m",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Annotate,
+        name: "note",
+        description: "attach, edit or clear a note on the stash",
+        keys: &[ch('I')],
+        label: "I",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Label,
+        name: "label",
+        description: "set the stash's labels (filter with /label:name)",
+        keys: &[ch('l')],
+        label: "l",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::StashHunks,
+        name: "stash-hunks",
+        description: "create stash from picked hunks",
+        keys: &[ch('s')],
+        label: "s",
+        footer: Some(("s", "stash hunks")),
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Import,
+        name: "import",
+        description: "import a patch file as a stash",
+        keys: &[ch('i')],
+        label: "i",
+        footer: Some(("i", "import")),
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Search,
+        name: "search",
+        description: "search / filter stashes",
+        keys: &[ch('/')],
+        label: "/",
+        footer: Some(("/", "search")),
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Grep,
+        name: "grep",
+        description: "find the stashes whose changes contain some text",
+        keys: &[Key::Chord('g', '/')],
+        label: "g /",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::ClearSearch,
+        name: "clear-search",
+        description: "clear filter",
+        keys: &[ch('c')],
+        label: "c",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::CycleSort,
+        name: "cycle-sort",
+        description: "cycle sort: index/newest/oldest/branch/files/size",
+        keys: &[ch('o')],
+        label: "o",
+        footer: Some(("o", "sort")),
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::GroupByBranch,
+        name: "group-by-branch",
+        description: "group by branch (Enter/Space folds)",
+        keys: &[ch('b')],
+        label: "b",
+        footer: Some(("b", "group")),
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::BranchOnly,
+        name: "branch-only",
+        description: "show only stashes made on the current branch",
+        keys: &[ch('B')],
+        label: "B",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::TogglePreview,
+        name: "toggle-preview",
+        description: "toggle preview pane",
+        keys: &[ch('v')],
+        label: "v",
+        footer: Some(("v", "preview")),
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::ToggleDates,
+        name: "toggle-dates",
+        description: "switch between relative and absolute dates (T is triage)",
+        keys: &[Key::Ctrl('t')],
+        label: "Ctrl-t",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::NarrowList,
+        name: "narrow-list",
+        description: "give the preview pane more room",
+        keys: &[ch('<'), Key::CtrlCode(KeyCode::Left)],
+        label: "< / Ctrl-←",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::WidenList,
+        name: "widen-list",
+        description: "give the stash list more room",
+        keys: &[ch('>'), Key::CtrlCode(KeyCode::Right)],
+        label: "> / Ctrl-→",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Refresh,
+        name: "refresh",
+        description: "reload the stash list, keeping the selection",
+        keys: &[ch('R'), Key::Code(KeyCode::F(5))],
+        label: "R / F5",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::SwitchRepo,
+        name: "switch-repo",
+        description: "open another repository: recent ones and the current one's siblings",
+        keys: &[Key::Ctrl('r')],
+        label: "Ctrl-r",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Worktrees,
+        name: "worktrees",
+        description: "list the repository's worktrees with their uncommitted changes",
+        keys: &[Key::Ctrl('w')],
+        label: "Ctrl-w",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Submodules,
+        name: "submodules",
+        description: "list submodules with their changes and stashes; Enter manages one",
+        keys: &[Key::Ctrl('s')],
+        label: "Ctrl-s",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::History,
+        name: "history",
+        description: "history of applies, pops, drops and pushes; restore dropped stashes",
+        keys: &[ch('L')],
+        label: "L",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::SelectFirst,
+        name: "select-first",
+        description: "first stash",
+        keys: &[Key::Chord('g', 'g')],
+        label: "g g",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::SelectLast,
+        name: "select-last",
+        description: "last stash",
+        keys: &[Key::Chord('g', 'e'), ch('G')],
+        label: "g e / G",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::ToggleFold,
+        name: "toggle-fold",
+        description: "fold / unfold the current group",
+        keys: &[Key::Chord('z', 'a')],
+        label: "z a",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::FoldAll,
+        name: "fold-all",
+        description: "fold all groups",
+        keys: &[Key::Chord('z', 'M')],
+        label: "z M",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::UnfoldAll,
+        name: "unfold-all",
+        description: "unfold all groups",
+        keys: &[Key::Chord('z', 'R')],
+        label: "z R",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::YankName,
+        name: "copy-name",
+        description: "copy stash name",
+        keys: &[Key::Chord('y', 'n')],
+        label: "y n",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::YankDiff,
+        name: "copy-diff",
+        description: "copy diff",
+        keys: &[Key::Chord('y', 'd')],
+        label: "y d",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::YankFiles,
+        name: "copy-files",
+        description: "copy file list",
+        keys: &[Key::Chord('y', 'f')],
+        label: "y f",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Help,
+        name: "help",
+        description: "this help",
+        keys: &[ch('?')],
+        label: "?",
+        footer: Some(("?", "help")),
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Palette,
+        name: "command-palette",
+        description: "search and run any command",
+        keys: &[ch(':')],
+        label: ":",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Repeat,
+        name: "repeat",
+        description: "repeat the last change on the selected stash",
+        keys: &[ch('.')],
+        label: ".",
+        footer: None,
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::Quit,
+        name: "quit",
+        description: "quit",
+        keys: &[ch('q'), Key::Code(KeyCode::Esc)],
+        label: "q / Esc",
+        footer: Some(("q", "quit")),
+        scope: Scope::List,
+    },
+    Binding {
+        action: Action::ScrollUp,
+        name: "scroll-up",
+        description: "scroll up",
+        keys: &[Key::Code(KeyCode::Up), ch('k')],
+        label: "↑ / k",
+        footer: None,
+        scope: Scope::View,
+    },
+    Binding {
+        action: Action::ScrollDown,
+        name: "scroll-down",
+        description: "scroll down",
+        keys: &[Key::Code(KeyCode::Down), ch('j')],
+        label: "↓ / j",
+        footer: Some(("↑↓/jk", "scroll")),
+        scope: Scope::View,
+    },
+    Binding {
+        action: Action::HalfPageUp,
+        name: "half-page-up",
+        description: "scroll up half a page",
+        keys: &[Key::Ctrl('u')],
+        label: "Ctrl-u",
+        footer: None,
+        scope: Scope::View,
+    },
+    Binding {
+        action: Action::HalfPageDown,
+        name: "half-page-down",
+        description: "scroll down half a page",
+        keys: &[Key::Ctrl('d')],
+        label: "Ctrl-d",
+        footer: None,
+        scope: Scope::View,
+    },
+    Binding {
+        action: Action::PageUp,
+        name: "page-up",
+        description: "scroll up a page",
+        keys: &[Key::Code(KeyCode::PageUp)],
+        label: "PgUp",
+        footer: None,
+        scope: Scope::View,
+    },
+    Binding {
+        action: Action::PageDown,
+        name: "page-down",
+        description: "scroll down a page",
+        keys: &[Key::Code(KeyCode::PageDown)],
+        label: "PgDn",
+        footer: None,
+        scope: Scope::View,
+    },
+    Binding {
+        action: Action::ScrollTop,
+        name: "scroll-top",
+        description: "top",
+        keys: &[Key::Chord('g', 'g')],
+        label: "g g",
+        footer: Some(("gg/ge", "top/end")),
+        scope: Scope::View,
+    },
+    Binding {
+        action: Action::ScrollBottom,
+        name: "scroll-bottom",
+        description: "bottom",
+        keys: &[Key::Chord('g', 'e'), ch('G')],
+        label: "g e / G",
+        footer: None,
+        scope: Scope::View,
+    },
+    Binding {
+        action: Action::ToggleFile,
+        name: "toggle-file",
+        description: "fold / unfold the file under the cursor",
+        keys: &[Key::Code(KeyCode::Enter)],
+        label: "Enter",
+        footer: None,
+        scope: Scope::Diff,
+    },
+    Binding {
+        action: Action::NextFile,
+        name: "next-file",
+        description: "next file",
+        keys: &[ch(']')],
+        label: "]",
+        footer: None,
+        scope: Scope::Diff,
+    },
+    Binding {
+        action: Action::PrevFile,
+        name: "previous-file",
+        description: "previous file",
+        keys: &[ch('[')],
+        label: "[",
+        footer: None,
+        scope: Scope::Diff,
+    },
+    Binding {
+        action: Action::NextHunk,
+        name: "next-hunk",
+        description: "next hunk",
+        keys: &[ch('}')],
+        label: "}",
+        footer: None,
+        scope: Scope::Diff,
+    },
+    Binding {
+        action: Action::PrevHunk,
+        name: "previous-hunk",
+        description: "previous hunk",
+        keys: &[ch('{')],
+        label: "{",
+        footer: None,
+        scope: Scope::Diff,
+    },
+    Binding {
+        action: Action::ScrollLeft,
+        name: "scroll-left",
+        description: "scroll left",
+        keys: &[Key::Code(KeyCode::Left), ch('h')],
+        label: "← / h",
+        footer: None,
+        scope: Scope::View,
+    },
+    Binding {
+        action: Action::ScrollRight,
+        name: "scroll-right",
+        description: "scroll right (turns wrapping off)",
+        keys: &[Key::Code(KeyCode::Right), ch('l')],
+        label: "→ / l",
+        footer: None,
+        scope: Scope::View,
+    },
+    Binding {
+        action: Action::ToggleWrap,
+        name: "toggle-wrap",
+        description: "wrap long lines / cut them off",
+        keys: &[ch('w')],
+        label: "w",
+        footer: None,
+        scope: Scope::View,
+    },
+    Binding {
+        action: Action::SearchDiff,
+        name: "search-diff",
+        description: "search the diff",
+        keys: &[ch('/')],
+        label: "/",
+        footer: Some(("/ n N", "search")),
+        scope: Scope::View,
+    },
+    Binding {
+        action: Action::NextMatch,
+        name: "next-match",
+        description: "next match",
+        keys: &[ch('n')],
+        label: "n",
+        footer: None,
+        scope: Scope::View,
+    },
+    Binding {
+        action: Action::PrevMatch,
+        name: "previous-match",
+        description: "previous match",
+        keys: &[ch('N')],
+        label: "N",
+        footer: None,
+        scope: Scope::View,
+    },
+    Binding {
+        action: Action::CycleAlgorithm,
+        name: "cycle-algorithm",
+        description: "cycle diff algorithm (myers/minimal/patience/histogram)",
+        keys: &[ch('a')],
+        label: "a",
+        footer: Some(("a", "algorithm")),
+        scope: Scope::Diff,
+    },
+    Binding {
+        action: Action::ToggleFunctionContext,
+        name: "toggle-function-context",
+        description: "toggle whole-function context",
+        keys: &[ch('F')],
+        label: "F",
+        footer: Some(("F", "functions")),
+        scope: Scope::Diff,
+    },
+    Binding {
+        action: Action::DiffWorktree,
+        name: "diff-worktree",
+        description: "diff against the working tree instead of the stash's base",
+        keys: &[ch('D')],
+        label: "D",
+        footer: None,
+        scope: Scope::Diff,
+    },
+    Binding {
+        action: Action::EditFile,
+        name: "edit-file",
+        description: "open the file under the cursor in $EDITOR",
+        keys: &[ch('o')],
+        label: "o",
+        footer: Some(("o/O", "edit/pager")),
+        scope: Scope::View,
+    },
+    Binding {
+        action: Action::PageDiff,
+        name: "page-diff",
+        description: "page the diff with $PAGER (or delta, or less)",
+        keys: &[ch('O')],
+        label: "O",
+        footer: None,
+        scope: Scope::View,
+    },
+    Binding {
+        action: Action::RenderDiff,
+        name: "render-diff",
+        description: "show the diff as the diff.external renderer draws it",
+        keys: &[ch('X')],
+        label: "X",
+        footer: None,
+        scope: Scope::Diff,
+    },
+    Binding {
+        action: Action::Back,
+        name: "back",
+        description: "back to the list (or the grep results)",
+        keys: &[Key::Code(KeyCode::Esc), ch('q')],
+        label: "Esc / q",
+        footer: Some(("Esc/q", "back")),
+        scope: Scope::View,
+    },
+    Binding {
+        action: Action::Palette,
+        name: "command-palette",
+        description: "search and run any command",
+        keys: &[ch(':')],
+        label: ":",
+        footer: None,
+        scope: Scope::View,
+    },
+];
+
+/// The action bound to a single key press, if any is active
+pub fn for_key(app: &App, key: KeyEvent) -> Option<Action> {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    let bound = |pressed: Key| {
+        BINDINGS
+            .iter()
+            .find(|b| b.scope.active(app) && b.keys.contains(&pressed))
+            .map(|b| b.action)
+    };
+    match key.code {
+        KeyCode::Char(c) if ctrl => bound(Key::Ctrl(c)),
+        // Ctrl with a key that has no Ctrl binding acts like the key alone
+        code if ctrl => bound(Key::CtrlCode(code)).or_else(|| bound(Key::Code(code))),
+        code => bound(Key::Code(code)),
+    }
+}
+
+/// Active two-key sequences: (first key, second key, description)
+pub fn chords(app: &App) -> Vec<(char, char, &'static str)> {
+    BINDINGS
+        .iter()
+        .filter(|b| b.scope.active(app))
+        .flat_map(|b| {
+            b.keys.iter().filter_map(|k| match k {
+                Key::Chord(first, second) => Some((*first, *second, b.description)),
+                Key::Code(_) | Key::Ctrl(_) | Key::CtrlCode(_) => None,
+            })
+        })
+        .collect()
+}
+
+/// The action bound to a completed two-key sequence
+pub fn for_chord(app: &App, first: char, second: char) -> Option<Action> {
+    BINDINGS
+        .iter()
+        .find(|b| b.scope.active(app) && b.keys.contains(&Key::Chord(first, second)))
+        .map(|b| b.action)
+}
+
+/// The registry entry of `action`
+pub fn binding(action: Action) -> Option<&'static Binding> {
+    BINDINGS.iter().find(|b| b.action == action)
+}
+
+/// Footer hints for the bindings of `scopes`, in registry order
+pub fn footer(scopes: &[Scope]) -> Vec<(&'static str, &'static str)> {
+    BINDINGS
+        .iter()
+        .filter(|b| scopes.contains(&b.scope))
+        .filter_map(|b| b.footer)
+        .collect()
+}
+
+/// Carry out `action`. Returns true if the app should quit.
+pub fn run(app: &mut App, action: Action) -> Result<bool> {
+    let has_stash = app.selected_stash().is_some();
+    // Typed before the key, as in `5j`; motions repeat by it and jumps go to it
+    let count = app.count.take();
+    if action.repeatable() && has_stash {
+        app.last_change = Some(LastChange {
+            action,
+            input: None,
+        });
+    }
+    match action {
+        Action::Quit => return Ok(true),
+        Action::MoveUp => (0..count.unwrap_or(1)).for_each(|_| app.move_up()),
+        Action::MoveDown => (0..count.unwrap_or(1)).for_each(|_| app.move_down()),
+        Action::GroupByBranch => app.toggle_grouped(),
+        Action::BranchOnly => app.toggle_branch_only(),
+        Action::TogglePreview => app.preview = !app.preview,
+        Action::ToggleDates => app.absolute_dates = !app.absolute_dates,
+        Action::NarrowList => app.resize_split(-SPLIT_STEP),
+        Action::WidenList => app.resize_split(SPLIT_STEP),
+        Action::Refresh => app.refresh()?,
+        Action::SwitchRepo => app.mode = Mode::Repos,
+        Action::Worktrees => app.mode = Mode::Worktrees,
+        Action::Submodules => app.mode = Mode::Submodules,
+        Action::History => app.mode = Mode::History,
+        Action::ViewDiff if has_stash => app.open_view(Mode::Diff),
+        Action::ViewFiles if has_stash => app.open_view(Mode::Files),
+        Action::BrowseTree if has_stash => app.open_tree(),
+        Action::WorkingTree => app.mode = Mode::Status,
+        Action::Apply if has_stash => app.ask(ConfirmAction::Apply)?,
+        Action::ApplyTo if has_stash => app.load_targets(),
+        Action::Pop if has_stash => app.ask(ConfirmAction::Pop)?,
+        Action::Drop if !app.marked.is_empty() => app.ask(ConfirmAction::DropMarked)?,
+        Action::Drop if has_stash => app.ask(ConfirmAction::Drop)?,
+        Action::ToggleMark if has_stash => {
+            app.toggle_mark();
+            app.move_down();
+        }
+        Action::UnmarkAll => app.marked.clear(),
+        Action::ToBranch if has_stash => app.mode = Mode::ToBranch,
+        Action::Squash if app.squash_order().len() < 2 => {
+            app.mode =
+                Mode::Message("Error: mark at least two stashes with Space first.".to_string());
+        }
+        Action::Squash => app.mode = Mode::Squash,
+        Action::Compare => {
+            if let Err(e) = app.compare_stashes() {
+                app.mode = Mode::Message(format!("Error: {e}"));
+            }
+        }
+        Action::TogglePin => {
+            if let Err(e) = app.toggle_pin() {
+                app.mode = Mode::Message(format!("Error: {e}"));
+            }
+        }
+        // Prune stashes whose changes are already committed
+        Action::PruneMerged if app.loading.is_none() => app.find_merged(),
+        Action::Export if !app.bulk_targets().is_empty() => app.mode = Mode::Export,
+        Action::Email if has_stash => app.mode = Mode::Email,
+        Action::Publish if has_stash => app.publish_stash(),
+        Action::RemoteStashes => app.mode = Mode::RemoteStashes,
+        Action::Archive if !app.bulk_targets().is_empty() => {
+            let result = app.archive_stashes();
+            app.report(result);
+        }
+        Action::ArchiveBrowser => app.mode = Mode::Archive,
+        Action::Recover => app.mode = Mode::Recover,
+        Action::ClearAll if !app.stashes.is_empty() => app.mode = Mode::ClearAll,
+        Action::OpenInBrowser if has_stash => {
+            let result = app.open_in_browser();
+            app.report(result);
+        }
+        Action::Cleanup => {
+            app.mode = if app.start_cleanup() {
+                Mode::Cleanup
+            } else {
+                Mode::Message("No stale stashes.".to_string())
+            };
+        }
+        Action::Triage if !app.stashes.is_empty() => {
+            if let Err(e) = app.start_todo() {
+                app.mode = Mode::Message(format!("Error: {e}"));
+            }
+        }
+        Action::Report if !app.stashes.is_empty() => {
+            let result = app.export_report();
+            app.report(result);
+        }
+        Action::NewStash => {
+            app.hunk_stash = false;
+            app.mode = Mode::NewStash;
+        }
+        Action::QuickStash => match app.quick_stash() {
+            Ok(Some(message)) => app.toast(format!("Stashed as '{message}'.")),
+            Ok(None) => app.mode = Mode::Message("No local changes to save.".to_string()),
+            Err(e) => app.mode = Mode::Message(format!("Error: {e}")),
+        },
+        Action::StashHunks => match app.load_hunks() {
+            Ok(()) if app.hunk_files.is_empty() => {
+                app.mode = Mode::Message("Error: no unstaged changes to pick from.".to_string());
+            }
+            Ok(()) => app.mode = Mode::HunkSelect,
+            Err(e) => app.mode = Mode::Message(format!("Error: {e}")),
+        },
+        Action::Rename if has_stash => app.mode = Mode::Rename,
+        Action::Remind if has_stash => app.mode = Mode::Remind(app.new_input()),
+        Action::Annotate if has_stash => app.mode = Mode::Annotate(app.new_input()),
+        Action::Label if has_stash => app.mode = Mode::Label(app.new_input()),
+        Action::Import => app.mode = Mode::ImportPatch(app.new_input()),
+        Action::Grep => {
+            app.grep_input.set(app.grep_pattern.clone());
+            app.grep_editing = true;
+            app.mode = Mode::Grep;
+        }
+        Action::Search => {
+            app.search_query.clear();
+            app.searching = true;
+            app.selected = 0;
+        }
+        Action::ClearSearch => {
+            app.search_query.clear();
+            app.selected = 0;
+        }
+        Action::CycleSort => app.cycle_sort(),
+        Action::Palette => modes::palette::open(app),
+        Action::Repeat => match app.last_change.clone() {
+            None => app.mode = Mode::Message("Nothing to repeat yet.".to_string()),
+            // Again in the format last chosen, without the popup
+            Some(LastChange {
+                action: Action::Export,
+                ..
+            }) if has_stash => app.export_stashes(),
+            Some(LastChange {
+                action,
+                input: None,
+            }) => return run(app, action),
+            Some(LastChange {
+                action,
+                input: Some(text),
+            }) if has_stash => {
+                let result = match action {
+                    Action::Label => app.set_labels(&text),
+                    Action::Remind => app.set_reminder(&text),
+                    _ => app.set_annotation(&text),
+                };
+                app.report(result);
+            }
+            Some(_) => {}
+        },
+        Action::Help => {
+            app.help_scroll = 0;
+            app.mode = Mode::Help;
+        }
+        Action::SelectFirst | Action::SelectLast if count.is_some() => {
+            app.select_nth(count.unwrap_or(1))
+        }
+        Action::SelectFirst => app.select_first(),
+        Action::SelectLast => app.select_last(),
+        Action::ToggleFold => app.toggle_fold_here(),
+        Action::FoldAll => app.fold_all(),
+        Action::UnfoldAll => app.unfold_all(),
+        Action::YankName | Action::YankDiff | Action::YankFiles if has_stash => {
+            let what = match action {
+                Action::YankName => 'n',
+                Action::YankDiff => 'd',
+                _ => 'f',
+            };
+            let result = app.yank(what);
+            app.report(result);
+        }
+
+        Action::ScrollUp => (0..count.unwrap_or(1)).for_each(|_| app.scroll_diff_up()),
+        Action::ScrollDown => (0..count.unwrap_or(1)).for_each(|_| app.scroll_diff_down()),
+        Action::PageUp => app.page_diff_up(count.unwrap_or(1) * app.diff_page.get()),
+        Action::PageDown => app.page_diff_down(count.unwrap_or(1) * app.diff_page.get()),
+        Action::HalfPageUp => app.page_diff_up(count.unwrap_or(1) * app.diff_page.get() / 2),
+        Action::HalfPageDown => app.page_diff_down(count.unwrap_or(1) * app.diff_page.get() / 2),
+        Action::ScrollTop | Action::ScrollBottom if count.is_some() => {
+            app.scroll_diff_to(count.unwrap_or(1))
+        }
+        Action::ScrollTop => app.diff_scroll = 0,
+        Action::ScrollBottom => app.diff_scroll = app.diff_len().saturating_sub(1),
+        // File and hunk positions refer to gsm's own diff
+        Action::ToggleFile
+        | Action::NextFile
+        | Action::PrevFile
+        | Action::NextHunk
+        | Action::PrevHunk
+            if app.diff_rendered.is_some() => {}
+        Action::ToggleFile => app.toggle_file_fold(),
+        Action::NextFile => app.jump_file(true),
+        Action::PrevFile => app.jump_file(false),
+        Action::NextHunk => app.jump_hunk(true),
+        Action::PrevHunk => app.jump_hunk(false),
+        Action::ScrollLeft => app.scroll_diff_left(),
+        Action::ScrollRight => app.scroll_diff_right(),
+        Action::ToggleWrap => app.toggle_diff_wrap(),
+        Action::SearchDiff => {
+            app.clear_diff_search();
+            app.diff_searching = true;
+        }
+        Action::NextMatch => app.next_diff_match(true),
+        Action::PrevMatch => app.next_diff_match(false),
+        Action::CycleAlgorithm => app.cycle_diff_algorithm(),
+        Action::ToggleFunctionContext => app.toggle_function_context(),
+        Action::DiffWorktree => app.toggle_diff_worktree(),
+        Action::EditFile => {
+            if let Err(e) = app.edit_file_at_cursor() {
+                app.mode = Mode::Message(format!("Error: {e}"));
+            }
+        }
+        Action::PageDiff => {
+            if let Err(e) = app.page_diff() {
+                app.mode = Mode::Message(format!("Error: {e}"));
+            }
+        }
+        Action::RenderDiff => {
+            if let Err(e) = app.toggle_rendered_diff() {
+                app.mode = Mode::Message(format!("Error: {e}"));
+            }
+        }
+        Action::Back if app.grep_drill => app.mode = Mode::Grep,
+        Action::Back => app.mode = Mode::Normal,
+
+        // Needs a selection (or marks) that isn't there
+        _ => {}
+    }
+    Ok(false)
+}
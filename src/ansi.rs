@@ -0,0 +1,134 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+
+/// Turn text carrying terminal colour codes (SGR escape sequences, as written
+/// by `--color=always`, delta or bat) into styled lines. Colours carry over
+/// from one line to the next as they would in a terminal; other escape
+/// sequences, such as cursor movement and hyperlinks, are dropped.
+pub fn to_lines(text: &str, base: Style) -> Vec<Line<'static>> {
+    let mut style = base;
+    text.lines()
+        .map(|line| parse_line(line, base, &mut style))
+        .collect()
+}
+
+/// One line of coloured text, starting from `base`
+pub fn to_line(line: &str, base: Style) -> Line<'static> {
+    let mut style = base;
+    parse_line(line, base, &mut style)
+}
+
+fn parse_line(line: &str, base: Style, style: &mut Style) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut text = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => {}
+            // Carriage returns would make the terminal overdraw the line
+            '\r' => continue,
+            c => {
+                text.push(c);
+                continue;
+            }
+        }
+        match chars.next() {
+            // CSI: parameters up to a final byte in @..~; only SGR ('m') is kept
+            Some('[') => {
+                let mut params = String::new();
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        if c == 'm' {
+                            if !text.is_empty() {
+                                spans.push(Span::styled(std::mem::take(&mut text), *style));
+                            }
+                            *style = apply_sgr(*style, base, &params);
+                        }
+                        break;
+                    }
+                    params.push(c);
+                }
+            }
+            // OSC (titles, hyperlinks): up to BEL or ESC \
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    if !text.is_empty() {
+        spans.push(Span::styled(text, *style));
+    }
+    Line::from(spans)
+}
+
+/// `style` after a Select Graphic Rendition sequence with parameters `params`,
+/// e.g. "1;38;5;208". A reset returns to `base`.
+fn apply_sgr(mut style: Style, base: Style, params: &str) -> Style {
+    let codes: Vec<u16> = params
+        .split([';', ':'])
+        .map(|p| p.parse().unwrap_or(0))
+        .collect();
+    let mut codes = codes.into_iter();
+    while let Some(code) = codes.next() {
+        style = match code {
+            0 => base,
+            1 => style.add_modifier(Modifier::BOLD),
+            2 => style.add_modifier(Modifier::DIM),
+            3 => style.add_modifier(Modifier::ITALIC),
+            4 => style.add_modifier(Modifier::UNDERLINED),
+            5 => style.add_modifier(Modifier::SLOW_BLINK),
+            7 => style.add_modifier(Modifier::REVERSED),
+            8 => style.add_modifier(Modifier::HIDDEN),
+            9 => style.add_modifier(Modifier::CROSSED_OUT),
+            22 => style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style.remove_modifier(Modifier::ITALIC),
+            24 => style.remove_modifier(Modifier::UNDERLINED),
+            25 => style.remove_modifier(Modifier::SLOW_BLINK),
+            27 => style.remove_modifier(Modifier::REVERSED),
+            28 => style.remove_modifier(Modifier::HIDDEN),
+            29 => style.remove_modifier(Modifier::CROSSED_OUT),
+            30..=37 => style.fg(Color::Indexed((code - 30) as u8)),
+            38 => match extended_color(&mut codes) {
+                Some(color) => style.fg(color),
+                None => style,
+            },
+            39 => Style {
+                fg: base.fg,
+                ..style
+            },
+            40..=47 => style.bg(Color::Indexed((code - 40) as u8)),
+            48 => match extended_color(&mut codes) {
+                Some(color) => style.bg(color),
+                None => style,
+            },
+            49 => Style {
+                bg: base.bg,
+                ..style
+            },
+            90..=97 => style.fg(Color::Indexed((code - 90 + 8) as u8)),
+            100..=107 => style.bg(Color::Indexed((code - 100 + 8) as u8)),
+            _ => style,
+        };
+    }
+    style
+}
+
+/// The colour after a 38 or 48: "5;N" for the 256-colour palette, or
+/// "2;R;G;B" for true colour
+fn extended_color(codes: &mut impl Iterator<Item = u16>) -> Option<Color> {
+    match codes.next()? {
+        5 => Some(Color::Indexed(codes.next()? as u8)),
+        2 => {
+            let (r, g, b) = (codes.next()?, codes.next()?, codes.next()?);
+            Some(Color::Rgb(r as u8, g as u8, b as u8))
+        }
+        _ => None,
+    }
+}
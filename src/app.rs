@@ -1,6 +1,30 @@
-use crate::{events, git, ui};
-use anyhow::Result;
-use ratatui::{backend::Backend, Terminal};
+use crate::cache::Lru;
+use crate::input::TextInput;
+use crate::{
+    actions, ansi, archive, audit, clipboard, config, diff, events, external, forge, git, hooks,
+    journal, modes, pins, preview, query, report, repos, state, todo, ui,
+};
+use anyhow::{Context, Result, bail};
+use chrono::{Days, Local, Months, NaiveDate, TimeZone};
+use crossterm::{
+    event::{DisableBracketedPaste, EnableBracketedPaste},
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+};
+use fuzzy_matcher::FuzzyMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use ratatui::{Terminal, backend::Backend, style::Style, text::Line};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Mode {
@@ -9,132 +33,4228 @@ pub enum Mode {
     Files,
     Confirm(ConfirmAction),
     NewStash,
+    ImportPatch(TextInput), // path of the patch file
+    Rename,
+    Remind(TextInput),   // date to revisit the stash on
+    Annotate(TextInput), // the stash's note
+    Label(TextInput),    // comma-separated labels
+    Grep,
+    ToBranch,
+    Squash,
+    Export,
+    Email,
+    RemoteStashes,
+    Archive,
+    Recover,
+    ClearAll,
+    PickTarget,
+    Repos,
+    Worktrees,
+    Submodules,
+    History,
+    Help,
+    Conflict,
+    HunkSelect,
+    Cleanup,
+    Tree,
+    Status,
+    Progress,
     Message(String), // show result message
 }
 
+impl Mode {
+    /// Name shown in the header's mode indicator
+    pub fn label(&self) -> &'static str {
+        match self {
+            Mode::Normal => "NORMAL",
+            Mode::Diff => "DIFF",
+            Mode::Files => "FILES",
+            Mode::Confirm(_) => "CONFIRM",
+            Mode::NewStash
+            | Mode::ImportPatch(_)
+            | Mode::Rename
+            | Mode::Remind(_)
+            | Mode::Annotate(_)
+            | Mode::Label(_)
+            | Mode::ToBranch
+            | Mode::Squash
+            | Mode::Email
+            | Mode::ClearAll => "INPUT",
+            Mode::Grep => "GREP",
+            Mode::PickTarget
+            | Mode::Repos
+            | Mode::Worktrees
+            | Mode::Submodules
+            | Mode::RemoteStashes => "PICK",
+            Mode::Archive => "ARCHIVE",
+            Mode::Recover => "RECOVER",
+            Mode::History => "HISTORY",
+            Mode::Help => "HELP",
+            Mode::Conflict => "CONFLICT",
+            Mode::HunkSelect => "HUNKS",
+            Mode::Cleanup => "CLEANUP",
+            Mode::Export => "EXPORT",
+            Mode::Tree => "TREE",
+            Mode::Status => "STATUS",
+            Mode::Progress => "RUNNING",
+            Mode::Message(_) => "MESSAGE",
+        }
+    }
+
+    /// The prompt text a mode carries with it, if any
+    pub fn input(&self) -> Option<&TextInput> {
+        match self {
+            Mode::ImportPatch(input)
+            | Mode::Remind(input)
+            | Mode::Annotate(input)
+            | Mode::Label(input) => Some(input),
+            _ => None,
+        }
+    }
+
+    pub fn input_mut(&mut self) -> Option<&mut TextInput> {
+        match self {
+            Mode::ImportPatch(input)
+            | Mode::Remind(input)
+            | Mode::Annotate(input)
+            | Mode::Label(input) => Some(input),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum ConfirmAction {
     Drop,
     Pop,
     Apply,
+    DropPinned, // second prompt before dropping a pinned stash
+    DropMarked,
+    PruneMerged,
+    RunTodo,
+    Rename,
+    ApplyToBranch, // switch this worktree to the picked branch, then apply
+}
+
+impl ConfirmAction {
+    /// Short description, as recorded in the operation journal
+    pub fn label(&self) -> &'static str {
+        match self {
+            ConfirmAction::Drop => "drop stash",
+            ConfirmAction::DropPinned => "drop pinned stash",
+            ConfirmAction::Pop => "pop stash",
+            ConfirmAction::Apply => "apply stash",
+            ConfirmAction::DropMarked => "drop marked stashes",
+            ConfirmAction::PruneMerged => "prune merged stashes",
+            ConfirmAction::RunTodo => "run TODO plan",
+            ConfirmAction::Rename => "rename stash",
+            ConfirmAction::ApplyToBranch => "apply stash to branch",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Index,
+    Newest,
+    Oldest,
+    Branch,
+    Files,
+    Size,
+}
+
+impl SortOrder {
+    pub fn next(self) -> Self {
+        match self {
+            SortOrder::Index => SortOrder::Newest,
+            SortOrder::Newest => SortOrder::Oldest,
+            SortOrder::Oldest => SortOrder::Branch,
+            SortOrder::Branch => SortOrder::Files,
+            SortOrder::Files => SortOrder::Size,
+            SortOrder::Size => SortOrder::Index,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortOrder::Index => "index",
+            SortOrder::Newest => "newest",
+            SortOrder::Oldest => "oldest",
+            SortOrder::Branch => "branch",
+            SortOrder::Files => "files changed",
+            SortOrder::Size => "lines changed",
+        }
+    }
+}
+
+/// What the new-stash popup stashes; Tab cycles through these
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum StashScope {
+    Tracked,
+    Untracked,
+    All,
+    KeepIndex,
+    Staged,
+}
+
+impl StashScope {
+    pub fn next(self) -> Self {
+        match self {
+            StashScope::Tracked => StashScope::Untracked,
+            StashScope::Untracked => StashScope::All,
+            StashScope::All => StashScope::KeepIndex,
+            StashScope::KeepIndex => StashScope::Staged,
+            StashScope::Staged => StashScope::Tracked,
+        }
+    }
+
+    pub fn prev(self) -> Self {
+        match self {
+            StashScope::Tracked => StashScope::Staged,
+            StashScope::Untracked => StashScope::Tracked,
+            StashScope::All => StashScope::Untracked,
+            StashScope::KeepIndex => StashScope::All,
+            StashScope::Staged => StashScope::KeepIndex,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            StashScope::Tracked => "tracked changes",
+            StashScope::Untracked => "tracked + untracked files",
+            StashScope::All => "everything, ignored files too (--all)",
+            StashScope::KeepIndex => "tracked changes, keep staged (--keep-index)",
+            StashScope::Staged => "staged changes only (--staged)",
+        }
+    }
+
+    pub fn options(self) -> git::PushOptions {
+        git::PushOptions {
+            include_untracked: self == StashScope::Untracked,
+            all: self == StashScope::All,
+            keep_index: self == StashScope::KeepIndex,
+            staged: self == StashScope::Staged,
+        }
+    }
+}
+
+/// What `e` writes each stash's diff as; Tab in the export popup cycles these
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum ExportFormat {
+    #[default]
+    Patch,
+    Html,
+    Diff,
+}
+
+impl ExportFormat {
+    pub fn next(self) -> Self {
+        match self {
+            ExportFormat::Patch => ExportFormat::Html,
+            ExportFormat::Html => ExportFormat::Diff,
+            ExportFormat::Diff => ExportFormat::Patch,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Patch => "patch",
+            ExportFormat::Html => "html",
+            ExportFormat::Diff => "diff",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ExportFormat::Patch => "patch, as the diff view shows it (git apply)",
+            ExportFormat::Html => "HTML page with a coloured diff",
+            ExportFormat::Diff => "unified diff with chosen context",
+        }
+    }
+
+    /// The patch is exactly what the diff view shows; the others honour the
+    /// popup's context size
+    pub fn has_context(self) -> bool {
+        self != ExportFormat::Patch
+    }
+}
+
+/// One line of the stash list: a stash, or a branch heading in grouped mode
+#[derive(Debug, Clone, Copy)]
+pub enum ListRow<'a> {
+    Group {
+        branch: &'a str,
+        count: usize,
+        collapsed: bool,
+    },
+    Stash(&'a git::Stash),
+}
+
+/// How the stash's originating branch relates to the current one
+#[derive(Debug, Clone, PartialEq)]
+pub enum BranchRelation {
+    Current,
+    Gone,
+    Diverged { ahead: usize, behind: usize },
+}
+
+/// One line of the stash tree browser
+#[derive(Debug, Clone)]
+pub struct TreeRow {
+    pub path: String, // full path; directories have no trailing slash
+    pub depth: usize,
+    pub is_dir: bool,
+    pub expanded: bool,
+}
+
+/// Where "apply to…" should put the selected stash
+#[derive(Debug, Clone)]
+pub enum ApplyTarget {
+    Worktree {
+        path: String,
+        branch: Option<String>,
+    },
+    Branch(String),
+}
+
+impl ApplyTarget {
+    pub fn label(&self) -> String {
+        match self {
+            ApplyTarget::Worktree { path, branch } => format!(
+                "worktree {path} ({})",
+                branch.as_deref().unwrap_or("detached")
+            ),
+            ApplyTarget::Branch(b) => format!("branch {b}"),
+        }
+    }
+}
+
+/// What the cleanup wizard does with a stale stash besides keeping it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CleanupStep {
+    Export,
+    Drop,
+}
+
+/// Separator between a stash's tracked changes and its untracked files in the
+/// diff and files views
+pub const UNTRACKED_HEADER: &str = "──── untracked files ────";
+
+/// Output of git work run on a worker thread, sent back to the render loop
+enum JobResult {
+    DiffLines {
+        request: u64,
+        lines: Vec<String>,
+    },
+    Preview {
+        sha: String,
+        lines: Vec<String>,
+    },
+    ViewInfo {
+        sha: String,
+        relation: Option<BranchRelation>,
+        note: Option<String>,
+    },
+    View {
+        request: u64,
+        raw: Result<String>,
+        relation: Option<BranchRelation>,
+        note: Option<String>,
+        details: Option<git::StashDetails>,
+    },
+    Merged(Result<HashSet<String>>),
+    Preflight {
+        sha: String,
+        result: Result<git::Preflight>,
+    },
+    Grep {
+        pattern: String,
+        hits: Result<Vec<GrepHit>>,
+    },
+    Applied {
+        pop: bool,
+        label: String,
+        files: Vec<String>,
+        result: Result<String>,
+    },
+    Exported {
+        written: Vec<String>,
+        result: Result<()>,
+    },
+    Published(Result<String>),
+    RemoteStashes(Result<Vec<git::RemoteStash>>),
+    RemoteImported(Result<String>),
+    LostStashes(Result<Vec<git::LostStash>>),
+    StashStamp(Option<String>),
+    Tree {
+        sha: String,
+        result: Result<(Vec<String>, HashSet<String>)>, // paths, changed paths
+    },
+    TreeFile {
+        key: (String, String),
+        lines: Vec<String>,
+    },
+    Targets(Result<Vec<ApplyTarget>>),
+    Numstat(Vec<(String, String, Vec<git::FileChange>)>), // SHA, name read by, files
+    Notes {
+        request: u64,
+        notes: HashMap<String, String>,
+    },
+}
+
+/// What a diff or files view showed, for reopening it without asking git again
+#[derive(Debug, Clone, PartialEq)]
+struct ViewKey {
+    sha: String,
+    files: bool,
+    opts: git::DiffOptions,
+}
+
+struct CachedView {
+    lines: Vec<String>,
+    details: Option<git::StashDetails>,
+}
+
+/// A stash whose changes matched a grep across stashes
+#[derive(Debug, Clone)]
+pub struct GrepHit {
+    pub sha: String,
+    pub name: String,
+    pub short_msg: String,
+    pub files: usize,
+    pub hunks: usize,
+    pub patch: String, // just the matching hunks, with their file headers
 }
 
 pub struct App {
+    pub config: config::Config,
     pub stashes: Vec<git::Stash>,
     pub selected: usize,
     pub mode: Mode,
-    pub diff_content: Vec<String>,
+    pub diff_content: diff::DiffModel,
+    pub diff_title: String, // what the diff/files view is showing
+    pub diff_options: git::DiffOptions,
+    pub applied: Option<(String, Vec<String>)>, // last applied stash and the files it touched
+    reviewing: Option<(String, Vec<String>)>,   // `applied` while its review diff is open
+    comparing: Option<(git::Stash, git::Stash)>, // two stashes diffed against each other
     pub diff_scroll: usize,
-    pub search_query: String,
+    pub diff_hscroll: usize, // columns hidden on the left when not wrapping
+    pub diff_wrap: bool,
+    pub diff_page: Cell<usize>, // lines the diff pane showed last frame, a page for PgUp/PgDn
+    pub diff_rendered: Option<Vec<Line<'static>>>, // diff.external's output, shown instead
+    pub diff_worktree: bool,    // diff stashes against the working tree, not their base
+    pub diff_query: TextInput,
+    pub diff_searching: bool,
+    pub diff_matches: Vec<usize>, // diff_content lines containing diff_query
+    pub diff_match: usize,        // index into diff_matches of the current match
+    pub line_ending_files: usize,
+    diff_files: Vec<usize>, // line numbers of `diff --git` headers in the open diff
+    diff_hunks: Vec<usize>, // line numbers of `@@` hunk headers
+    pub branch_relation: Option<BranchRelation>,
+    pub stash_note: Vec<String>, // captured environment of the stash in the diff/files view
+    pub stash_details: Option<git::StashDetails>, // of the stash in the diff/files view
+    pub search_query: TextInput,
     pub searching: bool,
-    pub new_stash_input: String,
-    pub new_stash_untracked: bool,
-    pub status_msg: Option<String>,
+    matcher: SkimMatcherV2,
+    pub sort_order: SortOrder,
+    pub grouped: bool,
+    pub branch_only: bool,       // hide stashes made on other branches
+    pub marked: HashSet<String>, // stash SHAs selected for bulk operations
+    pub prune_candidates: HashSet<String>, // stash SHAs already contained in HEAD
+    pub preflight: Option<std::result::Result<git::Preflight, String>>, // None while checking
+    pub confirm_input: String,   // index typed at a `type-index` prompt
+    preflight_sha: Option<String>,
+    pub collapsed: HashSet<String>, // branches folded in grouped mode
+    pub new_stash_input: TextInput,
+    pub new_stash_scope: StashScope,
+    pub rename_input: TextInput,
+    pub reminders: HashMap<String, NaiveDate>, // revisit date by stash SHA
+    pub annotations: HashMap<String, String>,  // free-text note by stash SHA
+    pub labels: HashMap<String, Vec<String>>,  // user labels by stash SHA
+    stash_paths: HashMap<String, Vec<String>>, // changed files by stash SHA, for file:
+    loading_numstat: bool,                     // a worker is filling in stashes' `numstat`
+    notes_requests: u64,                       // numbers each notes load; the latest counts
+    pub grep_input: TextInput,
+    pub grep_editing: bool,
+    pub grep_pattern: String, // what the shown results were searched for
+    pub grep_hits: Option<Vec<GrepHit>>, // None while searching
+    pub grep_selected: usize,
+    pub grep_drill: bool, // the diff view shows a grep hit; Esc goes back to the results
+    pub to_branch_name: TextInput,
+    pub to_branch_message: TextInput,
+    pub to_branch_field: usize, // 0 branch name, 1 commit message, 2 drop checkbox
+    pub to_branch_drop: bool,
+    pub squash_input: TextInput,
+    pub export_format: ExportFormat,
+    pub export_context: u32, // unchanged lines around changes, for formats that take it
+    pub email_input: TextInput, // recipient; kept between mails
+    pub remote_stashes: Option<Vec<git::RemoteStash>>, // None while listing
+    pub remote_stash_selected: usize,
+    pub archive_entries: Vec<archive::Entry>,
+    pub archive_selected: usize,
+    pub lost_stashes: Option<Vec<git::LostStash>>, // None while scanning
+    pub lost_selected: usize,
+    pub prune_expire: String, // gc.pruneExpire, shown on the recover screen
+    pub clear_input: TextInput, // must read CLEAR before everything is dropped
+    pub clear_archive: bool,  // archive the stashes before clearing them
+    pub pins: HashSet<String>, // SHAs of pinned stashes
+    pub hunk_files: Vec<diff::FilePatch>,
+    pub hunk_cursor: usize,
+    pub hunk_marked: HashSet<(usize, usize)>,
+    pub hunk_stash: bool, // NewStash popup stashes the marked hunks
+    pub conflicts: Vec<String>,
+    pub conflict_selected: usize,
+    pub conflict_preview: Vec<String>,
+    pub conflict_scroll: usize,
+    pub pending_editor: Option<String>,
+    pub pending_pager: Option<(String, bool)>, // diff text for the pager; true if rendered
+    pub pending_email: Option<(String, String)>, // patch file and recipient for send-email
+    pub pending_chord: Option<char>,           // first key of a multi-key sequence
+    pub count: Option<usize>,                  // count typed before a motion, e.g. the 5 of `5j`
+    pub help_scroll: usize,
+    pub palette: bool, // the command palette is open over the current view
+    pub last_change: Option<actions::LastChange>, // what `.` repeats
+    pub palette_input: TextInput,
+    pub palette_selected: usize,
+    pub todo_path: Option<String>, // TODO buffer open in the editor
+    pub todo_plan: Vec<(String, todo::Step)>, // edited plan awaiting confirmation
+    pub tree_stash: Option<git::Stash>, // stash being browsed
+    pub tree_paths: Vec<String>,
+    pub tree_changed: HashSet<String>, // paths the stash modifies
+    pub tree_expanded: HashSet<String>,
+    pub tree_selected: usize,
+    pub tree_content: Vec<String>,
+    tree_loading: Option<(String, String)>, // stash SHA and path of the file being read
+    pub tree_scroll: usize,
+    pub worktree_status: Vec<git::StatusEntry>, // uncommitted changes shown by `w`
+    pub worktree_scroll: usize,
+    pub cleanup_queue: Vec<String>, // stale stash SHAs the cleanup wizard walks through
+    pub cleanup_pos: usize,
+    pub cleanup_stat: Vec<String>,
+    pub cleanup_done: (usize, usize, usize), // kept, exported, dropped
+    pub targets: Vec<ApplyTarget>,
+    pub target_selected: usize,
+    pub repos: Vec<repos::Repo>, // the repo switcher's choices
+    pub repo_selected: usize,
+    pub worktree_list: Vec<(git::Worktree, Option<usize>)>, // changed files; None if unreadable
+    pub worktree_selected: usize,
+    pub submodules: Vec<git::Submodule>,
+    pub submodule_selected: usize, // 0 is the superproject entry when there is one
+    pub superproject: Option<PathBuf>,
+    pub history: Vec<audit::Entry>, // newest first
+    pub history_selected: usize,
+    status_msg: Option<(String, Instant)>, // toast and when it appeared
     pub current_branch: String,
+    pub user_email: Option<String>,
+    pub clone_kind: git::CloneKind,
+    pub location: git::Location, // repository root, start subdirectory and worktree name
+    pub preview: bool,
+    pub split: u16, // list width beside the preview pane, in percent
+    pub absolute_dates: bool,
+    pub msg_width: Cell<usize>, // room the list's message column had last frame
+    pub preview_cache: HashMap<String, Vec<String>>, // stat summary keyed by stash SHA
+    preview_pending: HashSet<String>,
+    pub loading: Option<&'static str>, // what the spinner is waiting on
+    pub spinner_tick: usize,
+    pub progress: Option<(String, Arc<git::Progress>)>, // title and output of a running operation
+    view_request: Option<u64>, // load the open view is waiting for, from `view_requests`
+    view_requests: u64,        // numbers each view load, so a stale one is told apart
+    pub diff_truncated: bool,  // the diff stopped streaming in part-way (Ctrl-C)
+    view_key: Option<ViewKey>, // where to cache the view's content once loaded
+    info_sha: Option<String>,  // stash whose note and branch the cached view is waiting for
+    diff_cache: Lru<ViewKey, CachedView>,
+    stash_stamp: Option<String>, // the stash reflog as last loaded, watched for outside changes
+    watching: bool,              // a worker is reading the stash reflog
+    last_watch: Instant,
+    refreshed_at: Option<Instant>, // when the list was last refreshed
+    jobs_tx: Sender<JobResult>,
+    jobs_rx: Receiver<JobResult>,
 }
 
 impl App {
-    pub fn new() -> Result<Self> {
+    pub fn new(config: config::Config) -> Result<Self> {
         let stashes = git::list_stashes()?;
         let current_branch = git::current_branch().unwrap_or_default();
-        Ok(Self {
+        let (jobs_tx, jobs_rx) = mpsc::channel();
+        let diff_options = git::DiffOptions::from_config(&config.diff);
+        let split = config.ui.split;
+        let input = config.ui.input;
+        let absolute_dates = config.ui.dates == config::DateStyle::Absolute;
+        let mut app = Self {
+            config,
             stashes,
             selected: 0,
             mode: Mode::Normal,
-            diff_content: Vec::new(),
+            diff_content: diff::DiffModel::default(),
+            diff_title: String::new(),
+            diff_options,
+            applied: None,
+            reviewing: None,
+            comparing: None,
             diff_scroll: 0,
-            search_query: String::new(),
+            diff_hscroll: 0,
+            diff_wrap: true,
+            diff_page: Cell::new(20),
+            diff_rendered: None,
+            diff_worktree: false,
+            diff_query: TextInput::new(input),
+            diff_searching: false,
+            diff_matches: Vec::new(),
+            diff_match: 0,
+            line_ending_files: 0,
+            diff_files: Vec::new(),
+            diff_hunks: Vec::new(),
+            branch_relation: None,
+            stash_note: Vec::new(),
+            stash_details: None,
+            search_query: TextInput::new(input),
             searching: false,
-            new_stash_input: String::new(),
-            new_stash_untracked: false,
+            matcher: SkimMatcherV2::default().ignore_case(),
+            sort_order: SortOrder::Index,
+            grouped: false,
+            branch_only: false,
+            marked: HashSet::new(),
+            prune_candidates: HashSet::new(),
+            preflight: None,
+            confirm_input: String::new(),
+            preflight_sha: None,
+            collapsed: HashSet::new(),
+            new_stash_input: TextInput::new(input),
+            new_stash_scope: StashScope::Tracked,
+            rename_input: TextInput::new(input),
+            reminders: HashMap::new(),
+            annotations: HashMap::new(),
+            labels: HashMap::new(),
+            stash_paths: HashMap::new(),
+            loading_numstat: false,
+            notes_requests: 0,
+            grep_input: TextInput::new(input),
+            grep_editing: false,
+            grep_pattern: String::new(),
+            grep_hits: Some(Vec::new()),
+            grep_selected: 0,
+            grep_drill: false,
+            to_branch_name: TextInput::new(input),
+            to_branch_message: TextInput::new(input),
+            to_branch_field: 0,
+            to_branch_drop: false,
+            squash_input: TextInput::new(input),
+            export_format: ExportFormat::default(),
+            export_context: 3,
+            email_input: TextInput::new(input),
+            remote_stashes: Some(Vec::new()),
+            remote_stash_selected: 0,
+            archive_entries: Vec::new(),
+            archive_selected: 0,
+            lost_stashes: Some(Vec::new()),
+            lost_selected: 0,
+            prune_expire: String::new(),
+            clear_input: TextInput::new(input),
+            clear_archive: true,
+            pins: pins::load(),
+            hunk_files: Vec::new(),
+            hunk_cursor: 0,
+            hunk_marked: HashSet::new(),
+            hunk_stash: false,
+            conflicts: Vec::new(),
+            conflict_selected: 0,
+            conflict_preview: Vec::new(),
+            conflict_scroll: 0,
+            pending_editor: None,
+            pending_pager: None,
+            pending_email: None,
+            pending_chord: None,
+            count: None,
+            help_scroll: 0,
+            palette: false,
+            last_change: None,
+            palette_input: TextInput::new(input),
+            palette_selected: 0,
+            todo_path: None,
+            todo_plan: Vec::new(),
+            tree_stash: None,
+            tree_paths: Vec::new(),
+            tree_changed: HashSet::new(),
+            tree_expanded: HashSet::new(),
+            tree_selected: 0,
+            tree_content: Vec::new(),
+            tree_loading: None,
+            tree_scroll: 0,
+            worktree_status: Vec::new(),
+            worktree_scroll: 0,
+            cleanup_queue: Vec::new(),
+            cleanup_pos: 0,
+            cleanup_stat: Vec::new(),
+            cleanup_done: (0, 0, 0),
+            targets: Vec::new(),
+            target_selected: 0,
+            repos: Vec::new(),
+            repo_selected: 0,
+            worktree_list: Vec::new(),
+            worktree_selected: 0,
+            submodules: Vec::new(),
+            submodule_selected: 0,
+            superproject: None,
+            history: Vec::new(),
+            history_selected: 0,
             status_msg: None,
             current_branch,
+            user_email: git::user_email(),
+            clone_kind: git::clone_kind(),
+            location: git::location(),
+            preview: false,
+            split,
+            absolute_dates,
+            msg_width: Cell::new(0),
+            preview_cache: HashMap::new(),
+            preview_pending: HashSet::new(),
+            loading: None,
+            spinner_tick: 0,
+            progress: None,
+            view_request: None,
+            view_requests: 0,
+            diff_truncated: false,
+            view_key: None,
+            info_sha: None,
+            diff_cache: Lru::new(DIFF_CACHE_SIZE),
+            stash_stamp: git::stash_stamp(),
+            watching: false,
+            last_watch: Instant::now(),
+            refreshed_at: None,
+            jobs_tx,
+            jobs_rx,
+        };
+        app.load_notes();
+        app.ensure_numstat();
+        app.restore_state(state::load());
+        if app.stashes.is_empty() && app.config.ui.onboarding {
+            // The getting-started panel suggests stashing what's changed
+            let _ = app.load_worktree_status();
+        }
+        Ok(app)
+    }
+
+    /// Put back the selection, search, sort and view the last session in this
+    /// repository ended with
+    fn restore_state(&mut self, saved: state::State) {
+        self.search_query.set(saved.search);
+        self.sort_order = saved.sort.unwrap_or(self.sort_order);
+        self.branch_only = saved.branch_only;
+        self.preview = saved.preview;
+        self.split = saved.split.unwrap_or(self.split);
+        self.ensure_stash_paths();
+
+        let Some(sha) = saved.selected else {
+            return;
+        };
+        let Some(i) = self
+            .visible_rows()
+            .iter()
+            .position(|row| matches!(row, ListRow::Stash(s) if s.sha == sha))
+        else {
+            return;
+        };
+        self.selected = i;
+        match saved.view {
+            state::View::Diff => self.open_view(Mode::Diff),
+            state::View::Files => self.open_view(Mode::Files),
+            state::View::List => {}
+        }
+    }
+
+    /// Save what `restore_state` puts back at the next launch
+    pub fn save_state(&self) -> Result<()> {
+        // Reviews, comparisons and grep results can't be reopened from a SHA
+        let plain_view = self.reviewing.is_none() && self.comparing.is_none() && !self.grep_drill;
+        let view = match self.mode {
+            Mode::Diff if plain_view => state::View::Diff,
+            Mode::Files if plain_view => state::View::Files,
+            _ => state::View::List,
+        };
+        state::save(&state::State {
+            selected: self.selected_stash().map(|s| s.sha.clone()),
+            search: self.search_query.to_string(),
+            sort: Some(self.sort_order),
+            branch_only: self.branch_only,
+            preview: self.preview,
+            split: (self.split != self.config.ui.split).then_some(self.split),
+            view,
         })
     }
 
     pub fn reload(&mut self) -> Result<()> {
         self.stashes = git::list_stashes()?;
         self.current_branch = git::current_branch().unwrap_or_default();
-        if self.selected >= self.stashes.len() && !self.stashes.is_empty() {
-            self.selected = self.stashes.len() - 1;
+        self.load_notes();
+        self.pins = pins::load();
+        self.ensure_numstat();
+        self.ensure_stash_paths();
+        if self.stashes.is_empty() && self.config.ui.onboarding {
+            let _ = self.load_worktree_status();
+        }
+        let shas: HashSet<&str> = self.stashes.iter().map(|s| s.sha.as_str()).collect();
+        self.marked.retain(|sha| shas.contains(sha.as_str()));
+        self.diff_cache
+            .retain(|key| shas.contains(key.sha.as_str()));
+        let len = self.visible_rows().len();
+        if self.selected >= len && len > 0 {
+            self.selected = len - 1;
         }
+        self.stash_stamp = git::stash_stamp();
+        Ok(())
+    }
+
+    /// Reload, keeping the cursor on the same stash (by SHA, since indices may
+    /// have shifted) if it still exists. The header notes the refresh briefly.
+    pub fn refresh(&mut self) -> Result<()> {
+        let sha = self.selected_stash().map(|s| s.sha.clone());
+        self.reload()?;
+        self.refreshed_at = Some(Instant::now());
+        self.keep_selection(sha);
         Ok(())
     }
 
+    /// Put the cursor back on the stash with `sha` after the rows changed, or
+    /// keep it within the list if that stash is gone or filtered out
+    fn keep_selection(&mut self, sha: Option<String>) {
+        let rows = self.visible_rows();
+        let found = sha.and_then(|sha| {
+            rows.iter()
+                .position(|row| matches!(row, ListRow::Stash(s) if s.sha == sha))
+        });
+        let len = rows.len();
+        self.selected = found.unwrap_or(self.selected.min(len.saturating_sub(1)));
+    }
+
+    /// Reload the list when another process creates or drops a stash. Reads the
+    /// stash reflog on a worker about once a second, and holds off while
+    /// anything but the list is open so nothing shifts under a prompt or view.
+    pub fn watch_stash_list(&mut self) {
+        if self.mode != Mode::Normal || self.watching || self.last_watch.elapsed() < WATCH_INTERVAL
+        {
+            return;
+        }
+        self.last_watch = Instant::now();
+        self.watching = true;
+        self.spawn(|| JobResult::StashStamp(git::stash_stamp()));
+    }
+
+    /// Show a routine success as a toast instead of a popup: the list stays
+    /// usable and the message goes away by itself
+    pub fn toast(&mut self, msg: impl Into<String>) {
+        self.status_msg = Some((msg.into(), Instant::now()));
+        self.mode = Mode::Normal;
+    }
+
+    /// Toast a successful outcome, or keep a popup for an error
+    pub fn report(&mut self, result: Result<String>) {
+        match result {
+            Ok(msg) => self.toast(msg),
+            Err(e) => self.mode = Mode::Message(format!("Error: {e:#}")),
+        }
+    }
+
+    /// Report a hook that failed or timed out, once nothing else is showing
+    pub fn poll_hooks(&mut self) {
+        if self.mode == Mode::Normal
+            && let Some(failure) = hooks::take_failure()
+        {
+            self.mode = Mode::Message(format!("Warning: {failure}"));
+        }
+    }
+
+    /// Drop the toast once it has been up for a few seconds
+    pub fn expire_toast(&mut self) {
+        if self
+            .status_msg
+            .as_ref()
+            .is_some_and(|(_, at)| at.elapsed() >= TOAST_DURATION)
+        {
+            self.status_msg = None;
+        }
+    }
+
+    /// The toast currently showing, if any
+    pub fn current_toast(&self) -> Option<&str> {
+        self.status_msg.as_ref().map(|(msg, _)| msg.as_str())
+    }
+
+    /// True for a moment after the list was refreshed
+    pub fn recently_refreshed(&self) -> bool {
+        self.refreshed_at
+            .is_some_and(|at| at.elapsed() < REFRESHED_INDICATOR)
+    }
+
+    /// Stashes fuzzy-matching the search (and made on the current branch, if
+    /// that filter is on), best match first, ties kept in the current sort
+    /// order. Operations still use each stash's `name`, so the view order never
+    /// affects which stash is hit.
     pub fn filtered_stashes(&self) -> Vec<&git::Stash> {
-        if self.search_query.is_empty() {
-            self.stashes.iter().collect()
-        } else {
-            let q = self.search_query.to_lowercase();
-            self.stashes
+        let query = query::parse(&self.search_query);
+        let now = Local::now().timestamp();
+        let mut scored: Vec<(i64, &git::Stash)> = self
+            .stashes
+            .iter()
+            .filter(|s| !self.branch_only || s.branch == self.current_branch)
+            .filter_map(|s| Some((self.match_score(&query, now, s)?, s)))
+            .collect();
+
+        match self.sort_order {
+            SortOrder::Index => {}
+            SortOrder::Newest => scored.sort_by_key(|(_, s)| std::cmp::Reverse(s.timestamp)),
+            SortOrder::Oldest => scored.sort_by_key(|(_, s)| s.timestamp),
+            SortOrder::Branch => scored.sort_by(|(_, a), (_, b)| a.branch.cmp(&b.branch)),
+            SortOrder::Files => {
+                scored.sort_by_key(|(_, s)| std::cmp::Reverse(s.files_changed.unwrap_or(0)))
+            }
+            SortOrder::Size => scored.sort_by_key(|(_, s)| {
+                let (ins, del) = s.line_counts().unwrap_or((0, 0));
+                std::cmp::Reverse(ins + del)
+            }),
+        }
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored.into_iter().map(|(_, s)| s).collect()
+    }
+
+    /// Best fuzzy score of the search's free text against a stash's message,
+    /// branch or note; `None` if none matches or a filter rules the stash out.
+    /// Everything matches an empty search.
+    fn match_score(&self, query: &query::Query, now: i64, stash: &git::Stash) -> Option<i64> {
+        if query.is_empty() {
+            return Some(0);
+        }
+        let ctx = query::Context {
+            labels: self
+                .labels
+                .get(&stash.sha)
+                .map(Vec::as_slice)
+                .unwrap_or_default(),
+            paths: self.stash_paths.get(&stash.sha).map(Vec::as_slice),
+            user_email: self.user_email.as_deref(),
+            now,
+        };
+        if !query.matches(stash, &ctx) {
+            return None;
+        }
+        if query.text.is_empty() {
+            return Some(0);
+        }
+        let note = self.annotations.get(&stash.sha);
+        [Some(&stash.short_msg), Some(&stash.branch), note]
+            .into_iter()
+            .flatten()
+            .filter_map(|t| self.matcher.fuzzy_match(t, &query.text))
+            .max()
+    }
+
+    /// Fuzzy score of `pattern` against `text`, as the search uses
+    pub fn fuzzy_score(&self, text: &str, pattern: &str) -> Option<i64> {
+        self.matcher.fuzzy_match(text, pattern)
+    }
+
+    /// Character positions in `text` matched by the search, for highlighting
+    pub fn search_matches(&self, text: &str) -> HashSet<usize> {
+        let query = query::parse(&self.search_query);
+        if query.text.is_empty() {
+            return HashSet::new();
+        }
+        self.matcher
+            .fuzzy_indices(text, &query.text)
+            .map(|(_, indices)| indices.into_iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// Load each stash's changed files once a `file:` filter needs them, from
+    /// its numstat; stashes the list didn't bring one for wait on `load_numstat`
+    pub fn ensure_stash_paths(&mut self) {
+        if !query::parse(&self.search_query).needs_paths() {
+            return;
+        }
+        let mut missing = false;
+        for stash in &self.stashes {
+            if self.stash_paths.contains_key(&stash.sha) {
+                continue;
+            }
+            match &stash.numstat {
+                Some(files) => {
+                    let paths = files.iter().map(|f| f.path.clone()).collect();
+                    self.stash_paths.insert(stash.sha.clone(), paths);
+                }
+                None => missing = true,
+            }
+        }
+        if missing {
+            self.load_numstat();
+        }
+    }
+
+    /// Cycle to the next sort order, fetching per-stash stats if needed
+    pub fn cycle_sort(&mut self) {
+        self.sort_order = self.sort_order.next();
+        self.ensure_numstat();
+        self.selected = 0;
+    }
+
+    /// Fill in `numstat` for stashes the list didn't bring it for (the libgit2
+    /// backend), once the size column or a size sort needs it
+    pub fn ensure_numstat(&mut self) {
+        let needed = matches!(self.sort_order, SortOrder::Files | SortOrder::Size)
+            || self
+                .config
+                .ui
+                .columns
                 .iter()
-                .filter(|s| {
-                    s.short_msg.to_lowercase().contains(&q)
-                        || s.branch.to_lowercase().contains(&q)
+                .any(|c| c.column == config::Column::Size);
+        if needed {
+            self.load_numstat();
+        }
+    }
+
+    /// Read the numstat of every stash that lacks one on a worker thread. A
+    /// stash git can't show gets an empty one rather than failing the list.
+    fn load_numstat(&mut self) {
+        let missing: Vec<(String, String)> = self
+            .stashes
+            .iter()
+            .filter(|s| s.numstat.is_none())
+            .map(|s| (s.sha.clone(), s.name.clone()))
+            .collect();
+        if missing.is_empty() || self.loading_numstat {
+            return;
+        }
+        self.loading_numstat = true;
+        self.spawn(move || {
+            let stats = missing
+                .into_iter()
+                .map(|(sha, name)| {
+                    let files = git::stash_numstat(&name).unwrap_or_default();
+                    (sha, name, files)
                 })
-                .collect()
+                .collect();
+            JobResult::Numstat(stats)
+        });
+    }
+
+    /// Read every gsm note on a worker thread, for the annotations, reminders
+    /// and labels they hold. A load still running is superseded, so notes
+    /// written meanwhile aren't overwritten by what it read before.
+    fn load_notes(&mut self) {
+        self.notes_requests += 1;
+        let request = self.notes_requests;
+        self.spawn(move || JobResult::Notes {
+            request,
+            notes: git::notes(),
+        });
+    }
+
+    /// Rows of the list view: flat, or grouped under collapsible branch headings.
+    /// `selected` indexes into these rows.
+    pub fn visible_rows(&self) -> Vec<ListRow<'_>> {
+        let filtered = self.filtered_stashes();
+        if !self.grouped {
+            return filtered.into_iter().map(ListRow::Stash).collect();
+        }
+
+        // Groups appear in order of their first stash, so sorting still applies
+        let mut branches: Vec<&str> = Vec::new();
+        for stash in &filtered {
+            if !branches.contains(&stash.branch.as_str()) {
+                branches.push(&stash.branch);
+            }
         }
+
+        let mut rows = Vec::new();
+        for branch in branches {
+            let members: Vec<&git::Stash> = filtered
+                .iter()
+                .filter(|s| s.branch == branch)
+                .copied()
+                .collect();
+            let collapsed = self.collapsed.contains(branch);
+            rows.push(ListRow::Group {
+                branch,
+                count: members.len(),
+                collapsed,
+            });
+            if !collapsed {
+                rows.extend(members.into_iter().map(ListRow::Stash));
+            }
+        }
+        rows
     }
 
     pub fn selected_stash(&self) -> Option<&git::Stash> {
-        let filtered = self.filtered_stashes();
-        filtered.get(self.selected).copied()
+        match self.visible_rows().get(self.selected) {
+            Some(ListRow::Stash(stash)) => Some(stash),
+            _ => None,
+        }
     }
 
-    pub fn load_diff(&mut self) -> Result<()> {
-        if let Some(stash) = self.selected_stash() {
-            let raw = git::stash_diff(&stash.name)?;
-            self.diff_content = raw.lines().map(|l| l.to_string()).collect();
-            self.diff_scroll = 0;
+    /// Branch of the heading under the cursor, if any
+    pub fn selected_group(&self) -> Option<String> {
+        match self.visible_rows().get(self.selected) {
+            Some(ListRow::Group { branch, .. }) => Some(branch.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Mark or unmark the stash under the cursor for bulk operations
+    pub fn toggle_mark(&mut self) {
+        if let Some(sha) = self.selected_stash().map(|s| s.sha.clone())
+            && !self.marked.remove(&sha)
+        {
+            self.marked.insert(sha);
+        }
+    }
+
+    /// Marked stashes in stash-list order
+    pub fn marked_stashes(&self) -> Vec<&git::Stash> {
+        self.stashes
+            .iter()
+            .filter(|s| self.marked.contains(&s.sha))
+            .collect()
+    }
+
+    /// Stashes a bulk action applies to: the marked set, or the selection if nothing is marked
+    pub fn bulk_targets(&self) -> Vec<&git::Stash> {
+        if self.marked.is_empty() {
+            self.selected_stash().into_iter().collect()
+        } else {
+            self.marked_stashes()
+        }
+    }
+
+    /// Stashes a confirmed action could remove or rewrite; empty for actions
+    /// that leave the stash list alone
+    pub fn affected_stashes(&self, action: &ConfirmAction) -> Vec<&git::Stash> {
+        match action {
+            ConfirmAction::Drop | ConfirmAction::DropPinned | ConfirmAction::Pop => {
+                self.selected_stash().into_iter().collect()
+            }
+            ConfirmAction::DropMarked => self
+                .marked_stashes()
+                .into_iter()
+                .filter(|s| !self.is_pinned(s))
+                .collect(),
+            ConfirmAction::PruneMerged => self.prune_stashes(),
+            ConfirmAction::RunTodo => {
+                // A rename re-stores every stash newer than the one renamed
+                let renamed = self
+                    .todo_plan
+                    .iter()
+                    .filter(|(_, step)| matches!(step, todo::Step::Rename(_)))
+                    .filter_map(|(sha, _)| self.stash_by_sha(sha))
+                    .map(|s| s.index)
+                    .max();
+                self.stashes
+                    .iter()
+                    .filter(|s| {
+                        renamed.is_some_and(|last| s.index <= last)
+                            || (!self.is_pinned(s)
+                                && self
+                                    .todo_plan
+                                    .iter()
+                                    .any(|(sha, step)| *sha == s.sha && *step == todo::Step::Drop))
+                    })
+                    .collect()
+            }
+            // Renaming drops and re-stores the stash and every newer one
+            ConfirmAction::Rename => match self.selected_stash() {
+                Some(target) => self
+                    .stashes
+                    .iter()
+                    .filter(|s| s.index <= target.index)
+                    .collect(),
+                None => Vec::new(),
+            },
+            ConfirmAction::Apply | ConfirmAction::ApplyToBranch => Vec::new(),
+        }
+    }
+
+    /// How `[confirm]` says to guard `action`; bulk actions always ask
+    pub fn confirm_policy(&self, action: &ConfirmAction) -> config::ConfirmPolicy {
+        let confirm = &self.config.confirm;
+        match action {
+            ConfirmAction::Apply => confirm.apply,
+            ConfirmAction::Pop => confirm.pop,
+            ConfirmAction::Drop | ConfirmAction::DropPinned => confirm.drop,
+            _ => config::ConfirmPolicy::Always,
+        }
+    }
+
+    /// Prompt before `action`, or run it straight away if the config says it
+    /// needs no confirmation. Dropping a pinned stash is always asked about.
+    pub fn ask(&mut self, action: ConfirmAction) -> Result<()> {
+        let prompt = match self.confirm_policy(&action) {
+            config::ConfirmPolicy::Never => false,
+            // A pop removes the stash, so only a plain apply can skip asking
+            config::ConfirmPolicy::DestructiveOnly => match action {
+                ConfirmAction::Apply => {
+                    let root = self.location.root.to_string_lossy().into_owned();
+                    !git::is_clean_in(&root).unwrap_or(false)
+                }
+                _ => true,
+            },
+            config::ConfirmPolicy::Always | config::ConfirmPolicy::TypeIndex => true,
+        };
+        if prompt {
+            self.mode = Mode::Confirm(action);
+        } else if action == ConfirmAction::Drop
+            && self.selected_stash().is_some_and(|s| self.is_pinned(s))
+        {
+            self.mode = Mode::Confirm(ConfirmAction::DropPinned);
+        } else {
+            self.run_confirmed(action)?;
         }
         Ok(())
     }
 
-    pub fn load_files(&mut self) -> Result<()> {
-        if let Some(stash) = self.selected_stash() {
-            let raw = git::stash_files(&stash.name)?;
-            self.diff_content = raw.lines().map(|l| l.to_string()).collect();
-            self.diff_scroll = 0;
+    /// Carry out a confirmed action and show its outcome. Stashes it could
+    /// remove are journaled until it has finished.
+    pub fn run_confirmed(&mut self, action: ConfirmAction) -> Result<()> {
+        let affected: Vec<git::Stash> = self
+            .affected_stashes(&action)
+            .into_iter()
+            .cloned()
+            .collect();
+        if !affected.is_empty()
+            && let Err(e) = journal::begin(action.label(), &affected.iter().collect::<Vec<_>>())
+        {
+            self.mode = Mode::Message(format!("Error: {e}"));
+            return Ok(());
+        }
+
+        let result = match action {
+            ConfirmAction::DropMarked => self.drop_marked(),
+            ConfirmAction::PruneMerged => self.prune_merged(),
+            ConfirmAction::RunTodo => self.run_todo(),
+            ConfirmAction::Rename => self.rename_selected(),
+            ConfirmAction::ApplyToBranch => self.apply_to_target(),
+            _ => match self.selected_stash().cloned() {
+                None => Err(anyhow::anyhow!("No stash selected")),
+                Some(stash) => match action {
+                    ConfirmAction::Apply | ConfirmAction::Pop => {
+                        // Finished by `poll_jobs`, which also closes the journal
+                        self.start_apply(stash, action == ConfirmAction::Pop);
+                        return Ok(());
+                    }
+                    // A pinned stash gets here only through its own confirmation
+                    _ => self
+                        .archive_and_drop(&HashSet::from([stash.sha.clone()]))
+                        .and_then(|_| self.unpin(&stash.sha))
+                        .map(|_| "Stash dropped.".to_string()),
+                },
+            },
+        };
+        // Close the journal before anything that can fail on the way out
+        let closed = if affected.is_empty() {
+            Ok(())
+        } else {
+            journal::finish()
+        };
+        self.finish_operation(&action, result)?;
+        closed
+    }
+
+    /// Reload and report the outcome of a confirmed operation; a failed apply
+    /// or pop that left conflicts opens the conflict screen instead
+    fn finish_operation(&mut self, action: &ConfirmAction, result: Result<String>) -> Result<()> {
+        match result {
+            Ok(msg) => {
+                self.reload()?;
+                // An apply that changed files keeps its popup for the `d` follow-up
+                if self.applied.is_some() {
+                    self.mode = Mode::Message(msg);
+                } else {
+                    self.toast(msg);
+                }
+            }
+            Err(_)
+                if matches!(action, ConfirmAction::Apply | ConfirmAction::Pop)
+                    && self.load_conflicts()? =>
+            {
+                // A failed pop keeps the stash, so the list is unchanged
+                self.mode = Mode::Conflict;
+            }
+            Err(e) if git::was_cancelled(&e) => {
+                self.reload()?;
+                self.mode = Mode::Message(
+                    "Cancelled; git may have changed part of the working tree.".to_string(),
+                );
+            }
+            Err(e) => {
+                self.reload()?;
+                self.mode = Mode::Message(format!("Error: {e}"));
+            }
         }
         Ok(())
     }
 
-    pub fn move_up(&mut self) {
-        if self.selected > 0 {
-            self.selected -= 1;
+    /// Refresh the uncommitted changes shown on the status screen
+    pub fn load_worktree_status(&mut self) -> Result<()> {
+        self.worktree_status = git::status()?;
+        self.worktree_scroll = self
+            .worktree_scroll
+            .min(self.worktree_status.len().saturating_sub(1));
+        Ok(())
+    }
+
+    /// Open the tree browser on the selected stash's full snapshot
+    pub fn open_tree(&mut self) {
+        let Some(stash) = self.selected_stash().cloned() else {
+            return;
+        };
+        let name = stash.name.clone();
+        let sha = stash.sha.clone();
+        self.tree_stash = Some(stash);
+        self.loading = Some(TREE_LOADING);
+        self.spawn(move || {
+            let result = git::stash_tree(&name).and_then(|paths| {
+                let changed = git::stash_numstat(&name)?;
+                Ok((paths, changed.into_iter().map(|f| f.path).collect()))
+            });
+            JobResult::Tree { sha, result }
+        });
+    }
+
+    /// Show a stash's tree once `open_tree`'s worker has listed it
+    fn show_tree(&mut self, paths: Vec<String>, changed: HashSet<String>) {
+        self.tree_paths = paths;
+        self.tree_changed = changed;
+        // Start with the directories leading to changed files open
+        self.tree_expanded = self
+            .tree_changed
+            .iter()
+            .flat_map(|p| {
+                let parts: Vec<&str> = p.split('/').collect();
+                (1..parts.len())
+                    .map(|n| parts[..n].join("/"))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        self.tree_selected = 0;
+        self.load_tree_file();
+        self.mode = Mode::Tree;
+    }
+
+    /// Visible rows of the tree: directories are listed once, and the
+    /// contents of collapsed directories are hidden
+    pub fn tree_rows(&self) -> Vec<TreeRow> {
+        let mut rows = Vec::new();
+        let mut seen_dirs: HashSet<String> = HashSet::new();
+
+        let mut paths: Vec<&String> = self.tree_paths.iter().collect();
+        // Sort by components so a directory's contents stay together
+        paths.sort_by(|a, b| a.split('/').cmp(b.split('/')));
+
+        for path in paths {
+            let parts: Vec<&str> = path.split('/').collect();
+            let mut visible = true;
+            for depth in 0..parts.len() {
+                let prefix = parts[..=depth].join("/");
+                let is_dir = depth + 1 < parts.len();
+                if !visible {
+                    break;
+                }
+                if is_dir {
+                    let expanded = self.tree_expanded.contains(&prefix);
+                    if seen_dirs.insert(prefix.clone()) {
+                        rows.push(TreeRow {
+                            path: prefix,
+                            depth,
+                            is_dir: true,
+                            expanded,
+                        });
+                    }
+                    visible = expanded;
+                } else {
+                    rows.push(TreeRow {
+                        path: prefix,
+                        depth,
+                        is_dir: false,
+                        expanded: false,
+                    });
+                }
+            }
         }
+        rows
     }
 
-    pub fn move_down(&mut self) {
-        let len = self.filtered_stashes().len();
-        if len > 0 && self.selected < len - 1 {
-            self.selected += 1;
+    /// Expand/collapse the directory under the cursor
+    pub fn toggle_tree_dir(&mut self) {
+        if let Some(row) = self.tree_rows().get(self.tree_selected)
+            && row.is_dir
+            && !self.tree_expanded.remove(&row.path)
+        {
+            self.tree_expanded.insert(row.path.clone());
         }
     }
 
-    pub fn scroll_diff_up(&mut self) {
-        if self.diff_scroll > 0 {
-            self.diff_scroll -= 1;
+    /// Collapse the directory under the cursor, or the one containing it
+    pub fn collapse_tree_dir(&mut self) {
+        let rows = self.tree_rows();
+        let Some(row) = rows.get(self.tree_selected) else {
+            return;
+        };
+        let dir = if row.is_dir && row.expanded {
+            row.path.clone()
+        } else {
+            match row.path.rsplit_once('/') {
+                Some((parent, _)) => parent.to_string(),
+                None => return,
+            }
+        };
+        self.tree_expanded.remove(&dir);
+        if let Some(i) = self.tree_rows().iter().position(|r| r.path == dir) {
+            self.tree_selected = i;
         }
+        self.load_tree_file();
     }
 
-    pub fn scroll_diff_down(&mut self) {
-        if self.diff_scroll + 1 < self.diff_content.len() {
-            self.diff_scroll += 1;
+    pub fn move_tree(&mut self, down: bool) {
+        let len = self.tree_rows().len();
+        if down && self.tree_selected + 1 < len {
+            self.tree_selected += 1;
+        } else if !down && self.tree_selected > 0 {
+            self.tree_selected -= 1;
         }
+        self.load_tree_file();
     }
-}
 
-pub fn run<B: Backend>(terminal: &mut Terminal<B>) -> Result<()> {
-    let mut app = App::new()?;
+    /// Show the file under the cursor as stored in the stash
+    fn load_tree_file(&mut self) {
+        self.tree_content.clear();
+        self.tree_loading = None;
+        self.tree_scroll = 0;
+        let row = self.tree_rows().get(self.tree_selected).cloned();
+        let (Some(stash), Some(row)) = (&self.tree_stash, row) else {
+            return;
+        };
+        if row.is_dir {
+            return;
+        }
 
-    loop {
-        terminal.draw(|f| ui::render(f, &app))?;
+        // Read on a worker, and render there with a configured command for
+        // this file type since that can be slow; else show the plain text
+        let command =
+            preview::command_for(&self.config.preview.commands, &row.path).map(str::to_string);
+        if let Some(command) = &command {
+            self.tree_content = vec![format!("(rendering with {command}…)")];
+        }
+        let name = stash.name.clone();
+        let key = (stash.sha.clone(), row.path);
+        self.tree_loading = Some(key.clone());
+        self.spawn(move || {
+            let lines = match (git::stash_file(&name, &key.1), command) {
+                (Err(e), _) => vec![format!("Error: {e}")],
+                (Ok(content), None) => plain_lines(&content, Vec::new()),
+                (Ok(content), Some(command)) => match preview::render(&command, &key.1, &content) {
+                    Ok(text) => text.lines().map(|l| l.to_string()).collect(),
+                    Err(e) => {
+                        let note = format!("(preview failed: {e:#}; showing plain text)");
+                        plain_lines(&content, vec![note])
+                    }
+                },
+            };
+            JobResult::TreeFile { key, lines }
+        });
+    }
 
-        if events::handle_events(&mut app)? {
-            break;
+    /// True if the stash is older than the configured `stale_days`
+    pub fn is_stale(&self, stash: &git::Stash) -> bool {
+        let days = self.config.stash.stale_days;
+        days > 0 && Local::now().timestamp() - stash.timestamp > i64::from(days) * 86_400
+    }
+
+    /// How far along from fresh (0.0) to old (1.0) the stash is, on a log
+    /// scale between `heat_fresh_days` and `heat_old_days`, so the first weeks
+    /// still show a change; `None` with the heat-map off
+    pub fn stash_heat(&self, stash: &git::Stash) -> Option<f64> {
+        let ui = &self.config.ui;
+        if !ui.heatmap {
+            return None;
         }
+        let days = (Local::now().timestamp() - stash.timestamp).max(0) as f64 / 86_400.0;
+        let fresh = f64::from(ui.heat_fresh_days.max(1));
+        let old = f64::from(ui.heat_old_days).max(fresh + 1.0);
+        if days <= fresh {
+            return Some(0.0);
+        }
+        Some(((days.ln() - fresh.ln()) / (old.ln() - fresh.ln())).min(1.0))
     }
 
-    Ok(())
-}
\ No newline at end of file
+    /// True once the stash's reminder date has arrived
+    pub fn is_due(&self, stash: &git::Stash) -> bool {
+        self.reminders
+            .get(&stash.sha)
+            .is_some_and(|date| *date <= Local::now().date_naive())
+    }
+
+    /// "You wanted to revisit…" banner listing every stash whose reminder is due
+    pub fn reminder_banner(&self) -> Option<String> {
+        let due: Vec<String> = self
+            .stashes
+            .iter()
+            .filter(|s| self.is_due(s))
+            .map(|s| {
+                format!(
+                    "  {}  {}  (since {})",
+                    s.name, s.short_msg, self.reminders[&s.sha]
+                )
+            })
+            .collect();
+        if due.is_empty() {
+            return None;
+        }
+        let which = if due.len() == 1 {
+            "this stash"
+        } else {
+            "these stashes"
+        };
+        Some(format!(
+            "Reminder: you wanted to revisit {which}:\n\n{}\n\n\
+             Press m on a stash to move or clear its reminder.",
+            due.join("\n")
+        ))
+    }
+
+    /// Set one field of a stash's note, reading the notes afresh after it
+    fn set_note_field(&mut self, sha: &str, key: &str, value: Option<&str>) -> Result<()> {
+        let result = git::set_note_field(sha, key, value);
+        self.load_notes();
+        result
+    }
+
+    /// Store `input` as the selected stash's reminder; empty clears it
+    pub fn set_reminder(&mut self, input: &str) -> Result<String> {
+        let Some(stash) = self.selected_stash().cloned() else {
+            bail!("No stash selected");
+        };
+        let input = input.trim();
+        if input.is_empty() {
+            self.set_note_field(&stash.sha, REMIND_KEY, None)?;
+            self.reminders.remove(&stash.sha);
+            return Ok(format!("Cleared the reminder on {}.", stash.name));
+        }
+
+        let date = parse_remind_date(input)?;
+        self.set_note_field(&stash.sha, REMIND_KEY, Some(&date.to_string()))?;
+        self.reminders.insert(stash.sha.clone(), date);
+        Ok(format!("Will remind you about {} on {date}.", stash.name))
+    }
+
+    /// Store `input` as the selected stash's note; empty clears it
+    pub fn set_annotation(&mut self, input: &str) -> Result<String> {
+        let Some(stash) = self.selected_stash().cloned() else {
+            bail!("No stash selected");
+        };
+        let input = input.trim().to_string();
+        if input.is_empty() {
+            self.set_note_field(&stash.sha, ANNOTATION_KEY, None)?;
+            self.annotations.remove(&stash.sha);
+            return Ok(format!("Cleared the note on {}.", stash.name));
+        }
+
+        self.set_note_field(&stash.sha, ANNOTATION_KEY, Some(&input))?;
+        self.annotations.insert(stash.sha.clone(), input);
+        Ok(format!("Saved the note on {}.", stash.name))
+    }
+
+    /// Store the comma-separated `input` as the selected stash's labels;
+    /// empty clears them
+    pub fn set_labels(&mut self, input: &str) -> Result<String> {
+        let Some(stash) = self.selected_stash().cloned() else {
+            bail!("No stash selected");
+        };
+        let mut labels: Vec<String> = Vec::new();
+        for label in input.split(',').map(str::trim) {
+            if label.contains(char::is_whitespace) {
+                bail!("Labels can't contain spaces: '{label}'");
+            }
+            if !label.is_empty() && !labels.iter().any(|l| l == label) {
+                labels.push(label.to_string());
+            }
+        }
+        if labels.is_empty() {
+            self.set_note_field(&stash.sha, LABELS_KEY, None)?;
+            self.labels.remove(&stash.sha);
+            return Ok(format!("Cleared the labels on {}.", stash.name));
+        }
+
+        self.set_note_field(&stash.sha, LABELS_KEY, Some(&labels.join(",")))?;
+        let msg = format!("Labelled {}: {}.", stash.name, labels.join(", "));
+        self.labels.insert(stash.sha.clone(), labels);
+        Ok(msg)
+    }
+
+    /// Every label in use, sorted
+    pub fn known_labels(&self) -> Vec<&str> {
+        let mut all: Vec<&str> = self.labels.values().flatten().map(String::as_str).collect();
+        all.sort_unstable();
+        all.dedup();
+        all
+    }
+
+    /// Start the cleanup wizard over every stale stash, oldest first.
+    /// Returns false if there is nothing stale.
+    pub fn start_cleanup(&mut self) -> bool {
+        let mut stale: Vec<&git::Stash> = self
+            .stashes
+            .iter()
+            .filter(|s| self.is_stale(s) && !self.is_pinned(s))
+            .collect();
+        stale.sort_by_key(|s| s.timestamp);
+        self.cleanup_queue = stale.into_iter().map(|s| s.sha.clone()).collect();
+        self.cleanup_pos = 0;
+        self.cleanup_done = (0, 0, 0);
+        self.load_cleanup_stat();
+        !self.cleanup_queue.is_empty()
+    }
+
+    /// The stale stash the wizard is currently asking about
+    pub fn cleanup_stash(&self) -> Option<&git::Stash> {
+        self.stash_by_sha(self.cleanup_queue.get(self.cleanup_pos)?)
+    }
+
+    fn load_cleanup_stat(&mut self) {
+        self.cleanup_stat = match self.cleanup_stash().map(|s| git::stash_files(&s.name)) {
+            Some(Ok(raw)) => raw.lines().map(|l| l.to_string()).collect(),
+            Some(Err(e)) => vec![format!("Error: {e}")],
+            None => Vec::new(),
+        };
+    }
+
+    /// Keep, export or drop the current stale stash, then move to the next.
+    /// Returns false once the queue is exhausted.
+    pub fn cleanup_step(&mut self, step: Option<CleanupStep>) -> Result<bool> {
+        let Some(stash) = self.cleanup_stash().cloned() else {
+            return Ok(false);
+        };
+        match step {
+            None => self.cleanup_done.0 += 1,
+            Some(CleanupStep::Export) => {
+                export_stash(&stash, &self.diff_options, ExportFormat::Patch)?;
+                self.cleanup_done.1 += 1;
+            }
+            Some(CleanupStep::Drop) => {
+                journal::begin(ConfirmAction::Drop.label(), &[&stash])?;
+                let result = self.drop_shas(&HashSet::from([stash.sha.clone()]));
+                let closed = journal::finish();
+                result?;
+                // Indices shifted; names in the queue are re-resolved by SHA
+                self.reload()?;
+                closed?;
+                self.cleanup_done.2 += 1;
+            }
+        }
+        self.cleanup_pos += 1;
+        self.load_cleanup_stat();
+        Ok(self.cleanup_pos < self.cleanup_queue.len())
+    }
+
+    /// Summary shown when the wizard finishes or is left early
+    pub fn cleanup_summary(&self) -> String {
+        let (kept, exported, dropped) = self.cleanup_done;
+        format!("Cleanup: kept {kept}, exported {exported}, dropped {dropped} stale stash(es).")
+    }
+
+    /// Commit the selected stash onto a new branch named `to_branch_name`, then
+    /// drop it if asked. A pinned stash is never dropped this way.
+    pub fn stash_to_branch(&mut self) -> Result<String> {
+        let Some(stash) = self.selected_stash().cloned() else {
+            bail!("No stash selected");
+        };
+        let branch = self.to_branch_name.trim();
+        let message = self.to_branch_message.trim();
+        if branch.is_empty() || message.is_empty() {
+            bail!("Both a branch name and a commit message are needed");
+        }
+
+        let commit = git::stash_to_branch(&stash.name, branch, message)?;
+        let short = &commit[..commit.len().min(7)];
+        let done = format!("Committed {} as {short} on new branch {branch}", stash.name);
+        if !self.to_branch_drop {
+            return Ok(format!("{done}."));
+        }
+        if self.is_pinned(&stash) {
+            return Ok(format!("{done}; kept the stash because it is pinned."));
+        }
+
+        journal::begin(ConfirmAction::Drop.label(), &[&stash])?;
+        let result = drop_by_sha(&HashSet::from([stash.sha.clone()]));
+        let closed = journal::finish();
+        if let Err(e) = result {
+            bail!("{done}, but dropping the stash failed: {e}");
+        }
+        self.reload()?;
+        closed?;
+        Ok(format!("{done} and dropped the stash."))
+    }
+
+    /// The marked stashes, oldest first: the order a squash applies them in
+    pub fn squash_order(&self) -> Vec<&git::Stash> {
+        let mut stashes: Vec<&git::Stash> = self
+            .stashes
+            .iter()
+            .filter(|s| self.marked.contains(&s.sha))
+            .collect();
+        stashes.sort_by_key(|s| std::cmp::Reverse(s.index));
+        stashes
+    }
+
+    /// Replace the marked stashes with one stash holding all their changes,
+    /// messaged `squash_input`. Pinned originals are kept.
+    pub fn squash_marked(&mut self) -> Result<String> {
+        let stashes: Vec<git::Stash> = self.squash_order().into_iter().cloned().collect();
+        let Some(newest) = stashes.last() else {
+            bail!("No stashes marked");
+        };
+        if stashes.len() < 2 {
+            bail!("Mark at least two stashes with Space to squash them");
+        }
+        let message = self.squash_input.trim();
+        if message.is_empty() {
+            bail!("The squashed stash needs a message");
+        }
+
+        let shas: Vec<String> = stashes.iter().map(|s| s.sha.clone()).collect();
+        let index_kept = git::squash_stashes(&shas, &newest.branch, message)?;
+
+        let originals: Vec<&git::Stash> = stashes.iter().filter(|s| !self.is_pinned(s)).collect();
+        journal::begin("squash stashes", &originals)?;
+        let result = self.drop_shas(&shas.into_iter().collect());
+        let closed = journal::finish();
+        self.marked.clear();
+        self.reload()?;
+        closed?;
+        let kept = match result {
+            Ok((_, kept)) => kept,
+            Err(e) => bail!("Squashed into stash@{{0}}, but dropping the originals failed: {e}"),
+        };
+
+        let mut msg = format!("Squashed {} stashes into stash@{{0}}", stashes.len());
+        if kept > 0 {
+            msg.push_str(&format!("; kept {kept} pinned original(s)"));
+        }
+        if !index_kept {
+            msg.push_str("; staged changes didn't combine, so all are unstaged");
+        }
+        msg.push('.');
+        Ok(msg)
+    }
+
+    /// Drop every marked stash except pinned ones
+    pub fn drop_marked(&mut self) -> Result<String> {
+        let (dropped, kept) = self.drop_shas(&self.marked)?;
+        self.marked.clear();
+        if kept > 0 {
+            return Ok(format!("Dropped {dropped} stash(es); kept {kept} pinned."));
+        }
+        Ok(format!("Dropped {dropped} stash(es)."))
+    }
+
+    /// Drop every stash but the pinned ones, archiving them first if asked
+    /// to. Dropping them one by one keeps each in the history log.
+    pub fn clear_all(&mut self) -> Result<String> {
+        let targets: Vec<&git::Stash> =
+            self.stashes.iter().filter(|s| !self.is_pinned(s)).collect();
+        let kept = self.stashes.len() - targets.len();
+        // `drop_shas` archives them itself when `archive_on_drop` is set
+        if self.clear_archive && !self.config.stash.archive_on_drop {
+            for stash in &targets {
+                archive::save(stash).with_context(|| {
+                    format!("Failed to archive {}; nothing dropped", stash.name)
+                })?;
+            }
+        }
+        let shas: HashSet<String> = targets.iter().map(|s| s.sha.clone()).collect();
+        journal::begin("clear all stashes", &targets)?;
+        let result = self.drop_shas(&shas);
+        let closed = journal::finish();
+        self.marked.clear();
+        self.reload()?;
+        closed?;
+
+        let mut msg = format!("Cleared {} stash(es)", result?.0);
+        if self.clear_archive {
+            msg.push_str(&format!(", archived in {}", archive::dir()?.display()));
+        }
+        if kept > 0 {
+            msg.push_str(&format!("; kept {kept} pinned"));
+        }
+        msg.push('.');
+        Ok(msg)
+    }
+
+    pub fn is_pinned(&self, stash: &git::Stash) -> bool {
+        self.pins.contains(&stash.sha)
+    }
+
+    /// Pin or unpin the selected stash. Pinned stashes are skipped by bulk
+    /// drops and cleanup, and dropping one on its own asks twice.
+    pub fn toggle_pin(&mut self) -> Result<()> {
+        let Some(sha) = self.selected_stash().map(|s| s.sha.clone()) else {
+            return Ok(());
+        };
+        if !self.pins.remove(&sha) {
+            self.pins.insert(sha);
+        }
+        pins::save(&self.pins)
+    }
+
+    /// Forget the pin of a stash that has been dropped
+    pub fn unpin(&mut self, sha: &str) -> Result<()> {
+        if self.pins.remove(sha) {
+            pins::save(&self.pins)?;
+        }
+        Ok(())
+    }
+
+    /// Find stashes whose changes have all landed in HEAD since they were made.
+    /// Runs in the background; the prune prompt opens when the scan finishes.
+    pub fn find_merged(&mut self) {
+        let stashes: Vec<(String, String)> = self
+            .stashes
+            .iter()
+            .filter(|s| !self.is_pinned(s))
+            .map(|s| (s.sha.clone(), s.name.clone()))
+            .collect();
+        self.loading = Some("checking for merged stashes");
+        self.spawn(move || {
+            let mut merged = HashSet::new();
+            for (sha, name) in stashes {
+                match git::stash_is_merged(&name) {
+                    Ok(true) => {
+                        merged.insert(sha);
+                    }
+                    Ok(false) => {}
+                    Err(e) => return JobResult::Merged(Err(e)),
+                }
+            }
+            JobResult::Merged(Ok(merged))
+        });
+    }
+
+    /// Predict in the background whether applying the selected stash would
+    /// conflict; the confirm prompt shows the answer once it arrives
+    pub fn start_preflight(&mut self) {
+        self.preflight = None;
+        self.preflight_sha = None;
+        let Some(sha) = self.selected_stash().map(|s| s.sha.clone()) else {
+            return;
+        };
+        self.preflight_sha = Some(sha.clone());
+        self.spawn(move || {
+            let result = git::preflight_apply(&sha);
+            JobResult::Preflight { sha, result }
+        });
+    }
+
+    /// Forget a pending preflight check once its prompt is gone
+    pub fn cancel_preflight(&mut self) {
+        self.preflight_sha = None;
+    }
+
+    /// Stashes found by `find_merged`, in stash-list order
+    pub fn prune_stashes(&self) -> Vec<&git::Stash> {
+        self.stashes
+            .iter()
+            .filter(|s| self.prune_candidates.contains(&s.sha))
+            .collect()
+    }
+
+    /// Drop stashes by SHA, archiving each first when `archive_on_drop` is
+    /// set. Pinned stashes are kept, since each needs its own confirmation to
+    /// drop. Returns how many were dropped and how many pinned ones kept.
+    fn drop_shas(&self, shas: &HashSet<String>) -> Result<(usize, usize)> {
+        let unpinned: HashSet<String> = shas.difference(&self.pins).cloned().collect();
+        let dropped = self.archive_and_drop(&unpinned)?;
+        Ok((dropped, shas.len() - unpinned.len()))
+    }
+
+    /// `drop_shas` without sparing pinned stashes, for a drop the user
+    /// confirmed for that one stash
+    fn archive_and_drop(&self, shas: &HashSet<String>) -> Result<usize> {
+        if self.config.stash.archive_on_drop {
+            for stash in self.stashes.iter().filter(|s| shas.contains(&s.sha)) {
+                archive::save(stash).with_context(|| {
+                    format!("Failed to archive {}; nothing dropped", stash.name)
+                })?;
+            }
+        }
+        drop_by_sha(shas)
+    }
+
+    /// Copy the selected or marked stashes into the archive directory
+    pub fn archive_stashes(&self) -> Result<String> {
+        let targets = self.bulk_targets();
+        for stash in &targets {
+            archive::save(stash)?;
+        }
+        Ok(format!(
+            "Archived {} stash(es) to {}.",
+            targets.len(),
+            archive::dir()?.display()
+        ))
+    }
+
+    /// Read the archive for the browser, keeping the selection in range
+    pub fn load_archive(&mut self) -> Result<()> {
+        self.archive_entries = archive::list()?;
+        self.archive_selected = self
+            .archive_selected
+            .min(self.archive_entries.len().saturating_sub(1));
+        Ok(())
+    }
+
+    /// Restore the archived stash selected in the browser
+    pub fn restore_archived(&mut self) -> Result<String> {
+        let Some(entry) = self.archive_entries.get(self.archive_selected).cloned() else {
+            bail!("Nothing archived");
+        };
+        if self.has_stash(&entry.sha) {
+            bail!("'{}' is already in the stash list", entry.short_msg);
+        }
+        archive::restore(&entry)?;
+        self.reload()?;
+        Ok(format!("Restored '{}' as stash@{{0}}.", entry.short_msg))
+    }
+
+    /// Drop the stashes found by `find_merged`
+    pub fn prune_merged(&mut self) -> Result<String> {
+        let (dropped, _) = self.drop_shas(&self.prune_candidates)?;
+        self.prune_candidates.clear();
+        Ok(format!("Pruned {dropped} merged stash(es)."))
+    }
+
+    /// Write each bulk target as `stash-<index>-<message>.<ext>` in the chosen
+    /// format into the current directory, on a worker thread with a progress popup
+    pub fn export_stashes(&mut self) {
+        let targets: Vec<git::Stash> = self.bulk_targets().into_iter().cloned().collect();
+        let format = self.export_format;
+        let mut opts = self.diff_options.clone();
+        if format.has_context() {
+            opts.context = Some(self.export_context);
+        }
+        let progress = self.start_progress(format!("Exporting {} stash(es)", targets.len()));
+        self.spawn(move || {
+            let mut written = Vec::new();
+            let mut result = Ok(());
+            for stash in &targets {
+                if progress.is_cancelled() {
+                    result = Err(git::Cancelled.into());
+                    break;
+                }
+                match export_stash(stash, &opts, format) {
+                    Ok(file) => {
+                        let done = written.len() + 1;
+                        progress.report(format!("[{done}/{}] {file}", targets.len()));
+                        written.push(file);
+                    }
+                    Err(e) => {
+                        result = Err(e);
+                        break;
+                    }
+                }
+            }
+            JobResult::Exported { written, result }
+        });
+    }
+
+    /// Write the selected stash as a `git format-patch` mail into the current
+    /// directory, and queue it for `git send-email` if a recipient was given
+    pub fn email_stash(&mut self) -> Result<String> {
+        let Some(stash) = self.selected_stash() else {
+            bail!("No stash selected");
+        };
+        let file_name = export_file_name(stash, "patch");
+        let path = local_path(&file_name);
+        let mail = git::format_patch(&stash.name, &stash.short_msg)?;
+        fs::write(&path, mail).with_context(|| format!("Failed to write {file_name}"))?;
+        let to = self.email_input.trim();
+        if to.is_empty() {
+            return Ok(format!("Wrote {file_name}; send it with git send-email."));
+        }
+        self.pending_email = Some((path.to_string_lossy().into_owned(), to.to_string()));
+        Ok(format!("Sending {file_name} to {to}"))
+    }
+
+    /// Push the selected stash to `refs/stashes/<user>/<slug>-<sha>` on the
+    /// configured remote, on a worker thread with a progress popup
+    pub fn publish_stash(&mut self) {
+        let Some(stash) = self.selected_stash().cloned() else {
+            return;
+        };
+        let user = self
+            .user_email
+            .as_deref()
+            .and_then(|email| email.split('@').next())
+            .map(str::to_string)
+            .or_else(|| std::env::var("USER").ok())
+            .map(|user| slug(&user))
+            .filter(|user| !user.is_empty())
+            .unwrap_or_else(|| "me".to_string());
+        // The short SHA keeps two stashes with the same message apart, while
+        // publishing the same stash again lands on the same ref
+        let short: String = stash.sha.chars().take(7).collect();
+        let name = match slug(&stash.short_msg) {
+            message if message.is_empty() => format!("{user}/{short}"),
+            message => format!("{user}/{message}-{short}"),
+        };
+        let remote = self.config.stash.remote.clone();
+        self.start_progress(format!("Publishing {} to {remote}", stash.name));
+        self.spawn(move || {
+            let result = git::publish_stash(&stash.sha, &remote, &name).map(|()| {
+                let refname = format!("{}{name}", git::REMOTE_STASH_PREFIX);
+                format!("Published {} as {refname} on {remote}.", stash.name)
+            });
+            JobResult::Published(result)
+        });
+    }
+
+    /// List the stashes published on the configured remote in the background
+    pub fn load_remote_stashes(&mut self) {
+        self.remote_stashes = None;
+        self.remote_stash_selected = 0;
+        self.loading = Some("listing published stashes");
+        let remote = self.config.stash.remote.clone();
+        self.spawn(move || JobResult::RemoteStashes(git::list_remote_stashes(&remote)));
+    }
+
+    /// Fetch the selected published stash into the local stash list
+    pub fn import_remote_stash(&mut self) {
+        let Some(stash) = self
+            .remote_stashes
+            .as_ref()
+            .and_then(|list| list.get(self.remote_stash_selected))
+            .cloned()
+        else {
+            return;
+        };
+        let remote = self.config.stash.remote.clone();
+        self.start_progress(format!("Importing {} from {remote}", stash.name));
+        self.spawn(move || JobResult::RemoteImported(git::import_remote_stash(&remote, &stash)));
+    }
+
+    /// True if a published stash is already in the local stash list
+    pub fn has_stash(&self, sha: &str) -> bool {
+        self.stashes.iter().any(|s| s.sha == sha)
+    }
+
+    /// Open the selected stash's base commit on the remote's web forge
+    pub fn open_in_browser(&self) -> Result<String> {
+        let Some(stash) = self.selected_stash() else {
+            bail!("No stash selected");
+        };
+        let url = forge::base_commit_url(&self.config.forge, &stash.name)?;
+        forge::open(&url)?;
+        Ok(format!("Opened {url}"))
+    }
+
+    /// Write every stash into a TODO buffer and open it in the editor
+    pub fn start_todo(&mut self) -> Result<()> {
+        let path = git::git_path("gsm-todo")?;
+        fs::write(&path, todo::render(&self.stashes))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        // The editor runs from the top level, so hand it an absolute path
+        let path = fs::canonicalize(&path)?.to_string_lossy().to_string();
+        self.todo_path = Some(path.clone());
+        self.pending_editor = Some(path);
+        Ok(())
+    }
+
+    /// Read back the edited TODO buffer and ask to confirm the resulting plan
+    pub fn load_todo(&mut self, path: &str) -> Result<()> {
+        let text = fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?;
+        let _ = fs::remove_file(path);
+        self.todo_plan = todo::parse(&text, &self.stashes)?;
+        self.mode = if self.todo_plan.is_empty() {
+            Mode::Message("Nothing to do.".to_string())
+        } else {
+            Mode::Confirm(ConfirmAction::RunTodo)
+        };
+        Ok(())
+    }
+
+    /// Carry out the confirmed TODO plan: renames and exports first, then drops
+    pub fn run_todo(&mut self) -> Result<String> {
+        let plan = std::mem::take(&mut self.todo_plan);
+        let (mut renamed, mut exported) = (0, 0);
+
+        for (sha, step) in &plan {
+            let Some(stash) = self.stashes.iter().find(|s| s.sha == *sha) else {
+                continue;
+            };
+            match step {
+                todo::Step::Rename(message) => {
+                    git::rename_stash(sha, message)?;
+                    renamed += 1;
+                }
+                todo::Step::Export => {
+                    export_stash(stash, &self.diff_options, ExportFormat::Patch)?;
+                    exported += 1;
+                }
+                todo::Step::Drop => {}
+            }
+        }
+
+        let drops: HashSet<String> = plan
+            .into_iter()
+            .filter(|(_, step)| *step == todo::Step::Drop)
+            .map(|(sha, _)| sha)
+            .collect();
+        let (dropped, kept) = self.drop_shas(&drops)?;
+        self.marked
+            .retain(|sha| !drops.contains(sha) || self.pins.contains(sha));
+
+        let mut msg =
+            format!("Renamed {renamed}, exported {exported}, dropped {dropped} stash(es)");
+        if kept > 0 {
+            msg.push_str(&format!("; kept {kept} pinned"));
+        }
+        msg.push('.');
+        Ok(msg)
+    }
+
+    /// The stash a TODO plan entry refers to
+    pub fn stash_by_sha(&self, sha: &str) -> Option<&git::Stash> {
+        self.stashes.iter().find(|s| s.sha == sha)
+    }
+
+    /// Write a Markdown report of the marked stashes (or all of them) to `stash-report.md`
+    pub fn export_report(&self) -> Result<String> {
+        let stashes = if self.marked.is_empty() {
+            self.stashes.iter().collect()
+        } else {
+            self.marked_stashes()
+        };
+        let file_name = "stash-report.md";
+        fs::write(local_path(file_name), report::markdown(&stashes)?)
+            .with_context(|| format!("Failed to write {file_name}"))?;
+        Ok(format!(
+            "Wrote report of {} stash(es) to {file_name}",
+            stashes.len()
+        ))
+    }
+
+    /// Give the selected stash the message in `rename_input`. Its position in
+    /// the list is unchanged.
+    pub fn rename_selected(&self) -> Result<String> {
+        let Some(stash) = self.selected_stash() else {
+            bail!("No stash selected");
+        };
+        let message = self.rename_input.trim();
+        git::rename_stash(&stash.sha, message)?;
+        Ok(format!("Renamed {} to '{message}'.", stash.name))
+    }
+
+    /// Copy the selected stash's name, diff or file list to the clipboard
+    pub fn yank(&self, what: char) -> Result<String> {
+        let Some(stash) = self.selected_stash() else {
+            bail!("No stash selected");
+        };
+        let (text, label) = match what {
+            'n' => (stash.name.clone(), "name"),
+            'd' => (git::stash_diff(&stash.name, &self.diff_options)?, "diff"),
+            _ => {
+                let paths: Vec<String> = git::stash_numstat(&stash.name)?
+                    .into_iter()
+                    .map(|f| f.path)
+                    .collect();
+                (paths.join("\n"), "file list")
+            }
+        };
+        let target = clipboard::copy(&text)?;
+        Ok(format!("Copied {label} of {} to the {target}", stash.name))
+    }
+
+    pub fn toggle_grouped(&mut self) {
+        self.grouped = !self.grouped;
+        self.selected = 0;
+    }
+
+    /// Show only the stashes made on the checked-out branch, or all again
+    pub fn toggle_branch_only(&mut self) {
+        self.branch_only = !self.branch_only;
+        self.selected = 0;
+    }
+
+    /// Fold or unfold the branch heading under the cursor
+    pub fn toggle_collapsed(&mut self) {
+        if let Some(branch) = self.selected_group()
+            && !self.collapsed.remove(&branch)
+        {
+            self.collapsed.insert(branch);
+        }
+    }
+
+    /// Fold or unfold the group the cursor is in, moving onto its heading
+    pub fn toggle_fold_here(&mut self) {
+        if !self.grouped {
+            return;
+        }
+        if let Some(branch) = self.selected_stash().map(|s| s.branch.clone()) {
+            self.selected = self
+                .visible_rows()
+                .iter()
+                .position(|r| matches!(r, ListRow::Group { branch: b, .. } if *b == branch))
+                .unwrap_or(0);
+        }
+        self.toggle_collapsed();
+    }
+
+    /// Group by branch with every group folded
+    pub fn fold_all(&mut self) {
+        if !self.grouped {
+            self.toggle_grouped();
+        }
+        self.collapsed = self.stashes.iter().map(|s| s.branch.clone()).collect();
+        self.selected = 0;
+    }
+
+    pub fn unfold_all(&mut self) {
+        self.collapsed.clear();
+    }
+
+    pub fn select_first(&mut self) {
+        self.selected = 0;
+    }
+
+    pub fn select_last(&mut self) {
+        self.selected = self.visible_rows().len().saturating_sub(1);
+    }
+
+    /// Select the `n`th row, counting from 1 like a count prefix
+    pub fn select_nth(&mut self, n: usize) {
+        self.selected = n
+            .saturating_sub(1)
+            .min(self.visible_rows().len().saturating_sub(1));
+    }
+
+    /// Open the diff (or files) view for the selected stash. The content loads on
+    /// a worker thread, so a huge `stash show -p` never freezes the interface.
+    pub fn open_view(&mut self, mode: Mode) {
+        let Some(stash) = self.selected_stash() else {
+            return;
+        };
+        let (sha, name, branch) = (stash.sha.clone(), stash.name.clone(), stash.branch.clone());
+        let current = self.current_branch.clone();
+        let files = mode == Mode::Files;
+        let opts = self.diff_options.clone();
+        let worktree = self.diff_worktree && !files;
+        self.diff_title = format!("{} — {}", stash.name, stash.short_msg);
+        if worktree {
+            self.diff_title.push_str(" (working tree → stash)");
+        }
+        self.reviewing = None;
+        self.comparing = None;
+
+        self.reset_view();
+        self.mode = mode;
+
+        // The working tree changes under the stash, so that comparison is never cached
+        let key = ViewKey {
+            sha: sha.clone(),
+            files,
+            opts: opts.clone(),
+        };
+        if !worktree && let Some(cached) = self.diff_cache.get(&key) {
+            let (lines, details) = (cached.lines.clone(), cached.details.clone());
+            self.show_view(lines);
+            self.stash_details = details;
+            // The note and the branch can change without the stash changing
+            self.info_sha = Some(sha.clone());
+            self.spawn(move || JobResult::ViewInfo {
+                note: git::read_note(&sha),
+                relation: branch_relation(&branch, &current),
+                sha,
+            });
+            return;
+        }
+        self.view_key = (!worktree).then_some(key);
+        let request = self.start_view_request();
+        self.loading = Some(if files {
+            "loading files"
+        } else {
+            "loading diff"
+        });
+
+        let tx = self.jobs_tx.clone();
+        self.spawn(move || {
+            let raw = if files {
+                git::stash_numstat(&name).map(|files| diff::stat_lines(&files).join("\n"))
+            } else if worktree {
+                git::stash_vs_worktree(&name, &opts)
+            } else {
+                // Arrives through `JobResult::DiffLines`; only the untracked
+                // files are left to add below
+                stream_diff(request, &name, &opts, &tx).map(|()| String::new())
+            }
+            .and_then(|mut raw| {
+                // Untracked files have no counterpart to compare with
+                if worktree {
+                    return Ok(raw);
+                }
+                if let Some(untracked) = git::stash_untracked(&name, files)? {
+                    if !raw.is_empty() && !raw.ends_with('\n') {
+                        raw.push('\n');
+                    }
+                    raw.push_str(UNTRACKED_HEADER);
+                    raw.push('\n');
+                    raw.push_str(&untracked);
+                }
+                Ok(raw)
+            });
+            JobResult::View {
+                note: git::read_note(&sha),
+                details: git::stash_details(&sha).ok(),
+                request,
+                raw,
+                relation: branch_relation(&branch, &current),
+            }
+        });
+    }
+
+    /// Search the changes of every stash for `grep_input` in the background
+    pub fn start_grep(&mut self) {
+        let pattern = self.grep_input.trim().to_string();
+        if pattern.is_empty() {
+            return;
+        }
+        self.grep_pattern = pattern.clone();
+        self.grep_hits = None;
+        self.grep_selected = 0;
+        self.grep_editing = false;
+        self.loading = Some("searching stashes");
+
+        let stashes = self.stashes.clone();
+        let opts = self.diff_options.clone();
+        self.spawn(move || JobResult::Grep {
+            hits: grep_stashes(&stashes, &pattern, &opts),
+            pattern,
+        });
+    }
+
+    /// Show the matching hunks of the selected grep hit in the diff view
+    pub fn open_grep_hit(&mut self) {
+        let Some(hit) = self
+            .grep_hits
+            .as_ref()
+            .and_then(|hits| hits.get(self.grep_selected))
+            .cloned()
+        else {
+            return;
+        };
+        // Select the stash in the list too, so view actions like apply hit it
+        if let Some(i) = self
+            .visible_rows()
+            .iter()
+            .position(|row| matches!(row, ListRow::Stash(s) if s.sha == hit.sha))
+        {
+            self.selected = i;
+        }
+
+        self.reset_view();
+        self.reviewing = None;
+        self.diff_title = format!("{} — hunks matching '{}'", hit.name, self.grep_pattern);
+        self.diff_content = diff::DiffModel::new(hit.patch.lines().map(String::from).collect());
+        self.index_diff();
+        self.diff_query.set(self.grep_pattern.clone());
+        self.search_diff();
+        self.grep_drill = true;
+        self.mode = Mode::Diff;
+    }
+
+    /// Empty the diff/files view before loading something new into it
+    fn reset_view(&mut self) {
+        self.diff_content.clear();
+        self.diff_scroll = 0;
+        self.diff_hscroll = 0;
+        self.clear_diff_search();
+        self.line_ending_files = 0;
+        self.diff_files.clear();
+        self.diff_hunks.clear();
+        self.branch_relation = None;
+        self.stash_note.clear();
+        self.stash_details = None;
+        self.diff_truncated = false;
+        self.view_request = None; // whatever was loading is for the old view
+        self.view_key = None;
+        self.info_sha = None;
+        self.diff_rendered = None;
+    }
+
+    /// Fill the view with fully loaded content
+    fn show_view(&mut self, lines: Vec<String>) {
+        if self.mode == Mode::Files {
+            self.diff_content = diff::DiffModel::new(lines);
+        } else {
+            self.append_diff(lines);
+            self.line_ending_files = count_line_ending_files(self.diff_content.lines());
+        }
+    }
+
+    /// Add lines to the end of the diff view as they stream in
+    fn append_diff(&mut self, lines: Vec<String>) {
+        self.diff_content.extend(lines);
+        self.index_diff();
+        if !self.diff_query.is_empty() {
+            self.find_diff_matches();
+        }
+    }
+
+    /// True while the open diff is shown but still streaming in
+    pub fn diff_streaming(&self) -> bool {
+        self.view_request.is_some() && self.loading.is_none()
+    }
+
+    /// Number a new load for the open view; results of earlier ones are dropped
+    fn start_view_request(&mut self) -> u64 {
+        self.view_requests += 1;
+        self.view_request = Some(self.view_requests);
+        self.view_requests
+    }
+
+    /// Apply or pop `stash` on a worker thread, with git's output in the
+    /// progress popup. `poll_jobs` picks up the result and remembers which
+    /// files it touched, so they can be reviewed afterwards.
+    fn start_apply(&mut self, stash: git::Stash, pop: bool) {
+        let verb = if pop { "Popping" } else { "Applying" };
+        let progress = self.start_progress(format!("{verb} {}", stash.name));
+        self.spawn(move || {
+            // Read before popping: the stash is gone afterwards
+            let files: Vec<String> = git::stash_numstat(&stash.name)
+                .map(|files| files.into_iter().map(|f| f.path).collect())
+                .unwrap_or_default();
+            let result = git::apply_stash_with(&stash.name, pop, &progress);
+            JobResult::Applied {
+                pop,
+                label: format!("{} — {}", stash.name, stash.short_msg),
+                files,
+                result,
+            }
+        });
+    }
+
+    /// Open the progress popup for an operation about to start on a worker
+    /// thread, which reports into the returned handle
+    fn start_progress(&mut self, title: String) -> Arc<git::Progress> {
+        let progress = Arc::new(git::Progress::default());
+        self.progress = Some((title, progress.clone()));
+        self.mode = Mode::Progress;
+        progress
+    }
+
+    /// Ask the running operation to stop
+    pub fn cancel_progress(&self) {
+        if let Some((_, progress)) = &self.progress {
+            progress.cancel();
+        }
+    }
+
+    /// Ctrl-C: kill the git processes of any background work and go back to
+    /// the list. Returns false if nothing was running.
+    pub fn cancel_running(&mut self) -> bool {
+        let checking = self.preflight_sha.is_some() && self.preflight.is_none();
+        if self.loading.is_none()
+            && self.progress.is_none()
+            && self.preview_pending.is_empty()
+            && self.view_request.is_none()
+            && !checking
+        {
+            return false;
+        }
+        git::cancel_running();
+        if self.progress.is_some() {
+            // The operation reports back once its process is gone
+            return true;
+        }
+        if self.diff_streaming() {
+            // Keep what has arrived so far
+            self.view_request = None;
+            self.diff_truncated = true;
+            return true;
+        }
+        self.loading = None;
+        self.view_request = None; // ignore the diff if it still arrives
+        self.cancel_preflight();
+        self.mode = Mode::Message("Cancelled.".to_string());
+        true
+    }
+
+    /// Show `git diff HEAD` for the files the last apply/pop touched
+    pub fn review_applied(&mut self) {
+        let Some(applied) = self.applied.take() else {
+            return;
+        };
+        self.reviewing = Some(applied);
+        self.load_review();
+    }
+
+    /// (Re)load the review diff of `reviewing`
+    fn load_review(&mut self) {
+        let Some((label, files)) = self.reviewing.clone() else {
+            return;
+        };
+        let opts = self.diff_options.clone();
+        self.reset_view();
+        self.diff_title = format!("result of {label} (working tree vs HEAD)");
+
+        let request = self.start_view_request();
+        self.loading = Some("loading diff");
+        self.mode = Mode::Diff;
+        self.spawn(move || JobResult::View {
+            request,
+            raw: git::diff_head(&files, &opts),
+            relation: None,
+            note: None,
+            details: None,
+        });
+    }
+
+    /// Diff two stashes' snapshots against each other: the two marked stashes,
+    /// or the one marked stash and the selected one. The older stash is the
+    /// "before" side.
+    pub fn compare_stashes(&mut self) -> Result<()> {
+        let mut pair: Vec<git::Stash> = self
+            .stashes
+            .iter()
+            .filter(|s| self.marked.contains(&s.sha))
+            .cloned()
+            .collect();
+        if pair.len() == 1
+            && let Some(selected) = self.selected_stash()
+            && selected.sha != pair[0].sha
+        {
+            pair.push(selected.clone());
+        }
+        let Ok([mut older, mut newer]) = <[git::Stash; 2]>::try_from(pair) else {
+            bail!("Mark a stash with Space and select another to compare");
+        };
+        // Higher index is older
+        if older.index < newer.index {
+            std::mem::swap(&mut older, &mut newer);
+        }
+        self.comparing = Some((older, newer));
+        self.load_comparison();
+        Ok(())
+    }
+
+    /// (Re)load the diff between the two stashes in `comparing`
+    fn load_comparison(&mut self) {
+        let Some((older, newer)) = self.comparing.clone() else {
+            return;
+        };
+        let opts = self.diff_options.clone();
+        self.reset_view();
+        self.diff_title = format!(
+            "{} — {}  →  {} — {}",
+            older.name, older.short_msg, newer.name, newer.short_msg
+        );
+
+        let request = self.start_view_request();
+        self.loading = Some("loading diff");
+        self.mode = Mode::Diff;
+        self.spawn(move || JobResult::View {
+            request,
+            raw: git::diff_commits(&older.sha, &newer.sha, &opts),
+            relation: None,
+            note: None,
+            details: None,
+        });
+    }
+
+    /// Switch to the next diff algorithm and reload the open diff with it
+    pub fn cycle_diff_algorithm(&mut self) {
+        self.diff_options.algorithm = self.diff_options.algorithm.next();
+        self.reload_diff();
+    }
+
+    /// Diff the open stash against the current working tree instead of the
+    /// commit it was made on, or back
+    pub fn toggle_diff_worktree(&mut self) {
+        if self.reviewing.is_some() || self.comparing.is_some() {
+            return;
+        }
+        self.diff_worktree = !self.diff_worktree;
+        self.open_view(Mode::Diff);
+    }
+
+    /// Show whole enclosing functions around each change, or just the hunks
+    pub fn toggle_function_context(&mut self) {
+        self.diff_options.function_context = !self.diff_options.function_context;
+        self.reload_diff();
+    }
+
+    /// Reload the open diff after its options changed
+    fn reload_diff(&mut self) {
+        if self.reviewing.is_some() {
+            self.load_review();
+        } else if self.comparing.is_some() {
+            self.load_comparison();
+        } else {
+            self.open_view(Mode::Diff);
+        }
+    }
+
+    /// Path of the file whose section holds the top visible line of the
+    /// diff/files view, relative to the repository root
+    pub fn file_at_cursor(&self) -> Option<String> {
+        let path_of = |line: &str| -> Option<String> {
+            if self.mode == Mode::Files {
+                let (path, _) = line.split_once(" | ")?;
+                Some(path.trim().to_string())
+            } else {
+                let rest = line.strip_prefix("diff --git a/")?;
+                let (_, path) = rest.split_once(" b/")?;
+                Some(path.to_string())
+            }
+        };
+        let split = (self.diff_scroll + 1).min(self.diff_content.len());
+        let (above, below) = self.diff_content.lines().split_at(split);
+        // The section we're inside, else the first one below the cursor
+        above
+            .iter()
+            .rev()
+            .chain(below)
+            .find_map(|line| path_of(line))
+    }
+
+    /// Queue the file under the cursor for the editor
+    pub fn edit_file_at_cursor(&mut self) -> Result<()> {
+        let Some(path) = self.file_at_cursor() else {
+            bail!("No file under the cursor");
+        };
+        if !std::path::Path::new(&git::toplevel()?).join(&path).exists() {
+            bail!("{path} does not exist in the working tree");
+        }
+        self.pending_editor = Some(path);
+        Ok(())
+    }
+
+    /// Queue the open diff for the pager, through the `diff.external`
+    /// renderer if one is set. The files view pages the full diff.
+    pub fn page_diff(&mut self) -> Result<()> {
+        let stash = self.selected_stash().cloned();
+        let text = if self.mode == Mode::Diff {
+            self.diff_content.text()
+        } else {
+            let Some(stash) = &stash else {
+                bail!("No stash open");
+            };
+            git::stash_diff(&stash.name, &self.diff_options)?
+        };
+        if text.is_empty() {
+            bail!("Nothing to page");
+        }
+        // Worktree and comparison diffs aren't the stash's own, which a
+        // renderer like difftastic would fetch from git itself
+        let plain = self.comparing.is_some() || self.diff_worktree;
+        self.pending_pager = match (&self.config.diff.external, stash) {
+            (Some(command), Some(stash)) if !plain => {
+                let width = crossterm::terminal::size().map_or(80, |(w, _)| w);
+                Some((external::render(command, &stash.name, &text, width)?, true))
+            }
+            _ => Some((text, false)),
+        };
+        Ok(())
+    }
+
+    /// Switch the diff pane between gsm's own diff and the `diff.external`
+    /// renderer's coloured output
+    pub fn toggle_rendered_diff(&mut self) -> Result<()> {
+        if self.diff_rendered.take().is_some() {
+            self.diff_scroll = 0;
+            return Ok(());
+        }
+        let Some(command) = self.config.diff.external.clone() else {
+            bail!("Set diff.external in the config to pick a renderer, e.g. \"delta\"");
+        };
+        if self.comparing.is_some() || self.diff_worktree {
+            bail!("Only a stash's own diff can be rendered externally");
+        }
+        let Some(stash) = self.selected_stash().cloned() else {
+            bail!("No stash open");
+        };
+        // Inside the pane's borders
+        let width = crossterm::terminal::size()
+            .map_or(80, |(w, _)| w)
+            .saturating_sub(2);
+        let text = external::render(&command, &stash.name, &self.diff_content.text(), width)?;
+        self.diff_rendered = Some(ansi::to_lines(&text, Style::default()));
+        self.diff_scroll = 0;
+        Ok(())
+    }
+
+    /// Lines in the diff pane, whichever way it's drawn
+    pub fn diff_len(&self) -> usize {
+        match &self.diff_rendered {
+            Some(lines) => lines.len(),
+            None => self.diff_content.len(),
+        }
+    }
+
+    /// Forget the diff/files view once it's left, discarding a load still in flight
+    pub fn close_view(&mut self) {
+        self.view_request = None;
+        self.view_key = None;
+        self.info_sha = None;
+        self.loading = None;
+        self.reviewing = None;
+        self.comparing = None;
+        self.grep_drill = false;
+        self.diff_rendered = None;
+        self.clear_diff_search();
+    }
+
+    pub fn clear_diff_search(&mut self) {
+        self.diff_query.clear();
+        self.diff_searching = false;
+        self.diff_matches.clear();
+        self.diff_match = 0;
+    }
+
+    /// An empty text field with the configured key style, for a prompt mode
+    pub fn new_input(&self) -> TextInput {
+        TextInput::new(self.config.ui.input)
+    }
+
+    /// The text field taking keys in the current mode, if any
+    pub fn active_input(&mut self) -> Option<&mut TextInput> {
+        if self.palette {
+            return Some(&mut self.palette_input);
+        }
+        if self.mode.input().is_some() {
+            return self.mode.input_mut();
+        }
+        Some(match self.mode {
+            Mode::Normal if self.searching => &mut self.search_query,
+            Mode::Diff | Mode::Files if self.diff_searching => &mut self.diff_query,
+            Mode::Grep if self.grep_editing => &mut self.grep_input,
+            Mode::NewStash => &mut self.new_stash_input,
+            Mode::Rename => &mut self.rename_input,
+            Mode::Squash => &mut self.squash_input,
+            Mode::Email => &mut self.email_input,
+            Mode::ClearAll => &mut self.clear_input,
+            Mode::ToBranch if self.to_branch_field == 0 => &mut self.to_branch_name,
+            Mode::ToBranch if self.to_branch_field == 1 => &mut self.to_branch_message,
+            _ => return None,
+        })
+    }
+
+    /// Put pasted text into the open prompt; elsewhere it is ignored
+    pub fn paste(&mut self, text: &str) -> Result<()> {
+        let Some(input) = self.active_input() else {
+            return Ok(());
+        };
+        input.insert_str(text);
+        self.input_changed()
+    }
+
+    /// Follow an edit the active field made outside its prompt's own key
+    /// handling: the stash and diff searches update as they're typed
+    pub fn input_changed(&mut self) -> Result<()> {
+        if self.palette {
+            self.palette_selected = 0;
+        } else if self.searching {
+            self.selected = 0;
+            self.ensure_stash_paths();
+        } else if self.diff_searching {
+            self.search_diff();
+        }
+        Ok(())
+    }
+
+    /// Find `diff_query` (case-insensitively) in the loaded diff and jump to the
+    /// first match at or below the current scroll position
+    pub fn search_diff(&mut self) {
+        self.find_diff_matches();
+        self.diff_match = self
+            .diff_matches
+            .iter()
+            .position(|&i| i >= self.diff_scroll)
+            .unwrap_or(0);
+        self.scroll_to_match();
+    }
+
+    /// Lines of the visible diff containing `diff_query`
+    fn find_diff_matches(&mut self) {
+        let query = self.diff_query.to_ascii_lowercase();
+        self.diff_matches = if query.is_empty() {
+            Vec::new()
+        } else {
+            self.diff_content
+                .lines()
+                .iter()
+                .enumerate()
+                .filter(|(_, l)| l.to_ascii_lowercase().contains(&query))
+                .map(|(i, _)| i)
+                .collect()
+        };
+    }
+
+    /// Move to the next (or previous) match, wrapping around the ends
+    pub fn next_diff_match(&mut self, forward: bool) {
+        let count = self.diff_matches.len();
+        if count == 0 {
+            return;
+        }
+        self.diff_match = if forward {
+            (self.diff_match + 1) % count
+        } else {
+            (self.diff_match + count - 1) % count
+        };
+        self.scroll_to_match();
+    }
+
+    /// Scroll so the current match sits near the top, with a little context above
+    fn scroll_to_match(&mut self) {
+        if let Some(&line) = self.diff_matches.get(self.diff_match) {
+            self.diff_scroll = line.saturating_sub(2);
+        }
+    }
+
+    /// Collect the apply targets on a worker, then offer them to pick from
+    pub fn load_targets(&mut self) {
+        self.loading = Some(TARGETS_LOADING);
+        self.spawn(|| JobResult::Targets(apply_targets()));
+    }
+
+    /// Fill the repo switcher with recent and sibling repositories
+    pub fn load_repos(&mut self) -> Result<()> {
+        let here = PathBuf::from(git::toplevel()?);
+        self.repos = repos::candidates(&here);
+        self.repo_selected = 0;
+        Ok(())
+    }
+
+    /// List the repository's worktrees with how many changed files each has,
+    /// selecting the one gsm runs in
+    pub fn load_worktree_list(&mut self) -> Result<()> {
+        let here = self.location.root.to_string_lossy().into_owned();
+        self.worktree_list = git::list_worktrees()?
+            .into_iter()
+            .map(|w| {
+                let changed = git::changed_files_in(&w.path).ok();
+                (w, changed)
+            })
+            .collect();
+        self.worktree_selected = self
+            .worktree_list
+            .iter()
+            .position(|(w, _)| w.path == here)
+            .unwrap_or(0);
+        Ok(())
+    }
+
+    /// Put the stash dropped or popped by the selected history entry back on
+    /// the stash list
+    pub fn restore_from_history(&mut self) -> Result<String> {
+        let Some(entry) = self.history.get(self.history_selected).cloned() else {
+            bail!("Nothing selected");
+        };
+        if !entry.restorable() {
+            bail!("Only stashes that were dropped or popped can be restored");
+        }
+        if self.stashes.iter().any(|s| s.sha == entry.sha) {
+            bail!("That stash is already in the list");
+        }
+        git::restore_stash(&entry.sha, &entry.message)?;
+        self.reload()?;
+        self.history = audit::load();
+        self.history_selected = 0;
+        Ok(format!("Restored \"{}\" as stash@{{0}}.", entry.message))
+    }
+
+    /// Look for lost stash commits in the background
+    pub fn scan_lost_stashes(&mut self) {
+        self.lost_stashes = None;
+        self.lost_selected = 0;
+        self.loading = Some("scanning for lost stashes");
+        self.prune_expire = git::prune_expire();
+        self.spawn(|| JobResult::LostStashes(git::find_lost_stashes()));
+    }
+
+    /// Put the lost stash selected on the recover screen back on the list
+    pub fn recover_lost_stash(&mut self) -> Result<String> {
+        let Some(lost) = self
+            .lost_stashes
+            .as_ref()
+            .and_then(|list| list.get(self.lost_selected))
+            .cloned()
+        else {
+            bail!("Nothing selected");
+        };
+        git::restore_stash(&lost.sha, &lost.subject)?;
+        self.reload()?;
+        if let Some(list) = &mut self.lost_stashes {
+            list.retain(|s| s.sha != lost.sha);
+            self.lost_selected = self.lost_selected.min(list.len().saturating_sub(1));
+        }
+        Ok(format!("Recovered \"{}\" as stash@{{0}}.", lost.subject))
+    }
+
+    /// Enumerate the submodules, and the superproject if this is one of them
+    pub fn load_submodules(&mut self) -> Result<()> {
+        self.submodules = git::list_submodules()?;
+        self.superproject = git::superproject();
+        self.submodule_selected = 0;
+        Ok(())
+    }
+
+    /// Number of rows in the submodule picker
+    pub fn submodule_rows(&self) -> usize {
+        self.submodules.len() + usize::from(self.superproject.is_some())
+    }
+
+    /// Reopen gsm inside the selected submodule, or back in the superproject
+    pub fn open_submodule(&mut self) -> Result<String> {
+        let index = match &self.superproject {
+            Some(parent) if self.submodule_selected == 0 => {
+                let parent = parent.clone();
+                return self.switch_repo(&parent);
+            }
+            Some(_) => self.submodule_selected - 1,
+            None => self.submodule_selected,
+        };
+        let Some(submodule) = self.submodules.get(index) else {
+            bail!("No submodule selected");
+        };
+        if !submodule.initialized {
+            bail!(
+                "{} is not checked out; run `git submodule update --init` first",
+                submodule.path
+            );
+        }
+        let path = self.location.root.join(&submodule.path);
+        self.switch_repo(&path)
+    }
+
+    /// Reopen gsm on the repository or worktree at `path`. Everything loaded
+    /// from the old one is thrown away; the config carries over. If the new
+    /// one can't be loaded, gsm stays on the old one.
+    pub fn switch_repo(&mut self, path: &Path) -> Result<String> {
+        if self.loading.is_some() || self.progress.is_some() || !self.preview_pending.is_empty() {
+            bail!("Still loading; try again in a moment");
+        }
+        let _ = self.save_state();
+        let previous = git::current_repo();
+        git::switch_repo(path, self.config.git.backend)?;
+        *self = match App::new(self.config.clone()) {
+            Ok(app) => app,
+            Err(e) => {
+                git::restore_repo(previous);
+                return Err(e.context(format!("Failed to load {}", path.display())));
+            }
+        };
+        repos::remember(&self.location.root);
+        Ok(format!(
+            "Switched to {} ({} stash(es)).",
+            path.display(),
+            self.stashes.len()
+        ))
+    }
+
+    /// Apply the selected stash onto the chosen target, refusing to touch dirty worktrees
+    pub fn apply_to_target(&mut self) -> Result<String> {
+        let stash_name = match self.selected_stash() {
+            Some(s) => s.name.clone(),
+            None => bail!("No stash selected"),
+        };
+        let target = match self.targets.get(self.target_selected) {
+            Some(t) => t.clone(),
+            None => bail!("No target selected"),
+        };
+
+        match &target {
+            ApplyTarget::Worktree { path, .. } => {
+                if !git::is_clean_in(path)? {
+                    bail!("{path} has uncommitted changes; commit or stash them first");
+                }
+                git::apply_stash_in(path, &stash_name)?;
+            }
+            ApplyTarget::Branch(branch) => {
+                let here = git::toplevel()?;
+                if !git::is_clean_in(&here)? {
+                    bail!("Working tree has uncommitted changes; cannot switch to {branch}");
+                }
+                let original = git::head_ref()?;
+                git::switch_branch(branch)?;
+                if let Err(e) = git::apply_stash(&stash_name) {
+                    // The tree was clean and the stash is kept, so undoing the
+                    // half-done apply loses nothing
+                    let back = git::abort_merge().and_then(|()| git::checkout(&original));
+                    let e = format!("{e:#}");
+                    let e = e.trim_end_matches([':', ' ']);
+                    match back {
+                        Ok(()) => bail!("On {branch}: {e}\nNothing changed; back on {original}."),
+                        Err(back) => {
+                            bail!("On {branch}: {e}\nSwitching back to {original} failed: {back:#}")
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(format!("{stash_name} applied to {}.", target.label()))
+    }
+
+    /// Re-read the conflicted file list; returns false once nothing is left to resolve
+    pub fn load_conflicts(&mut self) -> Result<bool> {
+        self.conflicts = git::conflicted_files()?;
+        if self.conflict_selected >= self.conflicts.len() {
+            self.conflict_selected = self.conflicts.len().saturating_sub(1);
+        }
+        self.load_conflict_preview()?;
+        Ok(!self.conflicts.is_empty())
+    }
+
+    /// Load the selected conflicted file, scrolled to its first conflict marker
+    pub fn load_conflict_preview(&mut self) -> Result<()> {
+        self.conflict_preview.clear();
+        self.conflict_scroll = 0;
+        if let Some(path) = self.selected_conflict() {
+            let full = Path::new(&git::toplevel()?).join(path);
+            let content = fs::read_to_string(&full).unwrap_or_default();
+            self.conflict_preview = content.lines().map(|l| l.to_string()).collect();
+            self.conflict_scroll = self
+                .conflict_preview
+                .iter()
+                .position(|l| l.starts_with("<<<<<<<"))
+                .unwrap_or(0);
+        }
+        Ok(())
+    }
+
+    pub fn selected_conflict(&self) -> Option<&String> {
+        self.conflicts.get(self.conflict_selected)
+    }
+
+    /// Load unstaged working tree changes for hunk selection
+    pub fn load_hunks(&mut self) -> Result<()> {
+        self.hunk_files = diff::parse(&git::worktree_diff()?);
+        self.hunk_cursor = 0;
+        self.hunk_marked.clear();
+        Ok(())
+    }
+
+    /// All hunks as (file, hunk) pairs, in display order
+    pub fn hunk_refs(&self) -> Vec<(usize, usize)> {
+        self.hunk_files
+            .iter()
+            .enumerate()
+            .flat_map(|(fi, f)| (0..f.hunks.len()).map(move |hi| (fi, hi)))
+            .collect()
+    }
+
+    pub fn toggle_hunk(&mut self) {
+        if let Some(r) = self.hunk_refs().get(self.hunk_cursor).copied()
+            && !self.hunk_marked.remove(&r)
+        {
+            self.hunk_marked.insert(r);
+        }
+    }
+
+    /// Mark every hunk of the file under the cursor, or unmark them if all are marked
+    pub fn toggle_hunk_file(&mut self) {
+        let Some((fi, _)) = self.hunk_refs().get(self.hunk_cursor).copied() else {
+            return;
+        };
+        let refs: Vec<(usize, usize)> = (0..self.hunk_files[fi].hunks.len())
+            .map(|hi| (fi, hi))
+            .collect();
+        if refs.iter().all(|r| self.hunk_marked.contains(r)) {
+            for r in &refs {
+                self.hunk_marked.remove(r);
+            }
+        } else {
+            self.hunk_marked.extend(refs);
+        }
+    }
+
+    /// Create a stash from the NewStash popup: the marked hunks, or every change.
+    /// With `capture_env` set, the repository's state is noted on the new stash.
+    /// Returns false if git found nothing to stash.
+    pub fn create_stash(&mut self, message: &str) -> Result<bool> {
+        // Capture first: stashing can clean up exactly what we want to record
+        let env = self.config.stash.capture_env.then(git::capture_env);
+
+        let before = git::stash_top();
+        if self.hunk_stash {
+            self.stash_marked_hunks(message)?;
+        } else {
+            git::push_stash(message, self.new_stash_scope.options())?;
+        }
+        record_env(before.as_deref(), env.as_deref())
+    }
+
+    /// The message a stash typed as `input` gets, after `stash.template`
+    pub fn stash_message(&self, input: &str) -> String {
+        let input = input.trim();
+        let Some(template) = &self.config.stash.template else {
+            return input.to_string();
+        };
+        let now = Local::now();
+        let branch = match self.current_branch.as_str() {
+            "" => "HEAD",
+            branch => branch,
+        };
+        template
+            .replace("{input}", input)
+            .replace("{branch}", branch)
+            .replace("{date}", &now.format("%Y-%m-%d").to_string())
+            .replace("{time}", &now.format("%H:%M").to_string())
+            .trim()
+            .to_string()
+    }
+
+    /// Stash every change, untracked files included, under a generated
+    /// message like "gsm: main 2025-06-01 14:03". Returns the message, or
+    /// `None` when there was nothing to stash.
+    pub fn quick_stash(&mut self) -> Result<Option<String>> {
+        let branch = match self.current_branch.as_str() {
+            "" => "HEAD",
+            branch => branch,
+        };
+        let message = format!("gsm: {branch} {}", Local::now().format("%Y-%m-%d %H:%M"));
+        self.hunk_stash = false;
+        self.new_stash_scope = StashScope::Untracked;
+        let created = self.create_stash(&message)?;
+        self.reload()?;
+        Ok(created.then_some(message))
+    }
+
+    /// Stash the marked hunks and remove them from the working tree
+    pub fn stash_marked_hunks(&mut self, message: &str) -> Result<()> {
+        let patch = diff::build_patch(&self.hunk_files, |fi, hi| {
+            self.hunk_marked.contains(&(fi, hi))
+        });
+        git::stash_patch(&patch, message)?;
+        self.hunk_marked.clear();
+        Ok(())
+    }
+
+    /// Run `job` on a worker thread; its result is picked up by `poll_jobs`
+    fn spawn(&self, job: impl FnOnce() -> JobResult + Send + 'static) {
+        let tx = self.jobs_tx.clone();
+        // The job keeps to this repository even if the user switches away
+        let repo = git::current_repo();
+        thread::spawn(move || {
+            let _ = tx.send(repo.enter(job));
+        });
+    }
+
+    /// Apply results from finished background jobs and advance the spinner
+    pub fn poll_jobs(&mut self) {
+        let started = Instant::now();
+        // A streaming diff can queue results faster than they are taken in;
+        // leave the rest for the next frame rather than stop drawing
+        while started.elapsed() < JOB_BUDGET
+            && let Ok(result) = self.jobs_rx.try_recv()
+        {
+            match result {
+                JobResult::DiffLines { request, lines } => {
+                    if self.view_request != Some(request) {
+                        continue;
+                    }
+                    // The first batch replaces the spinner; the rest follow it
+                    self.loading = None;
+                    self.append_diff(lines);
+                }
+                JobResult::ViewInfo {
+                    sha,
+                    relation,
+                    note,
+                } => {
+                    if self.info_sha.as_deref() != Some(sha.as_str()) {
+                        continue;
+                    }
+                    self.info_sha = None;
+                    self.branch_relation = relation;
+                    self.stash_note = environment_lines(note.as_deref());
+                }
+                JobResult::Preview { sha, lines } => {
+                    self.preview_pending.remove(&sha);
+                    self.preview_cache.insert(sha, lines);
+                }
+                JobResult::View {
+                    request,
+                    raw,
+                    relation,
+                    note,
+                    details,
+                } => {
+                    // The view was closed or reloaded (another stash, the other
+                    // of diff and files, new options) meanwhile
+                    if self.view_request != Some(request) {
+                        continue;
+                    }
+                    self.view_request = None;
+                    self.loading = None;
+                    match raw {
+                        Ok(raw) if self.mode == Mode::Files => {
+                            self.show_view(raw.lines().map(|l| l.to_string()).collect());
+                        }
+                        Ok(raw) => {
+                            // Keep trailing \r so line-ending changes stay visible
+                            self.show_view(
+                                raw.split_terminator('\n').map(|l| l.to_string()).collect(),
+                            );
+                        }
+                        Err(e) => {
+                            self.view_key = None;
+                            self.mode = Mode::Message(format!("Error: {e}"));
+                        }
+                    }
+                    if let Some(key) = self.view_key.take() {
+                        let cached = CachedView {
+                            lines: self.diff_content.all_lines().to_vec(),
+                            details: details.clone(),
+                        };
+                        self.diff_cache.insert(key, cached);
+                    }
+                    self.branch_relation = relation;
+                    self.stash_details = details;
+                    self.stash_note = environment_lines(note.as_deref());
+                }
+                JobResult::Merged(result) => {
+                    self.loading = None;
+                    let mode = match result {
+                        Ok(merged) if merged.is_empty() => {
+                            Mode::Message("No stashes are fully merged into HEAD.".to_string())
+                        }
+                        Ok(merged) => {
+                            self.prune_candidates = merged;
+                            Mode::Confirm(ConfirmAction::PruneMerged)
+                        }
+                        Err(e) if git::was_cancelled(&e) => continue,
+                        Err(e) => Mode::Message(format!("Error: {e}")),
+                    };
+                    // Don't yank the user out of whatever they opened while waiting
+                    if self.mode == Mode::Normal {
+                        self.mode = mode;
+                    }
+                }
+                JobResult::StashStamp(stamp) => {
+                    self.watching = false;
+                    if self.mode == Mode::Normal
+                        && stamp != self.stash_stamp
+                        && let Err(e) = self.refresh()
+                    {
+                        self.mode = Mode::Message(format!("Error: {e}"));
+                    }
+                }
+                JobResult::Tree { sha, result } => {
+                    if self.tree_stash.as_ref().map(|s| s.sha.as_str()) != Some(sha.as_str()) {
+                        continue;
+                    }
+                    if self.loading == Some(TREE_LOADING) {
+                        self.loading = None;
+                    }
+                    // Opening the tree waits in the list; drop it once the
+                    // user has moved on
+                    if self.mode != Mode::Normal {
+                        continue;
+                    }
+                    match result {
+                        Ok((paths, changed)) => self.show_tree(paths, changed),
+                        Err(e) => self.mode = Mode::Message(format!("Error: {e}")),
+                    }
+                }
+                JobResult::TreeFile { key, lines } => {
+                    if self.tree_loading.as_ref() == Some(&key) {
+                        self.tree_loading = None;
+                        self.tree_content = lines;
+                    }
+                }
+                JobResult::Targets(result) => {
+                    if self.loading != Some(TARGETS_LOADING) {
+                        continue;
+                    }
+                    self.loading = None;
+                    if self.mode != Mode::Normal {
+                        continue;
+                    }
+                    match result {
+                        Ok(targets) if targets.is_empty() => {
+                            self.mode =
+                                Mode::Message("Error: no other branches or worktrees.".to_string());
+                        }
+                        Ok(targets) => {
+                            self.targets = targets;
+                            self.target_selected = 0;
+                            self.mode = Mode::PickTarget;
+                        }
+                        Err(e) => self.mode = Mode::Message(format!("Error: {e}")),
+                    }
+                }
+                JobResult::Numstat(stats) => {
+                    self.loading_numstat = false;
+                    let sha = self.selected_stash().map(|s| s.sha.clone());
+                    // A list that shifted meanwhile means a name read another stash
+                    for (sha, name, files) in stats {
+                        if let Some(stash) = self
+                            .stashes
+                            .iter_mut()
+                            .find(|s| s.sha == sha && s.name == name)
+                        {
+                            stash.files_changed = Some(files.len());
+                            stash.numstat = Some(files);
+                        }
+                    }
+                    // Stashes that arrived while this ran still need theirs
+                    self.ensure_stash_paths();
+                    self.ensure_numstat();
+                    self.keep_selection(sha);
+                }
+                JobResult::Notes { request, notes } => {
+                    if request != self.notes_requests {
+                        continue;
+                    }
+                    let sha = self.selected_stash().map(|s| s.sha.clone());
+                    self.reminders = reminders_from(&notes);
+                    self.annotations = git::note_fields(&notes, ANNOTATION_KEY);
+                    self.labels = labels_from(&notes);
+                    self.keep_selection(sha);
+                    // The first load is the one at launch, when due reminders are announced
+                    if request == 1
+                        && self.mode == Mode::Normal
+                        && let Some(banner) = self.reminder_banner()
+                    {
+                        self.mode = Mode::Message(banner);
+                    }
+                }
+                JobResult::Preflight { sha, result } => {
+                    if self.preflight_sha.as_deref() == Some(sha.as_str()) {
+                        self.preflight = Some(result.map_err(|e| e.to_string()));
+                    }
+                }
+                JobResult::Grep { pattern, hits } => {
+                    // A newer search replaced this one
+                    if pattern != self.grep_pattern || self.grep_hits.is_some() {
+                        continue;
+                    }
+                    self.loading = None;
+                    match hits {
+                        Ok(hits) => self.grep_hits = Some(hits),
+                        Err(e) => {
+                            self.grep_hits = Some(Vec::new());
+                            if !git::was_cancelled(&e) {
+                                self.mode = Mode::Message(format!("Error: {e}"));
+                            }
+                        }
+                    }
+                }
+                JobResult::Applied {
+                    pop,
+                    label,
+                    files,
+                    result,
+                } => {
+                    self.progress = None;
+                    let verb = if pop { "popped" } else { "applied" };
+                    let result = result.map(|_| {
+                        if files.is_empty() {
+                            return format!("Stash {verb} successfully.");
+                        }
+                        self.applied = Some((label, files));
+                        format!("Stash {verb} successfully. Press d to review the result.")
+                    });
+                    let action = if pop {
+                        ConfirmAction::Pop
+                    } else {
+                        ConfirmAction::Apply
+                    };
+                    let closed = journal::finish();
+                    let finished = self.finish_operation(&action, result).and(closed);
+                    if let Err(e) = finished {
+                        self.mode = Mode::Message(format!("Error: {e}"));
+                    }
+                }
+                JobResult::Exported { written, result } => {
+                    self.progress = None;
+                    match result {
+                        Ok(()) => self.toast(format!("Exported {}", written.join(", "))),
+                        Err(e) if git::was_cancelled(&e) => {
+                            self.mode = Mode::Message(format!(
+                                "Cancelled after exporting {} stash(es).",
+                                written.len()
+                            ))
+                        }
+                        Err(e) => self.mode = Mode::Message(format!("Error: {e}")),
+                    }
+                }
+                JobResult::Published(result) => {
+                    self.progress = None;
+                    self.report(result);
+                }
+                JobResult::RemoteStashes(result) => {
+                    self.loading = None;
+                    match result {
+                        Ok(list) => self.remote_stashes = Some(list),
+                        Err(e) => {
+                            self.remote_stashes = Some(Vec::new());
+                            if self.mode == Mode::RemoteStashes {
+                                self.mode = Mode::Message(format!("Error: {e}"));
+                            }
+                        }
+                    }
+                }
+                JobResult::LostStashes(result) => {
+                    self.loading = None;
+                    match result {
+                        // Stashes still listed are reachable through the reflog
+                        Ok(mut lost) => {
+                            lost.retain(|s| !self.has_stash(&s.sha));
+                            self.lost_stashes = Some(lost);
+                        }
+                        Err(e) => {
+                            self.lost_stashes = Some(Vec::new());
+                            if self.mode == Mode::Recover {
+                                self.mode = Mode::Message(format!("Error: {e}"));
+                            }
+                        }
+                    }
+                }
+                JobResult::RemoteImported(result) => {
+                    self.progress = None;
+                    let result = result.and_then(|msg| self.reload().map(|()| msg));
+                    self.report(result.map(|msg| format!("Imported '{msg}' as stash@{{0}}.")));
+                }
+            }
+        }
+
+        if self.loading.is_some() || self.progress.is_some() || self.diff_streaming() {
+            self.spinner_tick = self.spinner_tick.wrapping_add(1);
+        }
+    }
+
+    /// When `stash` was made, as the list shows it: relative, or absolute in
+    /// the configured format
+    pub fn stash_date(&self, stash: &git::Stash) -> String {
+        match Local.timestamp_opt(stash.timestamp, 0).single() {
+            Some(t) if self.absolute_dates => t
+                .format_with_items(self.config.ui.date_format.items())
+                .to_string(),
+            _ => stash.date.clone(),
+        }
+    }
+
+    /// Move the border between the stash list and the preview pane by
+    /// `step` percent of the width
+    pub fn resize_split(&mut self, step: i16) {
+        self.split = self
+            .split
+            .saturating_add_signed(step)
+            .clamp(MIN_SPLIT, 100 - MIN_SPLIT);
+    }
+
+    /// Start loading the selected stash's preview in the background, so moving
+    /// the cursor never waits on git
+    pub fn update_preview(&mut self) {
+        if !self.preview {
+            return;
+        }
+        let Some(stash) = self.selected_stash() else {
+            return;
+        };
+        if self.preview_cache.contains_key(&stash.sha) || self.preview_pending.contains(&stash.sha)
+        {
+            return;
+        }
+        // The list already brought the counts along; no need to ask git again
+        if let Some(files) = &stash.numstat {
+            let lines = diff::stat_lines(files);
+            self.preview_cache.insert(stash.sha.clone(), lines);
+            return;
+        }
+
+        let (sha, name) = (stash.sha.clone(), stash.name.clone());
+        self.preview_pending.insert(sha.clone());
+        self.spawn(move || {
+            let lines = match git::stash_files(&name) {
+                Ok(raw) => raw.lines().map(|l| l.to_string()).collect(),
+                Err(e) if git::was_cancelled(&e) => vec!["Cancelled.".to_string()],
+                Err(e) => vec![format!("Error: {e}")],
+            };
+            JobResult::Preview { sha, lines }
+        });
+    }
+
+    pub fn move_up(&mut self) {
+        if self.selected > 0 {
+            self.selected -= 1;
+        }
+    }
+
+    pub fn move_down(&mut self) {
+        let len = self.visible_rows().len();
+        if len > 0 && self.selected < len - 1 {
+            self.selected += 1;
+        }
+    }
+
+    pub fn scroll_diff_up(&mut self) {
+        if self.diff_scroll > 0 {
+            self.diff_scroll -= 1;
+        }
+    }
+
+    pub fn page_diff_up(&mut self, lines: usize) {
+        self.diff_scroll = self.diff_scroll.saturating_sub(lines.max(1));
+    }
+
+    pub fn page_diff_down(&mut self, lines: usize) {
+        let last = self.diff_len().saturating_sub(1);
+        self.diff_scroll = (self.diff_scroll + lines.max(1)).min(last);
+    }
+
+    /// Scroll so line `n` (counting from 1) is at the top, as `{n}G` does
+    pub fn scroll_diff_to(&mut self, n: usize) {
+        self.diff_scroll = n.saturating_sub(1).min(self.diff_len().saturating_sub(1));
+    }
+
+    pub fn scroll_diff_down(&mut self) {
+        if self.diff_scroll + 1 < self.diff_len() {
+            self.diff_scroll += 1;
+        }
+    }
+
+    /// Note where each file and hunk starts, for `]`/`[` and `}`/`{`
+    fn index_diff(&mut self) {
+        self.diff_files.clear();
+        self.diff_hunks.clear();
+        for (i, line) in self.diff_content.lines().iter().enumerate() {
+            if line.starts_with("diff --git ") {
+                self.diff_files.push(i);
+            } else if line.starts_with("@@") {
+                self.diff_hunks.push(i);
+            }
+        }
+    }
+
+    /// Fold the file under the cursor down to its header, or unfold it
+    pub fn toggle_file_fold(&mut self) {
+        let Some(header) = self.diff_content.toggle_file(self.diff_scroll) else {
+            return;
+        };
+        self.diff_scroll = header;
+        self.index_diff();
+        self.find_diff_matches();
+        self.diff_match = self
+            .diff_match
+            .min(self.diff_matches.len().saturating_sub(1));
+    }
+
+    /// Scroll to the next (or previous) file in the diff
+    pub fn jump_file(&mut self, forward: bool) {
+        self.diff_scroll = jump(&self.diff_files, self.diff_scroll, forward);
+    }
+
+    /// Scroll to the next (or previous) hunk in the diff
+    pub fn jump_hunk(&mut self, forward: bool) {
+        self.diff_scroll = jump(&self.diff_hunks, self.diff_scroll, forward);
+    }
+
+    /// Scroll long lines sideways; this switches wrapping off
+    pub fn scroll_diff_right(&mut self) {
+        self.diff_wrap = false;
+        let widest = self
+            .diff_content
+            .lines()
+            .iter()
+            .map(|l| l.chars().count())
+            .max()
+            .unwrap_or(0);
+        self.diff_hscroll = (self.diff_hscroll + HSCROLL_STEP).min(widest.saturating_sub(1));
+    }
+
+    pub fn scroll_diff_left(&mut self) {
+        self.diff_hscroll = self.diff_hscroll.saturating_sub(HSCROLL_STEP);
+    }
+
+    /// Wrap long diff lines, or cut them off at the edge and scroll sideways
+    pub fn toggle_diff_wrap(&mut self) {
+        self.diff_wrap = !self.diff_wrap;
+        self.diff_hscroll = 0;
+    }
+}
+
+/// The first of `marks` after `from` (or the last before it), staying put if
+/// there is none
+fn jump(marks: &[usize], from: usize, forward: bool) -> usize {
+    let target = if forward {
+        marks.iter().find(|&&m| m > from)
+    } else {
+        marks.iter().rev().find(|&&m| m < from)
+    };
+    target.copied().unwrap_or(from)
+}
+
+/// Columns moved per sideways scroll in the diff view
+const HSCROLL_STEP: usize = 8;
+
+/// Work out how far a stash's branch has moved relative to the current one
+fn branch_relation(branch: &str, current: &str) -> Option<BranchRelation> {
+    if branch == current {
+        Some(BranchRelation::Current)
+    } else if !git::branch_exists(branch) {
+        Some(BranchRelation::Gone)
+    } else {
+        git::ahead_behind(branch)
+            .ok()
+            .map(|(ahead, behind)| BranchRelation::Diverged { ahead, behind })
+    }
+}
+
+/// Write a stash's diff to `stash-<index>-<message>.<ext>`, returning the file name
+fn export_stash(
+    stash: &git::Stash,
+    opts: &git::DiffOptions,
+    format: ExportFormat,
+) -> Result<String> {
+    let file_name = export_file_name(stash, format.extension());
+    let diff = git::stash_diff(&stash.name, opts)?;
+    let contents = match format {
+        ExportFormat::Html => report::html_diff(stash, &diff)?,
+        ExportFormat::Patch | ExportFormat::Diff => diff,
+    };
+    fs::write(local_path(&file_name), contents)
+        .with_context(|| format!("Failed to write {file_name}"))?;
+    Ok(file_name)
+}
+
+/// A file name typed or written by gsm, resolved against the directory gsm was
+/// started in, or the root of a repository switched to since
+fn local_path(file_name: &str) -> PathBuf {
+    git::location().work_dir().join(file_name)
+}
+
+/// `stash-<index>-<message>.<extension>`, with the message cut to a short slug
+fn export_file_name(stash: &git::Stash, extension: &str) -> String {
+    format!(
+        "stash-{}-{}.{extension}",
+        stash.index,
+        slug(&stash.short_msg)
+    )
+}
+
+/// The first few words of `text`, lowercased ASCII joined by dashes
+fn slug(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>()
+        .split('-')
+        .filter(|p| !p.is_empty())
+        .take(6)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Lines in the first batch of a streamed diff: about a screenful, so the view
+/// fills at once
+const FIRST_BATCH: usize = 200;
+/// Lines per later batch; bigger, since each one re-indexes the whole diff
+const STREAM_BATCH: usize = 20_000;
+
+/// Send a stash's diff to the open view in batches of lines as git produces it
+fn stream_diff(
+    request: u64,
+    name: &str,
+    opts: &git::DiffOptions,
+    tx: &Sender<JobResult>,
+) -> Result<()> {
+    let mut partial = String::new();
+    let mut batch: Vec<String> = Vec::new();
+    let send = |lines: Vec<String>| {
+        let _ = tx.send(JobResult::DiffLines { request, lines });
+    };
+    let mut sent = false;
+    git::stash_diff_chunked(name, opts, &mut |chunk| {
+        partial.push_str(chunk);
+        let Some(end) = partial.rfind('\n') else {
+            return;
+        };
+        let rest = partial.split_off(end + 1);
+        batch.extend(partial.split_terminator('\n').map(String::from));
+        partial = rest;
+        if batch.len() >= if sent { STREAM_BATCH } else { FIRST_BATCH } {
+            send(std::mem::take(&mut batch));
+            sent = true;
+        }
+    })?;
+    if !partial.is_empty() {
+        batch.push(partial);
+    }
+    if !batch.is_empty() {
+        send(batch);
+    }
+    Ok(())
+}
+
+/// Drop stashes by commit SHA. Dropping shifts the indices of older stashes, so
+/// refs are resolved fresh and dropped from the highest index down.
+fn drop_by_sha(shas: &HashSet<String>) -> Result<usize> {
+    let mut indices: Vec<usize> = git::list_stashes()?
+        .iter()
+        .filter(|s| shas.contains(&s.sha))
+        .map(|s| s.index)
+        .collect();
+    indices.sort_unstable_by(|a, b| b.cmp(a));
+
+    for index in &indices {
+        git::drop_stash(&format!("stash@{{{index}}}"))?;
+    }
+    Ok(indices.len())
+}
+
+/// Count files in a diff where a line was removed and re-added differing only by CRLF/LF
+fn count_line_ending_files(diff: &[String]) -> usize {
+    let mut count = 0;
+    // Removed lines without their \r, and whether they had one
+    let mut removed: HashSet<(&str, bool)> = HashSet::new();
+    let mut flagged = false;
+
+    for line in diff {
+        if line.starts_with("diff --git") {
+            removed.clear();
+            flagged = false;
+        } else if flagged || line.starts_with("---") || line.starts_with("+++") {
+            continue;
+        } else if let Some(old) = line.strip_prefix('-') {
+            removed.insert((old.trim_end_matches('\r'), old.ends_with('\r')));
+        } else if let Some(new) = line.strip_prefix('+') {
+            let differs_only_by_cr =
+                removed.contains(&(new.trim_end_matches('\r'), !new.ends_with('\r')));
+            if differs_only_by_cr {
+                flagged = true;
+                count += 1;
+            }
+        }
+    }
+
+    count
+}
+
+/// Read a patch file and store it as a new stash, returning the stash message used
+pub fn import_patch(path: &str, message: Option<&str>) -> Result<String> {
+    let patch =
+        fs::read_to_string(local_path(path)).with_context(|| format!("Failed to read {path}"))?;
+    if patch.trim().is_empty() {
+        bail!("{path} is empty");
+    }
+
+    let message = match message {
+        Some(m) => m.to_string(),
+        None => {
+            let file_name = Path::new(path)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.to_string());
+            format!("imported {file_name}")
+        }
+    };
+
+    git::import_patch(&patch, &message)?;
+    Ok(message)
+}
+
+/// How often the stash reflog is checked for outside changes
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long the header shows that the list reloaded by itself
+const REFRESHED_INDICATOR: Duration = Duration::from_secs(2);
+
+/// Diffs and file lists kept for reopening without git
+const DIFF_CACHE_SIZE: usize = 8;
+
+/// Time per frame spent taking in finished background work
+const JOB_BUDGET: Duration = Duration::from_millis(50);
+
+/// How long a toast stays up
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+
+/// What the spinner says while apply targets load; their result is only
+/// taken while it still does
+const TARGETS_LOADING: &str = "finding apply targets";
+
+/// What the spinner says while a stash's tree is listed
+const TREE_LOADING: &str = "listing files";
+
+/// Narrowest either side of the list/preview split may get, in percent
+pub const MIN_SPLIT: u16 = 20;
+
+/// How far `<` and `>` move the split, in percent
+pub const SPLIT_STEP: i16 = 5;
+
+/// Note `env` on the stash a push just made, if it made one: git reports
+/// "No local changes to save" as success, and then stash@{0} is still the
+/// stash that was `before`. Returns whether a stash was made.
+fn record_env(before: Option<&str>, env: Option<&str>) -> Result<bool> {
+    let Some(sha) = git::stash_top().filter(|top| Some(top.as_str()) != before) else {
+        return Ok(false);
+    };
+    if let Some(env) = env.filter(|e| !e.is_empty()) {
+        git::merge_note_fields(&sha, env)
+            .context("Stash created, but recording its environment failed")?;
+    }
+    Ok(true)
+}
+
+/// Other worktrees and branches not checked out anywhere, as apply targets
+fn apply_targets() -> Result<Vec<ApplyTarget>> {
+    let here = git::toplevel()?;
+    let worktrees = git::list_worktrees()?;
+
+    let mut targets: Vec<ApplyTarget> = worktrees
+        .iter()
+        .filter(|w| w.path != here)
+        .map(|w| ApplyTarget::Worktree {
+            path: w.path.clone(),
+            branch: w.branch.clone(),
+        })
+        .collect();
+
+    for branch in git::list_branches()? {
+        let checked_out = worktrees
+            .iter()
+            .any(|w| w.branch.as_deref() == Some(branch.as_str()));
+        if !checked_out {
+            targets.push(ApplyTarget::Branch(branch));
+        }
+    }
+    Ok(targets)
+}
+
+/// A file's contents as lines for the tree browser, after `lines`
+fn plain_lines(content: &[u8], mut lines: Vec<String>) -> Vec<String> {
+    if content.contains(&0) {
+        lines.push("(binary file)".to_string());
+    } else {
+        lines.extend(
+            String::from_utf8_lossy(content)
+                .lines()
+                .map(|l| l.to_string()),
+        );
+    }
+    lines
+}
+
+/// Note field holding a stash's reminder date
+const REMIND_KEY: &str = "remind";
+const ANNOTATION_KEY: &str = "note";
+const LABELS_KEY: &str = "labels";
+
+/// The environment captured in a stash's gsm note, without the fields that
+/// annotations, reminders and labels keep in the same note
+fn environment_lines(note: Option<&str>) -> Vec<String> {
+    note.unwrap_or_default()
+        .lines()
+        .filter(|line| {
+            ![REMIND_KEY, ANNOTATION_KEY, LABELS_KEY].iter().any(|key| {
+                line.strip_prefix(key)
+                    .is_some_and(|rest| rest.starts_with(": "))
+            })
+        })
+        .map(String::from)
+        .collect()
+}
+
+fn reminders_from(notes: &HashMap<String, String>) -> HashMap<String, NaiveDate> {
+    git::note_fields(notes, REMIND_KEY)
+        .into_iter()
+        .filter_map(|(sha, date)| Some((sha, date.parse().ok()?)))
+        .collect()
+}
+
+/// Stashes with added or removed lines containing `pattern` (ignoring case),
+/// each with a patch of just the hunks that matched
+fn grep_stashes(
+    stashes: &[git::Stash],
+    pattern: &str,
+    opts: &git::DiffOptions,
+) -> Result<Vec<GrepHit>> {
+    let needle = pattern.to_lowercase();
+    let mut hits = Vec::new();
+    for stash in stashes {
+        let mut raw = git::stash_diff(&stash.name, opts)?;
+        if let Some(untracked) = git::stash_untracked(&stash.name, false)? {
+            if !raw.is_empty() && !raw.ends_with('\n') {
+                raw.push('\n');
+            }
+            raw.push_str(&untracked);
+        }
+        let files = diff::parse(&raw);
+        let hunks: usize = files
+            .iter()
+            .map(|f| {
+                f.hunks
+                    .iter()
+                    .filter(|h| h.changes_contain(&needle))
+                    .count()
+            })
+            .sum();
+        if hunks == 0 {
+            continue;
+        }
+        hits.push(GrepHit {
+            sha: stash.sha.clone(),
+            name: stash.name.clone(),
+            short_msg: stash.short_msg.clone(),
+            files: files
+                .iter()
+                .filter(|f| f.hunks.iter().any(|h| h.changes_contain(&needle)))
+                .count(),
+            hunks,
+            patch: diff::build_patch(&files, |fi, hi| {
+                files[fi].hunks[hi].changes_contain(&needle)
+            }),
+        });
+    }
+    Ok(hits)
+}
+
+fn labels_from(notes: &HashMap<String, String>) -> HashMap<String, Vec<String>> {
+    git::note_fields(notes, LABELS_KEY)
+        .into_iter()
+        .map(|(sha, list)| (sha, list.split(',').map(String::from).collect()))
+        .collect()
+}
+
+/// `2025-07-01`, or a delay from today: `3d`, `2w`, `1m`
+fn parse_remind_date(input: &str) -> Result<NaiveDate> {
+    remind_date_from(input, Local::now().date_naive())
+}
+
+/// `parse_remind_date` with relative dates counted from `today`
+fn remind_date_from(input: &str, today: NaiveDate) -> Result<NaiveDate> {
+    if let Ok(date) = input.parse::<NaiveDate>() {
+        return Ok(date);
+    }
+    let (count, unit) =
+        input.split_at(input.len() - input.chars().last().map_or(0, char::len_utf8));
+    let date = match (count.trim_start_matches('+').parse::<u32>(), unit) {
+        (Ok(n), "d") => today.checked_add_days(Days::new(n.into())),
+        (Ok(n), "w") => today.checked_add_days(Days::new(u64::from(n) * 7)),
+        (Ok(n), "m") => today.checked_add_months(Months::new(n)),
+        _ => bail!("Not a date: '{input}' (use YYYY-MM-DD, 3d, 2w or 1m)"),
+    };
+    date.with_context(|| format!("Date out of range: {input}"))
+}
+
+/// Suspend the TUI, open `path` in $EDITOR (or vi), then restore the TUI
+fn open_in_editor<B: Backend>(terminal: &mut Terminal<B>, path: &str) -> Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let here = git::toplevel()?;
+
+    let _suspended = Suspended::new(terminal)?;
+    // Editors may carry arguments, e.g. EDITOR="code --wait"
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+    Command::new(program)
+        .args(parts)
+        .arg(path)
+        .current_dir(here)
+        .status()
+        .with_context(|| format!("Failed to run {editor}"))?;
+    Ok(())
+}
+
+/// Suspend the TUI and hand `file` to `git send-email`, which may ask about
+/// the mail or SMTP on the terminal before sending it
+fn send_email<B: Backend>(terminal: &mut Terminal<B>, file: &str, to: &str) -> Result<()> {
+    // send-email runs from the top level, so hand it an absolute path
+    let path = fs::canonicalize(file)?;
+    let here = git::toplevel()?;
+
+    let _suspended = Suspended::new(terminal)?;
+    let status = Command::new("git")
+        .args(["send-email", "--to", to])
+        .arg(&path)
+        .current_dir(here)
+        .status()
+        .context("Failed to run git send-email")?;
+    if !status.success() {
+        bail!("git send-email did not send the mail");
+    }
+    Ok(())
+}
+
+/// Suspend the TUI and pipe `text` into $PAGER, else delta, else less. Text
+/// that is already `rendered` in colour skips delta.
+fn open_in_pager<B: Backend>(terminal: &mut Terminal<B>, text: &str, rendered: bool) -> Result<()> {
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| {
+        let has_delta = !rendered
+            && Command::new("delta")
+                .arg("--version")
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok_and(|s| s.success());
+        if has_delta { "delta" } else { "less -R" }.to_string()
+    });
+
+    let _suspended = Suspended::new(terminal)?;
+    // Pagers may carry arguments too, e.g. PAGER="less -FRX"
+    let mut parts = pager.split_whitespace();
+    let program = parts.next().unwrap_or("less");
+    Command::new(program)
+        .args(parts)
+        .stdin(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            if let Some(mut stdin) = child.stdin.take() {
+                // The pager may quit before reading everything
+                let _ = stdin.write_all(text.as_bytes());
+                let _ = stdin.write_all(b"\n");
+            }
+            child.wait()
+        })
+        .with_context(|| format!("Failed to run {pager}"))?;
+    Ok(())
+}
+
+/// The terminal handed over to a program run in the foreground. The TUI is
+/// put back when this is dropped, however the program's run ended.
+struct Suspended<'a, B: Backend> {
+    terminal: &'a mut Terminal<B>,
+}
+
+impl<'a, B: Backend> Suspended<'a, B> {
+    fn new(terminal: &'a mut Terminal<B>) -> Result<Self> {
+        disable_raw_mode()?;
+        let suspended = Self { terminal };
+        execute!(io::stdout(), DisableBracketedPaste, LeaveAlternateScreen)?;
+        Ok(suspended)
+    }
+}
+
+impl<B: Backend> Drop for Suspended<'_, B> {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), EnterAlternateScreen, EnableBracketedPaste);
+        let _ = enable_raw_mode();
+        let _ = self.terminal.clear();
+    }
+}
+
+pub fn run<B: Backend>(terminal: &mut Terminal<B>, config: config::Config) -> Result<()> {
+    let mut app = App::new(config)?;
+    if let Ok(here) = git::toplevel() {
+        repos::remember(Path::new(&here));
+    }
+    if let Some(guidance) = journal::recover()? {
+        app.mode = Mode::Message(guidance);
+    }
+
+    let mut last_mode = app.mode.clone();
+    loop {
+        app.poll_jobs();
+        app.poll_hooks();
+        app.expire_toast();
+        app.watch_stash_list();
+        app.update_preview();
+        terminal.draw(|f| ui::render(f, &app))?;
+
+        if events::handle_events(&mut app)? {
+            break;
+        }
+
+        if let Some(path) = app.pending_editor.take() {
+            let todo = app.todo_path.take();
+            if let Err(e) = open_in_editor(terminal, &path) {
+                if let Some(todo) = todo {
+                    let _ = fs::remove_file(todo);
+                }
+                app.mode = Mode::Message(format!("Error: {e:#}"));
+            } else if let Some(todo) = todo {
+                if let Err(e) = app.load_todo(&todo) {
+                    app.mode = Mode::Message(format!("Error: {e}"));
+                }
+            } else if app.mode == Mode::Conflict && !app.load_conflicts()? {
+                app.reload()?;
+                app.toast("All conflicts resolved.");
+            }
+        }
+
+        if let Some((text, rendered)) = app.pending_pager.take()
+            && let Err(e) = open_in_pager(terminal, &text, rendered)
+        {
+            app.mode = Mode::Message(format!("Error: {e:#}"));
+        }
+
+        if let Some((file, to)) = app.pending_email.take() {
+            match send_email(terminal, &file, &to) {
+                Ok(()) => app.toast(format!("Sent {file} to {to}.")),
+                Err(e) => app.mode = Mode::Message(format!("Error: {e} ({file} was kept)")),
+            }
+        }
+
+        // Mode switches this turn run their enter/exit hooks before the next frame
+        modes::transition(&mut app, &last_mode);
+        last_mode = app.mode.clone();
+    }
+
+    // Losing the session state isn't worth an error on the way out
+    let _ = app.save_state();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn environment_lines_skip_other_fields() {
+        let note = "describe: v1-dirty\nnote: check tests\nremind: 2025-01-01\n\
+                    labels: wip\nrebase in progress\nnotes: not a field";
+        assert_eq!(
+            environment_lines(Some(note)),
+            [
+                "describe: v1-dirty",
+                "rebase in progress",
+                "notes: not a field"
+            ]
+        );
+        assert!(environment_lines(None).is_empty());
+    }
+
+    #[test]
+    fn remind_date_absolute() {
+        let today = date(2024, 1, 31);
+        assert_eq!(
+            remind_date_from("2025-06-01", today).unwrap(),
+            date(2025, 6, 1)
+        );
+    }
+
+    #[test]
+    fn remind_date_relative() {
+        let today = date(2024, 1, 31);
+        assert_eq!(remind_date_from("3d", today).unwrap(), date(2024, 2, 3));
+        assert_eq!(remind_date_from("+2w", today).unwrap(), date(2024, 2, 14));
+        // Month arithmetic clamps to the end of a shorter month
+        assert_eq!(remind_date_from("1m", today).unwrap(), date(2024, 2, 29));
+        assert_eq!(remind_date_from("0d", today).unwrap(), today);
+    }
+
+    #[test]
+    fn remind_date_rejects_garbage() {
+        let today = date(2024, 1, 31);
+        for input in ["", "d", "3y", "soon", "2024-13-01", "-1d", "3dé"] {
+            assert!(remind_date_from(input, today).is_err(), "{input}");
+        }
+    }
+
+    fn lines(text: &str) -> Vec<String> {
+        text.split('\n').map(String::from).collect()
+    }
+
+    #[test]
+    fn line_ending_changes_counted_once_per_file() {
+        let diff = lines(
+            "diff --git a/crlf.txt b/crlf.txt\n--- a/crlf.txt\n+++ b/crlf.txt\n\
+             -one\r\n-two\r\n+one\n+two\n\
+             diff --git a/lf.txt b/lf.txt\n-same\n+same\r\n\
+             diff --git a/edit.txt b/edit.txt\n-old\r\n+new\n",
+        );
+        assert_eq!(count_line_ending_files(&diff), 2);
+        // A file's own ---/+++ header lines are never compared
+        let headers = lines("diff --git a/x b/x\n--- a/x\r\n+++ a/x\n");
+        assert_eq!(count_line_ending_files(&headers), 0);
+    }
+
+    #[test]
+    fn jump_between_marks() {
+        let marks = [3, 10, 20];
+        assert_eq!(jump(&marks, 0, true), 3);
+        assert_eq!(jump(&marks, 3, true), 10);
+        assert_eq!(jump(&marks, 15, false), 10);
+        assert_eq!(jump(&marks, 10, false), 3);
+        // Nothing further that way: stay put
+        assert_eq!(jump(&marks, 20, true), 20);
+        assert_eq!(jump(&marks, 2, false), 2);
+        assert_eq!(jump(&[], 5, true), 5);
+    }
+
+    #[test]
+    fn slug_keeps_six_ascii_words() {
+        assert_eq!(slug("WIP: Fix the login form!"), "wip-fix-the-login-form");
+        assert_eq!(slug("Größe ändern"), "gr-e-ndern");
+        assert_eq!(
+            slug("one two three four five six seven"),
+            "one-two-three-four-five-six"
+        );
+        assert_eq!(slug("日本語 🎉"), "");
+    }
+
+    #[test]
+    fn record_env_leaves_an_older_stash_alone_when_nothing_was_stashed() {
+        let repo = git::testing::TempRepo::new();
+        repo.stash("first");
+        repo.git(&[
+            "notes",
+            "--ref",
+            "gsm",
+            "add",
+            "-m",
+            "note: keep me",
+            "stash@{0}",
+        ]);
+        repo.enter(|| {
+            let before = git::stash_top();
+            git::push_stash("nothing", git::PushOptions::default()).unwrap();
+            assert!(!record_env(before.as_deref(), Some("describe: v1")).unwrap());
+        });
+        assert_eq!(
+            repo.git(&["notes", "--ref", "gsm", "show", "stash@{0}"]),
+            "note: keep me"
+        );
+    }
+
+    #[test]
+    fn record_env_merges_into_the_new_stash_note() {
+        let repo = git::testing::TempRepo::new();
+        repo.enter(|| {
+            let before = git::stash_top();
+            repo.write("a.txt", "changed\n");
+            git::push_stash("second", git::PushOptions::default()).unwrap();
+            assert!(record_env(before.as_deref(), Some("describe: v1")).unwrap());
+        });
+        assert_eq!(
+            repo.git(&["notes", "--ref", "gsm", "show", "stash@{0}"]),
+            "describe: v1"
+        );
+    }
+
+    #[test]
+    fn drop_by_sha_survives_shifting_indices() {
+        let repo = git::testing::TempRepo::new();
+        for message in ["s0", "s1", "s2", "s3", "s4"] {
+            repo.stash(message);
+        }
+        let sha = |n: usize| repo.git(&["rev-parse", &format!("stash@{{{n}}}")]);
+        // stash@{1} is s3 and stash@{3} is s1; dropping s3 first would have
+        // moved s1 to stash@{2}
+        let shas = HashSet::from([sha(1), sha(3), "0".repeat(40)]);
+
+        let dropped = repo.enter(|| drop_by_sha(&shas)).unwrap();
+        assert_eq!(dropped, 2);
+        assert_eq!(
+            repo.git(&["stash", "list", "--format=%gs"]),
+            "On main: s4\nOn main: s2\nOn main: s0"
+        );
+    }
+}
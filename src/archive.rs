@@ -0,0 +1,96 @@
+use crate::git;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Stashes kept outside the repository, each as `<sha>.bundle` plus a
+/// `<sha>.json` of what it was, in `$XDG_DATA_HOME/gsm/archive/<repo>-<id>/`
+/// (falling back to `~/.local/share`), where `<id>` tells apart repositories
+/// with the same name. They outlive drops, `git stash clear` and garbage
+/// collection of the stash itself; restoring one needs the commit it was made
+/// on to still be in the repository.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub sha: String,
+    pub message: String, // full stash subject, restored as it was
+    pub short_msg: String,
+    pub branch: String,
+    pub created: String,
+    pub archived: i64, // unix seconds
+    #[serde(skip)]
+    pub bundle: PathBuf,
+}
+
+/// The archive directory for the current repository
+pub fn dir() -> Result<PathBuf> {
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/share")))
+        .context("Neither XDG_DATA_HOME nor HOME is set")?;
+    let top = git::toplevel()?;
+    let name = Path::new(&top)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "repository".to_string());
+    // Keyed by the shared git dir, so every worktree of a repository shares one
+    let id = fnv1a(git::common_dir()?.to_string_lossy().as_bytes());
+    Ok(base
+        .join("gsm")
+        .join("archive")
+        .join(format!("{name}-{id:016x}")))
+}
+
+/// 64-bit FNV-1a, stable across builds unlike `DefaultHasher`
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Archive a stash; archiving it again just refreshes the copy
+pub fn save(stash: &git::Stash) -> Result<()> {
+    let dir = dir()?;
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    git::bundle_stash(&stash.sha, &dir.join(format!("{}.bundle", stash.sha)))?;
+
+    let entry = Entry {
+        sha: stash.sha.clone(),
+        message: stash.message.clone(),
+        short_msg: stash.short_msg.clone(),
+        branch: stash.branch.clone(),
+        created: stash.created.clone(),
+        archived: chrono::Local::now().timestamp(),
+        bundle: PathBuf::new(),
+    };
+    let path = dir.join(format!("{}.json", stash.sha));
+    fs::write(&path, serde_json::to_string_pretty(&entry)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Everything archived for this repository, most recently archived first;
+/// entries whose bundle has gone missing are left out
+pub fn list() -> Result<Vec<Entry>> {
+    let dir = dir()?;
+    let Ok(files) = fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+    let mut entries: Vec<Entry> = files
+        .filter_map(|f| f.ok().map(|f| f.path()))
+        .filter(|path| path.extension().is_some_and(|e| e == "json"))
+        .filter_map(|path| {
+            let raw = fs::read_to_string(&path).ok()?;
+            let mut entry: Entry = serde_json::from_str(&raw).ok()?;
+            entry.bundle = path.with_extension("bundle");
+            entry.bundle.exists().then_some(entry)
+        })
+        .collect();
+    entries.sort_by_key(|e| std::cmp::Reverse(e.archived));
+    Ok(entries)
+}
+
+/// Bring an archived stash back to the top of the stash list
+pub fn restore(entry: &Entry) -> Result<()> {
+    git::unbundle_stash(&entry.bundle, &entry.sha, &entry.message)
+}
@@ -0,0 +1,61 @@
+use crate::git;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+
+/// Every apply, pop, drop and push, one JSON object per line, oldest first
+const LOG_FILE: &str = "gsm-log.jsonl";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub time: i64, // unix seconds
+    pub action: String,
+    pub sha: String,     // stash commit; empty if it couldn't be resolved
+    pub message: String, // the stash's subject, e.g. "On main: wip"
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Entry {
+    /// Whether the entry removed a stash that `git stash store` could bring back
+    pub fn restorable(&self) -> bool {
+        self.ok && !self.sha.is_empty() && matches!(self.action.as_str(), "drop" | "pop")
+    }
+}
+
+/// Append one entry. The log is a convenience, so failing to write it never
+/// fails the operation it describes.
+pub fn record(action: &str, sha: &str, message: &str, error: Option<String>) {
+    let entry = Entry {
+        time: chrono::Local::now().timestamp(),
+        action: action.to_string(),
+        sha: sha.to_string(),
+        message: message.to_string(),
+        ok: error.is_none(),
+        error,
+    };
+    let Ok(path) = git::git_path(LOG_FILE) else {
+        return;
+    };
+    let Ok(line) = serde_json::to_string(&entry) else {
+        return;
+    };
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+        let _ = writeln!(file, "{line}");
+    }
+}
+
+/// Logged entries, newest first; lines that don't parse are skipped
+pub fn load() -> Vec<Entry> {
+    let Ok(path) = git::git_path(LOG_FILE) else {
+        return Vec::new();
+    };
+    let raw = fs::read_to_string(path).unwrap_or_default();
+    let mut entries: Vec<Entry> = raw
+        .lines()
+        .filter_map(|l| serde_json::from_str(l).ok())
+        .collect();
+    entries.reverse();
+    entries
+}
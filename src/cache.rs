@@ -0,0 +1,36 @@
+use std::collections::VecDeque;
+
+/// A small least-recently-used cache. Lookups scan the entries, which is fine
+/// for the handful it is meant to hold.
+pub struct Lru<K, V> {
+    capacity: usize,
+    entries: VecDeque<(K, V)>, // most recently used first
+}
+
+impl<K: PartialEq, V> Lru<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// Look up `key`, marking it as just used
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let i = self.entries.iter().position(|(k, _)| k == key)?;
+        let entry = self.entries.remove(i)?;
+        self.entries.push_front(entry);
+        self.entries.front().map(|(_, v)| v)
+    }
+
+    /// Add or replace an entry, evicting the least recently used one if full
+    pub fn insert(&mut self, key: K, value: V) {
+        self.entries.retain(|(k, _)| *k != key);
+        self.entries.push_front((key, value));
+        self.entries.truncate(self.capacity);
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(&K) -> bool) {
+        self.entries.retain(|(k, _)| keep(k));
+    }
+}
@@ -0,0 +1,201 @@
+use crate::{app, config, git, report, ui};
+use anyhow::{Context, Result, bail};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use serde::Serialize;
+use std::io::Write;
+use std::path::PathBuf;
+use unicode_width::UnicodeWidthStr;
+
+/// gsm : Git Stash Manager
+///
+/// Run without a subcommand to open the interactive TUI.
+#[derive(Parser, Debug)]
+#[command(name = "gsm", version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Open the repository at this path instead of the current directory
+    #[arg(long, global = true, value_name = "PATH")]
+    pub repo: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Print the stash list as a plain table (also used when stdout isn't a terminal)
+    List,
+
+    /// Import a patch file as a new stash without touching the working tree
+    Import {
+        /// Path to the patch file (e.g. from `git diff` or `git stash show -p`)
+        file: String,
+
+        /// Stash message (defaults to "imported <file name>")
+        #[arg(short, long)]
+        message: Option<String>,
+    },
+
+    /// Print a stash in a format suited to other tools
+    Show {
+        /// Stash to show: an index (`2`) or a ref (`stash@{2}`)
+        #[arg(default_value = "0")]
+        stash: String,
+
+        /// Output representation
+        #[arg(short, long, value_enum, default_value_t = ShowFormat::Patch)]
+        format: ShowFormat,
+    },
+
+    /// Write a Markdown report of all stashes, for pasting into an issue or wiki
+    Report {
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Print a shell completion script, e.g. `gsm completions zsh > _gsm`
+    Completions {
+        /// Shell to generate completions for
+        shell: Shell,
+    },
+
+    /// Print the man page in roff format, e.g. `gsm man > gsm.1`
+    Man,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum ShowFormat {
+    /// Full unified diff
+    Patch,
+    /// `git diff --stat` style summary
+    Stat,
+    /// Changed file paths, one per line
+    Files,
+    /// Stash metadata and per-file counts as JSON
+    Json,
+    /// One line: ref, branch, message, totals and age
+    Summary,
+}
+
+/// Run a subcommand without starting the TUI
+pub fn run(command: Command, config: &config::Config) -> Result<()> {
+    match command {
+        Command::List => list()?,
+        Command::Import { file, message } => {
+            let msg = app::import_patch(&file, message.as_deref())?;
+            println!("Imported {file} as stash@{{0}}: {msg}");
+        }
+        Command::Show { stash, format } => {
+            show(&stash, format, &git::DiffOptions::from_config(&config.diff))?
+        }
+        Command::Completions { shell } => completions(shell)?,
+        Command::Man => man()?,
+        Command::Report { output } => {
+            let stashes = git::list_stashes()?;
+            let report = report::markdown(&stashes.iter().collect::<Vec<_>>())?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, report)
+                        .with_context(|| format!("Failed to write {path}"))?;
+                    println!("Wrote report of {} stash(es) to {path}", stashes.len());
+                }
+                None => print!("{report}"),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write completions for `shell` to stdout, generated from the CLI definition
+pub fn completions(shell: Shell) -> Result<()> {
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut Cli::command(), "gsm", &mut script);
+    std::io::stdout()
+        .write_all(&script)
+        .context("Failed to write the completion script")
+}
+
+/// Write the man page to stdout, generated from the CLI definition
+pub fn man() -> Result<()> {
+    clap_mangen::Man::new(Cli::command())
+        .render(&mut std::io::stdout())
+        .context("Failed to write the man page")
+}
+
+/// Plain-text stash table: ref, branch, age and message, one stash per line
+fn list() -> Result<()> {
+    let stashes = git::list_stashes()?;
+    let name_width = stashes.iter().map(|s| s.name.len()).max().unwrap_or(0);
+    let branch_width = stashes.iter().map(|s| s.branch.width()).max().unwrap_or(0);
+    let date_width = stashes.iter().map(|s| s.date.len()).max().unwrap_or(0);
+
+    for stash in &stashes {
+        println!(
+            "{:<name_width$}  {}  {:<date_width$}  {}",
+            stash.name,
+            ui::fit(&stash.branch, branch_width),
+            stash.date,
+            stash.short_msg
+        );
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct ShowJson<'a> {
+    #[serde(flatten)]
+    stash: &'a git::Stash,
+    files: Vec<git::FileChange>,
+}
+
+fn show(stash_arg: &str, format: ShowFormat, opts: &git::DiffOptions) -> Result<()> {
+    let stash = find_stash(stash_arg)?;
+
+    match format {
+        ShowFormat::Patch => print!("{}", git::stash_diff(&stash.name, opts)?),
+        ShowFormat::Stat => print!("{}", git::stash_files(&stash.name)?),
+        ShowFormat::Files => {
+            for file in git::stash_numstat(&stash.name)? {
+                println!("{}", file.path);
+            }
+        }
+        ShowFormat::Json => {
+            let files = git::stash_numstat(&stash.name)?;
+            let json = ShowJson {
+                stash: &stash,
+                files,
+            };
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        }
+        ShowFormat::Summary => {
+            let files = git::stash_numstat(&stash.name)?;
+            let insertions: usize = files.iter().filter_map(|f| f.insertions).sum();
+            let deletions: usize = files.iter().filter_map(|f| f.deletions).sum();
+            println!(
+                "{} ({}) {} — {} file(s), +{} -{}, {}",
+                stash.name,
+                stash.branch,
+                stash.short_msg,
+                files.len(),
+                insertions,
+                deletions,
+                stash.date
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Resolve `2` or `stash@{2}` to a listed stash
+fn find_stash(arg: &str) -> Result<git::Stash> {
+    let name = match arg.parse::<usize>() {
+        Ok(index) => format!("stash@{{{index}}}"),
+        Err(_) => arg.to_string(),
+    };
+
+    match git::list_stashes()?.into_iter().find(|s| s.name == name) {
+        Some(stash) => Ok(stash),
+        None => bail!("No such stash: {arg}"),
+    }
+}
@@ -0,0 +1,44 @@
+use anyhow::Result;
+use base64::Engine;
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+/// The system clipboard, kept open for the whole session: on X11 and Wayland
+/// the copied text is served by this process and vanishes when it's dropped.
+static CLIPBOARD: Mutex<Option<arboard::Clipboard>> = Mutex::new(None);
+
+/// Copy `text` to the clipboard. Returns where it went, for the status message.
+///
+/// Over SSH the local system clipboard is the wrong machine's, so the text is
+/// sent to the terminal as an OSC 52 sequence instead. The same fallback is
+/// used when no system clipboard is available.
+pub fn copy(text: &str) -> Result<&'static str> {
+    let over_ssh =
+        std::env::var_os("SSH_TTY").is_some() || std::env::var_os("SSH_CONNECTION").is_some();
+    if !over_ssh && copy_system(text).is_ok() {
+        return Ok("clipboard");
+    }
+    copy_osc52(text)?;
+    Ok("terminal clipboard (OSC 52)")
+}
+
+fn copy_system(text: &str) -> Result<()> {
+    let mut clipboard = CLIPBOARD.lock().expect("clipboard lock poisoned");
+    if clipboard.is_none() {
+        *clipboard = Some(arboard::Clipboard::new()?);
+    }
+    if let Some(clipboard) = clipboard.as_mut() {
+        clipboard.set_text(text)?;
+    }
+    Ok(())
+}
+
+/// Ask the terminal to set its clipboard. Works through SSH in most modern
+/// terminals; tmux needs `set -g set-clipboard on`.
+fn copy_osc52(text: &str) -> Result<()> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{encoded}\x07")?;
+    stdout.flush()?;
+    Ok(())
+}
@@ -0,0 +1,411 @@
+use anyhow::{Context, Result};
+use chrono::format::{Item, StrftimeItems};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// User configuration, read from `$XDG_CONFIG_HOME/gsm/config.toml`
+/// (falling back to `~/.config/gsm/config.toml`). Every field is optional.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub ui: UiConfig,
+    pub git: GitConfig,
+    pub stash: StashConfig,
+    pub forge: ForgeConfig,
+    pub diff: DiffConfig,
+    pub preview: PreviewConfig,
+    pub confirm: ConfirmConfig,
+    pub hooks: HooksConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct UiConfig {
+    /// Where the key-hint bar goes: "bottom", "top" or "hidden"
+    pub hints: HintPlacement,
+    /// Show the getting-started panel when a repository has no stashes
+    pub onboarding: bool,
+    /// Share of the width the stash list gets beside the preview pane, in
+    /// percent; `<` and `>` adjust it per repository
+    pub split: u16,
+    /// How the list shows when stashes were made: "relative" ("2 days ago")
+    /// or "absolute"; Ctrl-t switches
+    pub dates: DateStyle,
+    /// strftime format for absolute dates
+    pub date_format: DateFormat,
+    /// Columns of the stash list in order, each "name" or "name:width";
+    /// columns that don't fit a narrow terminal are left out
+    pub columns: Vec<ColumnSpec>,
+    /// Colour dates by age, from green up to `heat_fresh_days` to red from
+    /// `heat_old_days` on; when off, only stale stashes stand out
+    pub heatmap: bool,
+    pub heat_fresh_days: u32,
+    pub heat_old_days: u32,
+    /// Key style of text fields: "emacs" (readline keys) or "vim", which
+    /// adds a normal mode on Esc
+    pub input: InputStyle,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            hints: HintPlacement::default(),
+            onboarding: true,
+            split: 55,
+            dates: DateStyle::default(),
+            date_format: DateFormat::try_from("%Y-%m-%d %H:%M".to_string())
+                .expect("the default date format is valid"),
+            columns: ["index", "branch", "message:35", "date"]
+                .into_iter()
+                .filter_map(|c| ColumnSpec::try_from(c.to_string()).ok())
+                .collect(),
+            heatmap: true,
+            heat_fresh_days: 1,
+            heat_old_days: 90,
+            input: InputStyle::default(),
+        }
+    }
+}
+
+/// A strftime format, parsed once when the config is read; chrono would
+/// panic formatting with a bad one
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct DateFormat(Vec<Item<'static>>);
+
+impl DateFormat {
+    pub fn items(&self) -> std::slice::Iter<'_, Item<'static>> {
+        self.0.iter()
+    }
+}
+
+impl TryFrom<String> for DateFormat {
+    type Error = String;
+
+    fn try_from(raw: String) -> Result<Self, Self::Error> {
+        StrftimeItems::new(&raw)
+            .parse_to_owned()
+            .map(Self)
+            .map_err(|_| format!("bad date format '{raw}'"))
+    }
+}
+
+/// A stash list column, e.g. "branch" or "message:50"
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ColumnSpec {
+    pub column: Column,
+    pub width: Option<usize>, // the column's own default when unset
+}
+
+impl TryFrom<String> for ColumnSpec {
+    type Error = String;
+
+    fn try_from(raw: String) -> Result<Self, Self::Error> {
+        let (name, width) = match raw.split_once(':') {
+            Some((name, width)) => {
+                let width = width
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("bad width in '{raw}'"))?;
+                (name, Some(width))
+            }
+            None => (raw.as_str(), None),
+        };
+        let column = match name.trim() {
+            "index" => Column::Index,
+            "branch" => Column::Branch,
+            "message" => Column::Message,
+            "date" => Column::Date,
+            "size" => Column::Size,
+            "author" => Column::Author,
+            other => {
+                return Err(format!(
+                    "unknown column '{other}' (index, branch, message, date, size or author)"
+                ));
+            }
+        };
+        Ok(Self { column, width })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Column {
+    Index,
+    Branch,
+    Message,
+    Date,
+    Size,
+    Author,
+}
+
+impl Column {
+    /// Width used when the config doesn't give one
+    pub fn default_width(self) -> usize {
+        match self {
+            Column::Index => 3,
+            Column::Branch => 20,
+            Column::Message => 35,
+            Column::Date => 16,
+            Column::Size => 18,
+            Column::Author => 16,
+        }
+    }
+
+    /// Which columns give way first when the list is too narrow; the message
+    /// always stays
+    pub fn priority(self) -> u8 {
+        match self {
+            Column::Author => 0,
+            Column::Size => 1,
+            Column::Branch => 2,
+            Column::Date => 3,
+            Column::Index => 4,
+            Column::Message => u8::MAX,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateStyle {
+    #[default]
+    Relative,
+    Absolute,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputStyle {
+    #[default]
+    Emacs,
+    Vim,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HintPlacement {
+    #[default]
+    Bottom,
+    Top,
+    Hidden,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct GitConfig {
+    /// How git is accessed: "cli" (the `git` binary) or "libgit2" (needs the
+    /// `libgit2` cargo feature)
+    pub backend: BackendKind,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    #[default]
+    Cli,
+    Libgit2,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StashConfig {
+    /// Record `git describe`, rebase/merge state and dirty submodules as a
+    /// note on each stash gsm creates
+    pub capture_env: bool,
+    /// Stashes older than this many days are flagged as stale; 0 disables
+    pub stale_days: u32,
+    /// Message for stashes made from the new-stash popup, e.g.
+    /// "{branch}: {input} ({date})", with `{input}` the text typed, `{branch}`
+    /// the current branch, `{date}` and `{time}`; unset keeps the text as typed
+    pub template: Option<String>,
+    /// Remote that `U` publishes stashes to and Ctrl-f lists published
+    /// stashes from
+    pub remote: String,
+    /// Copy every stash into the archive directory before dropping it
+    pub archive_on_drop: bool,
+}
+
+impl Default for StashConfig {
+    fn default() -> Self {
+        Self {
+            capture_env: false,
+            stale_days: 30,
+            template: None,
+            remote: "origin".to_string(),
+            archive_on_drop: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ForgeConfig {
+    /// Remote whose web page "open in browser" links to
+    pub remote: String,
+    /// URL template for a commit, e.g. "https://git.example.com/me/repo/commit/{sha}";
+    /// derived from the remote's URL when unset
+    pub commit_url: Option<String>,
+}
+
+impl Default for ForgeConfig {
+    fn default() -> Self {
+        Self {
+            remote: "origin".to_string(),
+            commit_url: None,
+        }
+    }
+}
+
+/// Shell commands run after stash events, with the stash described in
+/// `GSM_*` environment variables
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    pub on_apply: Option<String>,
+    pub on_pop: Option<String>,
+    pub on_drop: Option<String>,
+    pub on_push: Option<String>,
+    /// Seconds a hook may run before it is killed
+    pub timeout_secs: u64,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            on_apply: None,
+            on_pop: None,
+            on_drop: None,
+            on_push: None,
+            timeout_secs: 30,
+        }
+    }
+}
+
+impl HooksConfig {
+    /// The command configured for `event` ("apply", "pop", "drop" or "push")
+    pub fn command(&self, event: &str) -> Option<&str> {
+        match event {
+            "apply" => self.on_apply.as_deref(),
+            "pop" => self.on_pop.as_deref(),
+            "drop" => self.on_drop.as_deref(),
+            "push" => self.on_push.as_deref(),
+            _ => None,
+        }
+        .filter(|c| !c.trim().is_empty())
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct DiffConfig {
+    /// `git diff --diff-algorithm` used for stash diffs
+    pub algorithm: DiffAlgorithm,
+    /// Merge hunks separated by at most this many unchanged lines
+    /// (`--inter-hunk-context`)
+    pub inter_hunk_context: u32,
+    /// Widen hunks to their whole enclosing function (`--function-context`)
+    pub function_context: bool,
+    /// Renderer the pager shows diffs through, e.g. "delta", "diff-so-fancy"
+    /// or "difft"
+    pub external: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffAlgorithm {
+    #[default]
+    Myers,
+    Minimal,
+    Patience,
+    Histogram,
+}
+
+impl DiffAlgorithm {
+    pub fn next(self) -> Self {
+        match self {
+            DiffAlgorithm::Myers => DiffAlgorithm::Minimal,
+            DiffAlgorithm::Minimal => DiffAlgorithm::Patience,
+            DiffAlgorithm::Patience => DiffAlgorithm::Histogram,
+            DiffAlgorithm::Histogram => DiffAlgorithm::Myers,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DiffAlgorithm::Myers => "myers",
+            DiffAlgorithm::Minimal => "minimal",
+            DiffAlgorithm::Patience => "patience",
+            DiffAlgorithm::Histogram => "histogram",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct ConfirmConfig {
+    /// When `a` asks before applying
+    pub apply: ConfirmPolicy,
+    /// When `p` asks before popping
+    pub pop: ConfirmPolicy,
+    /// When `x` asks before dropping; pinned stashes are always asked about
+    pub drop: ConfirmPolicy,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConfirmPolicy {
+    #[default]
+    Always,
+    Never,
+    /// Only when it could lose work: applying onto uncommitted changes, and
+    /// every pop and drop
+    DestructiveOnly,
+    /// Ask, and require typing the stash's index instead of `y`
+    TypeIndex,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct PreviewConfig {
+    /// Commands that render a file for the tree browser, keyed by extension,
+    /// e.g. `md = "glow -"` or `png = "chafa {file}"`
+    pub commands: HashMap<String, String>,
+}
+
+/// Path of the config file, if a config directory can be determined
+pub fn config_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(base.join("gsm").join("config.toml"))
+}
+
+/// Load the config file; a missing file yields the defaults
+pub fn load() -> Result<Config> {
+    let Some(path) = config_path() else {
+        return Ok(Config::default());
+    };
+    if !path.exists() {
+        return Ok(Config::default());
+    }
+
+    let raw =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    toml::from_str(&raw).with_context(|| format!("Invalid config in {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn date_format_is_checked_when_read() {
+        let ui: UiConfig = toml::from_str(r#"date_format = "%d.%m.%Y""#).unwrap();
+        assert_eq!(ui.date_format.items().count(), 5);
+        assert!(toml::from_str::<UiConfig>(r#"date_format = "%Q""#).is_err());
+    }
+}
@@ -0,0 +1,371 @@
+use crate::app::UNTRACKED_HEADER;
+use crate::git::FileChange;
+use unicode_width::UnicodeWidthStr;
+
+/// A unified diff split into files and hunks, so parts of it can be picked
+/// and stitched back into a valid patch.
+#[derive(Debug, Clone)]
+pub struct FilePatch {
+    pub path: String,
+    pub header: Vec<String>, // "diff --git", "index", "---", "+++" ...
+    pub hunks: Vec<Hunk>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Hunk {
+    pub header: String, // "@@ -a,b +c,d @@ ..."
+    pub lines: Vec<String>,
+}
+
+impl Hunk {
+    /// True if an added or removed line contains `needle`, ignoring case.
+    /// `needle` must already be lowercase.
+    pub fn changes_contain(&self, needle: &str) -> bool {
+        self.lines.iter().any(|l| {
+            (l.starts_with('+') || l.starts_with('-')) && l[1..].to_lowercase().contains(needle)
+        })
+    }
+}
+
+/// Parse `git diff` output into files and hunks
+pub fn parse(raw: &str) -> Vec<FilePatch> {
+    let mut files: Vec<FilePatch> = Vec::new();
+
+    for line in raw.split_terminator('\n') {
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            let path = rest
+                .rsplit_once(" b/")
+                .map(|(_, p)| p.to_string())
+                .unwrap_or_else(|| rest.to_string());
+            files.push(FilePatch {
+                path,
+                header: vec![line.to_string()],
+                hunks: Vec::new(),
+            });
+            continue;
+        }
+
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+
+        if line.starts_with("@@") {
+            file.hunks.push(Hunk {
+                header: line.to_string(),
+                lines: Vec::new(),
+            });
+        } else if let Some(hunk) = file.hunks.last_mut() {
+            hunk.lines.push(line.to_string());
+        } else {
+            file.header.push(line.to_string());
+        }
+    }
+
+    files
+}
+
+/// Rebuild a patch containing only the hunks for which `keep(file, hunk)` is true
+pub fn build_patch(files: &[FilePatch], keep: impl Fn(usize, usize) -> bool) -> String {
+    let mut out = String::new();
+
+    for (fi, file) in files.iter().enumerate() {
+        let hunks: Vec<&Hunk> = file
+            .hunks
+            .iter()
+            .enumerate()
+            .filter(|(hi, _)| keep(fi, *hi))
+            .map(|(_, h)| h)
+            .collect();
+        if hunks.is_empty() {
+            continue;
+        }
+
+        for line in &file.header {
+            out.push_str(line);
+            out.push('\n');
+        }
+        for hunk in hunks {
+            out.push_str(&hunk.header);
+            out.push('\n');
+            for line in &hunk.lines {
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+    }
+
+    out
+}
+
+/// Widest +/- bar in a stat summary
+const STAT_BAR: usize = 40;
+
+/// A `--stat`-style summary built from numstat counts: one "path | N ++--"
+/// line per file with bars scaled to the busiest file, then the totals
+pub fn stat_lines(files: &[FileChange]) -> Vec<String> {
+    let total = |f: &FileChange| f.insertions.unwrap_or(0) + f.deletions.unwrap_or(0);
+    let widest_path = files.iter().map(|f| f.path.width()).max().unwrap_or(0);
+    // Pad by terminal columns, so wide characters in paths keep the bars aligned
+    let path = |f: &FileChange| {
+        let pad = widest_path - f.path.width();
+        format!("{}{}", f.path, " ".repeat(pad))
+    };
+    let busiest = files.iter().map(total).max().unwrap_or(0);
+    let count_width = busiest.to_string().len();
+    // Shrink bars only when they wouldn't fit; any change gets at least one mark
+    let scale = |n: usize| {
+        if busiest <= STAT_BAR || n == 0 {
+            n
+        } else {
+            (n * STAT_BAR).div_ceil(busiest)
+        }
+    };
+
+    let mut lines: Vec<String> = files
+        .iter()
+        .map(|f| match (f.insertions, f.deletions) {
+            (Some(ins), Some(del)) => format!(
+                " {} | {:>count_width$} {}{}",
+                path(f),
+                ins + del,
+                "+".repeat(scale(ins)),
+                "-".repeat(scale(del)),
+            ),
+            _ => format!(" {} | Bin", path(f)),
+        })
+        .collect();
+
+    let insertions: usize = files.iter().filter_map(|f| f.insertions).sum();
+    let deletions: usize = files.iter().filter_map(|f| f.deletions).sum();
+    lines.push(format!(
+        " {} file(s) changed, {insertions} insertion(s)(+), {deletions} deletion(s)(-)",
+        files.len()
+    ));
+    lines
+}
+
+/// The diff view's content, split into per-file sections that can be folded
+/// down to their header. Scrolling, search and jumps all work on the visible
+/// lines; `text` still gives the whole diff.
+#[derive(Debug, Clone, Default)]
+pub struct DiffModel {
+    all: Vec<String>,
+    files: Vec<FileSection>,
+    visible: Vec<String>,
+    owners: Vec<Option<usize>>, // file section of each visible line
+}
+
+#[derive(Debug, Clone)]
+struct FileSection {
+    start: usize, // the `diff --git` line
+    end: usize,   // one past the last line
+    added: usize,
+    removed: usize,
+    collapsed: bool,
+}
+
+impl DiffModel {
+    /// Lines outside any `diff --git` section (a diffstat, headings) are kept
+    /// as they are and can't be folded
+    pub fn new(lines: Vec<String>) -> Self {
+        let mut model = Self::default();
+        model.extend(lines);
+        model
+    }
+
+    /// Append lines, e.g. as a long diff streams in. A file cut off at the
+    /// end of one batch carries on into the next.
+    pub fn extend(&mut self, lines: Vec<String>) {
+        let start = self.all.len();
+        self.all.extend(lines);
+        for i in start..self.all.len() {
+            let line = &self.all[i];
+            let open = self.files.last_mut().filter(|f| f.end == i);
+            if line.starts_with("diff --git ") {
+                self.files.push(FileSection {
+                    start: i,
+                    end: i + 1,
+                    added: 0,
+                    removed: 0,
+                    collapsed: false,
+                });
+            } else if let Some(file) = open
+                && line != UNTRACKED_HEADER
+            {
+                file.end = i + 1;
+                if line.starts_with('+') && !line.starts_with("+++ ") {
+                    file.added += 1;
+                } else if line.starts_with('-') && !line.starts_with("--- ") {
+                    file.removed += 1;
+                }
+            }
+        }
+
+        if self.files.iter().any(|f| f.collapsed) {
+            self.rebuild();
+            return;
+        }
+        for i in start..self.all.len() {
+            let file = self.files.partition_point(|f| f.start <= i).checked_sub(1);
+            self.visible.push(self.all[i].clone());
+            self.owners.push(file.filter(|&f| i < self.files[f].end));
+        }
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.visible
+    }
+
+    pub fn len(&self) -> usize {
+        self.visible.len()
+    }
+
+    /// The whole diff, folded files included
+    pub fn text(&self) -> String {
+        self.all.join("\n")
+    }
+
+    /// Every line, folded files included
+    pub fn all_lines(&self) -> &[String] {
+        &self.all
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Fold or unfold the file whose section holds visible line `at`. Returns
+    /// where that file's header is afterwards.
+    pub fn toggle_file(&mut self, at: usize) -> Option<usize> {
+        let file = (*self.owners.get(at)?)?;
+        self.files[file].collapsed = !self.files[file].collapsed;
+        self.rebuild();
+        self.owners.iter().position(|&o| o == Some(file))
+    }
+
+    fn rebuild(&mut self) {
+        self.visible.clear();
+        self.owners.clear();
+        let mut i = 0;
+        let mut next_file = 0;
+        while i < self.all.len() {
+            let Some(file) = self.files.get(next_file).filter(|f| f.start == i) else {
+                self.visible.push(self.all[i].clone());
+                self.owners.push(None);
+                i += 1;
+                continue;
+            };
+            let owner = Some(next_file);
+            if file.collapsed {
+                self.visible.push(self.all[i].clone());
+                self.visible.push(format!(
+                    "    ⋯ {} line(s) folded: +{} -{}",
+                    file.end - file.start - 1,
+                    file.added,
+                    file.removed
+                ));
+                self.owners.extend([owner, owner]);
+            } else {
+                self.visible
+                    .extend(self.all[file.start..file.end].iter().cloned());
+                self.owners.extend((file.start..file.end).map(|_| owner));
+            }
+            i = file.end;
+            next_file += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(path: &str, counts: Option<(usize, usize)>) -> FileChange {
+        FileChange {
+            path: path.to_string(),
+            insertions: counts.map(|(i, _)| i),
+            deletions: counts.map(|(_, d)| d),
+        }
+    }
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn stat_lines_align_and_total() {
+        let files = [
+            change("a.rs", Some((2, 1))),
+            change("long/b.rs", Some((0, 10))),
+            change("img.png", None),
+        ];
+        assert_eq!(
+            stat_lines(&files),
+            [
+                " a.rs      |  3 ++-",
+                " long/b.rs | 10 ----------",
+                " img.png   | Bin",
+                " 3 file(s) changed, 2 insertion(s)(+), 11 deletion(s)(-)",
+            ]
+        );
+    }
+
+    #[test]
+    fn stat_lines_scale_busy_files() {
+        let files = [change("big", Some((400, 0))), change("small", Some((1, 0)))];
+        let stat = stat_lines(&files);
+        assert_eq!(stat[0].matches('+').count(), STAT_BAR);
+        // A change too small to scale still gets a mark
+        assert_eq!(stat[1].matches('+').count(), 1);
+    }
+
+    const DIFF: &str = "\
+ 2 files changed
+diff --git a/one b/one
+--- a/one
++++ b/one
+@@ -1 +1 @@
+-old
++new
+diff --git a/two b/two
+--- a/two
++++ b/two
+@@ -0,0 +1 @@
++added";
+
+    #[test]
+    fn diff_model_folds_files() {
+        let mut model = DiffModel::new(lines(DIFF));
+        assert_eq!(model.len(), 12);
+        assert_eq!(model.toggle_file(0), None); // the stat line belongs to no file
+
+        assert_eq!(model.toggle_file(3), Some(1));
+        assert_eq!(model.lines()[1], "diff --git a/one b/one");
+        assert_eq!(model.lines()[2], "    ⋯ 5 line(s) folded: +1 -1");
+        assert_eq!(model.lines()[3], "diff --git a/two b/two");
+        assert_eq!(model.len(), 8);
+        assert_eq!(model.text(), DIFF);
+
+        assert_eq!(model.toggle_file(2), Some(1));
+        assert_eq!(model.lines(), lines(DIFF));
+    }
+
+    #[test]
+    fn diff_model_extends_across_batches() {
+        let all = lines(DIFF);
+        let mut model = DiffModel::new(all[..4].to_vec());
+        model.extend(all[4..].to_vec());
+        model.toggle_file(1);
+        assert_eq!(model.lines()[2], "    ⋯ 5 line(s) folded: +1 -1");
+
+        // Lines arriving while a file is folded land inside it
+        let mut model = DiffModel::new(all[..9].to_vec());
+        model.toggle_file(8);
+        model.extend(all[9..].to_vec());
+        assert_eq!(
+            model.lines().last().unwrap(),
+            "    ⋯ 4 line(s) folded: +1 -0"
+        );
+        assert_eq!(model.all_lines(), all);
+    }
+}
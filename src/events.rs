@@ -1,5 +1,6 @@
-use crate::app::{App, ConfirmAction, Mode};
-use crate::git;
+use crate::actions::{self, Scope};
+use crate::app::App;
+use crate::modes;
 use anyhow::Result;
 use crossterm::event::{self, Event, KeyCode, KeyModifiers};
 use std::time::Duration;
@@ -10,215 +11,94 @@ pub fn handle_events(app: &mut App) -> Result<bool> {
         return Ok(false);
     }
 
-    if let Event::Key(key) = event::read()? {
-    match &app.mode.clone() {
-        Mode::Normal => {
-            if handle_normal(app, key.code, key.modifiers)? {
-                return Ok(true);
-            }
-        }
-        Mode::Diff | Mode::Files => {
-            handle_scroll(app, key.code)?;
-        }
-        Mode::Confirm(action) => {
-            handle_confirm(app, key.code, action.clone())?;
-        }
-        Mode::NewStash => {
-            handle_new_stash(app, key.code)?;
-        }
-        Mode::Message(_) => {
-            app.mode = Mode::Normal;
-        }
+    let key = match event::read()? {
+        Event::Key(key) => key,
+        Event::Paste(text) => {
+            app.paste(&text)?;
+            return Ok(false);
+        }
+        _ => return Ok(false),
+    };
+    // Ctrl-C stops whatever git is doing in the background
+    if key.code == KeyCode::Char('c')
+        && key.modifiers.contains(KeyModifiers::CONTROL)
+        && app.cancel_running()
+    {
+        return Ok(false);
     }
-}
-
-Ok(false)
-}
-
-fn handle_normal(app: &mut App, key: KeyCode, _mods: KeyModifiers) -> Result<bool> {
-    // If searching, intercept keys
-    if app.searching {
-        match key {
-            KeyCode::Esc => {
-                app.searching = false;
-                app.search_query.clear();
-                app.selected = 0;
-            }
-            KeyCode::Enter => {
-                app.searching = false;
-            }
-            KeyCode::Backspace => {
-                app.search_query.pop();
-                app.selected = 0;
-            }
-            KeyCode::Char(c) => {
-                app.search_query.push(c);
-                app.selected = 0;
-            }
-            _ => {}
+    // The vim style's normal mode in a text field comes before the prompt's keys
+    if let Some(input) = app.active_input()
+        && input.captures(&key)
+    {
+        if input.handle_key(key) {
+            app.input_changed()?;
         }
         return Ok(false);
     }
-
-    match key {
-        KeyCode::Char('q') | KeyCode::Esc => return Ok(true),
-
-        // Navigation
-        KeyCode::Up | KeyCode::Char('k') => app.move_up(),
-        KeyCode::Down | KeyCode::Char('j') => app.move_down(),
-
-        // View diff
-        KeyCode::Enter | KeyCode::Char('d') => {
-            if app.selected_stash().is_some() {
-                app.load_diff()?;
-                app.mode = Mode::Diff;
-            }
-        }
-
-        // View files
-        KeyCode::Char('f') => {
-            if app.selected_stash().is_some() {
-                app.load_files()?;
-                app.mode = Mode::Files;
-            }
-        }
-
-        // Apply (keep stash)
-        KeyCode::Char('a') => {
-            if app.selected_stash().is_some() {
-                app.mode = Mode::Confirm(ConfirmAction::Apply);
-            }
-        }
-
-        // Pop (apply + delete)
-        KeyCode::Char('p') => {
-            if app.selected_stash().is_some() {
-                app.mode = Mode::Confirm(ConfirmAction::Pop);
-            }
-        }
-
-        // Drop (delete)
-        KeyCode::Char('x') | KeyCode::Delete => {
-            if app.selected_stash().is_some() {
-                app.mode = Mode::Confirm(ConfirmAction::Drop);
-            }
-        }
-
-        // New stash
-        KeyCode::Char('n') => {
-            app.new_stash_input.clear();
-            app.new_stash_untracked = false;
-            app.mode = Mode::NewStash;
-        }
-
-        // Search
-        KeyCode::Char('/') => {
-            app.search_query.clear();
-            app.searching = true;
-            app.selected = 0;
-        }
-
-        // Clear search
-        KeyCode::Char('c') => {
-            app.search_query.clear();
-            app.selected = 0;
-        }
-
-        _ => {}
+    if app.palette {
+        return modes::palette::handle_key(app, key);
     }
-
-    Ok(false)
+    if handle_count(app, key.code) {
+        return Ok(false);
+    }
+    let quit = if handle_chord(app, key.code)? {
+        false
+    } else {
+        modes::controller(&app.mode).handle_key(app, key)?
+    };
+    // A count lasts for one command, which may take two keys as in `5gg`
+    if app.pending_chord.is_none() {
+        app.count = None;
+    }
+    Ok(quit)
 }
 
-fn handle_scroll(app: &mut App, key: KeyCode) -> Result<bool> {
+/// Collect the digits of a count prefix in the list and diff views. Returns
+/// true if the key was consumed.
+fn handle_count(app: &mut App, key: KeyCode) -> bool {
+    let takes_count = Scope::List.active(app) || Scope::View.active(app);
+    if !takes_count || app.pending_chord.is_some() {
+        return false;
+    }
     match key {
-        KeyCode::Esc | KeyCode::Char('q') => {
-            app.mode = Mode::Normal;
-        }
-        KeyCode::Up | KeyCode::Char('k') => app.scroll_diff_up(),
-        KeyCode::Down | KeyCode::Char('j') => app.scroll_diff_down(),
-        KeyCode::PageUp => {
-            for _ in 0..20 {
-                app.scroll_diff_up();
-            }
-        }
-        KeyCode::PageDown => {
-            for _ in 0..20 {
-                app.scroll_diff_down();
-            }
-        }
-        _ => {}
+        // 0 on its own isn't a count
+        KeyCode::Char(d @ '0'..='9') if d != '0' || app.count.is_some() => {
+            let digit = d.to_digit(10).unwrap_or(0) as usize;
+            let count = app
+                .count
+                .unwrap_or(0)
+                .saturating_mul(10)
+                .saturating_add(digit);
+            app.count = Some(count);
+            true
+        }
+        KeyCode::Esc if app.count.is_some() => {
+            app.count = None;
+            true
+        }
+        _ => false,
     }
-    Ok(false)
 }
 
-fn handle_confirm(app: &mut App, key: KeyCode, action: ConfirmAction) -> Result<bool> {
-    match key {
-        KeyCode::Char('y') | KeyCode::Enter => {
-            if let Some(stash) = app.selected_stash() {
-                let stash_name = stash.name.clone();
-                let result = match action {
-                    ConfirmAction::Apply => git::apply_stash(&stash_name)
-                        .map(|_| "Stash applied successfully.".to_string()),
-                    ConfirmAction::Pop => git::pop_stash(&stash_name)
-                        .map(|_| "Stash popped successfully.".to_string()),
-                    ConfirmAction::Drop => {
-                        git::drop_stash(&stash_name).map(|_| "Stash dropped.".to_string())
-                    }
-                };
+/// Start or finish a multi-key sequence. Returns true if the key was consumed.
+fn handle_chord(app: &mut App, key: KeyCode) -> Result<bool> {
+    let chords = actions::chords(app);
 
-                match result {
-                    Ok(msg) => {
-                        app.reload()?;
-                        app.mode = Mode::Message(msg);
-                    }
-                    Err(e) => {
-                        app.mode = Mode::Message(format!("Error: {e}"));
-                    }
-                }
-            }
+    if let Some(prefix) = app.pending_chord.take() {
+        // Any key ends the sequence; unknown ones (and Esc) just cancel it
+        if let KeyCode::Char(second) = key
+            && let Some(action) = actions::for_chord(app, prefix, second)
+        {
+            actions::run(app, action)?;
         }
-        KeyCode::Char('n') | KeyCode::Esc => {
-            app.mode = Mode::Normal;
-        }
-        _ => {}
+        return Ok(true);
     }
-    Ok(false)
-}
 
-fn handle_new_stash(app: &mut App, key: KeyCode) -> Result<bool> {
     match key {
-        KeyCode::Esc => {
-            app.mode = Mode::Normal;
-        }
-        KeyCode::Enter => {
-            let msg = app.new_stash_input.trim().to_string();
-            if !msg.is_empty() {
-                match git::push_stash(&msg, app.new_stash_untracked) {
-                    Ok(()) => {
-                        app.reload()?;
-                        app.mode = Mode::Message(format!("Stash '{}' created.", msg));
-                    }
-                    Err(e) => {
-                        app.mode = Mode::Message(format!("Error: {e}"));
-                    }
-                }
-            }
+        KeyCode::Char(c) if chords.iter().any(|(p, ..)| *p == c) => {
+            app.pending_chord = Some(c);
+            Ok(true)
         }
-        KeyCode::Backspace => {
-            app.new_stash_input.pop();
-        }
-        KeyCode::Char('u') if app.new_stash_input.is_empty() => {
-            // toggle untracked when input is empty via Ctrl-u-like shortcut
-            app.new_stash_untracked = !app.new_stash_untracked;
-        }
-        KeyCode::Char(c) => {
-            app.new_stash_input.push(c);
-        }
-        KeyCode::Tab => {
-            app.new_stash_untracked = !app.new_stash_untracked;
-        }
-        _ => {}
+        _ => Ok(false),
     }
-    Ok(false)
-}
\ No newline at end of file
+}
@@ -0,0 +1,49 @@
+use crate::git;
+use anyhow::{Context, Result, bail};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Render a stash's diff with the configured `diff.external` tool, returning
+/// its coloured output. difftastic compares whole files, so git runs it as its
+/// external diff over the stash; anything else (delta, diff-so-fancy) is a
+/// filter that reads the unified diff `patch` on stdin. `width` is the
+/// number of columns the output is laid out for.
+pub fn render(command: &str, stash_name: &str, patch: &str, width: u16) -> Result<String> {
+    let mut parts = command.split_whitespace();
+    let Some(program) = parts.next() else {
+        bail!("Empty diff.external command");
+    };
+    let width = width.to_string();
+
+    if is_difftastic(program) {
+        return git::stash_diff_external(stash_name, command, &width);
+    }
+
+    let output = Command::new(program)
+        .args(parts)
+        .env("COLUMNS", &width)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            if let Some(mut stdin) = child.stdin.take() {
+                let _ = stdin.write_all(patch.as_bytes());
+            }
+            child.wait_with_output()
+        })
+        .with_context(|| format!("Failed to run {program}"))?;
+    if !output.status.success() {
+        bail!(
+            "{program} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn is_difftastic(program: &str) -> bool {
+    let name = Path::new(program).file_name().and_then(|n| n.to_str());
+    matches!(name, Some("difft" | "difftastic"))
+}
@@ -0,0 +1,75 @@
+use crate::{config, git};
+use anyhow::{Context, Result, bail};
+use std::process::{Command, Stdio};
+
+/// Web URL of the commit a stash was made on top of, from the configured
+/// template or the remote's URL (GitHub/GitLab/Bitbucket style hosts)
+pub fn base_commit_url(config: &config::ForgeConfig, stash_name: &str) -> Result<String> {
+    let base = git::stash_base(stash_name)?;
+
+    if let Some(template) = &config.commit_url {
+        return Ok(template.replace("{sha}", &base));
+    }
+
+    let remote = git::remote_url(&config.remote)
+        .with_context(|| format!("No remote named {}", config.remote))?;
+    let Some(web) = web_url(&remote) else {
+        bail!("Can't tell the web address of {remote}; set forge.commit_url");
+    };
+
+    let path = if web.contains("gitlab") {
+        "-/commit"
+    } else if web.contains("bitbucket") {
+        "commits"
+    } else {
+        "commit"
+    };
+    Ok(format!("{web}/{path}/{base}"))
+}
+
+/// Turn a clone URL (`git@host:owner/repo.git`, `ssh://…`, `https://…`) into
+/// the repository's https page
+fn web_url(remote: &str) -> Option<String> {
+    let remote = remote.trim().trim_end_matches('/').trim_end_matches(".git");
+
+    let (host, path) = if let Some(rest) = remote
+        .strip_prefix("https://")
+        .or_else(|| remote.strip_prefix("http://"))
+        .or_else(|| remote.strip_prefix("ssh://"))
+        .or_else(|| remote.strip_prefix("git://"))
+    {
+        rest.split_once('/')?
+    } else {
+        // scp-like syntax: [user@]host:owner/repo
+        remote.split_once(':')?
+    };
+
+    // Drop credentials and ports: "git@host:22" -> "host"
+    let host = host.rsplit('@').next()?;
+    let host = host.split(':').next()?;
+    if host.is_empty() || path.is_empty() {
+        return None;
+    }
+    Some(format!("https://{host}/{path}"))
+}
+
+/// Open `url` with $BROWSER or the platform's default handler
+pub fn open(url: &str) -> Result<()> {
+    let (program, args): (String, Vec<&str>) = match std::env::var("BROWSER") {
+        Ok(browser) if !browser.is_empty() => (browser, Vec::new()),
+        _ if cfg!(target_os = "macos") => ("open".to_string(), Vec::new()),
+        _ if cfg!(windows) => ("cmd".to_string(), vec!["/C", "start", ""]),
+        _ => ("xdg-open".to_string(), Vec::new()),
+    };
+
+    // Detached and silent, so the browser's chatter never lands on the TUI
+    Command::new(&program)
+        .args(args)
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run {program}"))?;
+    Ok(())
+}
@@ -0,0 +1,212 @@
+use super::{DiffOptions, FileChange, GitBackend, Stash, parse_stash_subject};
+use crate::config::DiffAlgorithm;
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use git2::{Diff, DiffFormat, DiffStatsFormat, Oid, Patch, Repository};
+use std::sync::Mutex;
+
+/// In-process backend built on the git2 crate. Avoids spawning `git` for the
+/// list/diff calls made on every keypress.
+pub struct Libgit2Backend {
+    repo: Mutex<Repository>,
+}
+
+impl Libgit2Backend {
+    pub fn open() -> Result<Self> {
+        let repo = Repository::open(super::location().root)
+            .context("libgit2 could not open the repository")?;
+        Ok(Self {
+            repo: Mutex::new(repo),
+        })
+    }
+
+    /// Resolve `stash@{N}` to its commit id via the stash reflog
+    fn stash_oid(repo: &Repository, stash_name: &str) -> Result<Oid> {
+        let index: usize = stash_name
+            .strip_prefix("stash@{")
+            .and_then(|s| s.strip_suffix('}'))
+            .and_then(|n| n.parse().ok())
+            .with_context(|| format!("Not a stash reference: {stash_name}"))?;
+
+        let reflog = repo.reflog("refs/stash")?;
+        match reflog.get(index) {
+            Some(entry) => Ok(entry.id_new()),
+            None => bail!("No such stash: {stash_name}"),
+        }
+    }
+
+    /// Diff of a stash against its base commit, as `git stash show` does
+    fn with_diff<T>(
+        &self,
+        stash_name: &str,
+        opts: &DiffOptions,
+        f: impl FnOnce(&Diff) -> Result<T>,
+    ) -> Result<T> {
+        let repo = self.repo.lock().expect("libgit2 repository lock poisoned");
+        let commit = repo.find_commit(Self::stash_oid(&repo, stash_name)?)?;
+        let base = commit.parent(0)?;
+
+        // libgit2 has no histogram diff; patience is its closest relative.
+        // It has no function context either, so that option is ignored here.
+        let mut diff_opts = git2::DiffOptions::new();
+        diff_opts.interhunk_lines(opts.inter_hunk_context);
+        if let Some(lines) = opts.context {
+            diff_opts.context_lines(lines);
+        }
+        match opts.algorithm {
+            DiffAlgorithm::Myers => {}
+            DiffAlgorithm::Minimal => {
+                diff_opts.minimal(true);
+            }
+            DiffAlgorithm::Patience | DiffAlgorithm::Histogram => {
+                diff_opts.patience(true);
+            }
+        }
+
+        let diff = repo.diff_tree_to_tree(
+            Some(&base.tree()?),
+            Some(&commit.tree()?),
+            Some(&mut diff_opts),
+        )?;
+        f(&diff)
+    }
+}
+
+impl GitBackend for Libgit2Backend {
+    fn list_stashes(&self) -> Result<Vec<Stash>> {
+        let repo = self.repo.lock().expect("libgit2 repository lock poisoned");
+        let reflog = match repo.reflog("refs/stash") {
+            Ok(r) => r,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let mut stashes = Vec::new();
+        for (i, entry) in reflog.iter().enumerate() {
+            let message = entry.message().unwrap_or("").to_string();
+            let (branch, short_msg) = parse_stash_subject(&message);
+            let commit = repo.find_commit(entry.id_new()).ok();
+            let timestamp = commit
+                .as_ref()
+                .map_or(0, |c| c.committer().when().seconds());
+            let author = commit.as_ref().map_or_else(String::new, |c| {
+                let who = c.author();
+                format!(
+                    "{} <{}>",
+                    who.name().unwrap_or(""),
+                    who.email().unwrap_or("")
+                )
+            });
+
+            stashes.push(Stash {
+                index: i,
+                name: format!("stash@{{{i}}}"),
+                sha: entry.id_new().to_string(),
+                message,
+                branch,
+                short_msg,
+                date: relative_date(timestamp),
+                timestamp,
+                author,
+                created: absolute_date(commit.as_ref()),
+                files_changed: None,
+                numstat: None,
+            });
+        }
+
+        Ok(stashes)
+    }
+
+    fn stash_diff(&self, stash_name: &str, opts: &DiffOptions) -> Result<String> {
+        self.with_diff(stash_name, opts, |diff| {
+            let mut out = String::new();
+            diff.print(DiffFormat::Patch, |_, _, line| {
+                if matches!(line.origin(), '+' | '-' | ' ') {
+                    out.push(line.origin());
+                }
+                out.push_str(&String::from_utf8_lossy(line.content()));
+                true
+            })?;
+            Ok(out)
+        })
+    }
+
+    fn stash_files(&self, stash_name: &str) -> Result<String> {
+        self.with_diff(stash_name, &DiffOptions::default(), |diff| {
+            let buf = diff.stats()?.to_buf(DiffStatsFormat::FULL, 80)?;
+            Ok(buf.as_str().unwrap_or("").to_string())
+        })
+    }
+
+    fn stash_numstat(&self, stash_name: &str) -> Result<Vec<FileChange>> {
+        self.with_diff(stash_name, &DiffOptions::default(), |diff| {
+            let mut files = Vec::new();
+            for i in 0..diff.deltas().len() {
+                let Some(patch) = Patch::from_diff(diff, i)? else {
+                    continue;
+                };
+                let delta = patch.delta();
+                let path = delta
+                    .new_file()
+                    .path()
+                    .or_else(|| delta.old_file().path())
+                    .map(|p| p.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let (insertions, deletions) = if delta.new_file().is_binary() {
+                    (None, None)
+                } else {
+                    let (_, added, removed) = patch.line_stats()?;
+                    (Some(added), Some(removed))
+                };
+                files.push(FileChange {
+                    path,
+                    insertions,
+                    deletions,
+                });
+            }
+            Ok(files)
+        })
+    }
+
+    fn current_branch(&self) -> Result<String> {
+        let repo = self.repo.lock().expect("libgit2 repository lock poisoned");
+        let head = match repo.head() {
+            Ok(h) => h,
+            Err(_) => return Ok(String::new()), // unborn branch
+        };
+        if !head.is_branch() {
+            return Ok(String::new());
+        }
+        Ok(head.shorthand().unwrap_or("").to_string())
+    }
+}
+
+/// "2025-06-01 14:03:22 +0200"-style date in the committer's zone, like `%ci`
+fn absolute_date(commit: Option<&git2::Commit>) -> String {
+    let Some(when) = commit.map(|c| c.committer().when()) else {
+        return String::new();
+    };
+    FixedOffset::east_opt(when.offset_minutes() * 60)
+        .and_then(|zone| zone.timestamp_opt(when.seconds(), 0).single())
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S %z").to_string())
+        .unwrap_or_default()
+}
+
+/// "3 hours ago"-style date, matching git's `%cr` closely enough for the list
+fn relative_date(timestamp: i64) -> String {
+    let Some(then) = DateTime::<Utc>::from_timestamp(timestamp, 0) else {
+        return String::new();
+    };
+    let secs = (Utc::now() - then).num_seconds().max(0);
+
+    let (n, unit) = match secs {
+        s if s < 90 => (s, "second"),
+        s if s < 90 * 60 => (s / 60, "minute"),
+        s if s < 36 * 3600 => (s / 3600, "hour"),
+        s if s < 14 * 86400 => (s / 86400, "day"),
+        s if s < 10 * 7 * 86400 => (s / (7 * 86400), "week"),
+        s if s < 365 * 86400 => (s / (30 * 86400), "month"),
+        s => (s / (365 * 86400), "year"),
+    };
+    let plural = if n == 1 { "" } else { "s" };
+    format!("{n} {unit}{plural} ago")
+}
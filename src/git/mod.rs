@@ -0,0 +1,2147 @@
+#[cfg(feature = "libgit2")]
+mod libgit2;
+mod progress;
+mod scratch;
+#[cfg(test)]
+pub mod testing;
+
+use crate::audit;
+use crate::config::{self, BackendKind, DiffAlgorithm};
+use crate::hooks;
+use anyhow::{Context, Result, bail};
+use progress::{run_cancellable, run_chunked, run_streaming};
+use scratch::{Scratch, TempIndex};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::{Arc, RwLock};
+
+pub use progress::{Cancelled, Progress, cancel_running, was_cancelled};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Stash {
+    pub index: usize,
+    pub name: String,      // e.g. "stash@{0}"
+    pub sha: String,       // stash commit, stable while indices shift
+    pub message: String,   // e.g. "WIP on main: abc123 Some commit"
+    pub branch: String,    // extracted branch name
+    pub short_msg: String, // user-friendly short message
+    pub date: String,      // relative date from git
+    pub timestamp: i64,    // creation time, unix seconds
+    pub author: String,    // "Name <email>"
+    pub created: String,   // absolute creation date, e.g. "2025-06-01 14:03:22 +0200"
+    #[serde(skip)]
+    pub files_changed: Option<usize>, // from `numstat`, else filled lazily
+    #[serde(skip)]
+    pub numstat: Option<Vec<FileChange>>, // fetched with the list where the backend can
+}
+
+impl Stash {
+    /// Lines inserted and deleted across all files, once `numstat` is loaded;
+    /// binary files count for neither
+    pub fn line_counts(&self) -> Option<(usize, usize)> {
+        let files = self.numstat.as_ref()?;
+        Some(files.iter().fold((0, 0), |(ins, del), f| {
+            (
+                ins + f.insertions.unwrap_or(0),
+                del + f.deletions.unwrap_or(0),
+            )
+        }))
+    }
+}
+
+/// One file touched by a stash, from `--numstat`
+#[derive(Debug, Clone, Serialize)]
+pub struct FileChange {
+    pub path: String,
+    pub insertions: Option<usize>, // None for binary files
+    pub deletions: Option<usize>,
+}
+
+/// One path with uncommitted changes, from `git status --porcelain`
+#[derive(Debug, Clone)]
+pub struct StatusEntry {
+    pub path: String,
+    pub staged: char,   // index status: 'M', 'A', 'D', 'R', ... or ' '
+    pub unstaged: char, // worktree status, same letters; '?' for untracked
+}
+
+impl StatusEntry {
+    pub fn is_untracked(&self) -> bool {
+        self.staged == '?'
+    }
+
+    pub fn is_staged(&self) -> bool {
+        !self.is_untracked() && self.staged != ' '
+    }
+
+    pub fn is_unstaged(&self) -> bool {
+        !self.is_untracked() && self.unstaged != ' '
+    }
+}
+
+/// Commit-level facts about one stash, for the diff view header
+#[derive(Debug, Clone)]
+pub struct StashDetails {
+    pub sha: String,
+    pub author: String, // "Name <email>"
+    pub date: String,   // absolute creation date, e.g. "2025-06-01 14:03:22 +0200"
+    pub parent: String, // abbreviated SHA and subject of the commit it was made on
+}
+
+/// Predicted outcome of applying a stash onto the current checkout
+#[derive(Debug, Clone, PartialEq)]
+pub enum Preflight {
+    Clean,
+    Merge, // applies, but only through a 3-way merge
+    Conflicts(Vec<String>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Worktree {
+    pub path: String,
+    pub branch: Option<String>, // None when detached
+}
+
+/// A submodule of the current repository, with what gsm shows about it
+#[derive(Debug, Clone)]
+pub struct Submodule {
+    pub path: String,           // relative to the repository root
+    pub initialized: bool,      // checked out; uninitialized ones can't be opened
+    pub changed: Option<usize>, // files with uncommitted changes inside it
+    pub stashes: usize,
+}
+
+/// How much history and object data this clone has locally
+#[derive(Debug, Clone, PartialEq)]
+pub enum CloneKind {
+    Full,
+    Shallow,
+    Partial { remote: String }, // objects can be fetched on demand from `remote`
+}
+
+/// How stash diffs are computed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DiffOptions {
+    pub algorithm: DiffAlgorithm,
+    pub inter_hunk_context: u32,
+    pub function_context: bool,
+    pub context: Option<u32>, // unchanged lines around each change; git's 3 when None
+}
+
+impl DiffOptions {
+    pub fn from_config(config: &config::DiffConfig) -> Self {
+        Self {
+            algorithm: config.algorithm,
+            inter_hunk_context: config.inter_hunk_context,
+            function_context: config.function_context,
+            context: None,
+        }
+    }
+
+    /// Extra `git diff` arguments for these options
+    fn args(&self) -> Vec<String> {
+        let mut args = vec![format!("--diff-algorithm={}", self.algorithm.label())];
+        if self.inter_hunk_context > 0 {
+            args.push(format!("--inter-hunk-context={}", self.inter_hunk_context));
+        }
+        if self.function_context {
+            args.push("--function-context".to_string());
+        }
+        if let Some(lines) = self.context {
+            args.push(format!("--unified={lines}"));
+        }
+        args
+    }
+}
+
+/// Ensure we are inside a git repository
+pub fn assert_git_repo(dir: &Path) -> Result<()> {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .output()
+        .context("Failed to run git. Is git installed?")?;
+
+    if !status.status.success() {
+        bail!("Not inside a git repository. Please run gsm from within a git repo.");
+    }
+    Ok(())
+}
+
+/// Where gsm is running: the worktree root every git call runs in, and how
+/// it was reached from the directory gsm was started in
+#[derive(Debug, Clone, Default)]
+pub struct Location {
+    pub root: PathBuf,
+    pub prefix: String, // subdirectory gsm was started in, "" at the root
+    pub worktree: Option<String>, // name of the linked worktree, None for the main one
+}
+
+impl Location {
+    /// The directory gsm was started in, where exports and reports are written
+    pub fn work_dir(&self) -> PathBuf {
+        self.root.join(&self.prefix)
+    }
+}
+
+static LOCATION: RwLock<Option<Location>> = RwLock::new(None);
+
+/// Find the repository around `dir` and run all later git calls at its root,
+/// whichever subdirectory or worktree gsm was started in
+pub fn open_repo(dir: &Path) -> Result<Location> {
+    let out = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args([
+            "rev-parse",
+            "--show-toplevel",
+            "--show-prefix",
+            "--absolute-git-dir",
+            "--git-common-dir",
+        ])
+        .output()
+        .context("Failed to run git rev-parse")?;
+    if !out.status.success() {
+        bail!(
+            "Failed to locate the repository: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+    }
+
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let mut lines = stdout.lines();
+    let mut next = || lines.next().unwrap_or_default().to_string();
+    let (root, prefix, git_dir, common_dir) = (next(), next(), next(), next());
+
+    // The common dir comes back relative to `dir`
+    let same = |a: &Path, b: &Path| {
+        fs::canonicalize(a)
+            .ok()
+            .is_some_and(|a| fs::canonicalize(b).ok() == Some(a))
+    };
+    let git_dir = PathBuf::from(git_dir);
+    let worktree = if same(&git_dir, &dir.join(common_dir)) {
+        None
+    } else {
+        git_dir
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+    };
+
+    let location = Location {
+        root: PathBuf::from(root),
+        prefix: prefix.trim_end_matches('/').to_string(),
+        worktree,
+    };
+    *LOCATION.write().unwrap_or_else(|e| e.into_inner()) = Some(location.clone());
+    Ok(location)
+}
+
+/// The repository found by `open_repo`, or the one a background job was
+/// started in
+pub fn location() -> Location {
+    if let Some(repo) = JOB_REPO.with_borrow(Clone::clone) {
+        return repo.location;
+    }
+    LOCATION
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_default()
+}
+
+/// `git -C <root>`: a git command that runs at the repository root
+fn git_command() -> Command {
+    let mut cmd = Command::new("git");
+    let root = location().root;
+    if !root.as_os_str().is_empty() {
+        cmd.arg("-C").arg(root);
+    }
+    cmd
+}
+
+/// Read-side git operations. The default backend shells out to `git`; with
+/// the `libgit2` cargo feature they can run in-process via the git2 crate.
+/// Mutating operations always go through the `git` binary.
+pub trait GitBackend: Send + Sync {
+    fn list_stashes(&self) -> Result<Vec<Stash>>;
+    fn stash_diff(&self, stash_name: &str, opts: &DiffOptions) -> Result<String>;
+    /// Like `stash_diff`, handing the diff to `sink` in pieces as it is produced
+    fn stash_diff_chunked(
+        &self,
+        stash_name: &str,
+        opts: &DiffOptions,
+        sink: &mut dyn FnMut(&str),
+    ) -> Result<()> {
+        sink(&self.stash_diff(stash_name, opts)?);
+        Ok(())
+    }
+    fn stash_files(&self, stash_name: &str) -> Result<String>;
+    fn stash_numstat(&self, stash_name: &str) -> Result<Vec<FileChange>>;
+    fn current_branch(&self) -> Result<String>;
+}
+
+static BACKEND: RwLock<Option<Arc<dyn GitBackend>>> = RwLock::new(None);
+
+/// Select the backend used from now on. Called again after switching
+/// repositories, since the libgit2 backend holds the repository open.
+pub fn init_backend(kind: BackendKind) -> Result<()> {
+    let backend: Arc<dyn GitBackend> = match kind {
+        BackendKind::Cli => Arc::new(CliBackend),
+        #[cfg(feature = "libgit2")]
+        BackendKind::Libgit2 => Arc::new(libgit2::Libgit2Backend::open()?),
+        #[cfg(not(feature = "libgit2"))]
+        BackendKind::Libgit2 => {
+            bail!("git.backend = \"libgit2\" needs gsm built with `--features libgit2`")
+        }
+    };
+    *BACKEND.write().unwrap_or_else(|e| e.into_inner()) = Some(backend);
+    Ok(())
+}
+
+fn backend() -> Arc<dyn GitBackend> {
+    if let Some(repo) = JOB_REPO.with_borrow(Clone::clone) {
+        return repo.backend;
+    }
+    BACKEND
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_else(|| Arc::new(CliBackend))
+}
+
+/// A repository and the backend open on it, handed to a background job so
+/// its git calls keep going to that repository after a switch
+#[derive(Clone)]
+pub struct Repo {
+    location: Location,
+    backend: Arc<dyn GitBackend>,
+}
+
+thread_local! {
+    static JOB_REPO: RefCell<Option<Repo>> = const { RefCell::new(None) };
+}
+
+/// The repository git calls currently go to
+pub fn current_repo() -> Repo {
+    Repo {
+        location: location(),
+        backend: backend(),
+    }
+}
+
+impl Repo {
+    /// Run `f` with every git call on this thread going to this repository
+    pub fn enter<T>(self, f: impl FnOnce() -> T) -> T {
+        let previous = JOB_REPO.replace(Some(self));
+        let result = f();
+        JOB_REPO.set(previous);
+        result
+    }
+}
+
+/// Make `dir` the repository every later git call runs in. Jobs already
+/// running keep the repository they were started in; on failure the previous
+/// repository stays current.
+pub fn switch_repo(dir: &Path, kind: BackendKind) -> Result<()> {
+    let previous = current_repo();
+    let opened = assert_git_repo(dir)
+        .and_then(|()| open_repo(dir))
+        .and_then(|_| init_backend(kind));
+    if let Err(e) = opened {
+        restore_repo(previous);
+        return Err(e.context(format!("{} is not a usable git repository", dir.display())));
+    }
+    Ok(())
+}
+
+/// Make `repo`, from `current_repo`, current again, undoing a `switch_repo`
+pub fn restore_repo(repo: Repo) {
+    *LOCATION.write().unwrap_or_else(|e| e.into_inner()) = Some(repo.location);
+    *BACKEND.write().unwrap_or_else(|e| e.into_inner()) = Some(repo.backend);
+}
+
+/// List all stashes
+pub fn list_stashes() -> Result<Vec<Stash>> {
+    backend().list_stashes()
+}
+
+/// Get the diff for a specific stash
+pub fn stash_diff(stash_name: &str, opts: &DiffOptions) -> Result<String> {
+    backend().stash_diff(stash_name, opts)
+}
+
+/// The stash's diff in pieces as git produces it, so the start of a huge diff
+/// can be shown before the rest has been read
+pub fn stash_diff_chunked(
+    stash_name: &str,
+    opts: &DiffOptions,
+    sink: &mut dyn FnMut(&str),
+) -> Result<()> {
+    backend().stash_diff_chunked(stash_name, opts, sink)
+}
+
+/// Get the list of files changed in a stash
+pub fn stash_files(stash_name: &str) -> Result<String> {
+    backend().stash_files(stash_name)
+}
+
+/// Per-file insertion/deletion counts of a stash
+pub fn stash_numstat(stash_name: &str) -> Result<Vec<FileChange>> {
+    backend().stash_numstat(stash_name)
+}
+
+/// Every stash reflog entry, to notice when anything outside gsm changes the
+/// list. Asking git rather than watching `logs/refs/stash` works with reftable
+/// repositories too. `None` with no stashes.
+pub fn stash_stamp() -> Option<String> {
+    run_git(&[
+        "log",
+        "--walk-reflogs",
+        "--format=%H %gs",
+        "refs/stash",
+        "--",
+    ])
+    .ok()
+}
+
+/// The newest stash's commit, `None` with no stashes
+pub fn stash_top() -> Option<String> {
+    run_git(&["rev-parse", "--quiet", "--verify", "refs/stash"]).ok()
+}
+
+/// Get current branch name
+pub fn current_branch() -> Result<String> {
+    backend().current_branch()
+}
+
+/// The configured `user.email`, which `author:me` searches for
+pub fn user_email() -> Option<String> {
+    run_git(&["config", "--get", "user.email"])
+        .ok()
+        .map(|email| email.trim().to_string())
+        .filter(|email| !email.is_empty())
+}
+
+/// Split a stash subject ("WIP on <branch>: ..." or "On <branch>: ...") into
+/// its branch and the user-facing part of the message
+fn parse_stash_subject(message: &str) -> (String, String) {
+    let branch = message
+        .strip_prefix("WIP on ")
+        .or_else(|| message.strip_prefix("On "))
+        .and_then(|s| s.split(':').next())
+        .map(|b| b.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    // Short message: after the colon
+    let short_msg = message
+        .split_once(": ")
+        .map(|(_, rest)| rest)
+        .unwrap_or(message)
+        .to_string();
+
+    (branch, short_msg)
+}
+
+/// One record per stash: a separator, then name, SHA, unix time, relative
+/// date, author, absolute date and subject split by unit separators. The
+/// stash's `--numstat` lines follow on their own lines.
+const STASH_LOG_FORMAT: &str = "%x1e%gd%x1f%H%x1f%ct%x1f%cr%x1f%an <%ae>%x1f%ci%x1f%gs";
+
+/// Parse `git log --walk-reflogs refs/stash` output in `STASH_LOG_FORMAT`,
+/// with numstat lines if `with_numstat`
+fn parse_stash_log(raw: &str, with_numstat: bool) -> Vec<Stash> {
+    let mut stashes = Vec::new();
+    for record in raw.split('\x1e').skip(1) {
+        let mut lines = record.lines();
+        let fields: Vec<&str> = lines.next().unwrap_or("").splitn(7, '\x1f').collect();
+        let [name, sha, timestamp, date, author, created, message] = fields[..] else {
+            continue;
+        };
+        let numstat =
+            with_numstat.then(|| lines.filter_map(parse_numstat_line).collect::<Vec<_>>());
+        let (branch, short_msg) = parse_stash_subject(message);
+
+        stashes.push(Stash {
+            index: stashes.len(),
+            name: name.to_string(),
+            sha: sha.to_string(),
+            message: message.to_string(),
+            branch,
+            short_msg,
+            date: date.to_string(),
+            timestamp: timestamp.parse().unwrap_or(0),
+            author: author.to_string(),
+            created: created.to_string(),
+            files_changed: numstat.as_ref().map(Vec::len),
+            numstat,
+        });
+    }
+    stashes
+}
+
+/// One `--numstat` line: insertions, deletions and path, tab-separated
+fn parse_numstat_line(line: &str) -> Option<FileChange> {
+    let mut parts = line.splitn(3, '\t');
+    let insertions = parts.next()?.parse().ok();
+    let deletions = parts.next()?.parse().ok();
+    let path = parts.next()?.to_string();
+    Some(FileChange {
+        path,
+        insertions,
+        deletions,
+    })
+}
+
+/// The default backend: runs the `git` binary
+pub struct CliBackend;
+
+impl GitBackend for CliBackend {
+    fn list_stashes(&self) -> Result<Vec<Stash>> {
+        // Without any stashes there is no refs/stash to walk
+        if run_git(&["rev-parse", "--quiet", "--verify", "refs/stash"]).is_err() {
+            return Ok(Vec::new());
+        }
+        // The reflog with each stash's numstat against its base, all in one
+        // call instead of one `stash show` per stash. That reads every
+        // stash's tree, so one unreadable object fails it; the plain reflog
+        // still lists them, and their numstat loads one by one.
+        let format = format!("--format={STASH_LOG_FORMAT}");
+        let numstat = ["--first-parent", "-m", "--numstat"];
+        let log = |extra: &[&str]| {
+            let mut args = vec!["log", "--walk-reflogs", &format];
+            args.extend(extra);
+            args.extend(["refs/stash", "--"]);
+            run_git_raw(&args).context("Failed to read the stash list")
+        };
+        match log(&numstat) {
+            Ok(raw) => Ok(parse_stash_log(&raw, true)),
+            Err(_) => Ok(parse_stash_log(&log(&[])?, false)),
+        }
+    }
+
+    fn stash_diff(&self, stash_name: &str, opts: &DiffOptions) -> Result<String> {
+        let extra = opts.args();
+        let mut args = vec!["stash", "show", "-p", "--color=never"];
+        args.extend(extra.iter().map(|a| a.as_str()));
+        args.push(stash_name);
+        with_object_recovery(stash_name, || run_git_raw(&args)).context("Failed to get stash diff")
+    }
+
+    fn stash_diff_chunked(
+        &self,
+        stash_name: &str,
+        opts: &DiffOptions,
+        sink: &mut dyn FnMut(&str),
+    ) -> Result<()> {
+        let mut cmd = git_command();
+        cmd.args(["stash", "show", "-p", "--color=never"])
+            .args(opts.args())
+            .arg(stash_name);
+        // A multi-byte character may straddle two chunks
+        let mut carry = Vec::new();
+        let mut sent = false;
+        let output = run_chunked(cmd, &mut |chunk| {
+            sent = true;
+            carry.extend_from_slice(chunk);
+            let valid = match std::str::from_utf8(&carry) {
+                Ok(_) => carry.len(),
+                Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                Err(_) => carry.len(),
+            };
+            let rest = carry.split_off(valid);
+            sink(&String::from_utf8_lossy(&carry));
+            carry = rest;
+        })
+        .context("Failed to get stash diff")?;
+        if !carry.is_empty() {
+            sink(&String::from_utf8_lossy(&carry));
+        }
+        if output.status.success() {
+            return Ok(());
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !sent && is_missing_object(&stderr) {
+            // The buffered path knows how to fetch missing objects and retry
+            sink(&self.stash_diff(stash_name, opts)?);
+            return Ok(());
+        }
+        bail!("Failed to get stash diff: {}", stderr.trim())
+    }
+
+    fn stash_files(&self, stash_name: &str) -> Result<String> {
+        with_object_recovery(stash_name, || {
+            run_git_raw(&["stash", "show", "--stat", "--color=never", stash_name])
+        })
+        .context("Failed to get stash file list")
+    }
+
+    fn stash_numstat(&self, stash_name: &str) -> Result<Vec<FileChange>> {
+        let out = with_object_recovery(stash_name, || {
+            run_git(&["stash", "show", "--numstat", stash_name])
+        })?;
+        Ok(out.lines().filter_map(parse_numstat_line).collect())
+    }
+
+    fn current_branch(&self) -> Result<String> {
+        let output = git_command()
+            .args(["branch", "--show-current"])
+            .output()
+            .context("Failed to get current branch")?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+/// The stash's diff as rendered by `tool` running as git's external diff
+/// (`GIT_EXTERNAL_DIFF`), e.g. difftastic, coloured for a `width`-column terminal
+pub fn stash_diff_external(stash_name: &str, tool: &str, width: &str) -> Result<String> {
+    let mut cmd = git_command();
+    cmd.args(["stash", "show", "-p", "--ext-diff", stash_name])
+        .env("GIT_EXTERNAL_DIFF", tool)
+        .env("DFT_COLOR", "always")
+        .env("DFT_WIDTH", width);
+    let output = run_cancellable(cmd).with_context(|| format!("Failed to run {tool}"))?;
+    if !output.status.success() {
+        bail!(
+            "{tool} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Apply a stash (keep it in the list)
+pub fn apply_stash(stash_name: &str) -> Result<String> {
+    apply_stash_with(stash_name, false, &Progress::default())
+}
+
+/// Apply or pop a stash, streaming git's output into `progress`
+pub fn apply_stash_with(stash_name: &str, pop: bool, progress: &Progress) -> Result<String> {
+    let verb = if pop { "pop" } else { "apply" };
+    audited(verb, stash_name, || {
+        with_object_recovery(stash_name, || apply_stash_once(stash_name, verb, progress))
+    })
+}
+
+fn apply_stash_once(stash_name: &str, verb: &str, progress: &Progress) -> Result<String> {
+    let mut cmd = git_command();
+    cmd.args(["stash", verb, stash_name]);
+    let output = run_streaming(cmd, progress).with_context(|| format!("Failed to {verb} stash"))?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        bail!(
+            "Failed to {verb} stash: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )
+    }
+}
+
+/// Drop (delete) a stash
+pub fn drop_stash(stash_name: &str) -> Result<()> {
+    audited("drop", stash_name, || drop_stash_once(stash_name))
+}
+
+fn drop_stash_once(stash_name: &str) -> Result<()> {
+    let output = git_command()
+        .args(["stash", "drop", stash_name])
+        .output()
+        .context("Failed to drop stash")?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        bail!(
+            "Failed to drop stash: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )
+    }
+}
+
+/// What `git stash push` saves and what it leaves behind
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PushOptions {
+    /// Also stash untracked files (`--include-untracked`)
+    pub include_untracked: bool,
+    /// Also stash untracked and ignored files (`--all`)
+    pub all: bool,
+    /// Leave staged changes in place after stashing (`--keep-index`)
+    pub keep_index: bool,
+    /// Stash only staged changes (`--staged`, git 2.35+)
+    pub staged: bool,
+}
+
+/// Create a new stash with a custom message
+pub fn push_stash(message: &str, opts: PushOptions) -> Result<()> {
+    let (before, _) = stash_identity("stash@{0}");
+    let result = push_stash_once(message, opts);
+    let (sha, subject) = match &result {
+        Ok(()) => stash_identity("stash@{0}"),
+        Err(_) => (String::new(), message.to_string()),
+    };
+    // "No local changes to save" succeeds without making a stash
+    if result.is_err() || sha != before {
+        let error = result.as_ref().err().map(|e| format!("{e:#}"));
+        audit::record("push", &sha, &subject, error);
+    }
+    if result.is_ok() && sha != before {
+        hooks::fire("push", &sha, &subject, &parse_stash_subject(&subject).0);
+    }
+    result
+}
+
+fn push_stash_once(message: &str, opts: PushOptions) -> Result<()> {
+    let mut args = vec!["stash", "push", "-m", message];
+    if opts.all {
+        args.push("--all");
+    } else if opts.include_untracked {
+        args.push("--include-untracked");
+    }
+    if opts.keep_index {
+        args.push("--keep-index");
+    }
+    if opts.staged {
+        args.push("--staged");
+    }
+
+    let output = git_command()
+        .args(&args)
+        .output()
+        .context("Failed to push stash")?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if output.status.success() {
+        Ok(())
+    } else if opts.staged && stderr.contains("unknown option") {
+        bail!("Stashing only staged changes needs git 2.35 or newer")
+    } else {
+        bail!("Failed to create stash: {stderr}")
+    }
+}
+
+/// List local branch names
+pub fn list_branches() -> Result<Vec<String>> {
+    let out = run_git(&["for-each-ref", "--format=%(refname:short)", "refs/heads"])?;
+    Ok(out.lines().map(|l| l.to_string()).collect())
+}
+
+/// List all worktrees of this repository (including the main one)
+pub fn list_worktrees() -> Result<Vec<Worktree>> {
+    let out = run_git(&["worktree", "list", "--porcelain"])?;
+
+    let mut worktrees = Vec::new();
+    for block in out.split("\n\n") {
+        let mut path = None;
+        let mut branch = None;
+        for line in block.lines() {
+            if let Some(p) = line.strip_prefix("worktree ") {
+                path = Some(p.to_string());
+            } else if let Some(b) = line.strip_prefix("branch ") {
+                branch = Some(b.strip_prefix("refs/heads/").unwrap_or(b).to_string());
+            }
+        }
+        if let Some(path) = path {
+            worktrees.push(Worktree { path, branch });
+        }
+    }
+
+    Ok(worktrees)
+}
+
+/// True if a local branch with this name exists
+pub fn branch_exists(branch: &str) -> bool {
+    run_git(&[
+        "rev-parse",
+        "--verify",
+        "--quiet",
+        &format!("refs/heads/{branch}"),
+    ])
+    .is_ok()
+}
+
+/// Commits `branch` has that HEAD lacks (ahead) and vice versa (behind)
+pub fn ahead_behind(branch: &str) -> Result<(usize, usize)> {
+    let range = format!("HEAD...refs/heads/{branch}");
+    let out = run_git(&["rev-list", "--left-right", "--count", &range])?;
+    let mut counts = out.split_whitespace().map(|n| n.parse().unwrap_or(0));
+    let behind = counts.next().unwrap_or(0);
+    let ahead = counts.next().unwrap_or(0);
+    Ok((ahead, behind))
+}
+
+/// Absolute path of the current worktree root
+pub fn toplevel() -> Result<String> {
+    run_git(&["rev-parse", "--show-toplevel"])
+}
+
+/// True if the worktree at `dir` has no uncommitted changes to tracked files
+pub fn is_clean_in(dir: &str) -> Result<bool> {
+    let out = run_git(&["-C", dir, "status", "--porcelain", "--untracked-files=no"])?;
+    Ok(out.is_empty())
+}
+
+/// How many paths in the worktree at `dir` have uncommitted changes,
+/// untracked files included
+pub fn changed_files_in(dir: &str) -> Result<usize> {
+    let out = run_git(&["-C", dir, "status", "--porcelain"])?;
+    Ok(out.lines().count())
+}
+
+/// Every submodule, nested ones included, with its dirty state and stash
+/// count read by running git inside it
+pub fn list_submodules() -> Result<Vec<Submodule>> {
+    let out = run_git(&["submodule", "status", "--recursive"])?;
+    let root = location().root;
+    Ok(out
+        .lines()
+        .filter_map(|line| {
+            // "<state><sha> <path> (<describe>)"; state '-' means not initialized
+            let initialized = !line.starts_with('-');
+            let path = line.get(1..)?.split_whitespace().nth(1)?.to_string();
+            let dir = root.join(&path).to_string_lossy().into_owned();
+            let (changed, stashes) = if initialized {
+                let stashes = run_git(&["-C", &dir, "stash", "list"])
+                    .map(|l| l.lines().count())
+                    .unwrap_or(0);
+                (changed_files_in(&dir).ok(), stashes)
+            } else {
+                (None, 0)
+            };
+            Some(Submodule {
+                path,
+                initialized,
+                changed,
+                stashes,
+            })
+        })
+        .collect())
+}
+
+/// The repository this one is a submodule of, if any
+pub fn superproject() -> Option<PathBuf> {
+    run_git(&["rev-parse", "--show-superproject-working-tree"])
+        .ok()
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Apply a stash inside another worktree (stash refs are shared between worktrees)
+pub fn apply_stash_in(dir: &str, stash_name: &str) -> Result<String> {
+    audited("apply", stash_name, || {
+        run_git(&["-C", dir, "stash", "apply", stash_name])
+    })
+}
+
+/// Put a dropped stash commit back on the stash list, e.g. from the history
+/// screen. Works until git garbage-collects the unreachable commit.
+pub fn restore_stash(sha: &str, message: &str) -> Result<()> {
+    run_git(&["cat-file", "-e", &format!("{sha}^{{commit}}")])
+        .context("The stash commit no longer exists (it may have been garbage-collected)")?;
+    run_git(&["stash", "store", "-m", message, sha])?;
+    audit::record("restore", sha, message, None);
+    Ok(())
+}
+
+/// A stash commit no ref or reflog points at any more, e.g. after `git stash
+/// clear`; it lasts until `git gc` prunes it
+#[derive(Debug, Clone)]
+pub struct LostStash {
+    pub sha: String,
+    pub subject: String, // "WIP on <branch>: ..." or "On <branch>: <message>"
+    pub timestamp: i64,
+}
+
+/// Scan the object store with `git fsck` for unreachable commits shaped like
+/// a stash (a "WIP on"/"On" subject over two or three parents), newest first.
+/// Reads every object, so it takes a while in large repositories.
+pub fn find_lost_stashes() -> Result<Vec<LostStash>> {
+    let out = run_git(&["fsck", "--unreachable", "--no-progress"])?;
+    let shas: Vec<&str> = out
+        .lines()
+        .filter_map(|line| line.strip_prefix("unreachable commit "))
+        .collect();
+    if shas.is_empty() {
+        return Ok(Vec::new());
+    }
+    let commits = run_git_piped(
+        &[
+            "log",
+            "--no-walk",
+            "--stdin",
+            "--format=%H%x1f%P%x1f%ct%x1f%s",
+        ],
+        Some(&shas.join("\n")),
+        None,
+    )?;
+    let mut lost: Vec<LostStash> = commits
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\x1f');
+            let (sha, parents, time, subject) = (
+                fields.next()?,
+                fields.next()?,
+                fields.next()?,
+                fields.next()?,
+            );
+            let parents = parents.split_whitespace().count();
+            let stash_like = (parents == 2 || parents == 3)
+                && (subject.starts_with("WIP on ") || subject.starts_with("On "));
+            stash_like.then(|| LostStash {
+                sha: sha.to_string(),
+                subject: subject.to_string(),
+                timestamp: time.parse().unwrap_or(0),
+            })
+        })
+        .collect();
+    lost.sort_by_key(|s| std::cmp::Reverse(s.timestamp));
+    Ok(lost)
+}
+
+/// How long git gc keeps unreachable objects, as `gc.pruneExpire` gives it
+pub fn prune_expire() -> String {
+    run_git(&["config", "--get", "gc.pruneExpire"]).unwrap_or_else(|_| "2.weeks.ago".to_string())
+}
+
+/// Switch the current worktree to another branch
+pub fn switch_branch(branch: &str) -> Result<()> {
+    run_git(&["switch", branch])?;
+    Ok(())
+}
+
+/// What HEAD is on, for `checkout` to return to: the branch, or the commit
+/// when detached
+pub fn head_ref() -> Result<String> {
+    match current_branch()? {
+        branch if branch.is_empty() => run_git(&["rev-parse", "HEAD"]),
+        branch => Ok(branch),
+    }
+}
+
+/// Check out a branch, or detach at a commit
+pub fn checkout(target: &str) -> Result<()> {
+    run_git(&["checkout", "--quiet", target])?;
+    Ok(())
+}
+
+/// SHA, author and date of a stash, plus the commit it was stashed on top of
+pub fn stash_details(stash_name: &str) -> Result<StashDetails> {
+    let parent = format!("{stash_name}^1");
+    let out = run_git(&[
+        "show",
+        "-s",
+        "--format=%H%x1f%an <%ae>%x1f%ci%x1f%h %s",
+        stash_name,
+        &parent,
+    ])?;
+    let mut lines = out.lines().map(|l| l.split('\x1f').collect::<Vec<_>>());
+    let (Some(stash), Some(parent)) = (lines.next(), lines.next()) else {
+        bail!("Unexpected output from git show {stash_name}");
+    };
+    let [sha, author, date, _] = stash[..] else {
+        bail!("Unexpected output from git show {stash_name}");
+    };
+
+    Ok(StashDetails {
+        sha: sha.to_string(),
+        author: author.to_string(),
+        date: date.to_string(),
+        parent: parent.last().copied().unwrap_or_default().to_string(),
+    })
+}
+
+/// Uncommitted changes in the working tree: staged, unstaged and untracked
+pub fn status() -> Result<Vec<StatusEntry>> {
+    let out = run_git_raw(&["status", "--porcelain", "-z", "--untracked-files=all"])?;
+    Ok(parse_status(&out))
+}
+
+/// Parse `git status --porcelain -z` output
+fn parse_status(out: &str) -> Vec<StatusEntry> {
+    let mut entries = Vec::new();
+    let mut fields = out.split('\0').filter(|f| !f.is_empty());
+    while let Some(field) = fields.next() {
+        let mut chars = field.chars();
+        let (Some(staged), Some(unstaged)) = (chars.next(), chars.next()) else {
+            continue;
+        };
+        // Renames and copies are followed by their original path
+        if matches!(staged, 'R' | 'C') {
+            fields.next();
+        }
+        entries.push(StatusEntry {
+            path: field.get(3..).unwrap_or_default().to_string(),
+            staged,
+            unstaged,
+        });
+    }
+    entries
+}
+
+/// Which side of a conflict to keep
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+    Ours,   // the checked-out version ("Updated upstream")
+    Theirs, // the stashed version ("Stashed changes")
+}
+
+/// List files left unmerged by a conflicting apply/pop
+pub fn conflicted_files() -> Result<Vec<String>> {
+    let out = run_git(&["status", "--porcelain", "--untracked-files=no"])?;
+
+    Ok(out
+        .lines()
+        .filter(|l| l.len() > 3)
+        .filter(|l| {
+            let xy = &l[..2];
+            xy.contains('U') || xy == "AA" || xy == "DD"
+        })
+        .map(|l| l[3..].to_string())
+        .collect())
+}
+
+/// Resolve a conflicted file by taking one side, leaving the result unstaged
+pub fn checkout_side(path: &str, side: Side) -> Result<()> {
+    let flag = match side {
+        Side::Ours => "--ours",
+        Side::Theirs => "--theirs",
+    };
+    run_git(&["checkout", flag, "--", path])?;
+    run_git(&["reset", "-q", "--", path])?;
+    Ok(())
+}
+
+/// Predict whether applying a stash would conflict, without touching anything.
+/// The stash's patch is first checked as-is against the working tree; if it
+/// doesn't fit, a 3-way `--check` replays the merge `stash apply` falls back to.
+pub fn preflight_apply(stash_name: &str) -> Result<Preflight> {
+    let base = format!("{stash_name}^1");
+    let patch = run_git_raw(&[
+        "diff",
+        "--no-color",
+        "--no-ext-diff",
+        "--binary",
+        "--full-index",
+        &base,
+        stash_name,
+    ])?;
+    if patch.trim().is_empty() {
+        return Ok(Preflight::Clean);
+    }
+    let check_err = match run_git_piped(&["apply", "--check", "-"], Some(&patch), None) {
+        Ok(_) => return Ok(Preflight::Clean),
+        Err(e) => e.to_string(),
+    };
+
+    let output = git_output_piped(&["apply", "--check", "--3way", "-"], Some(&patch), None)?;
+    let report = String::from_utf8_lossy(&output.stderr);
+    let mut files: Vec<String> = if output.status.success() {
+        // e.g. "Applied patch to 'src/main.rs' with conflicts."
+        report
+            .lines()
+            .filter(|l| l.ends_with("with conflicts."))
+            .filter_map(|l| Some(l.split('\'').nth(1)?.to_string()))
+            .collect()
+    } else {
+        // Not even a 3-way merge fits; name what the plain check rejected
+        check_err
+            .lines()
+            .filter_map(|l| l.split_once("error: ").map(|(_, rest)| rest))
+            .filter_map(|rest| match rest.strip_prefix("patch failed: ") {
+                Some(at) => at.rsplit_once(':').map(|(path, _)| path),
+                None => rest.split_once(": ").map(|(path, _)| path),
+            })
+            .map(|path| path.to_string())
+            .collect()
+    };
+    if files.is_empty() && output.status.success() {
+        return Ok(Preflight::Merge);
+    }
+    // One file can be named by several hunks, not necessarily in a row
+    files.sort_unstable();
+    files.dedup();
+    Ok(Preflight::Conflicts(files))
+}
+
+/// Abort a conflicting apply, restoring conflicted files but keeping unrelated changes
+pub fn abort_merge() -> Result<()> {
+    run_git(&["reset", "--merge"])?;
+    Ok(())
+}
+
+/// Store a patch as a new stash on top of HEAD without touching the working tree
+pub fn import_patch(patch: &str, message: &str) -> Result<()> {
+    let head_tree = run_git(&["rev-parse", "HEAD^{tree}"])?;
+    store_patch_as_stash(&head_tree, patch, message)
+        .context("Patch does not apply on top of HEAD")?;
+    Ok(())
+}
+
+/// Unstaged changes of the working tree (relative to the index)
+pub fn worktree_diff() -> Result<String> {
+    let output = git_command()
+        .args(["diff", "--no-color", "--no-ext-diff"])
+        .output()
+        .context("Failed to get working tree diff")?;
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Stash exactly the given patch of unstaged changes, then remove it from the
+/// working tree. The index is recorded as-is and left untouched.
+pub fn stash_patch(patch: &str, message: &str) -> Result<()> {
+    let index_tree = run_git(&["write-tree"])?;
+    store_patch_as_stash(&index_tree, patch, message)?;
+
+    run_git_piped(&["apply", "-R", "-"], Some(patch), None)
+        .context("Stash created, but removing the hunks from the working tree failed")?;
+    Ok(())
+}
+
+/// Build a stash commit whose index is `index_tree` and whose working tree is
+/// `index_tree` plus `patch`, and push it onto the stash list
+fn store_patch_as_stash(index_tree: &str, patch: &str, message: &str) -> Result<()> {
+    let head = run_git(&["rev-parse", "HEAD"])?;
+    let branch = match current_branch()? {
+        b if b.is_empty() => "(no branch)".to_string(),
+        b => b,
+    };
+
+    // Build the stashed tree in a throwaway index so the real one stays untouched
+    let temp = TempIndex::new()?;
+    let index_file = temp.path();
+    let tree = (|| {
+        let index = Some(index_file.as_path());
+        run_git_piped(&["read-tree", index_tree], None, index)?;
+        run_git_piped(&["apply", "--cached", "-"], Some(patch), index)?;
+        run_git_piped(&["write-tree"], None, index)
+    })();
+    let tree = tree?;
+
+    // A stash is a merge commit of HEAD and a commit recording the index
+    let index_commit = run_git(&[
+        "commit-tree",
+        index_tree,
+        "-p",
+        &head,
+        "-m",
+        &format!("index on {branch}: {message}"),
+    ])?;
+    let subject = format!("On {branch}: {message}");
+    let stash_commit = run_git(&[
+        "commit-tree",
+        &tree,
+        "-p",
+        &head,
+        "-p",
+        &index_commit,
+        "-m",
+        &subject,
+    ])?;
+
+    run_git(&["stash", "store", "-m", &subject, &stash_commit])?;
+    Ok(())
+}
+
+/// Write a stash to a bundle at `path`: the stash commit with its index and
+/// untracked-file commits. The commit it was made on is left out, so the
+/// repository needs that commit to restore it.
+pub fn bundle_stash(sha: &str, path: &Path) -> Result<()> {
+    // A bundle is made from refs, so the stash gets one for the moment
+    let refname = format!("refs/gsm/archive/{sha}");
+    run_git(&["update-ref", &refname, sha])?;
+    let path = path.to_string_lossy();
+    let base = format!("^{sha}^1");
+    let result = run_git(&["bundle", "create", "--quiet", &path, &refname, &base]);
+    let _ = run_git(&["update-ref", "-d", &refname]);
+    result.map(|_| ())
+}
+
+/// Fetch a stash back out of a bundle made by `bundle_stash` and add it to
+/// the top of the stash list under `message`
+pub fn unbundle_stash(path: &Path, sha: &str, message: &str) -> Result<()> {
+    let refname = format!("refs/gsm/archive/{sha}");
+    // Fails when the commit the stash was made on is no longer here
+    run_git(&["bundle", "verify", "--quiet", &path.to_string_lossy()]).context(
+        "This repository no longer has the commit the stash was made on; \
+         fetch or recreate it, then restore again",
+    )?;
+    run_git(&[
+        "fetch",
+        "--quiet",
+        "--no-tags",
+        &path.to_string_lossy(),
+        &refname,
+    ])?;
+    run_git(&["stash", "store", "-m", message, sha])?;
+    Ok(())
+}
+
+/// Where published stashes live on a remote, as `<prefix><user>/<slug>-<short sha>`
+pub const REMOTE_STASH_PREFIX: &str = "refs/stashes/";
+
+/// A stash published on a remote under `REMOTE_STASH_PREFIX`
+#[derive(Debug, Clone)]
+pub struct RemoteStash {
+    pub sha: String,
+    pub name: String, // the ref without the prefix, "<user>/<slug>-<short sha>"
+}
+
+/// Push a stash commit, and with it the index and untracked-file commits it
+/// is made of, to `refs/stashes/<name>` on `remote`. Not forced, so a
+/// different stash already published under the name is never replaced.
+pub fn publish_stash(sha: &str, remote: &str, name: &str) -> Result<()> {
+    let refspec = format!("{sha}:{REMOTE_STASH_PREFIX}{name}");
+    run_git_network(&["push", "--quiet", remote, &refspec])?;
+    Ok(())
+}
+
+/// The stashes published on `remote`, sorted by name
+pub fn list_remote_stashes(remote: &str) -> Result<Vec<RemoteStash>> {
+    let pattern = format!("{REMOTE_STASH_PREFIX}*");
+    let out = run_git_network(&["ls-remote", remote, &pattern])?;
+    let mut stashes: Vec<RemoteStash> = out
+        .lines()
+        .filter_map(|line| {
+            let (sha, refname) = line.split_once('\t')?;
+            Some(RemoteStash {
+                sha: sha.to_string(),
+                name: refname.strip_prefix(REMOTE_STASH_PREFIX)?.to_string(),
+            })
+        })
+        .collect();
+    stashes.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(stashes)
+}
+
+/// Fetch a published stash and add it to the top of the local stash list
+/// under its original message, which is returned
+pub fn import_remote_stash(remote: &str, stash: &RemoteStash) -> Result<String> {
+    let refname = format!("{REMOTE_STASH_PREFIX}{}", stash.name);
+    run_git_network(&["fetch", "--quiet", "--no-tags", remote, &refname])?;
+    let subject = run_git(&["log", "-1", "--format=%s", &stash.sha])?;
+    run_git(&["stash", "store", "-m", &subject, &stash.sha])?;
+    Ok(subject)
+}
+
+/// The stash as a `git format-patch` mail, ready for `git send-email`. A stash
+/// is a merge commit, which format-patch skips, so its working-tree changes are
+/// recommitted on the commit it was taken on, as a dangling commit nothing
+/// refers to, and that is formatted instead. Untracked files are left out.
+pub fn format_patch(stash_name: &str, subject: &str) -> Result<String> {
+    let base = run_git(&["rev-parse", &format!("{stash_name}^1")])?;
+    let tree = run_git(&["rev-parse", &format!("{stash_name}^{{tree}}")])?;
+    let commit = run_git(&["commit-tree", &tree, "-p", &base, "-m", subject])?;
+    run_git_raw(&["format-patch", "-1", "--stdout", &commit])
+}
+
+/// Record a stash as an ordinary commit on a new branch made at the commit the
+/// stash was taken on, untracked files included. Neither the working tree nor
+/// the index is touched, so a failure leaves nothing to undo. Returns the SHA
+/// of the new commit.
+pub fn stash_to_branch(stash_name: &str, branch: &str, message: &str) -> Result<String> {
+    run_git(&["check-ref-format", "--branch", branch])
+        .with_context(|| format!("'{branch}' is not a valid branch name"))?;
+    if branch_exists(branch) {
+        bail!("A branch named '{branch}' already exists");
+    }
+    let base = run_git(&["rev-parse", &format!("{stash_name}^1")])?;
+
+    let untracked = format!("{stash_name}^3");
+    let tree = if run_git(&["rev-parse", "--verify", "--quiet", &untracked]).is_ok() {
+        // Lay the untracked files over the stashed tree in a throwaway index
+        let temp = TempIndex::new()?;
+        let index_file = temp.path();
+        let tree = (|| {
+            let index = Some(index_file.as_path());
+            run_git_piped(&["read-tree", stash_name], None, index)?;
+            let entries = run_git_raw(&["ls-tree", "-r", "-z", &untracked])?;
+            run_git_piped(
+                &["update-index", "-z", "--index-info"],
+                Some(&entries),
+                index,
+            )?;
+            run_git_piped(&["write-tree"], None, index)
+        })();
+        tree?
+    } else {
+        run_git(&["rev-parse", &format!("{stash_name}^{{tree}}")])?
+    };
+
+    let commit = run_git(&["commit-tree", &tree, "-p", &base, "-m", message])?;
+    run_git(&["branch", branch, &commit])?;
+    Ok(commit)
+}
+
+/// Combine several stashes into one new stash by applying them in turn, in the
+/// order given, on top of the first one's base in a scratch worktree. The
+/// originals and the user's checkout are left alone; if one stash conflicts
+/// with those before it, nothing is stored and the conflicting files are named.
+/// Staged changes stay staged and untracked files stay untracked (the newest
+/// copy of a file several stashes hold wins). Returns false if the staged
+/// changes didn't combine, in which case the new stash has them all unstaged.
+pub fn squash_stashes(shas: &[String], branch: &str, message: &str) -> Result<bool> {
+    let Some(first) = shas.first() else {
+        bail!("Nothing to squash");
+    };
+    let base = run_git(&["rev-parse", &format!("{first}^1")])?;
+    let scratch = Scratch::new(&base)?;
+
+    // Commit after each stash so the next one merges into a clean tree. Only
+    // tracked files go in; the untracked ones are gathered separately below.
+    for (i, sha) in shas.iter().enumerate() {
+        if let Err(e) = scratch.git(&["stash", "apply", sha]) {
+            let conflicts = scratch
+                .git(&["diff", "--name-only", "--diff-filter=U"])
+                .unwrap_or_default();
+            if conflicts.is_empty() {
+                bail!("Stash {} of {} failed to apply: {e}", i + 1, shas.len());
+            }
+            bail!(
+                "Stash {} of {} conflicts with the ones before it in {}. Nothing was changed.",
+                i + 1,
+                shas.len(),
+                conflicts.lines().collect::<Vec<_>>().join(", ")
+            );
+        }
+        scratch.git(&["add", "-u"])?;
+        scratch.git(&[
+            "commit",
+            "--quiet",
+            "--no-verify",
+            "--allow-empty",
+            "-m",
+            "squash",
+        ])?;
+        scratch.git(&["clean", "-fdxq"])?;
+    }
+
+    let tree = scratch.git(&["rev-parse", "HEAD^{tree}"])?;
+    let base_tree = run_git(&["rev-parse", &format!("{base}^{{tree}}")])?;
+    let index_tree = squash_index(shas, &base_tree);
+    let untracked = squash_untracked(shas)?;
+
+    // A stash is a merge commit of its base, a commit recording the index and,
+    // with untracked files, a root commit holding them
+    let index_commit = run_git(&[
+        "commit-tree",
+        index_tree.as_deref().unwrap_or(&base_tree),
+        "-p",
+        &base,
+        "-m",
+        &format!("index on {branch}: {message}"),
+    ])?;
+    let untracked_commit = match untracked {
+        Some(tree) => Some(run_git(&[
+            "commit-tree",
+            &tree,
+            "-m",
+            &format!("untracked files on {branch}: {message}"),
+        ])?),
+        None => None,
+    };
+    let subject = format!("On {branch}: {message}");
+    let mut args = vec!["commit-tree", &tree, "-p", &base, "-p", &index_commit];
+    if let Some(commit) = &untracked_commit {
+        args.extend(["-p", commit]);
+    }
+    args.extend(["-m", &subject]);
+    let stash_commit = run_git(&args)?;
+    run_git(&["stash", "store", "-m", &subject, &stash_commit])?;
+    Ok(index_tree.is_some())
+}
+
+/// The base tree with each stash's staged changes laid over it in turn, or
+/// `None` if one of them doesn't apply on top of those before it
+fn squash_index(shas: &[String], base_tree: &str) -> Option<String> {
+    let temp = TempIndex::new().ok()?;
+    let index_file = temp.path();
+    let tree = (|| {
+        let index = Some(index_file.as_path());
+        run_git_piped(&["read-tree", base_tree], None, index)?;
+        for sha in shas {
+            let patch =
+                run_git_raw(&["diff", "--binary", &format!("{sha}^1"), &format!("{sha}^2")])?;
+            if !patch.is_empty() {
+                run_git_piped(&["apply", "--cached", "-"], Some(&patch), index)?;
+            }
+        }
+        run_git_piped(&["write-tree"], None, index)
+    })();
+    tree.ok()
+}
+
+/// A tree of every untracked file the stashes hold, later stashes' copies
+/// winning; `None` if none of them has any
+fn squash_untracked(shas: &[String]) -> Result<Option<String>> {
+    let parents: Vec<String> = shas
+        .iter()
+        .map(|sha| format!("{sha}^3"))
+        .filter(|rev| run_git(&["rev-parse", "--verify", "--quiet", rev]).is_ok())
+        .collect();
+    if parents.is_empty() {
+        return Ok(None);
+    }
+    let temp = TempIndex::new()?;
+    let index_file = temp.path();
+    let tree = (|| {
+        let index = Some(index_file.as_path());
+        run_git_piped(&["read-tree", "--empty"], None, index)?;
+        for rev in &parents {
+            let entries = run_git_raw(&["ls-tree", "-r", "-z", rev])?;
+            run_git_piped(
+                &["update-index", "-z", "--index-info"],
+                Some(&entries),
+                index,
+            )?;
+        }
+        run_git_piped(&["write-tree"], None, index)
+    })();
+    tree.map(Some)
+}
+
+/// True if everything a stash changes is already in HEAD: either one of the
+/// commits since the stash's base has the same patch-id, or the stash's diff
+/// reverse-applies cleanly on top of HEAD (the changes landed piecemeal)
+pub fn stash_is_merged(stash_name: &str) -> Result<bool> {
+    let patch = stash_diff(stash_name, &DiffOptions::default())?;
+    if patch.trim().is_empty() {
+        return Ok(false);
+    }
+
+    let stash_id = patch_id(&patch)?;
+    let range = format!("{stash_name}^1..HEAD");
+    let log = run_git_raw(&["log", "-p", "--no-merges", "--format=commit %H", &range])?;
+    if !log.is_empty() && patch_id(&log)?.lines().any(|id| id == stash_id) {
+        return Ok(true);
+    }
+
+    let head_tree = run_git(&["rev-parse", "HEAD^{tree}"])?;
+    let temp = TempIndex::new()?;
+    let index_file = temp.path();
+    let index = Some(index_file.as_path());
+    let contained = run_git_piped(&["read-tree", &head_tree], None, index).and_then(|_| {
+        run_git_piped(
+            &["apply", "--cached", "--check", "-R", "-"],
+            Some(&patch),
+            index,
+        )
+    });
+    Ok(contained.is_ok())
+}
+
+/// `git patch-id --stable` of a patch (or a log of patches); the first field per line
+fn patch_id(patch: &str) -> Result<String> {
+    let out = run_git_piped(&["patch-id", "--stable"], Some(patch), None)?;
+    Ok(out
+        .lines()
+        .filter_map(|l| l.split_whitespace().next())
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Detect shallow and partial (promisor) clones
+pub fn clone_kind() -> CloneKind {
+    if run_git(&["config", "--get", "extensions.partialclone"]).is_ok()
+        || run_git(&["config", "--get-regexp", r"^remote\..*\.promisor$"]).is_ok()
+    {
+        let remote = run_git(&["config", "--get", "extensions.partialclone"])
+            .ok()
+            .filter(|r| !r.is_empty())
+            .unwrap_or_else(|| "origin".to_string());
+        return CloneKind::Partial { remote };
+    }
+    match run_git(&["rev-parse", "--is-shallow-repository"]) {
+        Ok(out) if out == "true" => CloneKind::Shallow,
+        _ => CloneKind::Full,
+    }
+}
+
+/// Change a stash's message. Git can't edit a stash entry, so the stash and
+/// every newer one are stored again, the renamed one with the new message and
+/// the rest as they were, and only then are the old entries dropped, which
+/// keeps every index the same. A failure part-way never loses an entry: stores
+/// are undone, and a failed drop leaves duplicates. The "On <branch>:" prefix
+/// is kept.
+pub fn rename_stash(sha: &str, message: &str) -> Result<()> {
+    let out = run_git(&[
+        "log",
+        "--walk-reflogs",
+        "--format=%H%x1f%gs",
+        "refs/stash",
+        "--",
+    ])?;
+    let entries: Vec<(&str, &str)> = out
+        .lines()
+        .filter_map(|line| line.split_once('\x1f'))
+        .collect();
+    let Some(index) = entries.iter().position(|(entry, _)| *entry == sha) else {
+        bail!("Stash {sha} is not in the stash list");
+    };
+    let message = message.replace('\n', " ");
+    let subject = match entries[index].1.split_once(": ") {
+        Some((prefix, _)) => format!("{prefix}: {message}"),
+        None => message,
+    };
+
+    let mut replacements = vec![(sha, subject.as_str())];
+    replacements.extend(entries[..index].iter().rev().map(|&(e, s)| (e, s)));
+    for (stored, (entry, subject)) in replacements.iter().enumerate() {
+        if let Err(e) = run_git(&["stash", "store", "-m", subject, entry]) {
+            // Only the new copies are on top, so nothing is lost dropping them
+            for _ in 0..stored {
+                run_git(&["stash", "drop", "--quiet", "stash@{0}"])?;
+            }
+            return Err(e.context("Failed to rename the stash; the list is unchanged"));
+        }
+    }
+
+    // The old entries now sit just below the new ones
+    let old = format!("stash@{{{}}}", index + 1);
+    for _ in 0..=index {
+        run_git(&["stash", "drop", "--quiet", &old]).context(
+            "Renamed the stash, but not every old entry could be dropped; \
+             the list has duplicates",
+        )?;
+    }
+    Ok(())
+}
+
+/// Untracked files saved with `stash push -u` live in a third parent, which
+/// `stash show` leaves out. Returns their patch (or `--stat` summary), if any.
+pub fn stash_untracked(stash_name: &str, stat: bool) -> Result<Option<String>> {
+    let untracked = format!("{stash_name}^3");
+    if run_git(&["rev-parse", "--verify", "--quiet", &untracked]).is_err() {
+        return Ok(None);
+    }
+    // The third parent is a root commit, so `show` diffs it against nothing
+    let format = if stat { "--stat" } else { "--patch" };
+    run_git_raw(&["show", "--format=", format, &untracked]).map(Some)
+}
+
+/// Every file path in a stash's snapshot of the working tree
+pub fn stash_tree(stash_name: &str) -> Result<Vec<String>> {
+    let out = with_object_recovery(stash_name, || {
+        run_git_raw(&["ls-tree", "-r", "-z", "--name-only", stash_name])
+    })?;
+    Ok(out
+        .split('\0')
+        .filter(|p| !p.is_empty())
+        .map(|p| p.to_string())
+        .collect())
+}
+
+/// Contents of one file as stored in a stash, byte for byte
+pub fn stash_file(stash_name: &str, path: &str) -> Result<Vec<u8>> {
+    run_git_bytes(&["show", &format!("{stash_name}:{path}")])
+}
+
+/// Working tree (and index) against HEAD, limited to `paths` from the repository root
+pub fn diff_head(paths: &[String], opts: &DiffOptions) -> Result<String> {
+    let extra = opts.args();
+    let pathspecs: Vec<String> = paths.iter().map(|p| format!(":(top,literal){p}")).collect();
+    let mut args = vec!["diff", "HEAD"];
+    args.extend(extra.iter().map(|a| a.as_str()));
+    args.push("--");
+    args.extend(pathspecs.iter().map(|p| p.as_str()));
+    run_git_raw(&args)
+}
+
+/// Diff two commits' trees, e.g. the snapshots of two stashes
+pub fn diff_commits(from: &str, to: &str, opts: &DiffOptions) -> Result<String> {
+    let extra = opts.args();
+    let mut args = vec!["diff"];
+    args.extend(extra.iter().map(|a| a.as_str()));
+    args.extend([from, to]);
+    run_git_raw(&args)
+}
+
+/// What applying a stash would change now: the working tree diffed against
+/// the stash's snapshot, limited to the files the stash touched
+pub fn stash_vs_worktree(stash_name: &str, opts: &DiffOptions) -> Result<String> {
+    let paths: Vec<String> = stash_numstat(stash_name)?
+        .into_iter()
+        .map(|f| format!(":(top,literal){}", f.path))
+        .collect();
+    if paths.is_empty() {
+        return Ok(String::new());
+    }
+    let extra = opts.args();
+    // -R swaps the prefixes too; swap them back so paths read a/ → b/ as usual
+    let mut args = vec![
+        "diff",
+        "-R",
+        "--src-prefix=b/",
+        "--dst-prefix=a/",
+        stash_name,
+    ];
+    args.extend(extra.iter().map(|a| a.as_str()));
+    args.push("--");
+    args.extend(paths.iter().map(|p| p.as_str()));
+    run_git_raw(&args)
+}
+
+/// Point `refname` at `sha`, creating it if needed
+pub fn update_ref(refname: &str, sha: &str) -> Result<()> {
+    run_git(&["update-ref", refname, sha])?;
+    Ok(())
+}
+
+pub fn delete_ref(refname: &str) -> Result<()> {
+    run_git(&["update-ref", "-d", refname])?;
+    Ok(())
+}
+
+/// Commit the stash was made on top of
+pub fn stash_base(stash_name: &str) -> Result<String> {
+    run_git(&["rev-parse", &format!("{stash_name}^1")])
+}
+
+/// Fetch URL of a remote
+pub fn remote_url(remote: &str) -> Result<String> {
+    run_git(&["remote", "get-url", remote])
+}
+
+/// Notes ref gsm keeps its per-stash metadata under
+const NOTES_REF: &str = "gsm";
+
+/// Describe the state the repository is in right now: `git describe`, any
+/// rebase/merge/cherry-pick in progress, and submodules with local changes.
+/// One `key: value` per line, for storing alongside a new stash.
+pub fn capture_env() -> String {
+    let mut lines = Vec::new();
+
+    if let Ok(describe) = run_git(&["describe", "--tags", "--always"]) {
+        lines.push(format!("describe: {describe}"));
+    }
+
+    let states = [
+        ("rebase-merge", "rebase in progress"),
+        ("rebase-apply", "rebase/am in progress"),
+        ("MERGE_HEAD", "merge in progress"),
+        ("CHERRY_PICK_HEAD", "cherry-pick in progress"),
+        ("REVERT_HEAD", "revert in progress"),
+        ("BISECT_LOG", "bisect in progress"),
+    ];
+    for (file, label) in states {
+        if git_path(file).is_ok_and(|p| p.exists()) {
+            lines.push(format!("state: {label}"));
+        }
+    }
+
+    // porcelain v2: "1 XY <sub> ..." where <sub> is "S<c><m><u>" for submodules
+    if let Ok(status) = run_git(&["status", "--porcelain=v2", "--ignore-submodules=none"]) {
+        let dirty: Vec<&str> = status
+            .lines()
+            .filter_map(|l| {
+                let fields: Vec<&str> = l.splitn(9, ' ').collect();
+                (fields.len() == 9 && fields[0] == "1" && fields[2].starts_with('S'))
+                    .then_some(fields[8])
+            })
+            .collect();
+        if !dirty.is_empty() {
+            lines.push(format!("dirty submodules: {}", dirty.join(", ")));
+        }
+    }
+
+    lines.join("\n")
+}
+
+/// Attach gsm's note to a stash commit, replacing any existing one
+pub fn write_note(rev: &str, text: &str) -> Result<()> {
+    let sha = run_git(&["rev-parse", rev])?;
+    run_git(&["notes", "--ref", NOTES_REF, "add", "-f", "-m", text, &sha])?;
+    Ok(())
+}
+
+/// Add `fields`, `key: value` lines, to gsm's note on a stash. Lines with the
+/// same keys are replaced; the note's other lines are kept.
+pub fn merge_note_fields(sha: &str, fields: &str) -> Result<()> {
+    let note = read_note(sha).unwrap_or_default();
+    write_note(sha, &merge_fields(&note, fields))
+}
+
+fn merge_fields(note: &str, fields: &str) -> String {
+    let keys: Vec<&str> = fields
+        .lines()
+        .filter_map(|l| l.split_once(": ").map(|(key, _)| key))
+        .collect();
+    note.lines()
+        .filter(|l| {
+            !l.split_once(": ")
+                .is_some_and(|(key, _)| keys.contains(&key))
+        })
+        .chain(fields.lines())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// gsm's note on a stash commit, if it has one
+pub fn read_note(sha: &str) -> Option<String> {
+    run_git(&["notes", "--ref", NOTES_REF, "show", sha])
+        .ok()
+        .filter(|n| !n.is_empty())
+}
+
+/// Set one `key: value` line of gsm's note on a stash, keeping the others.
+/// `None` removes the line, and the note with it once nothing is left.
+pub fn set_note_field(sha: &str, key: &str, value: Option<&str>) -> Result<()> {
+    let prefix = format!("{key}: ");
+    let mut lines: Vec<String> = read_note(sha)
+        .unwrap_or_default()
+        .lines()
+        .filter(|l| !l.starts_with(&prefix))
+        .map(String::from)
+        .collect();
+    if let Some(value) = value {
+        lines.push(format!("{prefix}{value}"));
+    }
+
+    if lines.is_empty() {
+        run_git(&[
+            "notes",
+            "--ref",
+            NOTES_REF,
+            "remove",
+            "--ignore-missing",
+            sha,
+        ])?;
+        Ok(())
+    } else {
+        write_note(sha, &lines.join("\n"))
+    }
+}
+
+/// Every gsm note, by annotated commit. Reads them all through one
+/// `cat-file --batch` rather than a `notes show` per stash.
+pub fn notes() -> HashMap<String, String> {
+    let Ok(list) = run_git(&["notes", "--ref", NOTES_REF, "list"]) else {
+        return HashMap::new();
+    };
+    let entries: Vec<(&str, &str)> = list.lines().filter_map(|l| l.split_once(' ')).collect();
+    if entries.is_empty() {
+        return HashMap::new();
+    }
+    let blobs: String = entries
+        .iter()
+        .map(|(blob, _)| format!("{blob}\n"))
+        .collect();
+    let Ok(output) = git_output_piped(&["cat-file", "--batch"], Some(&blobs), None) else {
+        return HashMap::new();
+    };
+    entries
+        .iter()
+        .zip(parse_batch(&output.stdout))
+        .filter_map(|((_, sha), note)| {
+            let note = note?.trim_end().to_string();
+            (!note.is_empty()).then(|| (sha.to_string(), note))
+        })
+        .collect()
+}
+
+/// Each object's contents in `cat-file --batch` output, in order; `None` for
+/// one git reported missing
+fn parse_batch(mut out: &[u8]) -> Vec<Option<String>> {
+    let mut objects = Vec::new();
+    while let Some(end) = out.iter().position(|&b| b == b'\n') {
+        let header = String::from_utf8_lossy(&out[..end]).to_string();
+        out = &out[end + 1..];
+        // "<object> missing" has no contents after it
+        let Some(size) = header
+            .split(' ')
+            .nth(2)
+            .and_then(|n| n.parse::<usize>().ok())
+        else {
+            objects.push(None);
+            continue;
+        };
+        let Some(content) = out.get(..size) else {
+            break;
+        };
+        objects.push(Some(String::from_utf8_lossy(content).to_string()));
+        out = out.get(size + 1..).unwrap_or_default();
+    }
+    objects
+}
+
+/// The `key: value` line `key` from each note in `notes`, by annotated commit
+pub fn note_fields(notes: &HashMap<String, String>, key: &str) -> HashMap<String, String> {
+    let prefix = format!("{key}: ");
+    notes
+        .iter()
+        .filter_map(|(sha, note)| {
+            let value = note.lines().find_map(|l| l.strip_prefix(&prefix))?;
+            Some((sha.clone(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// True if a git error means objects are missing locally rather than a real
+/// failure. A mistyped or unknown stash name is a real failure.
+fn is_missing_object(err: &str) -> bool {
+    let err = err.to_lowercase();
+    [
+        "bad object",
+        "missing blob",
+        "missing tree",
+        "missing commit",
+        "unable to read",
+        "could not read",
+        "is a promisor object",
+    ]
+    .iter()
+    .any(|needle| err.contains(needle))
+}
+
+/// Run `op` on a stash, record the outcome in the audit log and, if it
+/// worked, run the user's hook for it. The stash is resolved first, since a
+/// drop or pop removes it.
+fn audited<T>(action: &str, stash_name: &str, op: impl FnOnce() -> Result<T>) -> Result<T> {
+    let (sha, subject) = stash_identity(stash_name);
+    let result = op();
+    let error = result.as_ref().err().map(|e| format!("{e:#}"));
+    audit::record(action, &sha, &subject, error);
+    if result.is_ok() {
+        hooks::fire(action, &sha, &subject, &parse_stash_subject(&subject).0);
+    }
+    result
+}
+
+/// SHA and subject of a stash, or empty strings if it doesn't resolve
+fn stash_identity(stash_name: &str) -> (String, String) {
+    run_git(&["show", "-s", "--format=%H%x1f%s", stash_name])
+        .ok()
+        .and_then(|out| {
+            let (sha, subject) = out.split_once('\x1f')?;
+            Some((sha.to_string(), subject.to_string()))
+        })
+        .unwrap_or_default()
+}
+
+/// Run a stash operation; if it fails on missing objects, fetch them (partial clone)
+/// and retry once, or explain why they are unavailable (shallow clone)
+fn with_object_recovery<T>(stash_name: &str, op: impl Fn() -> Result<T>) -> Result<T> {
+    let err = match op() {
+        Ok(v) => return Ok(v),
+        Err(e) if is_missing_object(&format!("{e:#}")) => e,
+        Err(e) => return Err(e),
+    };
+
+    match clone_kind() {
+        CloneKind::Partial { remote } => {
+            let base = run_git(&["rev-parse", &format!("{stash_name}^1")])
+                .context("The stash's base commit is not available locally")?;
+            run_git(&["fetch", "--no-tags", &remote, &base]).with_context(|| {
+                format!(
+                    "Objects for {stash_name} are missing in this partial clone \
+                     and could not be fetched from {remote}"
+                )
+            })?;
+            op()
+        }
+        CloneKind::Shallow => Err(err.context(format!(
+            "{stash_name} refers to history missing from this shallow clone; \
+             run `git fetch --unshallow` (or `git fetch --deepen=<n>`) and retry"
+        ))),
+        CloneKind::Full => Err(err),
+    }
+}
+
+/// Resolve a path inside the git directory (e.g. `.git/<name>`)
+pub fn git_path(name: &str) -> Result<PathBuf> {
+    // git answers relative to the root it runs in, not to our own directory
+    Ok(location()
+        .root
+        .join(run_git(&["rev-parse", "--git-path", name])?))
+}
+
+/// The git directory shared by every worktree of the repository, canonicalized
+pub fn common_dir() -> Result<PathBuf> {
+    let dir = location()
+        .root
+        .join(run_git(&["rev-parse", "--git-common-dir"])?);
+    fs::canonicalize(&dir).with_context(|| format!("Cannot resolve {}", dir.display()))
+}
+
+/// Run a git command and return its stdout untouched, failing with stderr on error
+fn run_git_raw(args: &[&str]) -> Result<String> {
+    run_git_bytes(args).map(|out| String::from_utf8_lossy(&out).to_string())
+}
+
+/// Run a git command and return its stdout untouched, for binary output
+fn run_git_bytes(args: &[&str]) -> Result<Vec<u8>> {
+    let mut cmd = git_command();
+    cmd.args(args);
+    let output =
+        run_cancellable(cmd).with_context(|| format!("Failed to run git {}", args.join(" ")))?;
+
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        bail!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+    }
+}
+
+/// Run a git command and return its trimmed stdout, failing with stderr on error
+fn run_git(args: &[&str]) -> Result<String> {
+    run_git_raw(args).map(|out| out.trim_end().to_string())
+}
+
+/// Like `run_git`, for commands that talk to a remote. A credential prompt
+/// would write over the TUI and wait for keys it never gets, so git is told
+/// to fail instead; credential helpers and SSH agents still work.
+fn run_git_network(args: &[&str]) -> Result<String> {
+    let mut cmd = git_command();
+    cmd.env("GIT_TERMINAL_PROMPT", "0").args(args);
+    let output =
+        run_cancellable(cmd).with_context(|| format!("Failed to run git {}", args.join(" ")))?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string())
+}
+
+/// Run a git command, optionally feeding stdin and using an alternate index file
+fn run_git_piped(args: &[&str], input: Option<&str>, index_file: Option<&Path>) -> Result<String> {
+    let output = git_output_piped(args, input, index_file)?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .to_string())
+    } else {
+        bail!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+    }
+}
+
+/// Like `run_git_piped`, but hand back the raw output whatever the exit status
+fn git_output_piped(
+    args: &[&str],
+    input: Option<&str>,
+    index_file: Option<&Path>,
+) -> Result<Output> {
+    let mut cmd = git_command();
+    if let Some(index_file) = index_file {
+        cmd.env("GIT_INDEX_FILE", index_file);
+    }
+    let mut child = cmd
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run git {}", args.join(" ")))?;
+
+    let mut stdin = child.stdin.take().context("Failed to open git stdin")?;
+    // Write from another thread: git may fill its stdout before it has read
+    // all of a long input, and would wait on us while we wait on it
+    std::thread::scope(|scope| {
+        let writer = scope.spawn(move || match input {
+            Some(input) => stdin.write_all(input.as_bytes()),
+            None => Ok(()),
+        });
+        let output = child.wait_with_output()?;
+        writer.join().expect("stdin writer panicked")?;
+        Ok(output)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_object_errors() {
+        for err in [
+            "fatal: bad object 1234abcd",
+            "error: unable to read 1234abcd",
+            "fatal: missing blob object '1234abcd'",
+            "error: Could not read 1234abcd",
+        ] {
+            assert!(is_missing_object(err), "{err}");
+        }
+        for err in [
+            "fatal: ambiguous argument 'stash@{9}': unknown revision or path",
+            "fatal: bad revision 'stash@{9}'",
+            "error: stash@{9} is not a valid reference",
+        ] {
+            assert!(!is_missing_object(err), "{err}");
+        }
+    }
+    #[test]
+    fn merge_fields_replaces_same_keys_only() {
+        let note = "note: keep me\ndescribe: v0\nlabels: wip";
+        assert_eq!(
+            merge_fields(note, "describe: v1\nstate: merge in progress"),
+            "note: keep me\nlabels: wip\ndescribe: v1\nstate: merge in progress"
+        );
+        assert_eq!(merge_fields("", "describe: v1"), "describe: v1");
+    }
+    #[test]
+    fn rename_keeps_order_and_commits() {
+        let repo = testing::TempRepo::new();
+        for message in ["oldest", "middle", "newest"] {
+            repo.stash(message);
+        }
+        let list = || repo.git(&["log", "--walk-reflogs", "--format=%H %gs", "refs/stash"]);
+        let before: Vec<String> = list().lines().map(String::from).collect();
+        let middle = before[1].split_once(' ').unwrap().0.to_string();
+
+        repo.enter(|| rename_stash(&middle, "renamed")).unwrap();
+
+        let after: Vec<String> = list().lines().map(String::from).collect();
+        assert_eq!(after.len(), 3);
+        assert_eq!(after[0], before[0]);
+        assert_eq!(after[1], format!("{middle} On main: renamed"));
+        assert_eq!(after[2], before[2]);
+    }
+
+    #[test]
+    fn branch_from_stash_keeps_untracked_files_and_cleans_up() {
+        let repo = testing::TempRepo::new();
+        repo.write("a.txt", "changed\n");
+        repo.write("new.txt", "untracked\n");
+        repo.git(&["stash", "push", "-q", "-u", "-m", "with new"]);
+
+        repo.enter(|| stash_to_branch("stash@{0}", "from-stash", "from stash").unwrap());
+        assert_eq!(repo.git(&["show", "from-stash:new.txt"]), "untracked");
+        assert_eq!(repo.git(&["show", "from-stash:a.txt"]), "changed");
+        let git_dir = repo.dir.join(".git");
+        let leftover = fs::read_dir(git_dir)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .any(|e| e.file_name().to_string_lossy().starts_with("gsm-tmp"));
+        assert!(!leftover);
+    }
+
+    #[test]
+    fn batch_output_splits_into_objects() {
+        let out = b"aaa blob 5\nhello\nbbb missing\nccc blob 11\ntwo\nlines\n\n\n";
+        assert_eq!(
+            parse_batch(out),
+            [
+                Some("hello".to_string()),
+                None,
+                Some("two\nlines\n\n".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn notes_are_read_in_one_batch() {
+        let repo = testing::TempRepo::new();
+        repo.stash("first");
+        repo.stash("second");
+        let first = repo.git(&["rev-parse", "stash@{1}"]);
+        repo.git(&[
+            "notes",
+            "--ref",
+            NOTES_REF,
+            "add",
+            "-m",
+            "note: one\nlabels: a,b",
+            &first,
+        ]);
+
+        let notes = repo.enter(notes);
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[&first], "note: one\nlabels: a,b");
+        let labels = note_fields(&notes, "labels");
+        assert_eq!(labels[&first], "a,b");
+    }
+
+    #[test]
+    fn stash_log_records_with_numstat() {
+        let raw = "\x1estash@{0}\x1f1111\x1f1700000000\x1f2 hours ago\x1fAna <ana@example.com>\x1f\
+                   2023-11-14 22:13:20 +0000\x1fOn main: fix: login\n\n\
+                   3\t1\tsrc/a.rs\n-\t-\timg.png\n\
+                   \x1estash@{1}\x1f2222\x1f1600000000\x1f3 years ago\x1fBo <bo@example.com>\x1f\
+                   2020-09-13 12:26:40 +0000\x1fWIP on feat/x: abc1234 base commit\n\
+                   \x1ebroken record\n";
+        let stashes = parse_stash_log(raw, true);
+        assert_eq!(stashes.len(), 2);
+
+        let first = &stashes[0];
+        assert_eq!((first.index, first.name.as_str()), (0, "stash@{0}"));
+        assert_eq!(first.sha, "1111");
+        assert_eq!(first.timestamp, 1_700_000_000);
+        assert_eq!(first.branch, "main");
+        assert_eq!(first.short_msg, "fix: login");
+        assert_eq!(first.files_changed, Some(2));
+        let numstat = first.numstat.as_ref().unwrap();
+        assert_eq!(numstat[0].path, "src/a.rs");
+        assert_eq!(
+            (numstat[0].insertions, numstat[0].deletions),
+            (Some(3), Some(1))
+        );
+        assert_eq!((numstat[1].insertions, numstat[1].deletions), (None, None));
+
+        let second = &stashes[1];
+        assert_eq!(second.index, 1);
+        assert_eq!(second.branch, "feat/x");
+        assert_eq!(second.short_msg, "abc1234 base commit");
+        assert_eq!(second.numstat.as_ref().map(Vec::len), Some(0));
+
+        let plain = parse_stash_log(raw, false);
+        assert!(
+            plain
+                .iter()
+                .all(|s| s.numstat.is_none() && s.files_changed.is_none())
+        );
+    }
+
+    #[test]
+    fn numstat_lines() {
+        let change = parse_numstat_line("10\t0\tdir/name with\ttab").unwrap();
+        assert_eq!(change.path, "dir/name with\ttab");
+        assert_eq!((change.insertions, change.deletions), (Some(10), Some(0)));
+        assert!(parse_numstat_line("").is_none());
+        assert!(parse_numstat_line("1\t2").is_none());
+    }
+
+    #[test]
+    fn status_entries() {
+        let out = "M  staged.rs\0 M src/edited.rs\0R  new name.rs\0old.rs\0?? notes.txt\0";
+        let entries = parse_status(out);
+        let summary: Vec<(char, char, &str)> = entries
+            .iter()
+            .map(|e| (e.staged, e.unstaged, e.path.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ('M', ' ', "staged.rs"),
+                (' ', 'M', "src/edited.rs"),
+                ('R', ' ', "new name.rs"),
+                ('?', '?', "notes.txt"),
+            ]
+        );
+    }
+
+    #[test]
+    fn git_path_is_inside_the_repository() {
+        let repo = testing::TempRepo::new();
+        let path = repo.enter(|| git_path("gsm-state")).unwrap();
+        assert_eq!(path, repo.dir.join(".git/gsm-state"));
+    }
+}
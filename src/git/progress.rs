@@ -0,0 +1,213 @@
+use anyhow::{Context, Result};
+use std::fmt;
+use std::io::Read;
+use std::process::{Command, Output, Stdio};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+/// How often the cancel flag and the process are checked
+const POLL: Duration = Duration::from_millis(20);
+
+/// Bumped by `cancel_running`; commands started before the bump are killed
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+/// Kill every git process started through this module that is still running,
+/// e.g. on Ctrl-C. Their callers fail with `Cancelled`.
+pub fn cancel_running() {
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// The error an operation fails with when it was cancelled
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Whether `err` (or anything it wraps) is a cancellation
+pub fn was_cancelled(err: &anyhow::Error) -> bool {
+    err.chain().any(|e| e.is::<Cancelled>())
+}
+
+/// Output of a running operation, shared between the worker thread doing the
+/// work and the progress popup showing it. Setting `cancel` makes the worker
+/// kill its git process at the next poll.
+#[derive(Debug)]
+pub struct Progress {
+    lines: Mutex<Vec<String>>,
+    transient: AtomicBool, // the last line ended in \r and will be overwritten
+    cancelled: AtomicBool,
+    generation: usize, // `GENERATION` when the operation started
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self {
+            lines: Mutex::default(),
+            transient: AtomicBool::new(false),
+            cancelled: AtomicBool::new(false),
+            generation: GENERATION.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Progress {
+    /// Add a line of output
+    pub fn report(&self, line: impl Into<String>) {
+        self.push(line.into(), false);
+    }
+
+    /// The last `n` lines of output
+    pub fn tail(&self, n: usize) -> Vec<String> {
+        let lines = self.lines.lock().unwrap();
+        lines[lines.len().saturating_sub(n)..].to_vec()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || GENERATION.load(Ordering::Relaxed) != self.generation
+    }
+
+    /// Add a line; a line git ended with \r (e.g. "Updating files: 40%")
+    /// replaces itself on the next update, as it does in a terminal
+    fn push(&self, line: String, transient: bool) {
+        let mut lines = self.lines.lock().unwrap();
+        if self.transient.swap(transient, Ordering::Relaxed) {
+            lines.pop();
+        }
+        lines.push(line);
+    }
+}
+
+/// Run `cmd` with its output piped, feeding each line into `progress` as it
+/// arrives. The process is killed if the operation is cancelled.
+pub fn run_streaming(cmd: Command, progress: &Progress) -> Result<Output> {
+    run(cmd, Some(progress), None)
+}
+
+/// Run `cmd` to completion like `Command::output`, but kill it on
+/// `cancel_running`
+pub fn run_cancellable(cmd: Command) -> Result<Output> {
+    run(cmd, None, None)
+}
+
+/// Receives stdout in pieces as a command writes it
+type Sink<'a> = dyn FnMut(&[u8]) + 'a;
+
+/// Like `run_cancellable`, but hand stdout to `sink` as it arrives instead of
+/// collecting it; the returned output's stdout is empty
+pub fn run_chunked(cmd: Command, sink: &mut Sink) -> Result<Output> {
+    run(cmd, None, Some(sink))
+}
+
+fn run(
+    mut cmd: Command,
+    progress: Option<&Progress>,
+    mut sink: Option<&mut Sink>,
+) -> Result<Output> {
+    let generation = GENERATION.load(Ordering::Relaxed);
+    let cancelled = || {
+        GENERATION.load(Ordering::Relaxed) != generation
+            || progress.is_some_and(Progress::is_cancelled)
+    };
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run git")?;
+
+    // Reader threads aren't joined: a hook git started may hold a pipe open
+    // after git itself was killed
+    let (tx, rx) = mpsc::channel();
+    if let Some(out) = child.stdout.take() {
+        read_pipe(out, 0, tx.clone());
+    }
+    if let Some(err) = child.stderr.take() {
+        read_pipe(err, 1, tx);
+    }
+
+    let mut output = [Vec::new(), Vec::new()];
+    let mut partial = [Vec::new(), Vec::new()];
+    let mut open = true;
+    let status = loop {
+        if cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(Cancelled.into());
+        }
+        if open {
+            match rx.recv_timeout(POLL) {
+                Ok((0, chunk)) if let Some(sink) = sink.as_mut() => {
+                    sink(&chunk);
+                    continue;
+                }
+                Ok((stream, chunk)) => {
+                    output[stream].extend_from_slice(&chunk);
+                    if let Some(progress) = progress {
+                        report_lines(&mut partial[stream], &chunk, progress);
+                    }
+                    continue;
+                }
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => open = false,
+            }
+        }
+        // The pipes are closed, so git is exiting
+        match child.try_wait()? {
+            Some(status) => break status,
+            None => thread::sleep(Duration::from_millis(1)),
+        }
+    };
+    if let Some(progress) = progress {
+        for line in partial.iter().filter(|l| !l.is_empty()) {
+            progress.report(String::from_utf8_lossy(line).trim_end());
+        }
+    }
+    let [stdout, stderr] = output;
+    Ok(Output {
+        status,
+        stdout,
+        stderr,
+    })
+}
+
+/// Send chunks read from `pipe` until it closes
+fn read_pipe(mut pipe: impl Read + Send + 'static, stream: usize, tx: Sender<(usize, Vec<u8>)>) {
+    thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        while let Ok(n) = pipe.read(&mut buf) {
+            if n == 0 || tx.send((stream, buf[..n].to_vec())).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Report every \n- or \r-terminated line in `chunk`, carrying an unfinished
+/// line over in `partial`
+fn report_lines(partial: &mut Vec<u8>, chunk: &[u8], progress: &Progress) {
+    for &byte in chunk {
+        if byte != b'\n' && byte != b'\r' {
+            partial.push(byte);
+            continue;
+        }
+        if !partial.is_empty() {
+            let text = String::from_utf8_lossy(partial).trim_end().to_string();
+            progress.push(text, byte == b'\r');
+            partial.clear();
+        }
+    }
+}
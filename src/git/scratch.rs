@@ -0,0 +1,86 @@
+use super::{git_path, run_git};
+use anyhow::{Context, Result};
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+/// A throwaway detached worktree, for work that must not disturb the user's
+/// checkout. It is removed again when dropped, whether the work succeeded or not.
+pub struct Scratch {
+    dir: PathBuf,
+}
+
+impl Scratch {
+    /// Check out `rev` in a new temporary worktree
+    pub fn new(rev: &str) -> Result<Self> {
+        let dir = std::env::temp_dir().join(format!("gsm-scratch-{}", std::process::id()));
+        // Left over from a run that was killed part-way
+        let _ = fs::remove_dir_all(&dir);
+        let _ = run_git(&["worktree", "prune"]);
+
+        run_git(&[
+            "worktree",
+            "add",
+            "--detach",
+            "--quiet",
+            &dir.to_string_lossy(),
+            rev,
+        ])
+        .context("Failed to create a temporary worktree")?;
+        Ok(Self { dir })
+    }
+
+    /// Run git inside the scratch worktree
+    pub fn git(&self, args: &[&str]) -> Result<String> {
+        let dir = self.dir.to_string_lossy();
+        let mut full = vec!["-C", dir.as_ref()];
+        full.extend_from_slice(args);
+        run_git(&full)
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = run_git(&["worktree", "remove", "--force", &self.dir.to_string_lossy()]);
+        let _ = fs::remove_dir_all(&self.dir);
+        let _ = run_git(&["worktree", "prune"]);
+    }
+}
+
+/// A throwaway index file of its own, to use as `GIT_INDEX_FILE` while
+/// building a tree without touching the real index. Each one lives in a fresh
+/// directory under the git dir, so concurrent jobs never share one.
+pub struct TempIndex {
+    dir: PathBuf,
+}
+
+impl TempIndex {
+    pub fn new() -> Result<Self> {
+        let git_dir = git_path("gsm-tmp")?;
+        for _ in 0..16 {
+            // std seeds every RandomState from the OS, which is random enough here
+            let token = RandomState::new().build_hasher().finish();
+            let dir = PathBuf::from(format!("{}-{token:016x}", git_dir.display()));
+            match fs::create_dir(&dir) {
+                Ok(()) => return Ok(Self { dir }),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to create {}", dir.display()));
+                }
+            }
+        }
+        anyhow::bail!("Failed to find a free name for a temporary index")
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.dir.join("index")
+    }
+}
+
+impl Drop for TempIndex {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
@@ -0,0 +1,76 @@
+use super::{CliBackend, Location, Repo};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+/// A throwaway repository with one commit, for tests that need real git.
+/// Removed again when dropped.
+pub struct TempRepo {
+    pub dir: PathBuf,
+}
+
+impl TempRepo {
+    pub fn new() -> Self {
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("gsm-test-{}-{n}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let repo = Self { dir };
+        repo.git(&["init", "-q", "-b", "main"]);
+        repo.git(&["config", "user.name", "gsm"]);
+        repo.git(&["config", "user.email", "gsm@example.com"]);
+        repo.git(&["config", "commit.gpgsign", "false"]);
+        repo.write("a.txt", "one\n");
+        repo.git(&["add", "."]);
+        repo.git(&["commit", "-q", "-m", "base"]);
+        repo
+    }
+
+    /// Run git in the repository, panicking on failure
+    pub fn git(&self, args: &[&str]) -> String {
+        let out = Command::new("git")
+            .arg("-C")
+            .arg(&self.dir)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(
+            out.status.success(),
+            "git {args:?}: {}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+        String::from_utf8_lossy(&out.stdout).trim_end().to_string()
+    }
+
+    pub fn write(&self, file: impl AsRef<Path>, content: &str) {
+        fs::write(self.dir.join(file), content).unwrap();
+    }
+
+    /// Stash a change to `a.txt` with `message`
+    pub fn stash(&self, message: &str) {
+        self.write("a.txt", &format!("{message}\n"));
+        self.git(&["stash", "push", "-q", "-m", message]);
+    }
+
+    /// Run `f` with this thread's git calls going to this repository
+    pub fn enter<T>(&self, f: impl FnOnce() -> T) -> T {
+        let repo = Repo {
+            location: Location {
+                root: self.dir.clone(),
+                ..Location::default()
+            },
+            backend: Arc::new(CliBackend),
+        };
+        repo.enter(f)
+    }
+}
+
+impl Drop for TempRepo {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
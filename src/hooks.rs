@@ -0,0 +1,154 @@
+use crate::config::HooksConfig;
+use crate::git;
+use anyhow::{Context, Result, bail};
+use std::io::Read;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::sync::{Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Hooks run through `sh -c` (`cmd /C` on Windows) at the repository root, each
+// on a thread of its own so a slow one never holds up the UI or the operation
+// that fired it. A hook still running after the timeout is killed along with
+// everything it started. Failures are queued until the UI can report them.
+
+static CONFIG: RwLock<Option<HooksConfig>> = RwLock::new(None);
+static FAILURES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// How often a running hook is checked on
+const POLL: Duration = Duration::from_millis(50);
+
+/// Most bytes of a failed hook's stderr put in its report
+const STDERR_SHOWN: usize = 300;
+
+/// Use `config` for every later event
+pub fn init(config: &HooksConfig) {
+    *CONFIG.write().unwrap_or_else(|e| e.into_inner()) = Some(config.clone());
+}
+
+/// Run the hook for `event` ("apply", "pop", "drop" or "push") on a stash, if
+/// one is configured. The hook sees `GSM_EVENT`, `GSM_STASH_SHA`,
+/// `GSM_STASH_MESSAGE`, `GSM_STASH_BRANCH` and `GSM_REPO`.
+pub fn fire(event: &str, sha: &str, message: &str, branch: &str) {
+    let (command, timeout) = {
+        let config = CONFIG.read().unwrap_or_else(|e| e.into_inner());
+        let Some(config) = config.as_ref() else {
+            return;
+        };
+        let Some(command) = config.command(event) else {
+            return;
+        };
+        (
+            command.to_string(),
+            Duration::from_secs(config.timeout_secs),
+        )
+    };
+    let root = git::location().root;
+    let env = [
+        ("GSM_EVENT", event.to_string()),
+        ("GSM_STASH_SHA", sha.to_string()),
+        ("GSM_STASH_MESSAGE", message.to_string()),
+        ("GSM_STASH_BRANCH", branch.to_string()),
+        ("GSM_REPO", root.to_string_lossy().into_owned()),
+    ];
+    let event = event.to_string();
+    thread::spawn(move || {
+        let mut cmd = shell(&command);
+        cmd.envs(env).current_dir(root);
+        if let Err(e) = run(cmd, timeout) {
+            FAILURES
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(format!("on_{event} hook failed: {e:#}"));
+        }
+    });
+}
+
+/// The oldest hook failure not yet reported, if any
+pub fn take_failure() -> Option<String> {
+    let mut failures = FAILURES.lock().unwrap_or_else(|e| e.into_inner());
+    (!failures.is_empty()).then(|| failures.remove(0))
+}
+
+/// The platform shell running `command`, in a process group of its own on
+/// Unix so a timeout can kill whatever the hook started too
+fn shell(command: &str) -> Command {
+    let mut cmd;
+    if cfg!(windows) {
+        cmd = Command::new("cmd");
+        cmd.arg("/C");
+    } else {
+        cmd = Command::new("sh");
+        cmd.arg("-c");
+    }
+    cmd.arg(command);
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+    cmd
+}
+
+/// Run a hook to completion or until `timeout`, failing with its stderr
+fn run(mut cmd: Command, timeout: Duration) -> Result<()> {
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to start the hook's shell")?;
+
+    // Read stderr as it comes, so a chatty hook never fills the pipe and stalls
+    let (tx, rx) = mpsc::channel();
+    if let Some(mut pipe) = child.stderr.take() {
+        thread::spawn(move || {
+            let mut stderr = String::new();
+            let _ = pipe.read_to_string(&mut stderr);
+            let _ = tx.send(stderr);
+        });
+    }
+
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            if status.success() {
+                return Ok(());
+            }
+            // Something the hook left running in the background may hold
+            // stderr open; don't wait on it
+            let stderr = rx.recv_timeout(POLL).unwrap_or_default();
+            bail!("{status}: {}", tail(stderr.trim()));
+        }
+        if started.elapsed() >= timeout {
+            kill_tree(&mut child);
+            bail!("killed after {}s", timeout.as_secs());
+        }
+        thread::sleep(POLL);
+    }
+}
+
+/// The end of a hook's stderr, where the error usually is, short enough for a popup
+fn tail(stderr: &str) -> &str {
+    let start = stderr.len().saturating_sub(STDERR_SHOWN);
+    let start = (start..stderr.len())
+        .find(|&i| stderr.is_char_boundary(i))
+        .unwrap_or(stderr.len());
+    &stderr[start..]
+}
+
+/// Kill a hook's shell and every process it started
+fn kill_tree(child: &mut Child) {
+    let pid = child.id().to_string();
+    let mut kill = if cfg!(windows) {
+        let mut cmd = Command::new("taskkill");
+        cmd.args(["/T", "/F", "/PID", &pid]);
+        cmd
+    } else {
+        // The shell leads its own process group, so this reaches the whole group
+        let mut cmd = Command::new("kill");
+        cmd.args(["-KILL", "--", &format!("-{pid}")]);
+        cmd
+    };
+    let _ = kill.stdout(Stdio::null()).stderr(Stdio::null()).status();
+    let _ = child.kill();
+    let _ = child.wait();
+}
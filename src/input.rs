@@ -0,0 +1,250 @@
+use crate::config::InputStyle;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    style::{Modifier, Style},
+    text::Span,
+};
+use std::fmt;
+use std::ops::Deref;
+
+/// A one-line text field with a cursor, shared by every prompt. Typing inserts
+/// at the cursor; ←/→, Home/End, Backspace/Delete and the readline keys
+/// (Ctrl-a/e/f/b/k/w/u) edit, and pasted text goes in at the cursor. In the
+/// vim style, Esc switches to a small normal mode instead of leaving the field.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TextInput {
+    text: String,
+    cursor: usize, // byte offset into `text`, always on a char boundary
+    style: InputStyle,
+    normal: bool, // in the vim style's normal mode
+}
+
+impl TextInput {
+    pub fn new(style: InputStyle) -> Self {
+        Self {
+            style,
+            ..Self::default()
+        }
+    }
+
+    /// Replace the text, leaving the cursor at the end, ready to type
+    pub fn set(&mut self, text: impl Into<String>) {
+        self.text = text.into();
+        self.cursor = self.text.len();
+        self.normal = false;
+    }
+
+    pub fn clear(&mut self) {
+        self.set(String::new());
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    /// Insert pasted text; line breaks become spaces, as the field is one line
+    pub fn insert_str(&mut self, text: &str) {
+        let text: String = text
+            .trim_end_matches(['\r', '\n'])
+            .chars()
+            .map(|c| {
+                if c == '\n' || c == '\r' || c == '\t' {
+                    ' '
+                } else {
+                    c
+                }
+            })
+            .collect();
+        self.text.insert_str(self.cursor, &text);
+        self.cursor += text.len();
+    }
+
+    /// True if the field wants `key` before the prompt around it sees it: in
+    /// the vim style, Esc while inserting and letters in normal mode, which
+    /// would otherwise cancel the prompt or trigger its shortcuts
+    pub fn captures(&self, key: &KeyEvent) -> bool {
+        if self.style != InputStyle::Vim {
+            return false;
+        }
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => !self.normal,
+            KeyCode::Char(_) => self.normal && !ctrl,
+            _ => false,
+        }
+    }
+
+    /// Apply an editing key. Returns true if the text changed (not just the
+    /// cursor), so callers know to re-run a search.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let before = self.text.clone();
+        if self.normal {
+            self.normal_key(key);
+        } else {
+            self.insert_key(key);
+        }
+        self.text != before
+    }
+
+    fn insert_key(&mut self, key: KeyEvent) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc if self.style == InputStyle::Vim => {
+                self.normal = true;
+                // As in vim, the cursor steps back onto the last character typed
+                self.cursor = self.prev_boundary().unwrap_or(0);
+            }
+            KeyCode::Char(_) if ctrl => self.readline_key(key.code),
+            KeyCode::Char(c) => self.insert(c),
+            KeyCode::Backspace => self.delete_back(),
+            KeyCode::Delete => self.delete_forward(),
+            KeyCode::Left => self.cursor = self.prev_boundary().unwrap_or(self.cursor),
+            KeyCode::Right => self.cursor = self.next_boundary().unwrap_or(self.cursor),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.text.len(),
+            _ => {}
+        }
+    }
+
+    /// Ctrl-a/e (start/end), Ctrl-b/f (back/forward), Ctrl-k (delete to the
+    /// end), Ctrl-w (delete a word) and Ctrl-u (delete to the start)
+    fn readline_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char('a') => self.cursor = 0,
+            KeyCode::Char('e') => self.cursor = self.text.len(),
+            KeyCode::Char('b') => self.cursor = self.prev_boundary().unwrap_or(self.cursor),
+            KeyCode::Char('f') => self.cursor = self.next_boundary().unwrap_or(self.cursor),
+            KeyCode::Char('k') => self.text.truncate(self.cursor),
+            KeyCode::Char('w') => {
+                let start = self.word_start();
+                self.text.replace_range(start..self.cursor, "");
+                self.cursor = start;
+            }
+            KeyCode::Char('u') => {
+                self.text.replace_range(..self.cursor, "");
+                self.cursor = 0;
+            }
+            _ => {}
+        }
+    }
+
+    /// The vim style's normal mode: motions, a few deletions, and the ways
+    /// back into insert mode
+    fn normal_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('h') | KeyCode::Left | KeyCode::Backspace => {
+                self.cursor = self.prev_boundary().unwrap_or(self.cursor);
+            }
+            KeyCode::Char('l') | KeyCode::Right => {
+                self.cursor = self.next_boundary().unwrap_or(self.cursor);
+            }
+            KeyCode::Char('0' | '^') | KeyCode::Home => self.cursor = 0,
+            KeyCode::Char('$') | KeyCode::End => self.cursor = self.text.len(),
+            KeyCode::Char('w') => self.cursor = self.next_word_start(),
+            KeyCode::Char('b') => self.cursor = self.word_start(),
+            KeyCode::Char('x') | KeyCode::Delete => self.delete_forward(),
+            KeyCode::Char('X') => self.delete_back(),
+            KeyCode::Char('D') => self.text.truncate(self.cursor),
+            KeyCode::Char('C') => {
+                self.text.truncate(self.cursor);
+                self.normal = false;
+            }
+            KeyCode::Char('S') => self.set(String::new()),
+            KeyCode::Char('i') => self.normal = false,
+            KeyCode::Char('a') => {
+                self.cursor = self.next_boundary().unwrap_or(self.cursor);
+                self.normal = false;
+            }
+            KeyCode::Char('I') => {
+                self.cursor = 0;
+                self.normal = false;
+            }
+            KeyCode::Char('A') => {
+                self.cursor = self.text.len();
+                self.normal = false;
+            }
+            _ => {}
+        }
+        // The cursor rests on a character in normal mode, never past the end
+        if self.normal && self.cursor == self.text.len() {
+            self.cursor = self.prev_boundary().unwrap_or(0);
+        }
+    }
+
+    /// The text with the cursor drawn in: the character under it reversed, or
+    /// a trailing `_` at the end while typing
+    pub fn spans(&self, style: Style) -> Vec<Span<'static>> {
+        let (before, rest) = self.text.split_at(self.cursor);
+        let mut chars = rest.chars();
+        let under = match chars.next() {
+            Some(c) => c.to_string(),
+            None if self.normal => " ".to_string(),
+            None => return vec![Span::styled(format!("{before}_"), style)],
+        };
+        vec![
+            Span::styled(before.to_string(), style),
+            Span::styled(under, style.add_modifier(Modifier::REVERSED)),
+            Span::styled(chars.as_str().to_string(), style),
+        ]
+    }
+
+    fn delete_back(&mut self) {
+        if let Some(prev) = self.prev_boundary() {
+            self.text.replace_range(prev..self.cursor, "");
+            self.cursor = prev;
+        }
+    }
+
+    fn delete_forward(&mut self) {
+        if let Some(next) = self.next_boundary() {
+            self.text.replace_range(self.cursor..next, "");
+        }
+    }
+
+    fn prev_boundary(&self) -> Option<usize> {
+        self.text[..self.cursor]
+            .char_indices()
+            .next_back()
+            .map(|(i, _)| i)
+    }
+
+    fn next_boundary(&self) -> Option<usize> {
+        let c = self.text[self.cursor..].chars().next()?;
+        Some(self.cursor + c.len_utf8())
+    }
+
+    /// Where the word before the cursor starts, skipping spaces first
+    fn word_start(&self) -> usize {
+        let before = self.text[..self.cursor].trim_end();
+        before
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8())
+    }
+
+    /// Where the next word after the cursor starts, or the end
+    fn next_word_start(&self) -> usize {
+        let rest = &self.text[self.cursor..];
+        let word_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let gap = rest[word_end..]
+            .find(|c: char| !c.is_whitespace())
+            .unwrap_or(rest.len() - word_end);
+        self.cursor + word_end + gap
+    }
+}
+
+impl Deref for TextInput {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl fmt::Display for TextInput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
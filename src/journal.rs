@@ -0,0 +1,121 @@
+use crate::git;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// Intent record written before a destructive operation and removed once it
+/// finishes. Finding one at startup means gsm died part-way through.
+#[derive(Debug, Serialize, Deserialize)]
+struct Journal {
+    op: String,
+    started: i64,
+    stashes: Vec<JournalStash>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JournalStash {
+    sha: String,
+    name: String,
+    message: String,
+}
+
+const JOURNAL_FILE: &str = "gsm-journal.json";
+const BACKUP_PREFIX: &str = "refs/gsm/backup";
+
+fn backup_ref(sha: &str) -> String {
+    format!("{BACKUP_PREFIX}/{sha}")
+}
+
+/// Record `op` and pin each affected stash commit under a backup ref, so it
+/// stays reachable even if gsm is killed between dropping and finishing
+pub fn begin(op: &str, stashes: &[&git::Stash]) -> Result<()> {
+    for stash in stashes {
+        git::update_ref(&backup_ref(&stash.sha), &stash.sha)?;
+    }
+
+    let journal = Journal {
+        op: op.to_string(),
+        started: chrono::Local::now().timestamp(),
+        stashes: stashes
+            .iter()
+            .map(|s| JournalStash {
+                sha: s.sha.clone(),
+                name: s.name.clone(),
+                message: s.short_msg.clone(),
+            })
+            .collect(),
+    };
+    let path = git::git_path(JOURNAL_FILE)?;
+    fs::write(&path, serde_json::to_string_pretty(&journal)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The operation completed (successfully or with a reported error): drop the
+/// journal and its backup refs
+pub fn finish() -> Result<()> {
+    let path = git::git_path(JOURNAL_FILE)?;
+    let Ok(raw) = fs::read_to_string(&path) else {
+        return Ok(());
+    };
+    if let Ok(journal) = serde_json::from_str::<Journal>(&raw) {
+        for stash in &journal.stashes {
+            git::delete_ref(&backup_ref(&stash.sha))?;
+        }
+    }
+    fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))
+}
+
+/// Look for an interrupted operation left by a previous run. Stashes that
+/// survived lose their backup ref; for missing ones the ref is kept and the
+/// returned guidance explains how to restore them.
+pub fn recover() -> Result<Option<String>> {
+    let path = git::git_path(JOURNAL_FILE)?;
+    let Ok(raw) = fs::read_to_string(&path) else {
+        return Ok(None);
+    };
+    let journal: Journal = match serde_json::from_str(&raw) {
+        Ok(j) => j,
+        Err(_) => {
+            // Died while writing the journal itself, before touching anything
+            let _ = fs::remove_file(&path);
+            return Ok(None);
+        }
+    };
+
+    let listed: Vec<String> = git::list_stashes()?.into_iter().map(|s| s.sha).collect();
+    let mut lines = vec![format!(
+        "Warning: gsm was interrupted during \"{}\".",
+        journal.op
+    )];
+    for stash in &journal.stashes {
+        if listed.contains(&stash.sha) {
+            git::delete_ref(&backup_ref(&stash.sha))?;
+            lines.push(format!(
+                "{} ({}) is still in the stash list.",
+                stash.name, stash.message
+            ));
+        } else {
+            lines.push(format!(
+                "{} ({}) is gone from the list but kept at {}.",
+                stash.name,
+                stash.message,
+                backup_ref(&stash.sha)
+            ));
+            lines.push(format!(
+                "  Restore: git stash store -m \"{}\" {}",
+                stash.message,
+                backup_ref(&stash.sha)
+            ));
+            lines.push(format!(
+                "  Discard: git update-ref -d {}",
+                backup_ref(&stash.sha)
+            ));
+        }
+    }
+    if journal.op.starts_with("pop") || journal.op.starts_with("apply") {
+        lines.push("Check `git status`: the working tree may be partly updated.".to_string());
+    }
+
+    fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    Ok(Some(lines.join("\n")))
+}
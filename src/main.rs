@@ -1,33 +1,90 @@
+mod actions;
+mod ansi;
 mod app;
+mod archive;
+mod audit;
+mod cache;
+mod cli;
+mod clipboard;
+mod config;
+mod diff;
+mod events;
+mod external;
+mod forge;
 mod git;
+mod hooks;
+mod input;
+mod journal;
+mod modes;
+mod pins;
+mod preview;
+mod query;
+mod report;
+mod repos;
+mod state;
+mod todo;
 mod ui;
-mod events;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use clap::Parser;
 use crossterm::{
+    event::{DisableBracketedPaste, EnableBracketedPaste},
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
-use ratatui::{backend::CrosstermBackend, Terminal};
-use std::io;
+use ratatui::{Terminal, backend::CrosstermBackend};
+use std::io::{self, IsTerminal};
 
 fn main() -> Result<()> {
+    let cli = cli::Cli::parse();
+
+    // Generated from the CLI definition alone, so they work outside a repo
+    match cli.command {
+        Some(cli::Command::Completions { shell }) => return cli::completions(shell),
+        Some(cli::Command::Man) => return cli::man(),
+        _ => {}
+    }
+
+    let start = match &cli.repo {
+        Some(repo) => repo.clone(),
+        None => std::env::current_dir().context("Failed to read the current directory")?,
+    };
+
     // Check we're inside a git repo
-    git::assert_git_repo()?;
+    git::assert_git_repo(&start)?;
+    git::open_repo(&start)?;
+
+    let config = config::load()?;
+    git::init_backend(config.git.backend)?;
+    hooks::init(&config.hooks);
+
+    // Subcommands run without the TUI
+    if let Some(command) = cli.command {
+        return cli::run(command, &config);
+    }
+
+    // No usable terminal (piped output, TERM=dumb): print the list instead
+    if !io::stdout().is_terminal() || std::env::var("TERM").is_ok_and(|t| t == "dumb") {
+        return cli::run(cli::Command::List, &config);
+    }
 
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    execute!(stdout, EnterAlternateScreen, EnableBracketedPaste)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     // Run app
-    let result = app::run(&mut terminal);
+    let result = app::run(&mut terminal, config);
 
     // Restore terminal
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    execute!(
+        terminal.backend_mut(),
+        DisableBracketedPaste,
+        LeaveAlternateScreen
+    )?;
     terminal.show_cursor()?;
 
     if let Err(e) = result {
@@ -36,4 +93,4 @@ fn main() -> Result<()> {
     }
 
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,109 @@
+use super::ModeController;
+use crate::actions::{Action, LastChange};
+use crate::app::{App, Mode};
+use crate::ui::{self, BRAND, DIM, centered_rect};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::Alignment,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+
+/// Prompt for a free-text note to keep with a stash
+pub struct Annotate;
+
+impl ModeController for Annotate {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        ui::render_main(f, app);
+        draw(f, app);
+    }
+
+    /// Start from the note already attached, if any
+    fn enter(&self, app: &mut App) {
+        let text = app
+            .selected_stash()
+            .and_then(|s| app.annotations.get(&s.sha))
+            .cloned()
+            .unwrap_or_default();
+        if let Mode::Annotate(input) = &mut app.mode {
+            input.set(text);
+        }
+    }
+}
+
+fn handle(app: &mut App, key: KeyEvent) -> Result<bool> {
+    let Mode::Annotate(input) = &mut app.mode else {
+        return Ok(false);
+    };
+    match key.code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+        }
+        KeyCode::Enter => {
+            let text = input.to_string();
+            let result = app.set_annotation(&text);
+            if result.is_ok() {
+                app.last_change = Some(LastChange::typed(Action::Annotate, &text));
+            }
+            app.report(result);
+        }
+        _ => {
+            input.handle_key(key);
+        }
+    }
+    Ok(false)
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let Mode::Annotate(input) = &app.mode else {
+        return;
+    };
+    let area = centered_rect(60, 45, f.area());
+    f.render_widget(Clear, area);
+
+    let content = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            "Note for this stash:",
+            Style::default().fg(Color::Gray),
+        )),
+        Line::from(
+            input.spans(
+                Style::default()
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        ),
+        Line::from(""),
+        Line::from(Span::styled(
+            "Shown in the preview and searchable with /; empty clears",
+            Style::default().fg(DIM),
+        )),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("[Enter]", Style::default().fg(BRAND)),
+            Span::raw(" save   "),
+            Span::styled("[Esc]", Style::default().fg(Color::Red)),
+            Span::raw(" cancel"),
+        ]),
+    ];
+
+    let popup = Paragraph::new(content)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: false })
+        .block(
+            Block::default()
+                .title(" Note ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(BRAND)),
+        );
+
+    f.render_widget(popup, area);
+}
@@ -0,0 +1,124 @@
+use super::ModeController;
+use crate::app::{App, Mode};
+use crate::ui::{self, ADDED, BRAND, DIM, HIGHLIGHT_BG, centered_rect, fit};
+use anyhow::Result;
+use chrono::{Local, TimeZone};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+};
+
+/// Stashes copied into the archive directory, by hand or before a drop;
+/// Enter puts the selected one back on the stash list
+pub struct Archive;
+
+impl ModeController for Archive {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key.code)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        ui::render_main(f, app);
+        draw(f, app);
+    }
+
+    fn enter(&self, app: &mut App) {
+        if let Err(e) = app.load_archive() {
+            app.mode = Mode::Message(format!("Error: {e}"));
+        }
+    }
+}
+
+fn handle(app: &mut App, key: KeyCode) -> Result<bool> {
+    match key {
+        KeyCode::Esc | KeyCode::Char('q') => {
+            app.mode = Mode::Normal;
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            app.archive_selected = app.archive_selected.saturating_sub(1);
+        }
+        KeyCode::Down | KeyCode::Char('j')
+            if app.archive_selected + 1 < app.archive_entries.len() =>
+        {
+            app.archive_selected += 1;
+        }
+        KeyCode::Char('r') => {
+            if let Err(e) = app.load_archive() {
+                app.mode = Mode::Message(format!("Error: {e}"));
+            }
+        }
+        KeyCode::Enter if app.archive_selected < app.archive_entries.len() => {
+            let result = app.restore_archived();
+            app.report(result);
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let area = centered_rect(75, 60, f.area());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Archive ")
+        .title_bottom(Line::from(vec![
+            Span::styled(" [Enter]", Style::default().fg(BRAND)),
+            Span::raw(" restore  "),
+            Span::styled("[r]", Style::default().fg(BRAND)),
+            Span::raw(" refresh  "),
+            Span::styled("[Esc]", Style::default().fg(Color::Red)),
+            Span::raw(" close "),
+        ]))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(BRAND));
+
+    if app.archive_entries.is_empty() {
+        let text = Paragraph::new(Span::styled(
+            "Nothing archived yet; Z archives the selected stash.",
+            Style::default().fg(DIM),
+        ))
+        .block(block);
+        f.render_widget(text, area);
+        return;
+    }
+
+    let items: Vec<ListItem> = app
+        .archive_entries
+        .iter()
+        .map(|entry| {
+            let archived = Local
+                .timestamp_opt(entry.archived, 0)
+                .single()
+                .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_default();
+            let (status, color) = if app.has_stash(&entry.sha) {
+                ("in list", ADDED)
+            } else {
+                ("dropped", Color::Yellow)
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{archived}  "), Style::default().fg(DIM)),
+                Span::styled(format!("{status:<8} "), Style::default().fg(color)),
+                Span::styled(
+                    format!("{} ", fit(&entry.branch, 16)),
+                    Style::default().fg(BRAND),
+                ),
+                Span::styled(entry.short_msg.clone(), Style::default().fg(Color::White)),
+            ]))
+        })
+        .collect();
+
+    let mut state = ListState::default();
+    state.select(Some(app.archive_selected));
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().bg(HIGHLIGHT_BG).fg(Color::White))
+        .highlight_symbol("▶ ");
+
+    f.render_stateful_widget(list, area, &mut state);
+}
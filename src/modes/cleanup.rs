@@ -0,0 +1,149 @@
+use super::ModeController;
+use crate::app::{App, CleanupStep, Mode};
+use crate::ui::{BRAND, DIM, colorize_stat_line, render_key_bar};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+};
+
+/// Wizard walking through stale stashes one at a time
+pub struct Cleanup;
+
+impl ModeController for Cleanup {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key.code)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        draw(f, app);
+    }
+}
+
+fn handle(app: &mut App, key: KeyCode) -> Result<bool> {
+    let step = match key {
+        KeyCode::Char('k') | KeyCode::Char(' ') => None,
+        KeyCode::Char('e') => Some(CleanupStep::Export),
+        KeyCode::Char('d') => Some(CleanupStep::Drop),
+        KeyCode::Esc | KeyCode::Char('q') => {
+            app.toast(app.cleanup_summary());
+            return Ok(false);
+        }
+        _ => return Ok(false),
+    };
+
+    match app.cleanup_step(step) {
+        Ok(true) => {}
+        Ok(false) => app.toast(app.cleanup_summary()),
+        Err(e) => {
+            app.reload()?;
+            app.mode = Mode::Message(format!("Error: {e}"));
+        }
+    }
+    Ok(false)
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length(6),
+            Constraint::Min(1),
+            Constraint::Length(3),
+        ])
+        .split(f.area());
+
+    let title = Line::from(vec![
+        Span::styled(
+            " Cleanup ",
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            format!(
+                "  stale stash {} of {} (older than {} days)",
+                app.cleanup_pos + 1,
+                app.cleanup_queue.len(),
+                app.config.stash.stale_days
+            ),
+            Style::default().fg(Color::Gray),
+        ),
+    ]);
+    f.render_widget(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow))
+            .title(title),
+        chunks[0],
+    );
+
+    let info: Vec<Line> = match app.cleanup_stash() {
+        Some(stash) => {
+            let created = chrono::DateTime::from_timestamp(stash.timestamp, 0)
+                .map(|d| {
+                    d.with_timezone(&chrono::Local)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string()
+                })
+                .unwrap_or_default();
+            let field = |label: &str, value: String, color: Color| {
+                Line::from(vec![
+                    Span::styled(format!("{label:>9}  "), Style::default().fg(DIM)),
+                    Span::styled(value, Style::default().fg(color)),
+                ])
+            };
+            vec![
+                field("stash", stash.name.clone(), BRAND),
+                field("branch", stash.branch.clone(), Color::Cyan),
+                field("message", stash.short_msg.clone(), Color::White),
+                field(
+                    "created",
+                    format!("{created} ({})", stash.date),
+                    Color::Yellow,
+                ),
+            ]
+        }
+        None => Vec::new(),
+    };
+    f.render_widget(
+        Paragraph::new(info).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Rgb(80, 80, 100))),
+        ),
+        chunks[1],
+    );
+
+    let stat: Vec<Line> = app
+        .cleanup_stat
+        .iter()
+        .map(|l| colorize_stat_line(l))
+        .collect();
+    f.render_widget(
+        Paragraph::new(stat).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Rgb(80, 80, 100)))
+                .title(" Files "),
+        ),
+        chunks[2],
+    );
+
+    render_key_bar(
+        f,
+        chunks[3],
+        &[
+            ("k/Space", "keep"),
+            ("e", "export .patch"),
+            ("d", "drop"),
+            ("Esc/q", "stop"),
+        ],
+    );
+}
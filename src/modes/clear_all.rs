@@ -0,0 +1,115 @@
+use super::ModeController;
+use crate::app::{App, Mode};
+use crate::ui::{self, BRAND, DIM, centered_rect_lines};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::Alignment,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+/// What has to be typed before every stash is dropped
+const CONFIRM_WORD: &str = "CLEAR";
+
+/// Guarded prompt for dropping every stash at once: nothing happens until
+/// CLEAR is typed, and the stashes are archived first unless Tab turns it off
+pub struct ClearAll;
+
+impl ModeController for ClearAll {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        ui::render_main(f, app);
+        draw(f, app);
+    }
+
+    fn enter(&self, app: &mut App) {
+        app.clear_input.clear();
+        app.clear_archive = true;
+    }
+}
+
+fn handle(app: &mut App, key: KeyEvent) -> Result<bool> {
+    match key.code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+        }
+        KeyCode::Tab => app.clear_archive = !app.clear_archive,
+        KeyCode::Enter if app.clear_input.trim() == CONFIRM_WORD => {
+            let result = app.clear_all();
+            app.report(result);
+        }
+        KeyCode::Enter => {}
+        _ => {
+            app.clear_input.handle_key(key);
+        }
+    }
+    Ok(false)
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let area = centered_rect_lines(60, 11, f.area());
+    f.render_widget(Clear, area);
+
+    let pinned = app.stashes.iter().filter(|s| app.is_pinned(s)).count();
+    let count = app.stashes.len() - pinned;
+    let mut summary = format!("Drops all {count} stash(es)");
+    if pinned > 0 {
+        summary.push_str(&format!("; {pinned} pinned are kept"));
+    }
+    let (archive, archive_color) = if app.clear_archive {
+        ("archive them first (restore with Ctrl-a)", Color::Green)
+    } else {
+        ("don't archive", Color::Red)
+    };
+    let ready = app.clear_input.trim() == CONFIRM_WORD;
+
+    let content = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            summary,
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("[Tab] ", Style::default().fg(BRAND)),
+            Span::styled(archive, Style::default().fg(archive_color)),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled(
+            format!("Type {CONFIRM_WORD} to confirm:"),
+            Style::default().fg(Color::Gray),
+        )),
+        Line::from(
+            app.clear_input.spans(
+                Style::default()
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        ),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled(
+                "[Enter]",
+                Style::default().fg(if ready { Color::Red } else { DIM }),
+            ),
+            Span::raw(" clear   "),
+            Span::styled("[Esc]", Style::default().fg(BRAND)),
+            Span::raw(" cancel"),
+        ]),
+    ];
+
+    let popup = Paragraph::new(content).alignment(Alignment::Center).block(
+        Block::default()
+            .title(" Clear All Stashes ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Red)),
+    );
+
+    f.render_widget(popup, area);
+}
@@ -0,0 +1,301 @@
+use super::ModeController;
+use crate::app::{App, ApplyTarget, ConfirmAction, Mode};
+use crate::config::ConfirmPolicy;
+use crate::ui::{self, BRAND, DIM, centered_rect, centered_rect_lines};
+use crate::{git, todo};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::Alignment,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+/// Yes/no prompt guarding an action, listing the stashes it touches
+pub struct Confirm;
+
+impl ModeController for Confirm {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        let Mode::Confirm(action) = app.mode.clone() else {
+            return Ok(false);
+        };
+        handle(app, key.code, action)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        ui::render_main(f, app);
+        if let Mode::Confirm(action) = &app.mode {
+            draw(f, app, action);
+        }
+    }
+
+    fn enter(&self, app: &mut App) {
+        app.confirm_input.clear();
+        if matches!(
+            app.mode,
+            Mode::Confirm(ConfirmAction::Apply | ConfirmAction::Pop)
+        ) {
+            app.start_preflight();
+        }
+    }
+
+    fn exit(&self, app: &mut App) {
+        app.cancel_preflight();
+    }
+}
+
+fn handle(app: &mut App, key: KeyCode, action: ConfirmAction) -> Result<bool> {
+    let typed = app.confirm_policy(&action) == ConfirmPolicy::TypeIndex;
+    match key {
+        KeyCode::Char('y') | KeyCode::Enter if !typed => confirm(app, action)?,
+        KeyCode::Enter => {
+            let expected = app.selected_stash().map(|s| s.index.to_string());
+            if expected.as_deref() == Some(app.confirm_input.trim()) {
+                confirm(app, action)?;
+            } else {
+                app.confirm_input.clear();
+            }
+        }
+        KeyCode::Char(c) if typed && c.is_ascii_digit() => app.confirm_input.push(c),
+        KeyCode::Backspace => {
+            app.confirm_input.pop();
+        }
+        KeyCode::Char('n') | KeyCode::Esc => {
+            app.mode = Mode::Normal;
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn confirm(app: &mut App, action: ConfirmAction) -> Result<()> {
+    // Pinned stashes need a second yes
+    if action == ConfirmAction::Drop && app.selected_stash().is_some_and(|s| app.is_pinned(s)) {
+        app.confirm_input.clear();
+        app.mode = Mode::Confirm(ConfirmAction::DropPinned);
+        return Ok(());
+    }
+    app.run_confirmed(action)
+}
+
+/// Conflicting files listed in the prompt before the rest are summarised
+const PREFLIGHT_FILES: usize = 8;
+
+/// The dry-run verdict for an apply or pop
+fn preflight_lines(app: &App) -> Vec<Line<'static>> {
+    match &app.preflight {
+        None => vec![Line::from(Span::styled(
+            "checking whether it applies…",
+            Style::default().fg(DIM),
+        ))],
+        Some(Ok(git::Preflight::Clean)) => vec![Line::from(Span::styled(
+            "✓ will apply cleanly",
+            Style::default().fg(Color::Green),
+        ))],
+        Some(Ok(git::Preflight::Merge)) => vec![Line::from(Span::styled(
+            "✓ will apply cleanly with a 3-way merge",
+            Style::default().fg(Color::Green),
+        ))],
+        Some(Ok(git::Preflight::Conflicts(files))) => {
+            let mut lines = vec![Line::from(Span::styled(
+                "✗ would conflict in:",
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ))];
+            lines.extend(
+                files
+                    .iter()
+                    .take(PREFLIGHT_FILES)
+                    .map(|f| Line::from(Span::styled(f.clone(), Style::default().fg(Color::Red)))),
+            );
+            if files.len() > PREFLIGHT_FILES {
+                lines.push(Line::from(Span::styled(
+                    format!("… and {} more", files.len() - PREFLIGHT_FILES),
+                    Style::default().fg(Color::Red),
+                )));
+            }
+            lines
+        }
+        Some(Err(e)) => vec![Line::from(Span::styled(
+            format!("couldn't check: {e}"),
+            Style::default().fg(DIM),
+        ))],
+    }
+}
+
+fn draw(f: &mut Frame, app: &App, action: &ConfirmAction) {
+    let stash_line = |verb: &str, stash: &git::Stash, msg: &str| {
+        Line::from(vec![
+            Span::styled(verb.to_string(), Style::default().fg(Color::White)),
+            Span::styled(format!("{} ", stash.name), Style::default().fg(BRAND)),
+            Span::styled(msg.to_string(), Style::default().fg(Color::Gray)),
+        ])
+    };
+    let listed: Vec<Line> = match action {
+        ConfirmAction::DropMarked => app
+            .marked_stashes()
+            .into_iter()
+            .map(|s| {
+                if app.is_pinned(s) {
+                    stash_line("📌 kept ", s, &s.short_msg)
+                } else {
+                    stash_line("", s, &s.short_msg)
+                }
+            })
+            .collect(),
+        ConfirmAction::PruneMerged => app
+            .prune_stashes()
+            .into_iter()
+            .map(|s| stash_line("", s, &s.short_msg))
+            .collect(),
+        ConfirmAction::RunTodo => app
+            .todo_plan
+            .iter()
+            .filter_map(|(sha, step)| {
+                let stash = app.stash_by_sha(sha)?;
+                Some(match step {
+                    todo::Step::Drop if app.is_pinned(stash) => {
+                        stash_line("📌 kept ", stash, &stash.short_msg)
+                    }
+                    todo::Step::Drop => stash_line("drop   ", stash, &stash.short_msg),
+                    todo::Step::Export => stash_line("export ", stash, &stash.short_msg),
+                    todo::Step::Rename(msg) => stash_line("rename ", stash, &format!("→ {msg}")),
+                })
+            })
+            .collect(),
+        ConfirmAction::Rename => app
+            .selected_stash()
+            .into_iter()
+            .flat_map(|s| {
+                [
+                    stash_line("from ", s, &s.short_msg),
+                    stash_line("to   ", s, app.rename_input.trim()),
+                ]
+            })
+            .collect(),
+        ConfirmAction::Apply | ConfirmAction::Pop => preflight_lines(app),
+        ConfirmAction::ApplyToBranch => {
+            let branch = match app.targets.get(app.target_selected) {
+                Some(ApplyTarget::Branch(branch)) => branch.as_str(),
+                _ => "",
+            };
+            let mut lines: Vec<Line> = app
+                .selected_stash()
+                .into_iter()
+                .map(|s| stash_line("apply  ", s, &s.short_msg))
+                .collect();
+            lines.push(Line::from(vec![
+                Span::styled("switch ", Style::default().fg(Color::White)),
+                Span::styled(
+                    format!("{} → {branch}", app.current_branch),
+                    Style::default().fg(BRAND),
+                ),
+            ]));
+            lines
+        }
+        _ => Vec::new(),
+    };
+    let area = if listed.is_empty() {
+        centered_rect(50, 20, f.area())
+    } else {
+        // Body, blank lines, the listed lines, buttons and borders
+        centered_rect_lines(60, listed.len() as u16 + 7, f.area())
+    };
+    f.render_widget(Clear, area);
+
+    let (title, body, color) = match action {
+        ConfirmAction::Apply => (
+            "Apply Stash",
+            "Apply this stash? (it stays in the stash list)",
+            Color::Green,
+        ),
+        ConfirmAction::Pop => (
+            "Pop Stash",
+            "Apply and remove this stash from the list?",
+            Color::Yellow,
+        ),
+        ConfirmAction::Drop => (
+            "Drop Stash",
+            "Permanently delete this stash? This cannot be undone.",
+            Color::Red,
+        ),
+        ConfirmAction::DropPinned => (
+            "Drop Pinned Stash",
+            "This stash is pinned. Drop it anyway?",
+            Color::Red,
+        ),
+        ConfirmAction::DropMarked => (
+            "Drop Marked Stashes",
+            "Permanently delete these stashes? This cannot be undone.",
+            Color::Red,
+        ),
+        ConfirmAction::PruneMerged => (
+            "Prune Merged Stashes",
+            "These stashes are already contained in HEAD. Drop them?",
+            Color::Yellow,
+        ),
+        ConfirmAction::RunTodo => (
+            "Run TODO Plan",
+            "Carry out this plan? Drops cannot be undone.",
+            Color::Yellow,
+        ),
+        ConfirmAction::ApplyToBranch => (
+            "Apply on Another Branch",
+            "Switch branch and apply? A failure switches back.",
+            Color::Yellow,
+        ),
+        ConfirmAction::Rename => (
+            "Rename Stash",
+            "Rename? It and newer stashes are dropped and re-stored.",
+            Color::Yellow,
+        ),
+    };
+
+    let mut content = vec![
+        Line::from(""),
+        Line::from(Span::styled(body, Style::default().fg(Color::White))),
+        Line::from(""),
+    ];
+    if !listed.is_empty() {
+        content.extend(listed);
+        content.push(Line::from(""));
+    }
+    let typed_index = match app.selected_stash() {
+        Some(stash) if app.confirm_policy(action) == ConfirmPolicy::TypeIndex => Some(stash.index),
+        _ => None,
+    };
+    content.push(match typed_index {
+        Some(index) => Line::from(vec![
+            Span::styled(
+                format!("Type {index} and press Enter: "),
+                Style::default().fg(Color::White),
+            ),
+            Span::styled(
+                format!("{}_", app.confirm_input),
+                Style::default().fg(BRAND).add_modifier(Modifier::BOLD),
+            ),
+            Span::raw("    "),
+            Span::styled("[Esc] No", Style::default().fg(Color::Red)),
+        ]),
+        None => Line::from(vec![
+            Span::styled(
+                "[y] Yes",
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw("    "),
+            Span::styled("[n] No", Style::default().fg(Color::Red)),
+        ]),
+    });
+
+    let popup = Paragraph::new(content).alignment(Alignment::Center).block(
+        Block::default()
+            .title(format!(" {title} "))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(color)),
+    );
+
+    f.render_widget(popup, area);
+}
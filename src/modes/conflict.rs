@@ -0,0 +1,183 @@
+use super::ModeController;
+use crate::app::{App, Mode};
+use crate::git;
+use crate::ui::{ADDED, HIGHLIGHT_BG, REMOVED, render_key_bar};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+};
+
+/// Files left conflicted by a failed apply/pop, and ways to resolve them
+pub struct Conflict;
+
+impl ModeController for Conflict {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key.code)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        draw(f, app);
+    }
+}
+
+fn handle(app: &mut App, key: KeyCode) -> Result<bool> {
+    match key {
+        // Keep the conflict markers and resolve outside gsm
+        KeyCode::Esc | KeyCode::Char('q') => {
+            app.reload()?;
+            app.mode = Mode::Normal;
+        }
+        KeyCode::Up | KeyCode::Char('k') if app.conflict_selected > 0 => {
+            app.conflict_selected -= 1;
+            app.load_conflict_preview()?;
+        }
+        KeyCode::Down | KeyCode::Char('j') if app.conflict_selected + 1 < app.conflicts.len() => {
+            app.conflict_selected += 1;
+            app.load_conflict_preview()?;
+        }
+        KeyCode::PageUp => {
+            app.conflict_scroll = app.conflict_scroll.saturating_sub(20);
+        }
+        KeyCode::PageDown => {
+            app.conflict_scroll =
+                (app.conflict_scroll + 20).min(app.conflict_preview.len().saturating_sub(1));
+        }
+        KeyCode::Char('e') => {
+            app.pending_editor = app.selected_conflict().cloned();
+        }
+        KeyCode::Char('o') | KeyCode::Char('t') => {
+            if let Some(path) = app.selected_conflict().cloned() {
+                let side = if key == KeyCode::Char('o') {
+                    git::Side::Ours
+                } else {
+                    git::Side::Theirs
+                };
+                match git::checkout_side(&path, side) {
+                    Ok(()) if !app.load_conflicts()? => {
+                        app.reload()?;
+                        app.toast("All conflicts resolved.");
+                    }
+                    Ok(()) => {}
+                    Err(e) => app.mode = Mode::Message(format!("Error: {e}")),
+                }
+            }
+        }
+        KeyCode::Char('a') => {
+            let result = git::abort_merge();
+            app.reload()?;
+            app.mode = match result {
+                Ok(()) => Mode::Message("Apply aborted; conflicted files restored.".to_string()),
+                Err(e) => Mode::Message(format!("Error: {e}")),
+            };
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(1),
+            Constraint::Length(3),
+        ])
+        .split(f.area());
+
+    let title = Line::from(vec![
+        Span::styled(
+            " Conflict ",
+            Style::default()
+                .fg(Color::Black)
+                .bg(REMOVED)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            format!("  {} file(s) need resolving", app.conflicts.len()),
+            Style::default().fg(Color::Gray),
+        ),
+    ]);
+    f.render_widget(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(REMOVED))
+            .title(title),
+        chunks[0],
+    );
+
+    let body = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+        .split(chunks[1]);
+
+    let items: Vec<ListItem> = app
+        .conflicts
+        .iter()
+        .map(|p| ListItem::new(Span::styled(p.clone(), Style::default().fg(Color::Yellow))))
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(app.conflict_selected));
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Rgb(80, 80, 100)))
+                .title(" Files "),
+        )
+        .highlight_style(Style::default().bg(HIGHLIGHT_BG))
+        .highlight_symbol("▶ ");
+    f.render_stateful_widget(list, body[0], &mut state);
+
+    // Colour the file by region: ours, theirs, and the markers between them
+    let mut region = Style::default().fg(Color::Gray);
+    let lines: Vec<Line> = app
+        .conflict_preview
+        .iter()
+        .map(|line| {
+            let marker = Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD);
+            let style = if line.starts_with("<<<<<<<") {
+                region = Style::default().fg(Color::Cyan);
+                marker
+            } else if line.starts_with("=======") {
+                region = Style::default().fg(ADDED);
+                marker
+            } else if line.starts_with(">>>>>>>") {
+                region = Style::default().fg(Color::Gray);
+                marker
+            } else {
+                region
+            };
+            Line::from(Span::styled(line.clone(), style))
+        })
+        .skip(app.conflict_scroll)
+        .collect();
+
+    let preview = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Rgb(80, 80, 100)))
+            .title(" ours (upstream) / theirs (stash) "),
+    );
+    f.render_widget(preview, body[1]);
+
+    render_key_bar(
+        f,
+        chunks[2],
+        &[
+            ("↑↓/jk", "file"),
+            ("e", "open in $EDITOR"),
+            ("o", "take ours"),
+            ("t", "take theirs"),
+            ("a", "abort"),
+            ("Esc/q", "keep & exit"),
+        ],
+    );
+}
@@ -0,0 +1,92 @@
+use super::ModeController;
+use crate::app::{App, Mode};
+use crate::ui::{self, BRAND, DIM, centered_rect};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::Alignment,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+/// Prompt for who to mail the selected stash to; left blank, the patch is
+/// only written
+pub struct Email;
+
+impl ModeController for Email {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        ui::render_main(f, app);
+        draw(f, app);
+    }
+}
+
+fn handle(app: &mut App, key: KeyEvent) -> Result<bool> {
+    match key.code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+        }
+        KeyCode::Enter => {
+            let result = app.email_stash();
+            app.report(result);
+        }
+        _ => {
+            app.email_input.handle_key(key);
+        }
+    }
+    Ok(false)
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let area = centered_rect(60, 25, f.area());
+    f.render_widget(Clear, area);
+
+    let name = app
+        .selected_stash()
+        .map(|s| s.name.clone())
+        .unwrap_or_default();
+    let action = if app.email_input.trim().is_empty() {
+        " write patch   "
+    } else {
+        " send   "
+    };
+    let content = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            format!("Send {name} to:"),
+            Style::default().fg(Color::Gray),
+        )),
+        Line::from(
+            app.email_input.spans(
+                Style::default()
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        ),
+        Line::from(Span::styled(
+            "blank: write the patch only (untracked files left out)",
+            Style::default().fg(DIM),
+        )),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("[Enter]", Style::default().fg(BRAND)),
+            Span::raw(action),
+            Span::styled("[Esc]", Style::default().fg(Color::Red)),
+            Span::raw(" cancel"),
+        ]),
+    ];
+
+    let popup = Paragraph::new(content).alignment(Alignment::Center).block(
+        Block::default()
+            .title(" Email Stash ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(BRAND)),
+    );
+
+    f.render_widget(popup, area);
+}
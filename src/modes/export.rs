@@ -0,0 +1,111 @@
+use super::ModeController;
+use crate::actions::{Action, LastChange};
+use crate::app::{App, Mode};
+use crate::ui::{self, BRAND, DIM, centered_rect_lines};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::Alignment,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+/// Most context lines the popup offers
+const MAX_CONTEXT: u32 = 99;
+
+/// Choose the format, and for the formats that take one the context size,
+/// before exporting the selected or marked stashes
+pub struct Export;
+
+impl ModeController for Export {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        ui::render_main(f, app);
+        draw(f, app);
+    }
+}
+
+fn handle(app: &mut App, key: KeyEvent) -> Result<bool> {
+    match key.code {
+        KeyCode::Esc => app.mode = Mode::Normal,
+        KeyCode::Tab => app.export_format = app.export_format.next(),
+        KeyCode::Left | KeyCode::Char('-') if app.export_format.has_context() => {
+            app.export_context = app.export_context.saturating_sub(1);
+        }
+        KeyCode::Right | KeyCode::Char('+') if app.export_format.has_context() => {
+            app.export_context = (app.export_context + 1).min(MAX_CONTEXT);
+        }
+        KeyCode::Enter => {
+            app.last_change = Some(LastChange {
+                action: Action::Export,
+                input: None,
+            });
+            app.export_stashes();
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let area = centered_rect_lines(64, 9, f.area());
+    f.render_widget(Clear, area);
+
+    let format = app.export_format;
+    let count = app.bulk_targets().len();
+    let context = if format.has_context() {
+        Span::styled(
+            format!("◀ {} lines ▶", app.export_context),
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        )
+    } else {
+        Span::styled("as the diff view", Style::default().fg(DIM))
+    };
+    let content = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            format!(
+                "{count} stash(es) to stash-<index>-<message>.{}",
+                format.extension()
+            ),
+            Style::default().fg(Color::Gray),
+        )),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("[Tab] Format: ", Style::default().fg(BRAND)),
+            Span::styled(
+                format.label(),
+                Style::default()
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        ]),
+        Line::from(vec![
+            Span::styled("[←/→] Context: ", Style::default().fg(BRAND)),
+            context,
+        ]),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("[Enter]", Style::default().fg(BRAND)),
+            Span::raw(" export   "),
+            Span::styled("[Esc]", Style::default().fg(Color::Red)),
+            Span::raw(" cancel"),
+        ]),
+    ];
+
+    let popup = Paragraph::new(content).alignment(Alignment::Center).block(
+        Block::default()
+            .title(" Export Stashes ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(BRAND)),
+    );
+
+    f.render_widget(popup, area);
+}
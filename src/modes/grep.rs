@@ -0,0 +1,167 @@
+use super::ModeController;
+use crate::app::{App, Mode};
+use crate::ui::{BRAND, DIM, HIGHLIGHT_BG, fit, render_key_bar, spinner};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+};
+
+/// Search the changes of every stash for some text and list the stashes that
+/// contain it; Enter shows just the matching hunks
+pub struct Grep;
+
+impl ModeController for Grep {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        draw(f, app);
+    }
+}
+
+fn handle(app: &mut App, key: KeyEvent) -> Result<bool> {
+    if app.grep_editing {
+        match key.code {
+            KeyCode::Esc if app.grep_pattern.is_empty() => app.mode = Mode::Normal,
+            KeyCode::Esc => app.grep_editing = false,
+            KeyCode::Enter => app.start_grep(),
+            _ => {
+                app.grep_input.handle_key(key);
+            }
+        }
+        return Ok(false);
+    }
+
+    let count = app.grep_hits.as_ref().map_or(0, Vec::len);
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => app.mode = Mode::Normal,
+        KeyCode::Up | KeyCode::Char('k') => {
+            app.grep_selected = app.grep_selected.saturating_sub(1);
+        }
+        KeyCode::Down | KeyCode::Char('j') => {
+            app.grep_selected = (app.grep_selected + 1).min(count.saturating_sub(1));
+        }
+        KeyCode::Enter | KeyCode::Char('d') => app.open_grep_hit(),
+        KeyCode::Char('/') => {
+            app.grep_input.set(app.grep_pattern.clone());
+            app.grep_editing = true;
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(1),
+            Constraint::Length(3),
+        ])
+        .split(f.area());
+
+    let title = Line::from(vec![
+        Span::styled(
+            " Grep stashes ",
+            Style::default()
+                .fg(Color::Black)
+                .bg(BRAND)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            spinner(app).map(|s| format!("  {s}")).unwrap_or_default(),
+            Style::default().fg(DIM),
+        ),
+    ]);
+    let input = if app.grep_editing {
+        let style = Style::default()
+            .fg(Color::White)
+            .add_modifier(Modifier::BOLD);
+        let mut spans = vec![Span::styled(
+            "Find changes containing: ",
+            Style::default().fg(Color::Gray),
+        )];
+        spans.extend(app.grep_input.spans(style));
+        Line::from(spans)
+    } else {
+        Line::from(vec![
+            Span::styled("Changes containing: ", Style::default().fg(Color::Gray)),
+            Span::styled(app.grep_pattern.clone(), Style::default().fg(Color::White)),
+        ])
+    };
+    f.render_widget(
+        Paragraph::new(input).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(BRAND))
+                .title(title),
+        ),
+        chunks[0],
+    );
+
+    let body = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Rgb(80, 80, 100)));
+    match &app.grep_hits {
+        Some(hits) if !hits.is_empty() => {
+            let items: Vec<ListItem> = hits
+                .iter()
+                .map(|hit| {
+                    ListItem::new(Line::from(vec![
+                        Span::styled(format!("{:<12}", hit.name), Style::default().fg(BRAND)),
+                        Span::styled(fit(&hit.short_msg, 40), Style::default().fg(Color::Gray)),
+                        Span::styled(
+                            format!(
+                                "  {} hunk{} in {} file{}",
+                                hit.hunks,
+                                if hit.hunks == 1 { "" } else { "s" },
+                                hit.files,
+                                if hit.files == 1 { "" } else { "s" },
+                            ),
+                            Style::default().fg(DIM),
+                        ),
+                    ]))
+                })
+                .collect();
+            let mut state = ListState::default();
+            state.select(Some(app.grep_selected));
+            let list = List::new(items)
+                .block(body.title(format!(" {} stash(es) ", hits.len())))
+                .highlight_style(Style::default().bg(HIGHLIGHT_BG))
+                .highlight_symbol("▶ ");
+            f.render_stateful_widget(list, chunks[1], &mut state);
+        }
+        hits => {
+            let msg = match hits {
+                None => "Searching…",
+                Some(_) if app.grep_pattern.is_empty() => {
+                    "Type some text and press Enter to find the stashes that change it."
+                }
+                Some(_) => "No stash changes a line containing that text.",
+            };
+            f.render_widget(
+                Paragraph::new(Span::styled(msg, Style::default().fg(DIM))).block(body),
+                chunks[1],
+            );
+        }
+    }
+
+    let keys: &[(&str, &str)] = if app.grep_editing {
+        &[("Enter", "search"), ("Esc", "cancel")]
+    } else {
+        &[
+            ("↑↓/jk", "navigate"),
+            ("Enter/d", "matching hunks"),
+            ("/", "new search"),
+            ("Esc/q", "back"),
+        ]
+    };
+    render_key_bar(f, chunks[2], keys);
+}
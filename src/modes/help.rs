@@ -0,0 +1,85 @@
+use super::ModeController;
+use crate::actions::{BINDINGS, Scope};
+use crate::app::{App, Mode};
+use crate::ui::{self, BRAND, DIM, centered_rect};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+/// Popup listing the keybindings; j/k scroll, any other key closes it
+pub struct Help;
+
+impl ModeController for Help {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        match key.code {
+            KeyCode::Down | KeyCode::Char('j') => {
+                app.help_scroll = (app.help_scroll + 1).min(lines().len());
+            }
+            KeyCode::Up | KeyCode::Char('k') => app.help_scroll = app.help_scroll.saturating_sub(1),
+            _ => app.mode = Mode::Normal,
+        }
+        Ok(false)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        ui::render_main(f, app);
+        draw(f, app);
+    }
+}
+
+/// One section per scope, built from the action registry
+fn lines() -> Vec<Line<'static>> {
+    let mut content: Vec<Line> = vec![Line::from("")];
+    for (heading, scopes) in [
+        ("Stash list", &[Scope::List][..]),
+        ("Diff / files view", &[Scope::View, Scope::Diff][..]),
+    ] {
+        content.push(Line::from(Span::styled(
+            heading,
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        )));
+        for binding in BINDINGS.iter().filter(|b| scopes.contains(&b.scope)) {
+            content.push(Line::from(vec![
+                Span::styled(
+                    format!("{:>12}", binding.label),
+                    Style::default().fg(BRAND).add_modifier(Modifier::BOLD),
+                ),
+                Span::raw("   "),
+                Span::styled(binding.description, Style::default().fg(Color::Gray)),
+            ]));
+        }
+        content.push(Line::from(""));
+    }
+    content.push(Line::from(Span::styled(
+        "j/k to scroll, any other key to close",
+        Style::default().fg(DIM),
+    )));
+    content
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let area = centered_rect(60, 70, f.area());
+    f.render_widget(Clear, area);
+
+    let content = lines();
+    let max = content
+        .len()
+        .saturating_sub(area.height.saturating_sub(2) as usize);
+    let scroll = app.help_scroll.min(max) as u16;
+
+    let popup = Paragraph::new(content).scroll((scroll, 0)).block(
+        Block::default()
+            .title(" Keybindings ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(BRAND)),
+    );
+
+    f.render_widget(popup, area);
+}
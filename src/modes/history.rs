@@ -0,0 +1,148 @@
+use super::ModeController;
+use crate::app::{App, Mode};
+use crate::audit;
+use crate::ui::{ADDED, BRAND, DIM, HIGHLIGHT_BG, REMOVED, render_key_bar};
+use anyhow::Result;
+use chrono::{Local, TimeZone};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+};
+
+/// The audit log: every apply, pop, drop and push gsm made, newest first.
+/// Dropped and popped stashes can be put back from here.
+pub struct History;
+
+impl ModeController for History {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key.code)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        draw(f, app);
+    }
+
+    fn enter(&self, app: &mut App) {
+        app.history = audit::load();
+        app.history_selected = 0;
+    }
+}
+
+fn handle(app: &mut App, key: KeyCode) -> Result<bool> {
+    match key {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('L') => app.mode = Mode::Normal,
+        KeyCode::Up | KeyCode::Char('k') => {
+            app.history_selected = app.history_selected.saturating_sub(1);
+        }
+        KeyCode::Down | KeyCode::Char('j') if app.history_selected + 1 < app.history.len() => {
+            app.history_selected += 1;
+        }
+        KeyCode::Char('r') => {
+            let result = app.restore_from_history();
+            app.report(result);
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn action_color(action: &str) -> Color {
+    match action {
+        "drop" | "pop" => REMOVED,
+        "push" | "restore" => ADDED,
+        _ => Color::Cyan,
+    }
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(1),
+            Constraint::Length(3),
+        ])
+        .split(f.area());
+
+    let title = Line::from(Span::styled(
+        " History ",
+        Style::default()
+            .fg(Color::Black)
+            .bg(BRAND)
+            .add_modifier(Modifier::BOLD),
+    ));
+    let summary = match app.history.get(app.history_selected) {
+        Some(entry) => match &entry.error {
+            Some(error) => Span::styled(error.lines().next().unwrap_or("").to_string(), REMOVED),
+            None => Span::styled(entry.sha.clone(), Style::default().fg(DIM)),
+        },
+        None => Span::styled("Nothing recorded yet.", Style::default().fg(DIM)),
+    };
+    f.render_widget(
+        Paragraph::new(Line::from(summary)).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(BRAND))
+                .title(title),
+        ),
+        chunks[0],
+    );
+
+    let items: Vec<ListItem> = app
+        .history
+        .iter()
+        .map(|entry| {
+            let when = Local
+                .timestamp_opt(entry.time, 0)
+                .single()
+                .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_default();
+            let (mark, mark_color) = if entry.ok {
+                ("✓", ADDED)
+            } else {
+                ("✗", REMOVED)
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{when}  "), Style::default().fg(DIM)),
+                Span::styled(format!("{mark} "), Style::default().fg(mark_color)),
+                Span::styled(
+                    format!("{:<8}", entry.action),
+                    Style::default().fg(action_color(&entry.action)),
+                ),
+                Span::styled(
+                    format!("{:<9}", entry.sha.get(..7).unwrap_or("")),
+                    Style::default().fg(BRAND),
+                ),
+                Span::styled(entry.message.clone(), Style::default().fg(Color::Gray)),
+            ]))
+        })
+        .collect();
+
+    let mut state = ListState::default();
+    state.select((!app.history.is_empty()).then_some(app.history_selected));
+    f.render_stateful_widget(
+        List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Rgb(80, 80, 100))),
+            )
+            .highlight_style(Style::default().bg(HIGHLIGHT_BG).fg(Color::White)),
+        chunks[1],
+        &mut state,
+    );
+
+    render_key_bar(
+        f,
+        chunks[2],
+        &[
+            ("↑↓/jk", "navigate"),
+            ("r", "restore dropped stash"),
+            ("Esc/q/L", "back"),
+        ],
+    );
+}
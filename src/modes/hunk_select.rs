@@ -0,0 +1,159 @@
+use super::ModeController;
+use crate::app::{App, Mode};
+use crate::ui::{ADDED, BRAND, DIM, HIGHLIGHT_BG, colorize_diff_line, render_key_bar};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+};
+
+/// Hunk picker for stashing part of the working tree
+pub struct HunkSelect;
+
+impl ModeController for HunkSelect {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key.code)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        draw(f, app);
+    }
+}
+
+fn handle(app: &mut App, key: KeyCode) -> Result<bool> {
+    match key {
+        KeyCode::Esc | KeyCode::Char('q') => {
+            app.mode = Mode::Normal;
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            app.hunk_cursor = app.hunk_cursor.saturating_sub(1);
+        }
+        KeyCode::Down | KeyCode::Char('j') if app.hunk_cursor + 1 < app.hunk_refs().len() => {
+            app.hunk_cursor += 1;
+        }
+        KeyCode::Char(' ') => app.toggle_hunk(),
+        KeyCode::Char('a') => app.toggle_hunk_file(),
+        KeyCode::Enter if !app.hunk_marked.is_empty() => {
+            app.hunk_stash = true;
+            app.mode = Mode::NewStash;
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(1),
+            Constraint::Length(3),
+        ])
+        .split(f.area());
+
+    let refs = app.hunk_refs();
+    let title = Line::from(vec![
+        Span::styled(
+            " Pick Hunks ",
+            Style::default()
+                .fg(Color::Black)
+                .bg(BRAND)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            format!(
+                "  {}/{} hunks selected across {} file(s)",
+                app.hunk_marked.len(),
+                refs.len(),
+                app.hunk_files.len()
+            ),
+            Style::default().fg(Color::Gray),
+        ),
+    ]);
+    f.render_widget(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(BRAND))
+            .title(title),
+        chunks[0],
+    );
+
+    let body = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+        .split(chunks[1]);
+
+    let items: Vec<ListItem> = refs
+        .iter()
+        .map(|&(fi, hi)| {
+            let file = &app.hunk_files[fi];
+            let marked = app.hunk_marked.contains(&(fi, hi));
+            let (mark, mark_style) = if marked {
+                (
+                    "[x] ",
+                    Style::default().fg(ADDED).add_modifier(Modifier::BOLD),
+                )
+            } else {
+                ("[ ] ", Style::default().fg(DIM))
+            };
+            let range = file.hunks[hi]
+                .header
+                .split("@@")
+                .nth(1)
+                .unwrap_or("")
+                .trim()
+                .to_string();
+            ListItem::new(Line::from(vec![
+                Span::styled(mark, mark_style),
+                Span::styled(file.path.clone(), Style::default().fg(Color::Cyan)),
+                Span::styled(format!("  {range}"), Style::default().fg(DIM)),
+            ]))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(app.hunk_cursor));
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Rgb(80, 80, 100)))
+                .title(" Hunks "),
+        )
+        .highlight_style(Style::default().bg(HIGHLIGHT_BG))
+        .highlight_symbol("▶ ");
+    f.render_stateful_widget(list, body[0], &mut state);
+
+    let lines: Vec<Line> = refs
+        .get(app.hunk_cursor)
+        .map(|&(fi, hi)| {
+            let hunk = &app.hunk_files[fi].hunks[hi];
+            std::iter::once(&hunk.header)
+                .chain(hunk.lines.iter())
+                .map(|l| colorize_diff_line(l))
+                .collect()
+        })
+        .unwrap_or_default();
+    let preview = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Rgb(80, 80, 100))),
+    );
+    f.render_widget(preview, body[1]);
+
+    render_key_bar(
+        f,
+        chunks[2],
+        &[
+            ("↑↓/jk", "hunk"),
+            ("Space", "toggle"),
+            ("a", "toggle file"),
+            ("Enter", "stash selected"),
+            ("Esc/q", "cancel"),
+        ],
+    );
+}
@@ -0,0 +1,99 @@
+use super::ModeController;
+use crate::app::{App, Mode};
+use crate::ui::{self, BRAND, DIM, centered_rect};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::Alignment,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+/// Prompt for a patch file to store as a stash
+pub struct ImportPatch;
+
+impl ModeController for ImportPatch {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        ui::render_main(f, app);
+        draw(f, app);
+    }
+}
+
+fn handle(app: &mut App, key: KeyEvent) -> Result<bool> {
+    let Mode::ImportPatch(input) = &mut app.mode else {
+        return Ok(false);
+    };
+    match key.code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+        }
+        KeyCode::Enter => {
+            let path = input.trim().to_string();
+            if !path.is_empty() {
+                match crate::app::import_patch(&path, None) {
+                    Ok(msg) => {
+                        app.reload()?;
+                        app.toast(format!("Stash '{msg}' imported."));
+                    }
+                    Err(e) => {
+                        app.mode = Mode::Message(format!("Error: {e:#}"));
+                    }
+                }
+            }
+        }
+        _ => {
+            input.handle_key(key);
+        }
+    }
+    Ok(false)
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let Mode::ImportPatch(input) = &app.mode else {
+        return;
+    };
+    let area = centered_rect(60, 25, f.area());
+    f.render_widget(Clear, area);
+
+    let content = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            "Patch file to import:",
+            Style::default().fg(Color::Gray),
+        )),
+        Line::from(
+            input.spans(
+                Style::default()
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        ),
+        Line::from(""),
+        Line::from(Span::styled(
+            "Stored as a new stash; the working tree is not touched",
+            Style::default().fg(DIM),
+        )),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("[Enter]", Style::default().fg(BRAND)),
+            Span::raw(" import   "),
+            Span::styled("[Esc]", Style::default().fg(Color::Red)),
+            Span::raw(" cancel"),
+        ]),
+    ];
+
+    let popup = Paragraph::new(content).alignment(Alignment::Center).block(
+        Block::default()
+            .title(" Import Patch ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(BRAND)),
+    );
+
+    f.render_widget(popup, area);
+}
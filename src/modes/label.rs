@@ -0,0 +1,117 @@
+use super::ModeController;
+use crate::actions::{Action, LastChange};
+use crate::app::{App, Mode};
+use crate::ui::{self, BRAND, DIM, centered_rect, label_chip};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::Alignment,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+/// Prompt for the labels on a stash, comma-separated
+pub struct Label;
+
+impl ModeController for Label {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        ui::render_main(f, app);
+        draw(f, app);
+    }
+
+    /// Start from the labels already set, if any
+    fn enter(&self, app: &mut App) {
+        let text = app
+            .selected_stash()
+            .and_then(|s| app.labels.get(&s.sha))
+            .map(|labels| labels.join(", "))
+            .unwrap_or_default();
+        if let Mode::Label(input) = &mut app.mode {
+            input.set(text);
+        }
+    }
+}
+
+fn handle(app: &mut App, key: KeyEvent) -> Result<bool> {
+    let Mode::Label(input) = &mut app.mode else {
+        return Ok(false);
+    };
+    match key.code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+        }
+        KeyCode::Enter => {
+            let text = input.to_string();
+            let result = app.set_labels(&text);
+            if result.is_ok() {
+                app.last_change = Some(LastChange::typed(Action::Label, &text));
+            }
+            app.report(result);
+        }
+        _ => {
+            input.handle_key(key);
+        }
+    }
+    Ok(false)
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let Mode::Label(input) = &app.mode else {
+        return;
+    };
+    let area = centered_rect(60, 45, f.area());
+    f.render_widget(Clear, area);
+
+    let known = app.known_labels();
+    let mut in_use = vec![Span::styled("In use: ", Style::default().fg(DIM))];
+    if known.is_empty() {
+        in_use.push(Span::styled("none yet", Style::default().fg(DIM)));
+    }
+    for label in known {
+        in_use.push(label_chip(label));
+        in_use.push(Span::raw(" "));
+    }
+
+    let content = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            "Labels for this stash:",
+            Style::default().fg(Color::Gray),
+        )),
+        Line::from(
+            input.spans(
+                Style::default()
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        ),
+        Line::from(""),
+        Line::from(in_use),
+        Line::from(Span::styled(
+            "Comma-separated, e.g. wip, review; empty clears",
+            Style::default().fg(DIM),
+        )),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("[Enter]", Style::default().fg(BRAND)),
+            Span::raw(" save   "),
+            Span::styled("[Esc]", Style::default().fg(Color::Red)),
+            Span::raw(" cancel"),
+        ]),
+    ];
+
+    let popup = Paragraph::new(content).alignment(Alignment::Center).block(
+        Block::default()
+            .title(" Labels ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(BRAND)),
+    );
+
+    f.render_widget(popup, area);
+}
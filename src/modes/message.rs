@@ -0,0 +1,91 @@
+use super::ModeController;
+use crate::app::{App, Mode};
+use crate::ui::{self, DIM, centered_rect, centered_rect_lines};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::Alignment,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+
+/// Result popup; any key dismisses it
+pub struct Message;
+
+impl ModeController for Message {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        if key.code == KeyCode::Char('d') && app.applied.is_some() {
+            app.review_applied();
+        } else {
+            app.applied = None;
+            app.mode = Mode::Normal;
+        }
+        Ok(false)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        ui::render_main(f, app);
+        if let Mode::Message(msg) = &app.mode {
+            draw(f, msg);
+        }
+    }
+}
+
+fn draw(f: &mut Frame, msg: &str) {
+    let (title, color) = if msg.starts_with("Error") {
+        (" Error ", Color::Red)
+    } else if msg.starts_with("Warning") {
+        (" Warning ", Color::Yellow)
+    } else if msg.starts_with("Reminder") {
+        (" Reminder ", Color::Magenta)
+    } else {
+        (" Done ", Color::Green)
+    };
+
+    // Multi-line messages (e.g. recovery guidance) read better left-aligned
+    let multi_line = msg.contains('\n');
+    let area = if multi_line {
+        let width = (f.area().width as usize * 80 / 100)
+            .saturating_sub(2)
+            .max(1);
+        let rows: usize = msg
+            .lines()
+            .map(|l| l.chars().count().div_ceil(width).max(1))
+            .sum();
+        centered_rect_lines(80, rows as u16 + 5, f.area())
+    } else {
+        centered_rect(55, 18, f.area())
+    };
+    f.render_widget(Clear, area);
+
+    let mut content = vec![Line::from("")];
+    content.extend(msg.lines().map(|l| {
+        Line::from(Span::styled(
+            l.to_string(),
+            Style::default().fg(Color::White),
+        ))
+    }));
+    content.push(Line::from(""));
+    content.push(Line::from(Span::styled(
+        "Press any key to continue",
+        Style::default().fg(DIM),
+    )));
+
+    let popup = Paragraph::new(content)
+        .alignment(if multi_line {
+            Alignment::Left
+        } else {
+            Alignment::Center
+        })
+        .wrap(Wrap { trim: false })
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(color)),
+        );
+
+    f.render_widget(popup, area);
+}
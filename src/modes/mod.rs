@@ -0,0 +1,99 @@
+use crate::app::{App, Mode};
+use anyhow::Result;
+use crossterm::event::KeyEvent;
+use ratatui::Frame;
+use std::mem::discriminant;
+
+mod annotate;
+mod archive;
+mod cleanup;
+mod clear_all;
+mod confirm;
+mod conflict;
+mod email;
+mod export;
+mod grep;
+mod help;
+mod history;
+mod hunk_select;
+mod import;
+mod label;
+mod message;
+mod new_stash;
+mod normal;
+pub mod palette;
+mod pick_target;
+mod progress;
+mod recover;
+mod remind;
+mod remote_stashes;
+mod rename;
+mod repos;
+mod squash;
+mod status;
+mod submodules;
+mod to_branch;
+mod tree;
+mod view;
+mod worktrees;
+
+/// Key handling and drawing for one `Mode`. A new mode gets its own file in
+/// this directory implementing this trait, plus a line in `controller`.
+pub trait ModeController {
+    /// Handle one key press. Returns true if the app should quit.
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool>;
+
+    fn render(&self, f: &mut Frame, app: &App);
+
+    /// Called when the app switches into this mode from a different one
+    fn enter(&self, _app: &mut App) {}
+
+    /// Called when the app switches from this mode to a different one
+    fn exit(&self, _app: &mut App) {}
+}
+
+/// The controller in charge of `mode`
+pub fn controller(mode: &Mode) -> &'static dyn ModeController {
+    match mode {
+        Mode::Normal => &normal::Normal,
+        Mode::Diff => &view::View { files: false },
+        Mode::Files => &view::View { files: true },
+        Mode::Confirm(_) => &confirm::Confirm,
+        Mode::NewStash => &new_stash::NewStash,
+        Mode::ImportPatch(_) => &import::ImportPatch,
+        Mode::Rename => &rename::Rename,
+        Mode::Remind(_) => &remind::Remind,
+        Mode::Annotate(_) => &annotate::Annotate,
+        Mode::Label(_) => &label::Label,
+        Mode::Grep => &grep::Grep,
+        Mode::ToBranch => &to_branch::ToBranch,
+        Mode::Squash => &squash::Squash,
+        Mode::Export => &export::Export,
+        Mode::Email => &email::Email,
+        Mode::RemoteStashes => &remote_stashes::RemoteStashes,
+        Mode::Archive => &archive::Archive,
+        Mode::Recover => &recover::Recover,
+        Mode::ClearAll => &clear_all::ClearAll,
+        Mode::PickTarget => &pick_target::PickTarget,
+        Mode::Repos => &repos::Repos,
+        Mode::Worktrees => &worktrees::Worktrees,
+        Mode::Submodules => &submodules::Submodules,
+        Mode::History => &history::History,
+        Mode::Help => &help::Help,
+        Mode::Conflict => &conflict::Conflict,
+        Mode::HunkSelect => &hunk_select::HunkSelect,
+        Mode::Cleanup => &cleanup::Cleanup,
+        Mode::Tree => &tree::Tree,
+        Mode::Status => &status::Status,
+        Mode::Progress => &progress::Progress,
+        Mode::Message(_) => &message::Message,
+    }
+}
+
+/// Run the exit/enter hooks if the app left the kind of mode it was in
+pub fn transition(app: &mut App, from: &Mode) {
+    if discriminant(from) != discriminant(&app.mode) {
+        controller(from).exit(app);
+        controller(&app.mode).enter(app);
+    }
+}
@@ -0,0 +1,143 @@
+use super::ModeController;
+use crate::app::{App, Mode, StashScope};
+use crate::ui::{self, BRAND, DIM, centered_rect_lines};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::Alignment,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+/// Prompt for the message and scope of a new stash
+pub struct NewStash;
+
+impl ModeController for NewStash {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        ui::render_main(f, app);
+        draw(f, app);
+    }
+
+    fn enter(&self, app: &mut App) {
+        app.new_stash_input.clear();
+        app.new_stash_scope = StashScope::Tracked;
+    }
+}
+
+fn handle(app: &mut App, key: KeyEvent) -> Result<bool> {
+    match key.code {
+        KeyCode::Esc if app.hunk_stash => {
+            app.mode = Mode::HunkSelect;
+        }
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+        }
+        KeyCode::Enter if !app.new_stash_input.trim().is_empty() => {
+            let msg = app.stash_message(&app.new_stash_input);
+            match app.create_stash(&msg) {
+                Ok(true) => {
+                    app.reload()?;
+                    app.toast(format!("Stash '{}' created.", msg));
+                }
+                Ok(false) => app.mode = Mode::Message("No local changes to save.".to_string()),
+                Err(e) => {
+                    app.mode = Mode::Message(format!("Error: {e}"));
+                }
+            }
+        }
+        KeyCode::Enter => {}
+        KeyCode::Char('u') if app.new_stash_input.is_empty() => {
+            // toggle untracked when input is empty via Ctrl-u-like shortcut
+            app.new_stash_scope = if app.new_stash_scope == StashScope::Untracked {
+                StashScope::Tracked
+            } else {
+                StashScope::Untracked
+            };
+        }
+        KeyCode::Tab if !app.hunk_stash => {
+            app.new_stash_scope = app.new_stash_scope.next();
+        }
+        KeyCode::BackTab if !app.hunk_stash => {
+            app.new_stash_scope = app.new_stash_scope.prev();
+        }
+        _ => {
+            app.new_stash_input.handle_key(key);
+        }
+    }
+    Ok(false)
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    // Seven lines of content plus borders, so the options never get clipped;
+    // one more for the templated message
+    let templated = app.config.stash.template.is_some();
+    let area = centered_rect_lines(60, if templated { 10 } else { 9 }, f.area());
+    f.render_widget(Clear, area);
+
+    let untracked_label = if app.hunk_stash {
+        Span::styled(
+            format!("{} hunk(s) selected", app.hunk_marked.len()),
+            Style::default().fg(Color::Green),
+        )
+    } else {
+        let color = if app.new_stash_scope == StashScope::Tracked {
+            DIM
+        } else {
+            Color::Green
+        };
+        Span::styled(
+            format!("[Tab] Stash: {}", app.new_stash_scope.label()),
+            Style::default().fg(color),
+        )
+    };
+
+    let mut content = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            "Stash message:",
+            Style::default().fg(Color::Gray),
+        )),
+        Line::from(
+            app.new_stash_input.spans(
+                Style::default()
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        ),
+    ];
+    if templated {
+        content.push(Line::from(vec![
+            Span::styled("→ ", Style::default().fg(DIM)),
+            Span::styled(
+                app.stash_message(&app.new_stash_input),
+                Style::default().fg(BRAND),
+            ),
+        ]));
+    }
+    content.extend([
+        Line::from(""),
+        Line::from(untracked_label),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("[Enter]", Style::default().fg(BRAND)),
+            Span::raw(" save   "),
+            Span::styled("[Esc]", Style::default().fg(Color::Red)),
+            Span::raw(" cancel"),
+        ]),
+    ]);
+
+    let popup = Paragraph::new(content).alignment(Alignment::Center).block(
+        Block::default()
+            .title(" New Stash ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(BRAND)),
+    );
+
+    f.render_widget(popup, area);
+}
@@ -0,0 +1,55 @@
+use super::ModeController;
+use crate::actions;
+use crate::app::App;
+use crate::ui;
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::Frame;
+
+/// The stash list: navigation, search and every action on stashes
+pub struct Normal;
+
+impl ModeController for Normal {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        ui::render_main(f, app);
+    }
+}
+
+fn handle(app: &mut App, event: KeyEvent) -> Result<bool> {
+    let key = event.code;
+    // If searching, intercept keys
+    if app.searching {
+        match key {
+            KeyCode::Esc => {
+                app.searching = false;
+                app.search_query.clear();
+                app.selected = 0;
+            }
+            KeyCode::Enter => {
+                app.searching = false;
+            }
+            _ => {
+                if app.search_query.handle_key(event) {
+                    app.selected = 0;
+                    app.ensure_stash_paths();
+                }
+            }
+        }
+        return Ok(false);
+    }
+
+    // Fold / unfold a branch group
+    if matches!(key, KeyCode::Enter | KeyCode::Char(' ')) && app.selected_group().is_some() {
+        app.toggle_collapsed();
+        return Ok(false);
+    }
+
+    match actions::for_key(app, event) {
+        Some(action) => actions::run(app, action),
+        None => Ok(false),
+    }
+}
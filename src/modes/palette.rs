@@ -0,0 +1,141 @@
+use crate::actions::{self, Action, BINDINGS, Binding};
+use crate::app::App;
+use crate::ui::{BRAND, DIM, HIGHLIGHT_BG, centered_rect, fit};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+};
+use unicode_width::UnicodeWidthStr;
+
+// The palette floats over whichever view opened it rather than being a mode of
+// its own, so the diff view stays loaded underneath and the actions offered
+// are the ones that view's keys would run.
+
+/// Open the palette over the current view
+pub fn open(app: &mut App) {
+    app.palette = true;
+    app.palette_input.clear();
+    app.palette_selected = 0;
+}
+
+/// Actions the current view offers, best match for the typed text first
+pub fn entries(app: &App) -> Vec<&'static Binding> {
+    let query = app.palette_input.trim();
+    let mut scored: Vec<(i64, &Binding)> = BINDINGS
+        .iter()
+        .filter(|b| b.scope.active(app) && b.action != Action::Palette)
+        .filter_map(|b| {
+            if query.is_empty() {
+                return Some((0, b));
+            }
+            let text = format!("{} {}", b.description, b.name);
+            app.fuzzy_score(&text, query).map(|score| (score, b))
+        })
+        .collect();
+    // Stable, so ties keep registry order
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    scored.into_iter().map(|(_, b)| b).collect()
+}
+
+/// Returns true if the app should quit
+pub fn handle_key(app: &mut App, key: KeyEvent) -> Result<bool> {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    let last = entries(app).len().saturating_sub(1);
+    match key.code {
+        KeyCode::Esc => app.palette = false,
+        KeyCode::Up => app.palette_selected = app.palette_selected.saturating_sub(1),
+        KeyCode::Char('p') if ctrl => {
+            app.palette_selected = app.palette_selected.saturating_sub(1);
+        }
+        KeyCode::Down => app.palette_selected = (app.palette_selected + 1).min(last),
+        KeyCode::Char('n') if ctrl => {
+            app.palette_selected = (app.palette_selected + 1).min(last);
+        }
+        KeyCode::Enter => {
+            let chosen = entries(app).get(app.palette_selected).map(|b| b.action);
+            app.palette = false;
+            if let Some(action) = chosen {
+                return actions::run(app, action);
+            }
+        }
+        _ => {
+            if app.palette_input.handle_key(key) {
+                app.palette_selected = 0;
+            }
+        }
+    }
+    Ok(false)
+}
+
+pub fn draw(f: &mut Frame, app: &App) {
+    let area = centered_rect(60, 60, f.area());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Commands ")
+        .title_bottom(Line::from(vec![
+            Span::styled(" [Enter]", Style::default().fg(BRAND)),
+            Span::raw(" run  "),
+            Span::styled("[Esc]", Style::default().fg(Color::Red)),
+            Span::raw(" cancel "),
+        ]))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(BRAND));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Min(1)])
+        .split(inner);
+
+    let mut prompt = vec![Span::styled(": ", Style::default().fg(BRAND))];
+    prompt.extend(
+        app.palette_input.spans(
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        ),
+    );
+    f.render_widget(Paragraph::new(Line::from(prompt)), chunks[0]);
+
+    let entries = entries(app);
+    if entries.is_empty() {
+        f.render_widget(
+            Paragraph::new(Span::styled(
+                "No matching command",
+                Style::default().fg(DIM),
+            )),
+            chunks[1],
+        );
+        return;
+    }
+    let width = chunks[1].width as usize;
+    let items: Vec<ListItem> = entries
+        .iter()
+        .map(|b| {
+            // The description gives way to keep the keys in view
+            let room = width.saturating_sub(b.label.width() + 3);
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!(" {} ", fit(b.description, room)),
+                    Style::default().fg(Color::Gray),
+                ),
+                Span::styled(b.label, Style::default().fg(DIM)),
+            ]))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(app.palette_selected.min(entries.len() - 1)));
+    let list = List::new(items).highlight_style(
+        Style::default()
+            .bg(HIGHLIGHT_BG)
+            .add_modifier(Modifier::BOLD),
+    );
+    f.render_stateful_widget(list, chunks[1], &mut state);
+}
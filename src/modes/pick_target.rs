@@ -0,0 +1,96 @@
+use super::ModeController;
+use crate::app::{App, ApplyTarget, ConfirmAction, Mode};
+use crate::ui::{self, BRAND, HIGHLIGHT_BG, centered_rect};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+};
+
+/// Picker for the branch or worktree to apply a stash onto
+pub struct PickTarget;
+
+impl ModeController for PickTarget {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key.code)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        ui::render_main(f, app);
+        draw(f, app);
+    }
+}
+
+fn handle(app: &mut App, key: KeyCode) -> Result<bool> {
+    match key {
+        KeyCode::Esc | KeyCode::Char('q') => {
+            app.mode = Mode::Normal;
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            app.target_selected = app.target_selected.saturating_sub(1);
+        }
+        KeyCode::Down | KeyCode::Char('j') if app.target_selected + 1 < app.targets.len() => {
+            app.target_selected += 1;
+        }
+        // Switching the branch under the user's feet gets a prompt of its own
+        KeyCode::Enter
+            if matches!(
+                app.targets.get(app.target_selected),
+                Some(ApplyTarget::Branch(_))
+            ) =>
+        {
+            app.mode = Mode::Confirm(ConfirmAction::ApplyToBranch);
+        }
+        KeyCode::Enter => match app.apply_to_target() {
+            Ok(msg) => {
+                app.reload()?;
+                app.toast(msg);
+            }
+            Err(e) => {
+                app.mode = Mode::Message(format!("Error: {e:#}"));
+            }
+        },
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let area = centered_rect(60, 50, f.area());
+    f.render_widget(Clear, area);
+
+    let items: Vec<ListItem> = app
+        .targets
+        .iter()
+        .map(|t| {
+            ListItem::new(Line::from(Span::styled(
+                t.label(),
+                Style::default().fg(Color::Gray),
+            )))
+        })
+        .collect();
+
+    let mut state = ListState::default();
+    state.select(Some(app.target_selected));
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(" Apply to… ")
+                .title_bottom(Line::from(vec![
+                    Span::styled(" [Enter]", Style::default().fg(BRAND)),
+                    Span::raw(" apply  "),
+                    Span::styled("[Esc]", Style::default().fg(Color::Red)),
+                    Span::raw(" cancel "),
+                ]))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Green)),
+        )
+        .highlight_style(Style::default().bg(HIGHLIGHT_BG).fg(Color::White))
+        .highlight_symbol("▶ ");
+
+    f.render_stateful_widget(list, area, &mut state);
+}
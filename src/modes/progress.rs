@@ -0,0 +1,74 @@
+use super::ModeController;
+use crate::app::App;
+use crate::ui::{self, BRAND, DIM, SPINNER, centered_rect_lines};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+/// Lines of git output kept on screen
+const TAIL: usize = 8;
+
+/// A slow operation running on a worker thread, with its latest output.
+/// The job switches to the result message when it finishes.
+pub struct Progress;
+
+impl ModeController for Progress {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        if key.code == KeyCode::Esc {
+            app.cancel_progress();
+        }
+        Ok(false)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        ui::render_main(f, app);
+        draw(f, app);
+    }
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let Some((title, progress)) = &app.progress else {
+        return;
+    };
+    let area = centered_rect_lines(70, TAIL as u16 + 5, f.area());
+    f.render_widget(Clear, area);
+
+    let cancelled = progress.is_cancelled();
+    let status = if cancelled {
+        Span::styled(" cancelling…", Style::default().fg(Color::Yellow))
+    } else {
+        Span::styled(
+            format!(" {} {title}…", SPINNER[app.spinner_tick % SPINNER.len()]),
+            Style::default().fg(BRAND).add_modifier(Modifier::BOLD),
+        )
+    };
+    let mut content = vec![Line::from(status), Line::from("")];
+    let tail = progress.tail(TAIL);
+    if tail.is_empty() {
+        content.push(Line::from(Span::styled(
+            " waiting for output",
+            Style::default().fg(DIM),
+        )));
+    }
+    content.extend(tail.into_iter().map(|l| {
+        Line::from(Span::styled(
+            format!(" {l}"),
+            Style::default().fg(Color::Gray),
+        ))
+    }));
+
+    let block = Block::default()
+        .title(" Working ")
+        .title_bottom(Line::from(vec![
+            Span::styled(" [Esc/Ctrl-C]", Style::default().fg(Color::Red)),
+            Span::raw(" cancel "),
+        ]))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(BRAND));
+    f.render_widget(Paragraph::new(content).block(block), area);
+}
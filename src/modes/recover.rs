@@ -0,0 +1,132 @@
+use super::ModeController;
+use crate::app::{App, Mode};
+use crate::ui::{self, BRAND, DIM, HIGHLIGHT_BG, centered_rect};
+use anyhow::Result;
+use chrono::{Local, TimeZone};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+};
+
+/// Stash commits that nothing refers to any more, found with `git fsck`;
+/// Enter stores the selected one as a stash again
+pub struct Recover;
+
+impl ModeController for Recover {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key.code)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        ui::render_main(f, app);
+        draw(f, app);
+    }
+
+    fn enter(&self, app: &mut App) {
+        app.scan_lost_stashes();
+    }
+}
+
+fn handle(app: &mut App, key: KeyCode) -> Result<bool> {
+    let count = app.lost_stashes.as_ref().map_or(0, Vec::len);
+    match key {
+        KeyCode::Esc | KeyCode::Char('q') => {
+            app.mode = Mode::Normal;
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            app.lost_selected = app.lost_selected.saturating_sub(1);
+        }
+        KeyCode::Down | KeyCode::Char('j') if app.lost_selected + 1 < count => {
+            app.lost_selected += 1;
+        }
+        KeyCode::Char('r') => app.scan_lost_stashes(),
+        KeyCode::Enter if app.lost_selected < count => {
+            let result = app.recover_lost_stash();
+            app.report(result);
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let area = centered_rect(75, 60, f.area());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Recover Lost Stashes ")
+        .title_bottom(Line::from(vec![
+            Span::styled(" [Enter]", Style::default().fg(BRAND)),
+            Span::raw(" restore  "),
+            Span::styled("[r]", Style::default().fg(BRAND)),
+            Span::raw(" rescan  "),
+            Span::styled("[Esc]", Style::default().fg(Color::Red)),
+            Span::raw(" close "),
+        ]))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(BRAND));
+
+    let notice = match &app.lost_stashes {
+        None => ui::spinner(app),
+        Some(list) if list.is_empty() => Some("No lost stashes found.".to_string()),
+        Some(_) => None,
+    };
+    if let Some(notice) = notice {
+        let text = Paragraph::new(Span::styled(notice, Style::default().fg(DIM))).block(block);
+        f.render_widget(text, area);
+        return;
+    }
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Min(1)])
+        .split(inner);
+    // Unreachable commits only last until git gc prunes them
+    f.render_widget(
+        Paragraph::new(Span::styled(
+            format!(
+                "git gc prunes these after gc.pruneExpire ({}); restore what you need soon",
+                app.prune_expire
+            ),
+            Style::default().fg(Color::Yellow),
+        ))
+        .wrap(Wrap { trim: true }),
+        chunks[0],
+    );
+
+    let items: Vec<ListItem> = app
+        .lost_stashes
+        .iter()
+        .flatten()
+        .map(|lost| {
+            let when = Local
+                .timestamp_opt(lost.timestamp, 0)
+                .single()
+                .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_default();
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{when}  "), Style::default().fg(DIM)),
+                Span::styled(
+                    format!("{}  ", lost.sha.chars().take(7).collect::<String>()),
+                    Style::default().fg(BRAND),
+                ),
+                Span::styled(lost.subject.clone(), Style::default().fg(Color::White)),
+            ]))
+        })
+        .collect();
+
+    let mut state = ListState::default();
+    state.select(Some(app.lost_selected));
+
+    let list = List::new(items)
+        .highlight_style(Style::default().bg(HIGHLIGHT_BG).fg(Color::White))
+        .highlight_symbol("▶ ");
+
+    f.render_stateful_widget(list, chunks[1], &mut state);
+}
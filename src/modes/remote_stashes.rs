@@ -0,0 +1,123 @@
+use super::ModeController;
+use crate::app::{App, Mode};
+use crate::ui::{self, ADDED, BRAND, DIM, HIGHLIGHT_BG, centered_rect, fit};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+};
+
+/// Stashes published under `refs/stashes/` on the remote; Enter fetches the
+/// selected one into the local stash list
+pub struct RemoteStashes;
+
+impl ModeController for RemoteStashes {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key.code)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        ui::render_main(f, app);
+        draw(f, app);
+    }
+
+    fn enter(&self, app: &mut App) {
+        app.load_remote_stashes();
+    }
+}
+
+fn handle(app: &mut App, key: KeyCode) -> Result<bool> {
+    let count = app.remote_stashes.as_ref().map_or(0, Vec::len);
+    match key {
+        KeyCode::Esc | KeyCode::Char('q') => {
+            app.mode = Mode::Normal;
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            app.remote_stash_selected = app.remote_stash_selected.saturating_sub(1);
+        }
+        KeyCode::Down | KeyCode::Char('j') if app.remote_stash_selected + 1 < count => {
+            app.remote_stash_selected += 1;
+        }
+        KeyCode::Char('r') => app.load_remote_stashes(),
+        KeyCode::Enter if app.remote_stash_selected < count => {
+            let sha = app
+                .remote_stashes
+                .as_ref()
+                .map(|list| list[app.remote_stash_selected].sha.clone())
+                .unwrap_or_default();
+            if app.has_stash(&sha) {
+                app.mode = Mode::Message("Already in the stash list.".to_string());
+            } else {
+                app.import_remote_stash();
+            }
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let area = centered_rect(70, 50, f.area());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(format!(" Stashes on {} ", app.config.stash.remote))
+        .title_bottom(Line::from(vec![
+            Span::styled(" [Enter]", Style::default().fg(BRAND)),
+            Span::raw(" import  "),
+            Span::styled("[r]", Style::default().fg(BRAND)),
+            Span::raw(" refresh  "),
+            Span::styled("[Esc]", Style::default().fg(Color::Red)),
+            Span::raw(" close "),
+        ]))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(BRAND));
+
+    let notice = match &app.remote_stashes {
+        None => ui::spinner(app),
+        Some(list) if list.is_empty() => Some("Nothing published yet; U publishes one.".into()),
+        Some(_) => None,
+    };
+    if let Some(notice) = notice {
+        let text = Paragraph::new(Span::styled(notice, Style::default().fg(DIM))).block(block);
+        f.render_widget(text, area);
+        return;
+    }
+
+    let items: Vec<ListItem> = app
+        .remote_stashes
+        .iter()
+        .flatten()
+        .map(|stash| {
+            let (status, color) = if app.has_stash(&stash.sha) {
+                ("have", ADDED)
+            } else {
+                ("new", Color::Yellow)
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{} ", fit(&stash.name, 40)),
+                    Style::default().fg(Color::White),
+                ),
+                Span::styled(format!("{status:<5} "), Style::default().fg(color)),
+                Span::styled(
+                    stash.sha.chars().take(7).collect::<String>(),
+                    Style::default().fg(DIM),
+                ),
+            ]))
+        })
+        .collect();
+
+    let mut state = ListState::default();
+    state.select(Some(app.remote_stash_selected));
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().bg(HIGHLIGHT_BG).fg(Color::White))
+        .highlight_symbol("▶ ");
+
+    f.render_stateful_widget(list, area, &mut state);
+}
@@ -0,0 +1,94 @@
+use super::ModeController;
+use crate::app::{App, ConfirmAction, Mode};
+use crate::ui::{self, BRAND, centered_rect};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::Alignment,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+/// Prompt for a stash's new message
+pub struct Rename;
+
+impl ModeController for Rename {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        ui::render_main(f, app);
+        draw(f, app);
+    }
+
+    /// Start from the current message
+    fn enter(&self, app: &mut App) {
+        let text = app
+            .selected_stash()
+            .map(|s| s.short_msg.clone())
+            .unwrap_or_default();
+        app.rename_input.set(text);
+    }
+}
+
+fn handle(app: &mut App, key: KeyEvent) -> Result<bool> {
+    match key.code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+        }
+        KeyCode::Enter => {
+            let unchanged = app
+                .selected_stash()
+                .is_some_and(|s| s.short_msg == app.rename_input.trim());
+            if unchanged {
+                app.mode = Mode::Normal;
+            } else if !app.rename_input.trim().is_empty() {
+                app.mode = Mode::Confirm(ConfirmAction::Rename);
+            }
+        }
+        _ => {
+            app.rename_input.handle_key(key);
+        }
+    }
+    Ok(false)
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let area = centered_rect(60, 25, f.area());
+    f.render_widget(Clear, area);
+
+    let content = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            "New stash message:",
+            Style::default().fg(Color::Gray),
+        )),
+        Line::from(
+            app.rename_input.spans(
+                Style::default()
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        ),
+        Line::from(""),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("[Enter]", Style::default().fg(BRAND)),
+            Span::raw(" rename   "),
+            Span::styled("[Esc]", Style::default().fg(Color::Red)),
+            Span::raw(" cancel"),
+        ]),
+    ];
+
+    let popup = Paragraph::new(content).alignment(Alignment::Center).block(
+        Block::default()
+            .title(" Rename Stash ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(BRAND)),
+    );
+
+    f.render_widget(popup, area);
+}
@@ -0,0 +1,111 @@
+use super::ModeController;
+use crate::app::{App, Mode};
+use crate::ui::{self, BRAND, DIM, HIGHLIGHT_BG, centered_rect, fit};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+};
+
+/// Picker for another repository to open: recent ones first, then siblings
+pub struct Repos;
+
+impl ModeController for Repos {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key.code)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        ui::render_main(f, app);
+        draw(f, app);
+    }
+
+    fn enter(&self, app: &mut App) {
+        if let Err(e) = app.load_repos() {
+            app.mode = Mode::Message(format!("Error: {e}"));
+        }
+    }
+}
+
+fn handle(app: &mut App, key: KeyCode) -> Result<bool> {
+    match key {
+        KeyCode::Esc | KeyCode::Char('q') => {
+            app.mode = Mode::Normal;
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            app.repo_selected = app.repo_selected.saturating_sub(1);
+        }
+        KeyCode::Down | KeyCode::Char('j') if app.repo_selected + 1 < app.repos.len() => {
+            app.repo_selected += 1;
+        }
+        KeyCode::Enter if app.repo_selected < app.repos.len() => {
+            let path = app.repos[app.repo_selected].path.clone();
+            let result = app.switch_repo(&path);
+            app.report(result);
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let area = centered_rect(60, 50, f.area());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Switch repository ")
+        .title_bottom(Line::from(vec![
+            Span::styled(" [Enter]", Style::default().fg(BRAND)),
+            Span::raw(" open  "),
+            Span::styled("[Esc]", Style::default().fg(Color::Red)),
+            Span::raw(" cancel "),
+        ]))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(BRAND));
+
+    if app.repos.is_empty() {
+        let hint = Paragraph::new(vec![
+            Line::from(""),
+            Line::from(Span::styled(
+                " No other repositories found nearby or opened before.",
+                Style::default().fg(DIM),
+            )),
+            Line::from(Span::styled(
+                " Start gsm with --repo <path> to open one.",
+                Style::default().fg(DIM),
+            )),
+        ])
+        .block(block);
+        f.render_widget(hint, area);
+        return;
+    }
+
+    let items: Vec<ListItem> = app
+        .repos
+        .iter()
+        .map(|repo| {
+            let tag = if repo.recent { "recent " } else { "nearby " };
+            ListItem::new(Line::from(vec![
+                Span::styled(tag, Style::default().fg(DIM)),
+                Span::styled(
+                    format!("{} ", fit(&repo.name(), 20)),
+                    Style::default().fg(Color::White),
+                ),
+                Span::styled(repo.path.display().to_string(), Style::default().fg(DIM)),
+            ]))
+        })
+        .collect();
+
+    let mut state = ListState::default();
+    state.select(Some(app.repo_selected));
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().bg(HIGHLIGHT_BG).fg(Color::White))
+        .highlight_symbol("▶ ");
+
+    f.render_stateful_widget(list, area, &mut state);
+}
@@ -0,0 +1,105 @@
+use super::ModeController;
+use crate::app::{App, Mode};
+use crate::ui::{self, BRAND, DIM, centered_rect_lines};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::Alignment,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+/// Prompt for the message of the stash the marked stashes are squashed into
+pub struct Squash;
+
+impl ModeController for Squash {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        ui::render_main(f, app);
+        draw(f, app);
+    }
+
+    /// Suggest the newest stash's message
+    fn enter(&self, app: &mut App) {
+        let text = app
+            .squash_order()
+            .last()
+            .map(|s| s.short_msg.clone())
+            .unwrap_or_default();
+        app.squash_input.set(text);
+    }
+}
+
+fn handle(app: &mut App, key: KeyEvent) -> Result<bool> {
+    match key.code {
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+        }
+        KeyCode::Enter => {
+            let result = app.squash_marked();
+            app.report(result);
+        }
+        _ => {
+            app.squash_input.handle_key(key);
+        }
+    }
+    Ok(false)
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let stashes = app.squash_order();
+    let area = centered_rect_lines(64, stashes.len() as u16 + 10, f.area());
+    f.render_widget(Clear, area);
+
+    let mut content = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            "Applied in this order, then replaced by one stash:",
+            Style::default().fg(Color::Gray),
+        )),
+    ];
+    for stash in &stashes {
+        let pin = if app.is_pinned(stash) {
+            "  📌 kept"
+        } else {
+            ""
+        };
+        content.push(Line::from(vec![
+            Span::styled(format!("{:<11}", stash.name), Style::default().fg(BRAND)),
+            Span::styled(stash.short_msg.clone(), Style::default().fg(Color::Gray)),
+            Span::styled(pin, Style::default().fg(DIM)),
+        ]));
+    }
+    content.extend([
+        Line::from(""),
+        Line::from(Span::styled("Message:", Style::default().fg(Color::Gray))),
+        Line::from(
+            app.squash_input.spans(
+                Style::default()
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        ),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("[Enter]", Style::default().fg(BRAND)),
+            Span::raw(" squash   "),
+            Span::styled("[Esc]", Style::default().fg(Color::Red)),
+            Span::raw(" cancel"),
+        ]),
+    ]);
+
+    let popup = Paragraph::new(content).alignment(Alignment::Center).block(
+        Block::default()
+            .title(" Squash Stashes ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(BRAND)),
+    );
+
+    f.render_widget(popup, area);
+}
@@ -0,0 +1,195 @@
+use super::ModeController;
+use crate::app::{App, Mode};
+use crate::git::StatusEntry;
+use crate::ui::{ADDED, BRAND, DIM, REMOVED, render_key_bar};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+};
+
+/// What `n` would stash: the working tree's staged, unstaged and untracked files
+pub struct Status;
+
+impl ModeController for Status {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key.code)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        draw(f, app);
+    }
+
+    fn enter(&self, app: &mut App) {
+        app.worktree_scroll = 0;
+        if let Err(e) = app.load_worktree_status() {
+            app.mode = Mode::Message(format!("Error: {e}"));
+        }
+    }
+}
+
+fn handle(app: &mut App, key: KeyCode) -> Result<bool> {
+    match key {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('w') => app.mode = Mode::Normal,
+        KeyCode::Up | KeyCode::Char('k') => {
+            app.worktree_scroll = app.worktree_scroll.saturating_sub(1);
+        }
+        KeyCode::Down | KeyCode::Char('j') => {
+            app.worktree_scroll = (app.worktree_scroll + 1).min(lines(app).len().saturating_sub(1));
+        }
+        KeyCode::Char('r') => {
+            if let Err(e) = app.load_worktree_status() {
+                app.mode = Mode::Message(format!("Error: {e}"));
+            }
+        }
+        KeyCode::Char('n') => {
+            app.hunk_stash = false;
+            app.mode = Mode::NewStash;
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn status_color(code: char) -> Color {
+    match code {
+        'A' | '?' => ADDED,
+        'D' => REMOVED,
+        'U' => Color::Magenta,
+        _ => Color::Yellow,
+    }
+}
+
+/// One heading per non-empty section, followed by its files
+fn lines(app: &App) -> Vec<Line<'static>> {
+    let status = &app.worktree_status;
+    let sections: [(&str, Vec<(char, &str)>); 3] = [
+        (
+            "Staged",
+            status
+                .iter()
+                .filter(|e| e.is_staged())
+                .map(|e| (e.staged, e.path.as_str()))
+                .collect(),
+        ),
+        (
+            "Unstaged",
+            status
+                .iter()
+                .filter(|e| e.is_unstaged())
+                .map(|e| (e.unstaged, e.path.as_str()))
+                .collect(),
+        ),
+        (
+            "Untracked",
+            status
+                .iter()
+                .filter(|e| e.is_untracked())
+                .map(|e| ('?', e.path.as_str()))
+                .collect(),
+        ),
+    ];
+
+    let mut lines = Vec::new();
+    for (heading, entries) in sections {
+        if entries.is_empty() {
+            continue;
+        }
+        if !lines.is_empty() {
+            lines.push(Line::from(""));
+        }
+        lines.push(Line::from(Span::styled(
+            format!("{heading} ({})", entries.len()),
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        )));
+        for (code, path) in entries {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("  {code}  "),
+                    Style::default().fg(status_color(code)),
+                ),
+                Span::styled(path.to_string(), Style::default().fg(Color::Gray)),
+            ]));
+        }
+    }
+    if lines.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "Working tree clean: nothing to stash.",
+            Style::default().fg(DIM),
+        )));
+    }
+    lines
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(1),
+            Constraint::Length(3),
+        ])
+        .split(f.area());
+
+    let status = &app.worktree_status;
+    let count = |pred: fn(&StatusEntry) -> bool| status.iter().filter(|e| pred(e)).count();
+    let title = Line::from(vec![
+        Span::styled(
+            " Working tree ",
+            Style::default()
+                .fg(Color::Black)
+                .bg(BRAND)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            format!("  on {}", app.current_branch),
+            Style::default().fg(Color::Gray),
+        ),
+    ]);
+    f.render_widget(
+        Paragraph::new(Span::styled(
+            format!(
+                "{} staged · {} unstaged · {} untracked",
+                count(StatusEntry::is_staged),
+                count(StatusEntry::is_unstaged),
+                count(StatusEntry::is_untracked),
+            ),
+            Style::default().fg(DIM),
+        ))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(BRAND))
+                .title(title),
+        ),
+        chunks[0],
+    );
+
+    f.render_widget(
+        Paragraph::new(lines(app))
+            .scroll((app.worktree_scroll as u16, 0))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Rgb(80, 80, 100))),
+            ),
+        chunks[1],
+    );
+
+    render_key_bar(
+        f,
+        chunks[2],
+        &[
+            ("↑↓/jk", "scroll"),
+            ("n", "new stash"),
+            ("r", "refresh"),
+            ("Esc/q/w", "back"),
+        ],
+    );
+}
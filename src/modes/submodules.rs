@@ -0,0 +1,130 @@
+use super::ModeController;
+use crate::app::{App, Mode};
+use crate::ui::{self, ADDED, BRAND, DIM, HIGHLIGHT_BG, centered_rect, fit};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+};
+
+/// Picker for a submodule to manage stashes in. Enter reopens gsm inside
+/// it, where the usual keys create, view and apply its stashes; from inside
+/// a submodule the first row leads back to the superproject.
+pub struct Submodules;
+
+impl ModeController for Submodules {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key.code)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        ui::render_main(f, app);
+        draw(f, app);
+    }
+
+    fn enter(&self, app: &mut App) {
+        if let Err(e) = app.load_submodules() {
+            app.mode = Mode::Message(format!("Error: {e}"));
+        }
+    }
+}
+
+fn handle(app: &mut App, key: KeyCode) -> Result<bool> {
+    match key {
+        KeyCode::Esc | KeyCode::Char('q') => {
+            app.mode = Mode::Normal;
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            app.submodule_selected = app.submodule_selected.saturating_sub(1);
+        }
+        KeyCode::Down | KeyCode::Char('j') if app.submodule_selected + 1 < app.submodule_rows() => {
+            app.submodule_selected += 1;
+        }
+        KeyCode::Char('r') => {
+            if let Err(e) = app.load_submodules() {
+                app.mode = Mode::Message(format!("Error: {e}"));
+            }
+        }
+        KeyCode::Enter if app.submodule_rows() > 0 => {
+            let result = app.open_submodule();
+            app.report(result);
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let area = centered_rect(70, 50, f.area());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Submodules ")
+        .title_bottom(Line::from(vec![
+            Span::styled(" [Enter]", Style::default().fg(BRAND)),
+            Span::raw(" open  "),
+            Span::styled("[r]", Style::default().fg(BRAND)),
+            Span::raw(" refresh  "),
+            Span::styled("[Esc]", Style::default().fg(Color::Red)),
+            Span::raw(" close "),
+        ]))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(BRAND));
+
+    if app.submodule_rows() == 0 {
+        let hint = Paragraph::new(vec![
+            Line::from(""),
+            Line::from(Span::styled(
+                " This repository has no submodules.",
+                Style::default().fg(DIM),
+            )),
+        ])
+        .block(block);
+        f.render_widget(hint, area);
+        return;
+    }
+
+    let mut items: Vec<ListItem> = Vec::new();
+    if let Some(parent) = &app.superproject {
+        items.push(ListItem::new(Line::from(vec![
+            Span::styled(
+                format!("{:<30} ", "↑ superproject"),
+                Style::default().fg(BRAND),
+            ),
+            Span::styled(parent.display().to_string(), Style::default().fg(DIM)),
+        ])));
+    }
+    for submodule in &app.submodules {
+        let (status, color) = match submodule.changed {
+            _ if !submodule.initialized => ("not checked out".to_string(), DIM),
+            Some(0) => ("clean".to_string(), ADDED),
+            Some(n) => (format!("{n} changed"), Color::Yellow),
+            None => ("unreadable".to_string(), Color::Red),
+        };
+        let stashes = match submodule.stashes {
+            0 => String::new(),
+            n => format!("{n} stash(es)"),
+        };
+        items.push(ListItem::new(Line::from(vec![
+            Span::styled(
+                format!("{} ", fit(&submodule.path, 30)),
+                Style::default().fg(Color::White),
+            ),
+            Span::styled(format!("{status:<16} "), Style::default().fg(color)),
+            Span::styled(stashes, Style::default().fg(BRAND)),
+        ])));
+    }
+
+    let mut state = ListState::default();
+    state.select(Some(app.submodule_selected));
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().bg(HIGHLIGHT_BG).fg(Color::White))
+        .highlight_symbol("▶ ");
+
+    f.render_stateful_widget(list, area, &mut state);
+}
@@ -0,0 +1,151 @@
+use super::ModeController;
+use crate::app::{App, Mode};
+use crate::input::TextInput;
+use crate::ui::{self, BRAND, DIM, centered_rect_lines};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::Alignment,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+/// Prompt for the branch and commit message to turn a stash into
+pub struct ToBranch;
+
+impl ModeController for ToBranch {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        ui::render_main(f, app);
+        draw(f, app);
+    }
+
+    /// Suggest a branch named after the stash and its message as the commit
+    fn enter(&self, app: &mut App) {
+        let msg = app
+            .selected_stash()
+            .map(|s| s.short_msg.clone())
+            .unwrap_or_default();
+        app.to_branch_name.set(format!("stash/{}", slug(&msg)));
+        app.to_branch_message.set(msg);
+        app.to_branch_field = 0;
+        app.to_branch_drop = false;
+    }
+}
+
+/// `Fix the login form!` → `fix-the-login-form`
+fn slug(text: &str) -> String {
+    let lower = text.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .take(6)
+        .collect();
+    if words.is_empty() {
+        "wip".to_string()
+    } else {
+        words.join("-")
+    }
+}
+
+fn handle(app: &mut App, key: KeyEvent) -> Result<bool> {
+    let input = match app.to_branch_field {
+        0 => Some(&mut app.to_branch_name),
+        1 => Some(&mut app.to_branch_message),
+        _ => None,
+    };
+    match key.code {
+        KeyCode::Esc => app.mode = Mode::Normal,
+        KeyCode::Enter => {
+            let result = app.stash_to_branch();
+            app.report(result);
+        }
+        KeyCode::Tab | KeyCode::Down => app.to_branch_field = (app.to_branch_field + 1) % 3,
+        KeyCode::BackTab | KeyCode::Up => app.to_branch_field = (app.to_branch_field + 2) % 3,
+        KeyCode::Char(' ') if input.is_none() => app.to_branch_drop = !app.to_branch_drop,
+        _ => {
+            if let Some(input) = input {
+                input.handle_key(key);
+            }
+        }
+    }
+    Ok(false)
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let area = centered_rect_lines(64, 13, f.area());
+    f.render_widget(Clear, area);
+
+    let label = |text: &'static str, field: usize| {
+        let style = if app.to_branch_field == field {
+            Style::default().fg(BRAND).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Gray)
+        };
+        Line::from(Span::styled(text, style))
+    };
+    let value = |input: &TextInput, field: usize| {
+        let style = Style::default().fg(Color::White);
+        if app.to_branch_field == field {
+            Line::from(input.spans(style))
+        } else {
+            Line::from(Span::styled(input.to_string(), style))
+        }
+    };
+
+    let content = vec![
+        Line::from(""),
+        label("New branch (made where the stash was taken):", 0),
+        value(&app.to_branch_name, 0),
+        Line::from(""),
+        label("Commit message:", 1),
+        value(&app.to_branch_message, 1),
+        Line::from(""),
+        label(
+            if app.to_branch_drop {
+                "[x] drop the stash afterwards"
+            } else {
+                "[ ] drop the stash afterwards"
+            },
+            2,
+        ),
+        Line::from(Span::styled(
+            "Tab moves between fields; Space ticks the box",
+            Style::default().fg(DIM),
+        )),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("[Enter]", Style::default().fg(BRAND)),
+            Span::raw(" commit   "),
+            Span::styled("[Esc]", Style::default().fg(Color::Red)),
+            Span::raw(" cancel"),
+        ]),
+    ];
+
+    let popup = Paragraph::new(content).alignment(Alignment::Center).block(
+        Block::default()
+            .title(" Stash → Branch ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(BRAND)),
+    );
+
+    f.render_widget(popup, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn branch_slug() {
+        assert_eq!(slug("Fix the login form!"), "fix-the-login-form");
+        assert_eq!(slug("ÉTÉ: add v2 API"), "t-add-v2-api");
+        assert_eq!(slug("a b c d e f g"), "a-b-c-d-e-f");
+        assert_eq!(slug("!!!"), "wip");
+    }
+}
@@ -0,0 +1,159 @@
+use super::ModeController;
+use crate::ansi;
+use crate::app::{App, Mode};
+use crate::ui::{BRAND, DIM, HIGHLIGHT_BG, render_key_bar};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+};
+
+/// Browser over every file in a stash's snapshot
+pub struct Tree;
+
+impl ModeController for Tree {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key.code)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        draw(f, app);
+    }
+}
+
+fn handle(app: &mut App, key: KeyCode) -> Result<bool> {
+    match key {
+        KeyCode::Esc | KeyCode::Char('q') => app.mode = Mode::Normal,
+        KeyCode::Up | KeyCode::Char('k') => app.move_tree(false),
+        KeyCode::Down | KeyCode::Char('j') => app.move_tree(true),
+        KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => app.toggle_tree_dir(),
+        KeyCode::Left | KeyCode::Char('h') => app.collapse_tree_dir(),
+        KeyCode::PageUp => app.tree_scroll = app.tree_scroll.saturating_sub(20),
+        KeyCode::PageDown => {
+            app.tree_scroll = (app.tree_scroll + 20).min(app.tree_content.len().saturating_sub(1));
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(1),
+            Constraint::Length(3),
+        ])
+        .split(f.area());
+
+    let stash_info = app
+        .tree_stash
+        .as_ref()
+        .map(|s| format!("{} — {}", s.name, s.short_msg))
+        .unwrap_or_default();
+    let title = Line::from(vec![
+        Span::styled(
+            " Tree ",
+            Style::default()
+                .fg(Color::Black)
+                .bg(BRAND)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(format!("  {stash_info}"), Style::default().fg(Color::Gray)),
+    ]);
+    f.render_widget(
+        Paragraph::new(Span::styled(
+            format!("{} file(s); changed files in yellow", app.tree_paths.len()),
+            Style::default().fg(DIM),
+        ))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(BRAND))
+                .title(title),
+        ),
+        chunks[0],
+    );
+
+    let body = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
+        .split(chunks[1]);
+
+    let rows = app.tree_rows();
+    let items: Vec<ListItem> = rows
+        .iter()
+        .map(|row| {
+            let name = row.path.rsplit('/').next().unwrap_or(&row.path);
+            let indent = "  ".repeat(row.depth);
+            let (label, style) = if row.is_dir {
+                let arrow = if row.expanded { "▾" } else { "▸" };
+                (
+                    format!("{indent}{arrow} {name}/"),
+                    Style::default().fg(Color::Cyan),
+                )
+            } else if app.tree_changed.contains(&row.path) {
+                (
+                    format!("{indent}  {name}"),
+                    Style::default().fg(Color::Yellow),
+                )
+            } else {
+                (
+                    format!("{indent}  {name}"),
+                    Style::default().fg(Color::Gray),
+                )
+            };
+            ListItem::new(Span::styled(label, style))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(app.tree_selected));
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Rgb(80, 80, 100)))
+                .title(" Files "),
+        )
+        .highlight_style(Style::default().bg(HIGHLIGHT_BG))
+        .highlight_symbol("▶ ");
+    f.render_stateful_widget(list, body[0], &mut state);
+
+    let file_title = rows
+        .get(app.tree_selected)
+        .filter(|r| !r.is_dir)
+        .map(|r| format!(" {} ", r.path))
+        .unwrap_or_default();
+    let lines: Vec<Line> = app
+        .tree_content
+        .iter()
+        .skip(app.tree_scroll)
+        .map(|l| ansi::to_line(l, Style::default().fg(Color::Gray)))
+        .collect();
+    f.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Rgb(80, 80, 100)))
+                .title(file_title),
+        ),
+        body[1],
+    );
+
+    render_key_bar(
+        f,
+        chunks[2],
+        &[
+            ("↑↓/jk", "move"),
+            ("Enter/l", "expand"),
+            ("h", "collapse"),
+            ("PgUp/PgDn", "scroll file"),
+            ("Esc/q", "back"),
+        ],
+    );
+}
@@ -0,0 +1,317 @@
+use super::ModeController;
+use crate::actions::{self, Scope};
+use crate::app::{App, BranchRelation};
+use crate::ui::{
+    BRAND, DIM, SPINNER, chord_hints, colorize_diff_line, colorize_stat_line, key_span,
+    mode_indicator, render_scrollbar, spinner,
+};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Wrap},
+};
+
+/// Scrollable diff of one stash, or its diffstat when `files` is set
+pub struct View {
+    pub files: bool,
+}
+
+impl ModeController for View {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        draw(f, app, self.files);
+    }
+
+    fn exit(&self, app: &mut App) {
+        app.close_view();
+    }
+}
+
+fn handle(app: &mut App, event: KeyEvent) -> Result<bool> {
+    // Typing a search query
+    if app.diff_searching {
+        match event.code {
+            KeyCode::Esc => app.clear_diff_search(),
+            KeyCode::Enter => app.diff_searching = false,
+            _ => {
+                if app.diff_query.handle_key(event) {
+                    app.search_diff();
+                }
+            }
+        }
+        return Ok(false);
+    }
+
+    match actions::for_key(app, event) {
+        Some(action) => actions::run(app, action),
+        None => Ok(false),
+    }
+}
+
+fn draw(f: &mut Frame, app: &App, is_files: bool) {
+    let area = f.area();
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3 + header_lines(app).len() as u16),
+            Constraint::Min(1),
+            Constraint::Length(3),
+        ])
+        .split(area);
+
+    let mut title = vec![Span::styled(
+        " gsm ",
+        Style::default()
+            .fg(Color::Black)
+            .bg(BRAND)
+            .add_modifier(Modifier::BOLD),
+    )];
+    title.extend(mode_indicator(app));
+    title.push(Span::styled(
+        format!("  {}", app.diff_title),
+        Style::default().fg(Color::Gray),
+    ));
+    let title = Line::from(title);
+
+    let header = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(BRAND))
+        .title(title);
+
+    let branch = app
+        .selected_stash()
+        .map(|s| s.branch.clone())
+        .unwrap_or_default();
+    let relation = match &app.branch_relation {
+        Some(BranchRelation::Current) => format!("stashed on {branch} (current branch)"),
+        Some(BranchRelation::Gone) => format!("stashed on {branch} (branch no longer exists)"),
+        Some(BranchRelation::Diverged { ahead, behind }) => format!(
+            "stashed on {branch}: {ahead} ahead, {behind} behind {}",
+            app.current_branch
+        ),
+        None => String::new(),
+    };
+
+    let mut info = vec![Line::from(Span::styled(relation, Style::default().fg(DIM)))];
+    info.extend(header_lines(app));
+
+    f.render_widget(Paragraph::new(info).block(header), chunks[0]);
+
+    let visible_height = chunks[1].height.saturating_sub(2) as usize;
+    app.diff_page.set(visible_height);
+    let lines: Vec<Line> = match (spinner(app), &app.diff_rendered) {
+        (Some(status), _) => vec![Line::from(Span::styled(status, Style::default().fg(DIM)))],
+        // Search positions refer to gsm's own diff, so nothing is "current" here
+        (None, Some(rendered)) => rendered
+            .iter()
+            .skip(app.diff_scroll)
+            .take(visible_height)
+            .map(|line| highlight_query(line.clone(), &app.diff_query, false))
+            .collect(),
+        (None, None) => app
+            .diff_content
+            .lines()
+            .iter()
+            .enumerate()
+            .skip(app.diff_scroll)
+            .take(visible_height)
+            .map(|(i, line)| {
+                let current = app.diff_matches.get(app.diff_match) == Some(&i);
+                let line = if is_files && line.contains(" | ") {
+                    colorize_stat_line(line)
+                } else {
+                    colorize_diff_line(line)
+                };
+                highlight_query(line, &app.diff_query, current)
+            })
+            .collect(),
+    };
+
+    let mut body = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Rgb(80, 80, 100)));
+    if !app.diff_query.is_empty() {
+        let found = if app.diff_matches.is_empty() {
+            format!(" /{}: no matches ", app.diff_query)
+        } else {
+            format!(
+                " /{}: match {}/{} ",
+                app.diff_query,
+                app.diff_match + 1,
+                app.diff_matches.len()
+            )
+        };
+        body = body.title(
+            Line::from(Span::styled(found, Style::default().fg(Color::Yellow))).right_aligned(),
+        );
+    }
+    // Position, algorithm and line-ending churn along the bottom edge
+    let mut status = vec![Span::styled(
+        format!(" line {}/{} ", app.diff_scroll + 1, app.diff_len().max(1)),
+        Style::default().fg(DIM),
+    )];
+    if let Some(command) = app.config.diff.external.as_deref()
+        && app.diff_rendered.is_some()
+    {
+        status.push(Span::styled(
+            format!("rendered by {command} "),
+            Style::default().fg(BRAND),
+        ));
+    }
+    if app.diff_streaming() {
+        status.push(Span::styled(
+            format!(
+                "{} loading more… ",
+                SPINNER[app.spinner_tick % SPINNER.len()]
+            ),
+            Style::default().fg(BRAND),
+        ));
+    } else if app.diff_truncated {
+        status.push(Span::styled(
+            "cancelled: partial diff ",
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    if !app.diff_wrap {
+        status.push(Span::styled(
+            format!("col {} (no wrap) ", app.diff_hscroll + 1),
+            Style::default().fg(DIM),
+        ));
+    }
+    if !is_files {
+        status.push(Span::styled(
+            format!("{} ", app.diff_options.algorithm.label()),
+            Style::default().fg(DIM),
+        ));
+        if app.diff_options.function_context {
+            status.push(Span::styled("function context ", Style::default().fg(DIM)));
+        }
+        if app.line_ending_files > 0 {
+            status.push(Span::styled(
+                format!(
+                    "␍ {} file(s) with line-ending changes ",
+                    app.line_ending_files
+                ),
+                Style::default().fg(Color::Magenta),
+            ));
+        }
+    }
+    body = body.title_bottom(Line::from(status).right_aligned());
+    let mut diff = Paragraph::new(lines).block(body);
+    diff = if app.diff_wrap {
+        diff.wrap(Wrap { trim: false })
+    } else {
+        diff.scroll((0, app.diff_hscroll as u16))
+    };
+
+    f.render_widget(diff, chunks[1]);
+    render_scrollbar(f, chunks[1], app.diff_len(), app.diff_scroll);
+
+    let keys: Vec<Vec<Span>> = if app.diff_searching {
+        vec![
+            std::iter::once(Span::styled("/", Style::default().fg(Color::White)))
+                .chain(app.diff_query.spans(Style::default().fg(Color::White)))
+                .collect(),
+            key_span("Enter", "done"),
+            key_span("Esc", "cancel"),
+        ]
+    } else if let Some(pending) = chord_hints(app) {
+        pending
+    } else {
+        let scopes: &[Scope] = if is_files {
+            &[Scope::View]
+        } else {
+            &[Scope::View, Scope::Diff]
+        };
+        actions::footer(scopes)
+            .into_iter()
+            .map(|(k, label)| key_span(k, label))
+            .collect()
+    };
+
+    let mut footer_spans: Vec<Span> = Vec::new();
+    for (i, s) in keys.into_iter().enumerate() {
+        if i > 0 {
+            footer_spans.push(Span::raw("   "));
+        }
+        footer_spans.extend(s);
+    }
+
+    let footer = Paragraph::new(Line::from(footer_spans))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Rgb(60, 60, 80))),
+        )
+        .alignment(Alignment::Center);
+
+    f.render_widget(footer, chunks[2]);
+}
+
+/// Header lines below the branch relation: the stash commit's details and the
+/// environment gsm recorded when it was made, if any
+fn header_lines(app: &App) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    if let Some(details) = &app.stash_details {
+        lines.push(Line::from(vec![
+            Span::styled("stash ", Style::default().fg(DIM)),
+            Span::styled(details.sha.clone(), Style::default().fg(Color::Yellow)),
+            Span::styled("  by ", Style::default().fg(DIM)),
+            Span::styled(details.author.clone(), Style::default().fg(Color::Gray)),
+            Span::styled("  on ", Style::default().fg(DIM)),
+            Span::styled(details.date.clone(), Style::default().fg(Color::Gray)),
+        ]));
+        lines.push(Line::from(vec![
+            Span::styled("parent ", Style::default().fg(DIM)),
+            Span::styled(details.parent.clone(), Style::default().fg(Color::Gray)),
+        ]));
+    }
+    lines.extend(
+        app.stash_note
+            .iter()
+            .map(|l| Line::from(Span::styled(l.clone(), Style::default().fg(Color::Cyan)))),
+    );
+    lines
+}
+
+/// Pick out case-insensitive occurrences of `query` within an already coloured
+/// line; the current match is drawn brighter than the others
+fn highlight_query(line: Line<'static>, query: &str, current: bool) -> Line<'static> {
+    if query.is_empty() {
+        return line;
+    }
+    let hit = if current {
+        Style::default().fg(Color::Black).bg(BRAND)
+    } else {
+        Style::default().fg(Color::Black).bg(Color::Yellow)
+    };
+    // ASCII lowercasing keeps byte offsets aligned with the original text
+    let query = query.to_ascii_lowercase();
+
+    let mut spans = Vec::new();
+    for span in line.spans {
+        let text = span.content.to_string();
+        let lower = text.to_ascii_lowercase();
+        let mut start = 0;
+        while let Some(pos) = lower[start..].find(&query) {
+            let (from, to) = (start + pos, start + pos + query.len());
+            if from > start {
+                spans.push(Span::styled(text[start..from].to_string(), span.style));
+            }
+            spans.push(Span::styled(text[from..to].to_string(), hit));
+            start = to;
+        }
+        if start < text.len() {
+            spans.push(Span::styled(text[start..].to_string(), span.style));
+        }
+    }
+    Line::from(spans)
+}
@@ -0,0 +1,118 @@
+use super::ModeController;
+use crate::app::{App, Mode};
+use crate::ui::{self, ADDED, BRAND, DIM, HIGHLIGHT_BG, centered_rect, fit};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+};
+use std::path::{Path, PathBuf};
+
+/// The repository's worktrees and whether each has uncommitted changes;
+/// Enter reopens gsm in the selected one
+pub struct Worktrees;
+
+impl ModeController for Worktrees {
+    fn handle_key(&self, app: &mut App, key: KeyEvent) -> Result<bool> {
+        handle(app, key.code)
+    }
+
+    fn render(&self, f: &mut Frame, app: &App) {
+        ui::render_main(f, app);
+        draw(f, app);
+    }
+
+    fn enter(&self, app: &mut App) {
+        if let Err(e) = app.load_worktree_list() {
+            app.mode = Mode::Message(format!("Error: {e}"));
+        }
+    }
+}
+
+fn handle(app: &mut App, key: KeyCode) -> Result<bool> {
+    match key {
+        KeyCode::Esc | KeyCode::Char('q') => {
+            app.mode = Mode::Normal;
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            app.worktree_selected = app.worktree_selected.saturating_sub(1);
+        }
+        KeyCode::Down | KeyCode::Char('j')
+            if app.worktree_selected + 1 < app.worktree_list.len() =>
+        {
+            app.worktree_selected += 1;
+        }
+        KeyCode::Char('r') => {
+            if let Err(e) = app.load_worktree_list() {
+                app.mode = Mode::Message(format!("Error: {e}"));
+            }
+        }
+        KeyCode::Enter if app.worktree_selected < app.worktree_list.len() => {
+            let path = PathBuf::from(&app.worktree_list[app.worktree_selected].0.path);
+            if path == app.location.root {
+                app.mode = Mode::Normal;
+            } else {
+                let result = app.switch_repo(&path);
+                app.report(result);
+            }
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let area = centered_rect(70, 50, f.area());
+    f.render_widget(Clear, area);
+
+    let items: Vec<ListItem> = app
+        .worktree_list
+        .iter()
+        .map(|(worktree, changed)| {
+            let here = app.location.root == Path::new(&worktree.path);
+            let (status, color) = match changed {
+                Some(0) => ("clean".to_string(), ADDED),
+                Some(n) => (format!("{n} changed"), Color::Yellow),
+                None => ("missing".to_string(), Color::Red),
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(if here { "● " } else { "  " }, Style::default().fg(BRAND)),
+                Span::styled(
+                    format!(
+                        "{} ",
+                        fit(worktree.branch.as_deref().unwrap_or("(detached)"), 24)
+                    ),
+                    Style::default().fg(Color::White),
+                ),
+                Span::styled(format!("{status:<11} "), Style::default().fg(color)),
+                Span::styled(worktree.path.clone(), Style::default().fg(DIM)),
+            ]))
+        })
+        .collect();
+
+    let mut state = ListState::default();
+    state.select(Some(app.worktree_selected));
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(" Worktrees ")
+                .title_bottom(Line::from(vec![
+                    Span::styled(" [Enter]", Style::default().fg(BRAND)),
+                    Span::raw(" open  "),
+                    Span::styled("[r]", Style::default().fg(BRAND)),
+                    Span::raw(" refresh  "),
+                    Span::styled("[Esc]", Style::default().fg(Color::Red)),
+                    Span::raw(" close "),
+                ]))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(BRAND)),
+        )
+        .highlight_style(Style::default().bg(HIGHLIGHT_BG).fg(Color::White))
+        .highlight_symbol("▶ ");
+
+    f.render_stateful_widget(list, area, &mut state);
+}
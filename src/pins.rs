@@ -0,0 +1,29 @@
+use crate::git;
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs;
+
+/// Pinned stashes, one commit SHA per line. SHAs survive other stashes being
+/// added or dropped, which stash@{N} names do not.
+const PINS_FILE: &str = "gsm-pins";
+
+/// SHAs of the pinned stashes; empty if none were ever pinned
+pub fn load() -> HashSet<String> {
+    let Ok(path) = git::git_path(PINS_FILE) else {
+        return HashSet::new();
+    };
+    let raw = fs::read_to_string(path).unwrap_or_default();
+    raw.lines()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect()
+}
+
+pub fn save(pins: &HashSet<String>) -> Result<()> {
+    let path = git::git_path(PINS_FILE)?;
+    let mut shas: Vec<&str> = pins.iter().map(|s| s.as_str()).collect();
+    shas.sort_unstable();
+    let mut raw = shas.join("\n");
+    raw.push('\n');
+    fs::write(&path, raw).with_context(|| format!("Failed to write {}", path.display()))
+}
@@ -0,0 +1,153 @@
+use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::hash::{BuildHasher, Hasher, RandomState};
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The configured preview command for `path`, looked up by its extension
+pub fn command_for<'a>(commands: &'a HashMap<String, String>, path: &str) -> Option<&'a str> {
+    let ext = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+    commands.get(&ext).map(|c| c.as_str())
+}
+
+/// How long a preview command may run before it is killed
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often a running preview command is checked on
+const POLL: Duration = Duration::from_millis(20);
+
+/// Run a preview command over a file's contents and return its output,
+/// colour codes included. A `{file}` argument is replaced with a temporary
+/// copy of the file; without one, the contents are piped to the command's stdin.
+/// A command still running after `TIMEOUT` is killed.
+pub fn render(command: &str, path: &str, content: &[u8]) -> Result<String> {
+    let mut parts = command.split_whitespace();
+    let Some(program) = parts.next() else {
+        bail!("Empty preview command");
+    };
+    let args: Vec<&str> = parts.collect();
+
+    let uses_file = args.contains(&"{file}");
+    let tmp = if uses_file {
+        Some(temp_copy(path, content)?)
+    } else {
+        None
+    };
+    let output = run(program, &args, tmp.as_deref(), content);
+    if let Some(tmp) = &tmp {
+        let _ = fs::remove_file(tmp);
+    }
+
+    let output = output.with_context(|| format!("Failed to run {program}"))?;
+    if !output.status.success() {
+        bail!(
+            "{program} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Write `content` to a new file in the temp directory that nobody else can
+/// have created or read: the name is random and the open fails if it exists.
+/// The extension is kept, since some tools pick their renderer by it.
+fn temp_copy(path: &str, content: &[u8]) -> Result<PathBuf> {
+    let file_name = Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    for _ in 0..16 {
+        // std seeds every RandomState from the OS, which is random enough here
+        let token = RandomState::new().build_hasher().finish();
+        let tmp = std::env::temp_dir().join(format!("gsm-preview-{token:016x}-{file_name}"));
+        match options.open(&tmp) {
+            Ok(mut file) => {
+                file.write_all(content)
+                    .with_context(|| format!("Failed to write {}", tmp.display()))?;
+                return Ok(tmp);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to create {}", tmp.display()));
+            }
+        }
+    }
+    bail!("Failed to create a temporary file for the preview")
+}
+
+/// Run the command to completion or until `TIMEOUT`. Input and output go
+/// through threads of their own so a tool that stops reading, or writes more
+/// than a pipe holds, can't stall it.
+fn run(program: &str, args: &[&str], file: Option<&Path>, content: &[u8]) -> io::Result<Output> {
+    let mut child = Command::new(program)
+        .args(args.iter().map(|a| match (*a, file) {
+            ("{file}", Some(file)) => file.as_os_str().to_owned(),
+            _ => a.into(),
+        }))
+        .stdin(if file.is_some() {
+            Stdio::null()
+        } else {
+            Stdio::piped()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        let content = content.to_vec();
+        // The tool may not read everything, e.g. an image header is enough
+        thread::spawn(move || {
+            let _ = stdin.write_all(&content);
+        });
+    }
+    let stdout = child.stdout.take().map(drain);
+    let stderr = child.stderr.take().map(drain);
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if started.elapsed() >= TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::Error::new(
+                ErrorKind::TimedOut,
+                format!("killed after {}s", TIMEOUT.as_secs()),
+            ));
+        }
+        thread::sleep(POLL);
+    };
+    // Something the tool left running may hold a pipe open; don't wait past the timeout
+    let collect = |rx: Option<mpsc::Receiver<Vec<u8>>>| {
+        let left = TIMEOUT.saturating_sub(started.elapsed()).max(POLL);
+        rx.and_then(|rx| rx.recv_timeout(left).ok())
+            .unwrap_or_default()
+    };
+    Ok(Output {
+        status,
+        stdout: collect(stdout),
+        stderr: collect(stderr),
+    })
+}
+
+/// Read a pipe to its end on a thread, handing over what it held
+fn drain(mut pipe: impl Read + Send + 'static) -> mpsc::Receiver<Vec<u8>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = pipe.read_to_end(&mut buf);
+        let _ = tx.send(buf);
+    });
+    rx
+}
@@ -0,0 +1,245 @@
+use crate::git::Stash;
+
+/// A parsed search: `field:value` filters that must all hold, plus the free
+/// text left over, which is fuzzy-matched as before
+#[derive(Debug, Default)]
+pub struct Query {
+    pub filters: Vec<Filter>,
+    pub text: String,
+    pub error: Option<String>, // first filter that didn't parse, for the header
+}
+
+#[derive(Debug)]
+pub enum Filter {
+    Branch(String),
+    File(String),
+    Msg(String),
+    Label(String),
+    Author(String),
+    OlderThan(i64), // seconds
+    NewerThan(i64),
+}
+
+/// What a filter is checked against besides the stash itself
+pub struct Context<'a> {
+    pub labels: &'a [String],
+    pub paths: Option<&'a [String]>, // None until the file lists are loaded
+    pub user_email: Option<&'a str>, // what `author:me` stands for
+    pub now: i64,
+}
+
+impl Query {
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty() && self.text.is_empty()
+    }
+
+    /// True if the search needs each stash's changed files
+    pub fn needs_paths(&self) -> bool {
+        self.filters.iter().any(|f| matches!(f, Filter::File(_)))
+    }
+
+    /// True if every filter holds for `stash`
+    pub fn matches(&self, stash: &Stash, ctx: &Context) -> bool {
+        self.filters.iter().all(|filter| match filter {
+            Filter::Branch(want) => contains(&stash.branch, want),
+            Filter::Msg(want) => contains(&stash.short_msg, want),
+            Filter::Label(want) => ctx
+                .labels
+                .iter()
+                .any(|l| l.to_lowercase().starts_with(&want.to_lowercase())),
+            Filter::Author(want) if want.eq_ignore_ascii_case("me") => ctx
+                .user_email
+                .is_some_and(|email| contains(&stash.author, &format!("<{email}>"))),
+            Filter::Author(want) => contains(&stash.author, want),
+            Filter::File(want) => ctx
+                .paths
+                .is_some_and(|paths| paths.iter().any(|p| contains(p, want))),
+            Filter::OlderThan(secs) => ctx.now - stash.timestamp > *secs,
+            Filter::NewerThan(secs) => ctx.now - stash.timestamp < *secs,
+        })
+    }
+}
+
+/// Parse the search box. Words like `branch:feat`, `file:src/ui.rs`, `msg:fix`,
+/// `label:wip`, `author:ana` and `age:>7d` become filters; a value may be double-quoted to
+/// hold spaces. Anything else, including unknown fields, is free text.
+pub fn parse(input: &str) -> Query {
+    let mut query = Query::default();
+    let mut text = Vec::new();
+    for word in words(input) {
+        let Some((field, value)) = word.split_once(':') else {
+            text.push(word);
+            continue;
+        };
+        if !matches!(
+            field,
+            "branch" | "file" | "msg" | "label" | "author" | "age"
+        ) {
+            text.push(word);
+            continue;
+        }
+        // A bare `branch:` while typing filters nothing yet
+        if value.is_empty() {
+            continue;
+        }
+        let filter = match field {
+            "branch" => Filter::Branch(value.to_string()),
+            "file" => Filter::File(value.to_string()),
+            "msg" => Filter::Msg(value.to_string()),
+            "label" => Filter::Label(value.to_string()),
+            "author" => Filter::Author(value.to_string()),
+            _ => match parse_age(value) {
+                Some(filter) => filter,
+                None => {
+                    query
+                        .error
+                        .get_or_insert_with(|| format!("bad age '{value}'"));
+                    continue;
+                }
+            },
+        };
+        query.filters.push(filter);
+    }
+    query.text = text.join(" ");
+    query
+}
+
+/// Split on whitespace, keeping double-quoted runs together (quotes dropped)
+fn words(input: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in input.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// `>7d` (older than a week) or `<2h` (newer than two hours); a bare `3d`
+/// means newer than. Units are m(inutes), h, d, w.
+fn parse_age(value: &str) -> Option<Filter> {
+    let (older, amount) = match value.strip_prefix('>') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('<').unwrap_or(value)),
+    };
+    let unit = amount.chars().last()?;
+    let n: i64 = amount[..amount.len() - unit.len_utf8()].parse().ok()?;
+    let secs = n * match unit {
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    Some(if older {
+        Filter::OlderThan(secs)
+    } else {
+        Filter::NewerThan(secs)
+    })
+}
+
+fn contains(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stash(branch: &str, msg: &str, author: &str, timestamp: i64) -> Stash {
+        Stash {
+            index: 0,
+            name: "stash@{0}".to_string(),
+            sha: "0".repeat(40),
+            message: format!("On {branch}: {msg}"),
+            branch: branch.to_string(),
+            short_msg: msg.to_string(),
+            date: String::new(),
+            timestamp,
+            author: author.to_string(),
+            created: String::new(),
+            files_changed: None,
+            numstat: None,
+        }
+    }
+
+    fn ctx<'a>(labels: &'a [String], paths: Option<&'a [String]>) -> Context<'a> {
+        Context {
+            labels,
+            paths,
+            user_email: Some("ana@example.com"),
+            now: 1_000_000,
+        }
+    }
+
+    #[test]
+    fn parse_splits_filters_from_text() {
+        let query = parse(r#"branch:feat "msg:half done" login  flow foo:bar"#);
+        assert_eq!(query.filters.len(), 2);
+        assert!(matches!(&query.filters[0], Filter::Branch(b) if b == "feat"));
+        assert!(matches!(&query.filters[1], Filter::Msg(m) if m == "half done"));
+        assert_eq!(query.text, "login flow foo:bar");
+        assert!(query.error.is_none());
+    }
+
+    #[test]
+    fn parse_ages_and_errors() {
+        let query = parse("age:>7d age:2h age:soon branch:");
+        assert!(matches!(query.filters[0], Filter::OlderThan(s) if s == 7 * 24 * 60 * 60));
+        assert!(matches!(query.filters[1], Filter::NewerThan(s) if s == 2 * 60 * 60));
+        assert_eq!(query.filters.len(), 2);
+        assert_eq!(query.error.as_deref(), Some("bad age 'soon'"));
+        assert!(query.text.is_empty());
+    }
+
+    #[test]
+    fn matches_every_filter() {
+        let s = stash(
+            "feature/login",
+            "WIP form",
+            "Ana <ana@example.com>",
+            1_000_000 - 3600,
+        );
+        let labels = vec!["urgent".to_string()];
+        let paths = vec!["src/Login.rs".to_string()];
+        let ctx = ctx(&labels, Some(&paths));
+        for input in [
+            "branch:LOGIN msg:form",
+            "label:urg",
+            "author:me",
+            "author:ana",
+            "file:login.rs",
+            "age:<2h age:>30m",
+        ] {
+            assert!(parse(input).matches(&s, &ctx), "{input}");
+        }
+        for input in [
+            "branch:main",
+            "label:later",
+            "file:ui.rs",
+            "age:>2h",
+            "author:bo",
+        ] {
+            assert!(!parse(input).matches(&s, &ctx), "{input}");
+        }
+    }
+
+    #[test]
+    fn file_filter_waits_for_paths() {
+        let s = stash("main", "wip", "Ana <ana@example.com>", 0);
+        let query = parse("file:src");
+        assert!(query.needs_paths());
+        assert!(!query.matches(&s, &ctx(&[], None)));
+    }
+}
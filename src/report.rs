@@ -0,0 +1,140 @@
+use crate::git;
+use anyhow::Result;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Render a Markdown hand-off report: a summary table of the stashes, then each
+/// stash's diffstat in a collapsible `<details>` block
+pub fn markdown(stashes: &[&git::Stash]) -> Result<String> {
+    let repo = git::toplevel()
+        .ok()
+        .and_then(|top| {
+            Path::new(&top)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+        })
+        .unwrap_or_else(|| "repository".to_string());
+    let branch = git::current_branch().unwrap_or_default();
+
+    let mut out = String::new();
+    writeln!(out, "# Stash report: {repo}")?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "{} stash(es), generated {} on branch `{branch}`.",
+        stashes.len(),
+        chrono::Local::now().format("%Y-%m-%d %H:%M")
+    )?;
+    writeln!(out)?;
+    writeln!(out, "| Stash | Branch | Message | Age | Files | +/- |")?;
+    writeln!(out, "|-------|--------|---------|-----|------:|-----|")?;
+
+    let mut details = String::new();
+    for stash in stashes {
+        let files = git::stash_numstat(&stash.name)?;
+        let insertions: usize = files.iter().filter_map(|f| f.insertions).sum();
+        let deletions: usize = files.iter().filter_map(|f| f.deletions).sum();
+
+        writeln!(
+            out,
+            "| `{}` | `{}` | {} | {} | {} | +{insertions} -{deletions} |",
+            stash.name,
+            stash.branch,
+            table_cell(&stash.short_msg),
+            stash.date,
+            files.len()
+        )?;
+
+        writeln!(details, "<details>")?;
+        writeln!(
+            details,
+            "<summary><code>{}</code> {}</summary>",
+            stash.name,
+            html_escape(&stash.short_msg)
+        )?;
+        writeln!(details)?;
+        writeln!(details, "```")?;
+        write!(details, "{}", git::stash_files(&stash.name)?)?;
+        writeln!(details, "```")?;
+        writeln!(details)?;
+        writeln!(details, "</details>")?;
+        writeln!(details)?;
+    }
+
+    if !details.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "## Diffstats")?;
+        writeln!(out)?;
+        out.push_str(&details);
+    }
+    Ok(out)
+}
+
+/// Render a stash's diff as a self-contained HTML page, coloured as the diff
+/// view colours it, for attaching to a review or an email
+pub fn html_diff(stash: &git::Stash, diff: &str) -> Result<String> {
+    let title = format!("{}: {}", stash.name, html_escape(&stash.short_msg));
+    let mut out = String::new();
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html>")?;
+    writeln!(out, "<head>")?;
+    writeln!(out, "<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{title}</title>")?;
+    writeln!(out, "<style>")?;
+    writeln!(out, "body {{ font-family: sans-serif; margin: 2em; }}")?;
+    writeln!(
+        out,
+        "pre {{ font-family: monospace; background: #f6f8fa; padding: 1em; overflow-x: auto; }}"
+    )?;
+    writeln!(out, ".file {{ color: #9a6700; font-weight: bold; }}")?;
+    writeln!(out, ".hunk {{ color: #0969da; }}")?;
+    writeln!(out, ".add {{ color: #116329; background: #dafbe1; }}")?;
+    writeln!(out, ".del {{ color: #82071e; background: #ffebe9; }}")?;
+    writeln!(out, "</style>")?;
+    writeln!(out, "</head>")?;
+    writeln!(out, "<body>")?;
+    writeln!(out, "<h1>{title}</h1>")?;
+    writeln!(
+        out,
+        "<p>On branch <code>{}</code>, {}.</p>",
+        html_escape(&stash.branch),
+        stash.date
+    )?;
+    writeln!(out, "<pre>")?;
+    for line in diff.lines() {
+        let class = if line.starts_with("diff ")
+            || line.starts_with("index ")
+            || line.starts_with("---")
+            || line.starts_with("+++")
+        {
+            Some("file")
+        } else if line.starts_with("@@") {
+            Some("hunk")
+        } else if line.starts_with('+') {
+            Some("add")
+        } else if line.starts_with('-') {
+            Some("del")
+        } else {
+            None
+        };
+        match class {
+            Some(class) => writeln!(out, "<span class=\"{class}\">{}</span>", html_escape(line))?,
+            None => writeln!(out, "{}", html_escape(line))?,
+        }
+    }
+    writeln!(out, "</pre>")?;
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")?;
+    Ok(out)
+}
+
+/// Keep a message from breaking out of its table cell
+fn table_cell(text: &str) -> String {
+    html_escape(text).replace('|', "\\|")
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
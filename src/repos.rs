@@ -0,0 +1,92 @@
+use crate::config;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Repositories gsm was opened in, most recent first, one path per line,
+/// kept next to the config file
+const RECENT_FILE: &str = "recent-repos";
+const MAX_RECENT: usize = 10;
+
+/// A repository the switcher can open
+#[derive(Debug, Clone)]
+pub struct Repo {
+    pub path: PathBuf,
+    pub recent: bool, // from the recent list rather than a sibling directory
+}
+
+impl Repo {
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.path.display().to_string())
+    }
+}
+
+fn recent_path() -> Option<PathBuf> {
+    Some(config::config_path()?.parent()?.join(RECENT_FILE))
+}
+
+/// Recently opened repositories that still exist
+pub fn recent() -> Vec<PathBuf> {
+    let Some(path) = recent_path() else {
+        return Vec::new();
+    };
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(|l| PathBuf::from(l.trim()))
+        .filter(|p| !p.as_os_str().is_empty() && is_repo(p))
+        .collect()
+}
+
+/// Move `repo` to the front of the recent list. Failing to write the list
+/// only costs the history, so errors are ignored.
+pub fn remember(repo: &Path) {
+    let Some(path) = recent_path() else {
+        return;
+    };
+    let mut repos = recent();
+    repos.retain(|r| r != repo);
+    repos.insert(0, repo.to_path_buf());
+    repos.truncate(MAX_RECENT);
+    let raw: String = repos.iter().map(|r| format!("{}\n", r.display())).collect();
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let _ = fs::write(path, raw);
+}
+
+/// The switcher's list: recent repositories, then the other repositories
+/// next to `current`, leaving out `current` itself
+pub fn candidates(current: &Path) -> Vec<Repo> {
+    let mut repos: Vec<Repo> = recent()
+        .into_iter()
+        .filter(|p| p != current)
+        .map(|path| Repo { path, recent: true })
+        .collect();
+
+    let mut siblings: Vec<PathBuf> = current
+        .parent()
+        .and_then(|parent| fs::read_dir(parent).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p != current && is_repo(p))
+        .collect();
+    siblings.sort();
+    for path in siblings {
+        if !repos.iter().any(|r| r.path == path) {
+            repos.push(Repo {
+                path,
+                recent: false,
+            });
+        }
+    }
+    repos
+}
+
+/// A working tree root: `.git` is a directory, or a file for linked worktrees
+fn is_repo(dir: &Path) -> bool {
+    dir.join(".git").exists()
+}
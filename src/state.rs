@@ -0,0 +1,46 @@
+use crate::app::SortOrder;
+use crate::git;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// Where the UI was left, saved on exit and restored on the next launch in
+/// the same repository
+const STATE_FILE: &str = "gsm-state.toml";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    pub selected: Option<String>, // SHA of the selected stash
+    pub search: String,
+    pub sort: Option<SortOrder>,
+    pub branch_only: bool,
+    pub preview: bool,
+    pub split: Option<u16>, // list width beside the preview, in percent
+    pub view: View,
+}
+
+/// The screen to reopen; anything but the diff and file views reopens the list
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum View {
+    #[default]
+    List,
+    Diff,
+    Files,
+}
+
+/// The saved state; a missing or unreadable file yields the defaults
+pub fn load() -> State {
+    git::git_path(STATE_FILE)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|raw| toml::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+pub fn save(state: &State) -> Result<()> {
+    let path = git::git_path(STATE_FILE)?;
+    let raw = toml::to_string(state).context("Failed to serialize the session state")?;
+    fs::write(&path, raw).with_context(|| format!("Failed to write {}", path.display()))
+}
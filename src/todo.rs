@@ -0,0 +1,149 @@
+use crate::git;
+use anyhow::{Result, bail};
+
+/// What to do with one stash in an edited TODO plan
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    Drop,
+    Export,
+    Rename(String),
+}
+
+const HELP: &str = "\
+# Edit the verb in front of each stash, save and quit to review the plan.
+#
+# Verbs:
+#   k, keep    leave the stash alone
+#   d, drop    delete the stash
+#   e, export  write the stash to stash-<index>-<message>.patch
+#   r, rename  use the rest of the line as the new message
+#
+# Removing a line keeps that stash. An empty file cancels.
+";
+
+/// The TODO buffer: one `keep <sha> <message>` line per stash, newest first
+pub fn render(stashes: &[git::Stash]) -> String {
+    let mut out = String::new();
+    for stash in stashes {
+        out.push_str(&format!(
+            "keep {} {}\n",
+            &stash.sha[..7.min(stash.sha.len())],
+            stash.short_msg
+        ));
+    }
+    out.push('\n');
+    out.push_str(HELP);
+    out
+}
+
+/// Parse an edited TODO buffer into (stash SHA, step) pairs, skipping kept stashes
+pub fn parse(text: &str, stashes: &[git::Stash]) -> Result<Vec<(String, Step)>> {
+    let mut plan: Vec<(String, Step)> = Vec::new();
+
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut parts = line.splitn(3, char::is_whitespace);
+        let verb = parts.next().unwrap_or_default();
+        let Some(abbrev) = parts.next() else {
+            bail!("line {}: missing stash id", n + 1);
+        };
+        let rest = parts.next().unwrap_or_default().trim();
+
+        let matches: Vec<&git::Stash> = stashes
+            .iter()
+            .filter(|s| s.sha.starts_with(abbrev))
+            .collect();
+        let sha = match matches.as_slice() {
+            [stash] => stash.sha.clone(),
+            [] => bail!("line {}: no stash {abbrev}", n + 1),
+            _ => bail!("line {}: {abbrev} is ambiguous", n + 1),
+        };
+        if plan.iter().any(|(s, _)| *s == sha) {
+            bail!("line {}: {abbrev} is listed twice", n + 1);
+        }
+
+        let step = match verb {
+            "k" | "keep" => continue,
+            "d" | "drop" => Step::Drop,
+            "e" | "export" => Step::Export,
+            "r" | "rename" if rest.is_empty() => {
+                bail!("line {}: rename needs a message", n + 1)
+            }
+            "r" | "rename" => Step::Rename(rest.to_string()),
+            other => bail!("line {}: unknown verb '{other}'", n + 1),
+        };
+        plan.push((sha, step));
+    }
+
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stash(sha: &str, msg: &str) -> git::Stash {
+        git::Stash {
+            index: 0,
+            name: "stash@{0}".to_string(),
+            sha: sha.to_string(),
+            message: format!("On main: {msg}"),
+            branch: "main".to_string(),
+            short_msg: msg.to_string(),
+            date: String::new(),
+            timestamp: 0,
+            author: String::new(),
+            created: String::new(),
+            files_changed: None,
+            numstat: None,
+        }
+    }
+
+    fn stashes() -> Vec<git::Stash> {
+        vec![
+            stash("aaaa1111", "first"),
+            stash("bbbb2222", "second"),
+            stash("bbbb3333", "third"),
+        ]
+    }
+
+    #[test]
+    fn render_round_trips_as_keep() {
+        let stashes = stashes();
+        assert!(parse(&render(&stashes), &stashes).unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_verbs() {
+        let text = "d aaaa first\n# a comment\n\n  rename bbbb2  new  message \ne bbbb3\n";
+        assert_eq!(
+            parse(text, &stashes()).unwrap(),
+            [
+                ("aaaa1111".to_string(), Step::Drop),
+                (
+                    "bbbb2222".to_string(),
+                    Step::Rename("new  message".to_string())
+                ),
+                ("bbbb3333".to_string(), Step::Export),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_errors_name_the_line() {
+        for (text, err) in [
+            ("drop", "line 1: missing stash id"),
+            ("drop cccc", "line 1: no stash cccc"),
+            ("drop bbbb", "line 1: bbbb is ambiguous"),
+            ("d aaaa\ne aaaa1", "line 2: aaaa1 is listed twice"),
+            ("r aaaa", "line 1: rename needs a message"),
+            ("squash aaaa", "line 1: unknown verb 'squash'"),
+        ] {
+            assert_eq!(parse(text, &stashes()).unwrap_err().to_string(), err);
+        }
+    }
+}
@@ -1,104 +1,336 @@
-use crate::app::{App, ConfirmAction, Mode};
+use crate::actions::{self, Scope};
+use crate::app::{self, App, ListRow, MIN_SPLIT, Mode, SortOrder};
+use crate::config::{Column, ColumnSpec, HintPlacement};
+use crate::{git, modes, query};
 use ratatui::{
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    Frame,
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
-    Frame,
+    widgets::{
+        Block, Borders, List, ListItem, ListState, Paragraph, Scrollbar, ScrollbarOrientation,
+        ScrollbarState, Wrap,
+    },
 };
-
-const BRAND: Color = Color::Rgb(255, 135, 0);
-const ADDED: Color = Color::Green;
-const REMOVED: Color = Color::Red;
-const DIM: Color = Color::DarkGray;
-const HIGHLIGHT_BG: Color = Color::Rgb(45, 45, 60);
+use std::collections::HashSet;
+use std::path::Path;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+pub const BRAND: Color = Color::Rgb(255, 135, 0);
+pub const ADDED: Color = Color::Green;
+pub const REMOVED: Color = Color::Red;
+pub const DIM: Color = Color::DarkGray;
+pub const HIGHLIGHT_BG: Color = Color::Rgb(45, 45, 60);
+/// Narrowest the list's message column gets before other columns are dropped
+const MIN_MSG_WIDTH: usize = 20;
+/// Terminals narrower than this get a two-line-per-stash list and a minimal
+/// hint bar
+const NARROW_WIDTH: u16 = 80;
+/// Terminals shorter than this leave out the header
+const SHORT_HEIGHT: u16 = 20;
+pub const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
 
 pub fn render(f: &mut Frame, app: &App) {
-    match &app.mode {
-        Mode::Diff => render_diff_view(f, app, false),
-        Mode::Files => render_diff_view(f, app, true),
-        Mode::Confirm(action) => {
-            render_main(f, app);
-            render_confirm_popup(f, action);
+    modes::controller(&app.mode).render(f, app);
+    if app.palette {
+        modes::palette::draw(f, app);
+    }
+}
+
+pub fn render_main(f: &mut Frame, app: &App) {
+    let area = f.area();
+    // Short terminals give the header's rows to the list
+    let header = if area.height < SHORT_HEIGHT { 0 } else { 3 };
+
+    match app.config.ui.hints {
+        HintPlacement::Bottom => {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(header),
+                    Constraint::Min(5),
+                    Constraint::Length(3),
+                ])
+                .split(area);
+
+            if header > 0 {
+                render_header(f, chunks[0], app);
+            }
+            render_list_area(f, chunks[1], app);
+            render_footer(f, chunks[2], app);
         }
-        Mode::NewStash => {
-            render_main(f, app);
-            render_new_stash_popup(f, app);
+        HintPlacement::Top => {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(header),
+                    Constraint::Length(3),
+                    Constraint::Min(5),
+                ])
+                .split(area);
+
+            if header > 0 {
+                render_header(f, chunks[0], app);
+            }
+            render_footer(f, chunks[1], app);
+            render_list_area(f, chunks[2], app);
         }
-        Mode::Message(msg) => {
-            render_main(f, app);
-            render_message_popup(f, msg);
+        HintPlacement::Hidden => {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(header), Constraint::Min(5)])
+                .split(area);
+
+            if header > 0 {
+                render_header(f, chunks[0], app);
+            }
+            render_list_area(f, chunks[1], app);
         }
-        Mode::Normal => render_main(f, app),
     }
 }
 
-fn render_main(f: &mut Frame, app: &App) {
-    let area = f.area();
+fn render_header(f: &mut Frame, area: Rect, app: &App) {
+    let sort_indicator = if app.sort_order == SortOrder::Index {
+        String::new()
+    } else {
+        format!("  sort: {}", app.sort_order.label())
+    };
 
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),
-            Constraint::Min(5),
-            Constraint::Length(3),
-        ])
-        .split(area);
+    let clone_indicator = match app.clone_kind {
+        git::CloneKind::Full => "",
+        git::CloneKind::Shallow => " (shallow clone)",
+        git::CloneKind::Partial { .. } => " (partial clone)",
+    };
 
-    render_header(f, chunks[0], app);
-    render_stash_list(f, chunks[1], app);
-    render_footer(f, chunks[2], app);
-}
+    let marked_indicator = if app.marked.is_empty() {
+        String::new()
+    } else {
+        format!("  marked: {}", app.marked.len())
+    };
 
-fn render_header(f: &mut Frame, area: Rect, app: &App) {
-    let search_indicator = if app.searching {
-        format!("  🔍 /{}", app.search_query)
-    } else if !app.search_query.is_empty() {
-        format!("  filter: /{}", app.search_query)
+    let branch_indicator = if app.branch_only {
+        format!("  only: {}", app.current_branch)
     } else {
         String::new()
     };
 
-    let title = Line::from(vec![
-        Span::styled(
-            " gsm ",
-            Style::default()
-                .fg(Color::Black)
-                .bg(BRAND)
-                .add_modifier(Modifier::BOLD),
-        ),
-        Span::styled(
-            format!(
-                "  branch: {}  stashes: {}{}",
-                app.current_branch,
-                app.stashes.len(),
-                search_indicator
-            ),
-            Style::default().fg(Color::Gray),
+    // While typing, the query shows its cursor
+    let gray = Style::default().fg(Color::Gray);
+    let mut search_indicator = if app.searching {
+        let mut spans = vec![Span::styled("  🔍 /", gray)];
+        spans.extend(app.search_query.spans(gray));
+        spans
+    } else if !app.search_query.is_empty() {
+        vec![Span::styled(
+            format!("  filter: /{}", app.search_query),
+            gray,
+        )]
+    } else {
+        Vec::new()
+    };
+    if let Some(error) = query::parse(&app.search_query).error {
+        search_indicator.push(Span::styled(format!(" ({error})"), gray));
+    }
+
+    // Only worth the room when gsm wasn't started at the main worktree's root
+    let location = &app.location;
+    let mut repo_indicator = String::new();
+    if let Some(worktree) = &location.worktree {
+        repo_indicator.push_str(&format!("  worktree: {worktree}"));
+    }
+    if location.worktree.is_some() || !location.prefix.is_empty() {
+        repo_indicator.push_str(&format!("  root: {}", tilde(&location.root)));
+    }
+
+    let mut title = vec![Span::styled(
+        " gsm ",
+        Style::default()
+            .fg(Color::Black)
+            .bg(BRAND)
+            .add_modifier(Modifier::BOLD),
+    )];
+    title.extend(mode_indicator(app));
+    title.push(Span::styled(
+        format!(
+            "{}  branch: {}{}  stashes: {}{}{}{}",
+            repo_indicator,
+            app.current_branch,
+            clone_indicator,
+            app.stashes.len(),
+            marked_indicator,
+            sort_indicator,
+            branch_indicator,
         ),
-    ]);
+        gray,
+    ));
+    title.extend(search_indicator);
 
-    let block = Block::default()
+    let mut block = Block::default()
         .borders(Borders::ALL)
         .border_style(Style::default().fg(BRAND))
-        .title(title);
+        .title(Line::from(title));
+
+    if let Some(status) = spinner(app) {
+        block = block.title(
+            Line::from(Span::styled(
+                format!("{status} "),
+                Style::default().fg(BRAND),
+            ))
+            .right_aligned(),
+        );
+    } else if let Some(toast) = app.current_toast()
+        && app.config.ui.hints == HintPlacement::Hidden
+    {
+        // No hint bar to show it in
+        block = block.title(Line::from(vec![toast_span(toast), Span::raw(" ")]).right_aligned());
+    } else if app.recently_refreshed() {
+        block = block.title(
+            Line::from(Span::styled("↻ refreshed ", Style::default().fg(DIM))).right_aligned(),
+        );
+    } else if app.config.ui.hints == HintPlacement::Hidden {
+        // Without a hint bar, keep the help overlay discoverable
+        block = block.title(Line::from(key_span("?", "help ")).right_aligned());
+    }
 
     f.render_widget(block, area);
 }
 
+/// `path` with the home directory shortened to `~`
+fn tilde(path: &Path) -> String {
+    match std::env::var_os("HOME").and_then(|home| path.strip_prefix(home).ok()) {
+        Some(rest) => Path::new("~").join(rest).display().to_string(),
+        None => path.display().to_string(),
+    }
+}
+
+/// "⠙ loading diff…" while a background job is running
+pub fn spinner(app: &App) -> Option<String> {
+    app.loading
+        .map(|what| format!("{} {what}…", SPINNER[app.spinner_tick % SPINNER.len()]))
+}
+
+/// The stash list, with the preview pane beside it when enabled
+fn render_list_area(f: &mut Frame, area: Rect, app: &App) {
+    if !app.preview {
+        render_stash_list(f, area, app);
+        return;
+    }
+
+    let split = app.split.clamp(MIN_SPLIT, 100 - MIN_SPLIT);
+    let panes = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(split),
+            Constraint::Percentage(100 - split),
+        ])
+        .split(area);
+    render_stash_list(f, panes[0], app);
+    render_preview(f, panes[1], app);
+}
+
+fn render_preview(f: &mut Frame, area: Rect, app: &App) {
+    let mut lines: Vec<Line> = Vec::new();
+    if let Some(stash) = app.selected_stash() {
+        if let Some(note) = app.annotations.get(&stash.sha) {
+            lines.push(Line::from(vec![
+                Span::styled("✎ ", Style::default().fg(BRAND)),
+                Span::styled(note.clone(), Style::default().fg(Color::White)),
+            ]));
+            lines.push(Line::from(""));
+        }
+        match app.preview_cache.get(&stash.sha) {
+            Some(content) => lines.extend(content.iter().map(|l| colorize_stat_line(l))),
+            None => lines.push(Line::from(Span::styled(
+                "loading…",
+                Style::default().fg(DIM),
+            ))),
+        }
+    }
+
+    let p = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Rgb(80, 80, 100)))
+            .title(" Preview "),
+    );
+    f.render_widget(p, area);
+}
+
+/// Colour the +/- graph of a `--stat` line
+pub fn colorize_stat_line(line: &str) -> Line<'static> {
+    let Some((name, graph)) = line.rsplit_once('|') else {
+        return Line::from(Span::styled(line.to_string(), Style::default().fg(DIM)));
+    };
+
+    let mut spans = vec![
+        Span::styled(name.to_string(), Style::default().fg(Color::Gray)),
+        Span::styled("|".to_string(), Style::default().fg(DIM)),
+    ];
+    for c in graph.chars() {
+        let style = match c {
+            '+' => Style::default().fg(ADDED),
+            '-' => Style::default().fg(REMOVED),
+            _ => Style::default().fg(Color::Gray),
+        };
+        spans.push(Span::styled(c.to_string(), style));
+    }
+    Line::from(spans)
+}
+
 fn render_stash_list(f: &mut Frame, area: Rect, app: &App) {
-    let filtered = app.filtered_stashes();
+    let rows = app.visible_rows();
+    // Inside the borders, less the highlight symbol, group indent and mark
+    let indent = if app.grouped { 2 } else { 0 };
+    let room = (area.width as usize).saturating_sub(2 + 2 + indent + 2);
+    // Narrow terminals get two lines per stash: the message on the first, the
+    // branch and date below it
+    let compact = f.area().width < NARROW_WIDTH;
+    let columns = if compact {
+        let index = Column::Index.default_width();
+        vec![
+            (Column::Index, index),
+            (Column::Message, room.saturating_sub(index + 1).max(1)),
+        ]
+    } else {
+        layout_columns(&app.config.ui.columns, room)
+    };
+    let msg_width = columns.iter().find(|(c, _)| *c == Column::Message);
+    app.msg_width.set(msg_width.map_or(0, |(_, w)| *w));
 
-    let items: Vec<ListItem> = filtered
+    let items: Vec<ListItem> = rows
         .iter()
         .enumerate()
-        .map(|(i, stash)| {
+        .map(|(i, row)| {
+            let stash = match row {
+                ListRow::Group {
+                    branch,
+                    count,
+                    collapsed,
+                } => {
+                    let arrow = if *collapsed { "▸" } else { "▾" };
+                    return ListItem::new(Line::from(vec![
+                        Span::styled(
+                            format!("{arrow} {branch}"),
+                            Style::default()
+                                .fg(Color::Cyan)
+                                .add_modifier(Modifier::BOLD),
+                        ),
+                        Span::styled(format!("  ({count})"), Style::default().fg(DIM)),
+                    ]));
+                }
+                ListRow::Stash(stash) => stash,
+            };
             let is_selected = i == app.selected;
             let index_style = Style::default().fg(BRAND);
             let branch_style = Style::default()
                 .fg(Color::Cyan)
                 .add_modifier(Modifier::ITALIC);
-            let date_style = Style::default().fg(DIM);
+            let date_style = match app.stash_heat(stash) {
+                Some(heat) => Style::default().fg(heat_color(heat)),
+                None if app.is_stale(stash) => Style::default().fg(Color::Yellow),
+                None => Style::default().fg(DIM),
+            };
             let msg_style = if is_selected {
                 Style::default()
                     .fg(Color::White)
@@ -107,29 +339,83 @@ fn render_stash_list(f: &mut Frame, area: Rect, app: &App) {
                 Style::default().fg(Color::Gray)
             };
 
-            let line = Line::from(vec![
-                Span::styled(format!("{:<3}", stash.index), index_style),
-                Span::raw(" "),
-                Span::styled(
-                    format!("{:<20}", truncate(&stash.branch, 20)),
-                    branch_style,
-                ),
-                Span::raw(" "),
-                Span::styled(
-                    format!("{:<35}", truncate(&stash.short_msg, 35)),
-                    msg_style,
-                ),
-                Span::raw(" "),
-                Span::styled(stash.date.clone(), date_style),
-            ]);
-
-            ListItem::new(line)
+            let indent = if app.grouped { "  " } else { "" };
+            let mark = if app.marked.contains(&stash.sha) {
+                Span::styled("● ", Style::default().fg(Color::Magenta))
+            } else {
+                Span::raw("  ")
+            };
+            let mut spans = vec![Span::raw(indent), mark];
+            for &(column, width) in &columns {
+                let cell = |text: &str| fit(text, width);
+                match column {
+                    Column::Index => {
+                        spans.push(Span::styled(cell(&stash.index.to_string()), index_style));
+                    }
+                    Column::Branch => spans.extend(highlight_matches(
+                        &cell(&stash.branch),
+                        &app.search_matches(&stash.branch),
+                        branch_style,
+                    )),
+                    Column::Message => spans.extend(highlight_matches(
+                        &cell(&stash.short_msg),
+                        &app.search_matches(&stash.short_msg),
+                        msg_style,
+                    )),
+                    Column::Date => {
+                        spans.push(Span::styled(cell(&app.stash_date(stash)), date_style));
+                    }
+                    Column::Size => spans.extend(size_cell(stash, width)),
+                    Column::Author => {
+                        // The name alone; the email is in the diff view's header
+                        let name = stash.author.split(" <").next().unwrap_or("");
+                        spans.push(Span::styled(cell(name), Style::default().fg(Color::Gray)));
+                    }
+                }
+                spans.push(Span::raw(" "));
+            }
+            let mut lines = vec![spans];
+            if compact {
+                lines.push(vec![
+                    Span::raw(format!("{indent}      ")),
+                    Span::styled(stash.branch.clone(), branch_style),
+                    Span::raw("  "),
+                    Span::styled(app.stash_date(stash), date_style),
+                ]);
+            }
+            let tail = lines.last_mut().expect("a stash row has a line");
+            if app.is_pinned(stash) {
+                tail.push(Span::raw("  📌"));
+            }
+            if app.annotations.contains_key(&stash.sha) {
+                tail.push(Span::styled("  ✎", Style::default().fg(BRAND)));
+            }
+            for label in app.labels.get(&stash.sha).into_iter().flatten() {
+                tail.push(Span::raw(" "));
+                tail.push(label_chip(label));
+            }
+            if app.is_due(stash) {
+                tail.push(Span::styled(
+                    "  revisit",
+                    Style::default()
+                        .fg(Color::Magenta)
+                        .add_modifier(Modifier::BOLD),
+                ));
+            }
+
+            ListItem::new(lines.into_iter().map(Line::from).collect::<Vec<_>>())
         })
         .collect();
 
+    if app.stashes.is_empty() && app.config.ui.onboarding {
+        render_onboarding(f, area, app);
+        return;
+    }
     if items.is_empty() {
         let empty_msg = if app.stashes.is_empty() {
             "No stashes found. Press 'n' to create one."
+        } else if app.branch_only && app.search_query.is_empty() {
+            "No stashes on this branch. Press 'B' to show all."
         } else {
             "No stashes match your search."
         };
@@ -157,7 +443,7 @@ fn render_stash_list(f: &mut Frame, area: Rect, app: &App) {
                 .title(Line::from(vec![
                     Span::raw(" Stashes "),
                     Span::styled(
-                        format!("({}/{})", app.selected + 1, filtered.len()),
+                        format!("({}/{})", app.selected + 1, rows.len()),
                         Style::default().fg(DIM),
                     ),
                 ])),
@@ -166,26 +452,277 @@ fn render_stash_list(f: &mut Frame, area: Rect, app: &App) {
         .highlight_symbol("▶ ");
 
     f.render_stateful_widget(list, area, &mut state);
+    render_scrollbar(f, area, rows.len(), app.selected);
+}
+
+/// Fit the configured list columns into `width` cells, each followed by a
+/// space. The message gives up room first, down to `MIN_MSG_WIDTH`; then the
+/// least important columns are left out.
+fn layout_columns(specs: &[ColumnSpec], width: usize) -> Vec<(Column, usize)> {
+    let mut columns: Vec<(Column, usize)> = specs
+        .iter()
+        .map(|spec| {
+            (
+                spec.column,
+                spec.width.unwrap_or(spec.column.default_width()).max(1),
+            )
+        })
+        .collect();
+    loop {
+        let needed: usize = columns.iter().map(|(_, w)| w + 1).sum();
+        let excess = needed.saturating_sub(width);
+        if excess == 0 {
+            return columns;
+        }
+        if let Some((_, w)) = columns
+            .iter_mut()
+            .find(|(c, w)| *c == Column::Message && *w > MIN_MSG_WIDTH)
+        {
+            *w = w.saturating_sub(excess).max(MIN_MSG_WIDTH);
+            continue;
+        }
+        let least = columns
+            .iter()
+            .enumerate()
+            .filter(|(_, (c, _))| *c != Column::Message)
+            .min_by_key(|(_, (c, _))| c.priority())
+            .map(|(i, _)| i);
+        match least {
+            Some(i) => {
+                columns.remove(i);
+            }
+            // Only the message is left, and it fills whatever room there is
+            None => {
+                for (_, w) in &mut columns {
+                    *w = w.saturating_sub(excess).max(1);
+                }
+                return columns;
+            }
+        }
+    }
+}
+
+/// Green through yellow to red as `heat` goes from 0.0 to 1.0
+fn heat_color(heat: f64) -> Color {
+    const GREEN: (u8, u8, u8) = (95, 175, 95);
+    const YELLOW: (u8, u8, u8) = (215, 175, 75);
+    const RED: (u8, u8, u8) = (215, 85, 85);
+    let heat = heat.clamp(0.0, 1.0);
+    if heat < 0.5 {
+        interpolate(GREEN, YELLOW, heat * 2.0)
+    } else {
+        interpolate(YELLOW, RED, heat * 2.0 - 1.0)
+    }
+}
+
+/// The colour `t` of the way from `from` to `to`
+fn interpolate(from: (u8, u8, u8), to: (u8, u8, u8), t: f64) -> Color {
+    let mix = |a: u8, b: u8| (f64::from(a) + (f64::from(b) - f64::from(a)) * t).round() as u8;
+    Color::Rgb(mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
+}
+
+/// The size column: "3 files +120 −45", with the counts in diff colours and
+/// the file count brighter the larger the change. Falls back to the file
+/// count alone when the counts don't fit.
+fn size_cell(stash: &git::Stash, width: usize) -> Vec<Span<'static>> {
+    let (Some(files), Some((ins, del))) = (stash.files_changed, stash.line_counts()) else {
+        return vec![Span::raw(fit("", width))];
+    };
+    let files = if files == 1 {
+        "1 file".to_string()
+    } else {
+        format!("{files} files")
+    };
+    let weight = match ins + del {
+        0..=10 => Style::default().fg(DIM),
+        11..=200 => Style::default().fg(Color::Gray),
+        _ => Style::default()
+            .fg(Color::White)
+            .add_modifier(Modifier::BOLD),
+    };
+    let (ins, del) = (format!("+{ins}"), format!("−{del}"));
+    let used = files.width() + 1 + ins.width() + 1 + del.width();
+    if used > width {
+        return vec![Span::styled(fit(&files, width), weight)];
+    }
+    vec![
+        Span::styled(files, weight),
+        Span::raw(" "),
+        Span::styled(ins, Style::default().fg(ADDED)),
+        Span::raw(" "),
+        Span::styled(del, Style::default().fg(REMOVED)),
+        Span::raw(" ".repeat(width - used)),
+    ]
+}
+
+/// A scrollbar over the right border of the bordered `area`, placed for
+/// `position` among `total` lines; drawn only when they don't all fit
+pub fn render_scrollbar(f: &mut Frame, area: Rect, total: usize, position: usize) {
+    let track = area.inner(Margin {
+        vertical: 1,
+        horizontal: 0,
+    });
+    if total <= track.height as usize {
+        return;
+    }
+    let mut state = ScrollbarState::new(total)
+        .position(position)
+        .viewport_content_length(track.height as usize);
+    f.render_stateful_widget(
+        Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(None)
+            .end_symbol(None)
+            .track_symbol(Some("│"))
+            .track_style(Style::default().fg(Color::Rgb(60, 60, 80)))
+            .thumb_style(Style::default().fg(BRAND)),
+        track,
+        &mut state,
+    );
+}
+
+/// Getting-started panel shown instead of an empty list: the stash workflow
+/// in five steps, and what pressing `n` would stash right now
+fn render_onboarding(f: &mut Frame, area: Rect, app: &App) {
+    let steps = [
+        (
+            "n",
+            "Stash your work",
+            "name it; Tab picks tracked, untracked or staged only",
+        ),
+        (
+            "w",
+            "See what would be stashed",
+            "staged, unstaged and untracked files",
+        ),
+        (
+            "Enter",
+            "Look inside a stash",
+            "f lists its files, v opens a preview pane",
+        ),
+        (
+            "a",
+            "Bring it back",
+            "a applies and keeps it, p pops, x drops",
+        ),
+        ("?", "Everything else", "every key and what it does"),
+    ];
+
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            "  No stashes here yet. Here's how gsm works:",
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+    ];
+    for (i, (key, title, detail)) in steps.iter().enumerate() {
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {}  ", i + 1), Style::default().fg(DIM)),
+            Span::styled(
+                format!("{:<7}", format!("[{key}]")),
+                Style::default().fg(BRAND).add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(format!(" {title:<27}"), Style::default().fg(Color::White)),
+            Span::styled(*detail, Style::default().fg(Color::Gray)),
+        ]));
+    }
+
+    lines.push(Line::from(""));
+    let changed = app.worktree_status.len();
+    lines.push(Line::from(if changed == 0 {
+        Span::styled(
+            "  Your working tree is clean: edit something, then press n to stash it.",
+            Style::default().fg(DIM),
+        )
+    } else {
+        Span::styled(
+            format!(
+                "  Try it: {changed} changed file(s) in your working tree. Press n to stash them."
+            ),
+            Style::default().fg(ADDED),
+        )
+    }));
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "  Hide this panel for good with `onboarding = false` under [ui] in config.toml.",
+        Style::default().fg(DIM),
+    )));
+
+    let panel = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(BRAND))
+            .title(" Getting started "),
+    );
+    f.render_widget(panel, area);
+}
+
+/// A label drawn as a coloured chip; the colour is derived from the name so a
+/// label looks the same on every stash
+pub fn label_chip(label: &str) -> Span<'static> {
+    const CHIP_COLORS: [Color; 6] = [
+        Color::Rgb(95, 135, 215),
+        Color::Rgb(135, 175, 95),
+        Color::Rgb(215, 135, 95),
+        Color::Rgb(175, 120, 215),
+        Color::Rgb(95, 175, 175),
+        Color::Rgb(215, 175, 95),
+    ];
+    let hash = label
+        .bytes()
+        .fold(0usize, |h, b| h.wrapping_mul(31).wrapping_add(b as usize));
+    Span::styled(
+        format!(" {label} "),
+        Style::default()
+            .fg(Color::Black)
+            .bg(CHIP_COLORS[hash % CHIP_COLORS.len()]),
+    )
+}
+
+/// Split `text` into spans, picking out the characters the search matched
+fn highlight_matches(text: &str, matched: &HashSet<usize>, style: Style) -> Vec<Span<'static>> {
+    if matched.is_empty() {
+        return vec![Span::styled(text.to_string(), style)];
+    }
+
+    let hit = style
+        .fg(BRAND)
+        .add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+    let mut spans: Vec<Span> = Vec::new();
+    let mut run = String::new();
+    let mut run_hit = false;
+    for (i, c) in text.chars().enumerate() {
+        let is_hit = matched.contains(&i) && c != '…';
+        if is_hit != run_hit && !run.is_empty() {
+            spans.push(Span::styled(
+                std::mem::take(&mut run),
+                if run_hit { hit } else { style },
+            ));
+        }
+        run_hit = is_hit;
+        run.push(c);
+    }
+    spans.push(Span::styled(run, if run_hit { hit } else { style }));
+    spans
 }
 
 fn render_footer(f: &mut Frame, area: Rect, app: &App) {
-    let keys: Vec<Vec<Span>> = if app.searching {
+    let keys: Vec<Vec<Span>> = if let Some(pending) = chord_hints(app) {
+        pending
+    } else if app.searching {
         vec![
             key_span("Enter", "confirm"),
             key_span("Esc", "cancel search"),
         ]
+    } else if f.area().width < NARROW_WIDTH {
+        vec![key_span("?", "help")]
     } else {
-        vec![
-            key_span("↑↓/jk", "navigate"),
-            key_span("Enter/d", "diff"),
-            key_span("f", "files"),
-            key_span("a", "apply"),
-            key_span("p", "pop"),
-            key_span("x", "drop"),
-            key_span("n", "new"),
-            key_span("/", "search"),
-            key_span("q", "quit"),
-        ]
+        actions::footer(&[Scope::List])
+            .into_iter()
+            .map(|(k, label)| key_span(k, label))
+            .collect()
     };
 
     let mut spans: Vec<Span> = Vec::new();
@@ -196,8 +733,115 @@ fn render_footer(f: &mut Frame, area: Rect, app: &App) {
         spans.extend(s);
     }
 
-    let line = Line::from(spans);
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Rgb(60, 60, 80)));
+
+    // Echo the full message of a truncated selection so it can be read in place
+    if !app.searching
+        && let Some(stash) = app.selected_stash()
+        && stash.short_msg.width() > app.msg_width.get()
+    {
+        block = block.title(Span::styled(
+            format!(" {} ", stash.short_msg),
+            Style::default().fg(Color::White),
+        ));
+    }
+
+    if let Some(change) = &app.last_change {
+        let name = actions::binding(change.action).map_or("", |b| b.name);
+        block = block.title_bottom(
+            Line::from(vec![
+                Span::styled("[.]", Style::default().fg(BRAND)),
+                Span::styled(format!(" {name} "), Style::default().fg(DIM)),
+            ])
+            .right_aligned(),
+        );
+    }
+
+    let line = match app.current_toast() {
+        Some(toast) => Line::from(toast_span(toast)),
+        None => Line::from(spans),
+    };
     let p = Paragraph::new(line)
+        .block(block)
+        .alignment(Alignment::Center);
+
+    f.render_widget(p, area);
+}
+
+fn toast_span(toast: &str) -> Span<'static> {
+    Span::styled(
+        format!("✓ {toast}"),
+        Style::default().fg(ADDED).add_modifier(Modifier::BOLD),
+    )
+}
+
+/// Vim-style indicator of what keys will do: the mode (SEARCH while a query
+/// is being typed), then a pending count and first key of a sequence
+pub fn mode_indicator(app: &App) -> Vec<Span<'static>> {
+    let searching = app.searching || app.diff_searching;
+    let (label, color) = match &app.mode {
+        _ if app.palette => ("COMMAND", BRAND),
+        Mode::Normal | Mode::Diff | Mode::Files if searching => ("SEARCH", Color::Yellow),
+        Mode::Normal => ("NORMAL", Color::Blue),
+        Mode::Diff | Mode::Files => (app.mode.label(), ADDED),
+        Mode::Confirm(_) => ("CONFIRM", REMOVED),
+        mode => (mode.label(), Color::Magenta),
+    };
+    let mut spans = vec![Span::styled(
+        format!(" {label} "),
+        Style::default()
+            .fg(Color::Black)
+            .bg(color)
+            .add_modifier(Modifier::BOLD),
+    )];
+    let count = app.count.map(|n| n.to_string()).unwrap_or_default();
+    if let Some(prefix) = app.pending_chord {
+        spans.push(Span::styled(
+            format!(" {count}{prefix}… "),
+            Style::default().fg(BRAND).add_modifier(Modifier::BOLD),
+        ));
+    } else if !count.is_empty() {
+        spans.push(Span::styled(
+            format!(" {count}… "),
+            Style::default().fg(BRAND).add_modifier(Modifier::BOLD),
+        ));
+    }
+    spans
+}
+
+/// While a chord is pending, the keys that can complete it
+pub fn chord_hints(app: &App) -> Option<Vec<Vec<Span<'static>>>> {
+    let prefix = app.pending_chord?;
+    let mut keys = vec![vec![Span::styled(
+        format!("{prefix}…"),
+        Style::default()
+            .fg(Color::Black)
+            .bg(BRAND)
+            .add_modifier(Modifier::BOLD),
+    )]];
+    keys.extend(
+        actions::chords(app)
+            .iter()
+            .filter(|(p, ..)| *p == prefix)
+            .map(|(_, k, desc)| key_span(&k.to_string(), desc)),
+    );
+    keys.push(key_span("Esc", "cancel"));
+    Some(keys)
+}
+
+/// Footer bar of key hints for full-screen views
+pub fn render_key_bar(f: &mut Frame, area: Rect, keys: &[(&str, &str)]) {
+    let mut spans: Vec<Span> = Vec::new();
+    for (i, (key, desc)) in keys.iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::raw("  "));
+        }
+        spans.extend(key_span(key, desc));
+    }
+
+    let p = Paragraph::new(Line::from(spans))
         .block(
             Block::default()
                 .borders(Borders::ALL)
@@ -208,7 +852,7 @@ fn render_footer(f: &mut Frame, area: Rect, app: &App) {
     f.render_widget(p, area);
 }
 
-fn key_span(key: &str, desc: &str) -> Vec<Span<'static>> {
+pub fn key_span(key: &str, desc: &str) -> Vec<Span<'static>> {
     vec![
         Span::styled(
             format!("[{key}]"),
@@ -218,90 +862,21 @@ fn key_span(key: &str, desc: &str) -> Vec<Span<'static>> {
     ]
 }
 
-fn render_diff_view(f: &mut Frame, app: &App, is_files: bool) {
-    let area = f.area();
-
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),
-            Constraint::Min(1),
-            Constraint::Length(3),
-        ])
-        .split(area);
-
-    let stash_info = app
-        .selected_stash()
-        .map(|s| format!("{} — {}", s.name, s.short_msg))
-        .unwrap_or_default();
+pub fn colorize_diff_line(line: &str) -> Line<'static> {
+    // CR is invisible on screen; show it as a marker so CRLF<->LF churn can be spotted
+    let (line, has_cr) = match line.strip_suffix('\r') {
+        Some(l) => (l, true),
+        None => (line, false),
+    };
 
-    let title = Line::from(vec![
-        Span::styled(
-            if is_files { " Files " } else { " Diff " },
+    let (style, content) = if line == app::UNTRACKED_HEADER {
+        (
             Style::default()
-                .fg(Color::Black)
-                .bg(BRAND)
+                .fg(Color::Magenta)
                 .add_modifier(Modifier::BOLD),
-        ),
-        Span::styled(
-            format!("  {stash_info}"),
-            Style::default().fg(Color::Gray),
-        ),
-    ]);
-
-    let header = Block::default()
-        .borders(Borders::ALL)
-        .border_style(Style::default().fg(BRAND))
-        .title(title);
-
-    f.render_widget(header, chunks[0]);
-
-    let visible_height = chunks[1].height.saturating_sub(2) as usize;
-    let lines: Vec<Line> = app
-        .diff_content
-        .iter()
-        .skip(app.diff_scroll)
-        .take(visible_height)
-        .map(|line| colorize_diff_line(line))
-        .collect();
-
-    let diff = Paragraph::new(lines)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Rgb(80, 80, 100))),
-        )
-        .wrap(Wrap { trim: false });
-
-    f.render_widget(diff, chunks[1]);
-
-    let scroll_info = format!(
-        "line {}/{}",
-        app.diff_scroll + 1,
-        app.diff_content.len().max(1)
-    );
-
-    let mut footer_spans: Vec<Span> = Vec::new();
-    footer_spans.extend(key_span("↑↓/jk", "scroll"));
-    footer_spans.push(Span::raw("   "));
-    footer_spans.extend(key_span("PgUp/PgDn", "fast scroll"));
-    footer_spans.push(Span::raw("   "));
-    footer_spans.extend(key_span("Esc/q", "back"));
-    footer_spans.push(Span::raw(format!("   {scroll_info}")));
-
-    let footer = Paragraph::new(Line::from(footer_spans))
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Rgb(60, 60, 80))),
+            line.to_string(),
         )
-        .alignment(Alignment::Center);
-
-    f.render_widget(footer, chunks[2]);
-}
-
-fn colorize_diff_line(line: &str) -> Line<'static> {
-    let (style, content) = if line.starts_with('+') && !line.starts_with("+++") {
+    } else if line.starts_with('+') && !line.starts_with("+++") {
         (Style::default().fg(ADDED), line.to_string())
     } else if line.starts_with('-') && !line.starts_with("---") {
         (Style::default().fg(REMOVED), line.to_string())
@@ -322,143 +897,14 @@ fn colorize_diff_line(line: &str) -> Line<'static> {
         (Style::default().fg(Color::Gray), line.to_string())
     };
 
-    Line::from(Span::styled(content, style))
-}
-
-fn render_confirm_popup(f: &mut Frame, action: &ConfirmAction) {
-    let area = centered_rect(50, 20, f.area());
-    f.render_widget(Clear, area);
-
-    let (title, body, color) = match action {
-        ConfirmAction::Apply => (
-            "Apply Stash",
-            "Apply this stash? (it stays in the stash list)",
-            Color::Green,
-        ),
-        ConfirmAction::Pop => (
-            "Pop Stash",
-            "Apply and remove this stash from the list?",
-            Color::Yellow,
-        ),
-        ConfirmAction::Drop => (
-            "Drop Stash",
-            "Permanently delete this stash? This cannot be undone.",
-            Color::Red,
-        ),
-    };
-
-    let content = vec![
-        Line::from(""),
-        Line::from(Span::styled(body, Style::default().fg(Color::White))),
-        Line::from(""),
-        Line::from(vec![
-            Span::styled(
-                "[y] Yes",
-                Style::default()
-                    .fg(Color::Green)
-                    .add_modifier(Modifier::BOLD),
-            ),
-            Span::raw("    "),
-            Span::styled("[n] No", Style::default().fg(Color::Red)),
-        ]),
-    ];
-
-    let popup = Paragraph::new(content)
-        .alignment(Alignment::Center)
-        .block(
-            Block::default()
-                .title(format!(" {title} "))
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(color)),
-        );
-
-    f.render_widget(popup, area);
-}
-
-fn render_new_stash_popup(f: &mut Frame, app: &App) {
-    let area = centered_rect(60, 25, f.area());
-    f.render_widget(Clear, area);
-
-    let untracked_label = if app.new_stash_untracked {
-        Span::styled(
-            "[Tab] Include untracked: ON ",
-            Style::default().fg(Color::Green),
-        )
-    } else {
-        Span::styled(
-            "[Tab] Include untracked: off",
-            Style::default().fg(DIM),
-        )
-    };
-
-    let content = vec![
-        Line::from(""),
-        Line::from(Span::styled(
-            "Stash message:",
-            Style::default().fg(Color::Gray),
-        )),
-        Line::from(Span::styled(
-            format!("{}_", app.new_stash_input),
-            Style::default()
-                .fg(Color::White)
-                .add_modifier(Modifier::BOLD),
-        )),
-        Line::from(""),
-        Line::from(untracked_label),
-        Line::from(""),
-        Line::from(vec![
-            Span::styled("[Enter]", Style::default().fg(BRAND)),
-            Span::raw(" save   "),
-            Span::styled("[Esc]", Style::default().fg(Color::Red)),
-            Span::raw(" cancel"),
-        ]),
-    ];
-
-    let popup = Paragraph::new(content)
-        .alignment(Alignment::Center)
-        .block(
-            Block::default()
-                .title(" New Stash ")
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(BRAND)),
-        );
-
-    f.render_widget(popup, area);
-}
-
-fn render_message_popup(f: &mut Frame, msg: &str) {
-    let area = centered_rect(55, 18, f.area());
-    f.render_widget(Clear, area);
-
-    let is_error = msg.starts_with("Error");
-    let color = if is_error { Color::Red } else { Color::Green };
-
-    let content = vec![
-        Line::from(""),
-        Line::from(Span::styled(
-            msg.to_string(),
-            Style::default().fg(Color::White),
-        )),
-        Line::from(""),
-        Line::from(Span::styled(
-            "Press any key to continue",
-            Style::default().fg(DIM),
-        )),
-    ];
-
-    let popup = Paragraph::new(content)
-        .alignment(Alignment::Center)
-        .block(
-            Block::default()
-                .title(if is_error { " Error " } else { " Done " })
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(color)),
-        );
-
-    f.render_widget(popup, area);
+    let mut spans = vec![Span::styled(content, style)];
+    if has_cr {
+        spans.push(Span::styled("␍", Style::default().fg(Color::Magenta)));
+    }
+    Line::from(spans)
 }
 
-fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -478,10 +924,53 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         .split(popup_layout[1])[1]
 }
 
-fn truncate(s: &str, max: usize) -> String {
-    if s.len() <= max {
-        s.to_string()
-    } else {
-        format!("{}…", &s[..max - 1])
+/// Like `centered_rect`, but with a fixed height in lines (clamped to the area)
+pub fn centered_rect_lines(percent_x: u16, lines: u16, r: Rect) -> Rect {
+    let height = lines.min(r.height);
+    let area = Rect {
+        y: r.y + (r.height - height) / 2,
+        height,
+        ..r
+    };
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(area)[1]
+}
+
+/// `s` cut to at most `max` terminal columns, ending in … when shortened.
+/// Cuts between graphemes, so emoji and accented letters stay whole, and
+/// counts wide (e.g. CJK) characters as two columns.
+pub fn truncate(s: &str, max: usize) -> String {
+    if s.width() <= max {
+        return s.to_string();
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    for grapheme in s.graphemes(true) {
+        let w = grapheme.width();
+        if used + w + 1 > max {
+            break;
+        }
+        out.push_str(grapheme);
+        used += w;
     }
-}
\ No newline at end of file
+    if max > 0 {
+        out.push('…');
+    }
+    out
+}
+
+/// `s` truncated and then padded to exactly `width` columns, for aligning
+/// text in columns; `format!("{:<w$}")` pads by characters, not columns
+pub fn fit(s: &str, width: usize) -> String {
+    let mut out = truncate(s, width);
+    let pad = width.saturating_sub(out.width());
+    out.extend(std::iter::repeat_n(' ', pad));
+    out
+}